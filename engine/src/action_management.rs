@@ -1,13 +1,16 @@
 //! Action Management and Dynamic Routing
 //!
 //! Handles resolution of action directories, scanning for available actions,
-//! and matching dynamic routes (e.g. `/users/:id`).
+//! and matching dynamic routes (e.g. `/users/:id`). Captured params are
+//! percent-decoded, so `/users/john%20doe` gives `john doe`.
 
 use std::collections::HashMap;
 use std::env;
 use std::path::{Path, PathBuf};
 use serde::Deserialize;
 use serde_json::Value;
+use crate::fast_path::ReplyCache;
+use crate::query::{percent_decode, QuerySchema};
 
 /// Route configuration (loaded from routes.json)
 #[derive(Debug, Deserialize, Clone)]
@@ -15,13 +18,89 @@ pub struct RouteVal {
     pub r#type: String,
     #[serde(alias = "target")]
     pub value: Value,
+    /// Optional caching policy for reply routes
+    #[serde(default)]
+    pub cache: Option<ReplyCache>,
+    /// Content type for `json` / `text` reply routes, replacing the default
+    #[serde(default, rename = "contentType", alias = "content_type")]
+    pub content_type: Option<String>,
+    /// ETag / 304 handling for this action route (`__config.etag` enables it everywhere)
+    #[serde(default)]
+    pub etag: bool,
+    /// Actions run as middleware before this route's action, in order
+    #[serde(default)]
+    pub middleware: Vec<String>,
+    /// Types for `req.query` values (see `query.rs`)
+    #[serde(default, rename = "querySchema", alias = "query_schema")]
+    pub query_schema: Option<QuerySchema>,
 }
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct DynamicRoute {
+    #[serde(default)]
     pub method: String,
+    /// Further methods served by the same pattern (`t.route(["GET", "POST"], ...)`)
+    #[serde(default)]
+    pub methods: Vec<String>,
     pub pattern: String,
     pub action: String,
+    #[serde(default)]
+    pub etag: bool,
+    #[serde(default)]
+    pub middleware: Vec<String>,
+    #[serde(default, rename = "querySchema", alias = "query_schema")]
+    pub query_schema: Option<QuerySchema>,
+}
+
+impl DynamicRoute {
+    /// Every method this route answers. Compared ignoring case, since
+    /// routes.json may come from tooling that doesn't uppercase them.
+    pub fn serves(&self, method: &str) -> bool {
+        self.method.eq_ignore_ascii_case(method) || self.methods.iter().any(|m| m.eq_ignore_ascii_case(method))
+    }
+
+    fn all_methods(&self) -> impl Iterator<Item = &str> {
+        std::iter::once(self.method.as_str()).chain(self.methods.iter().map(String::as_str)).filter(|m| !m.is_empty())
+    }
+
+    /// `GET` or `GET,POST` for log messages.
+    pub fn method_label(&self) -> String {
+        self.all_methods().collect::<Vec<_>>().join(",")
+    }
+}
+
+/// `__config.trailingSlash`: how `/users/` relates to a route declared as
+/// `/users` (and the reverse). Dynamic routes already match either form.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum TrailingSlash {
+    /// The two forms are different routes
+    #[default]
+    Strict,
+    /// Either form reaches the route that exists
+    Ignore,
+    /// `/users/` answers with a redirect to `/users`
+    Redirect,
+}
+
+impl TrailingSlash {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "strict" => Some(Self::Strict),
+            "ignore" => Some(Self::Ignore),
+            "redirect" => Some(Self::Redirect),
+            _ => None,
+        }
+    }
+}
+
+/// `path` with its trailing slash removed, or one added. The root has no
+/// other form.
+pub fn toggle_trailing_slash(path: &str) -> Option<String> {
+    match path.strip_suffix('/') {
+        _ if path == "/" || path.is_empty() => None,
+        Some(trimmed) => Some(trimmed.to_string()),
+        None => Some(format!("{}/", path)),
+    }
 }
 
 /// Resolve the directory path where actions are stored.
@@ -68,16 +147,16 @@ pub fn find_actions_dir(dist_dir: &PathBuf) -> Option<PathBuf> {
 }
 
 /// Match a dynamic route against the current request path.
-pub fn match_dynamic_route(
+pub fn match_dynamic_route<'a>(
     method: &str,
     path: &str,
-    routes: &[DynamicRoute],
-) -> Option<(String, HashMap<String, String>)> {
+    routes: &'a [DynamicRoute],
+) -> Option<(&'a DynamicRoute, HashMap<String, String>)> {
     let path_segments: Vec<&str> =
         path.trim_matches('/').split('/').collect();
 
     for route in routes {
-        if route.method != method {
+        if !route.serves(method) {
             continue;
         }
 
@@ -105,12 +184,12 @@ pub fn match_dynamic_route(
                 let name = pat.trim_start_matches(':').trim_end_matches('*');
 
                 let rest = path_segments[i..].join("/");
-                params.insert(name.to_string(), rest);
+                params.insert(name.to_string(), percent_decode(&rest, false));
 
                 break;
             }
 
-            let val = match path_segments.get(i) {
+            let raw = match path_segments.get(i) {
                 Some(v) => *v,
                 None => {
                     matched = false;
@@ -120,6 +199,8 @@ pub fn match_dynamic_route(
 
             if pat.starts_with(':') {
                 let inner = &pat[1..];
+                // `+` is only a space in query strings, so it stays literal here
+                let val = percent_decode(raw, false);
 
                 let (name, ty) = inner
                     .split_once('<')
@@ -137,52 +218,192 @@ pub fn match_dynamic_route(
                     break;
                 }
 
-                params.insert(name.to_string(), val.to_string());
-            } else if pat != &val {
+                params.insert(name.to_string(), val);
+            } else if pat != &raw {
                 matched = false;
                 break;
             }
         }
 
         if matched {
-            return Some((route.action.clone(), params));
+            return Some((route, params));
         }
     }
 
     None
 }
 
-pub fn scan_actions(root: &PathBuf) -> HashMap<String, PathBuf> {
-    let mut map = HashMap::new();
-    
-    let dir = match find_actions_dir(root) {
+/// Keys of the `routes` object in source order, duplicates included
+/// (a `HashMap`/`serde_json::Map` would silently keep the last one).
+#[derive(Default)]
+struct RouteKeys(Vec<String>);
+
+impl<'de> Deserialize<'de> for RouteKeys {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct KeysVisitor;
+        impl<'de> serde::de::Visitor<'de> for KeysVisitor {
+            type Value = RouteKeys;
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("a routes object")
+            }
+            fn visit_map<A: serde::de::MapAccess<'de>>(self, mut map: A) -> Result<RouteKeys, A::Error> {
+                let mut keys = Vec::new();
+                while let Some(key) = map.next_key::<String>()? {
+                    map.next_value::<serde::de::IgnoredAny>()?;
+                    keys.push(key);
+                }
+                Ok(RouteKeys(keys))
+            }
+        }
+        deserializer.deserialize_map(KeysVisitor)
+    }
+}
+
+#[derive(Deserialize)]
+struct RoutesKeysFile {
+    #[serde(default)]
+    routes: RouteKeys,
+}
+
+/// Route definitions that shadow each other, as human-readable messages:
+/// exact routes declared more than once in `routes.json` (last wins), and
+/// dynamic routes with the same method and shape (first wins, the later
+/// one is unreachable).
+pub fn find_route_conflicts(raw_routes_json: &str, dynamic_routes: &[DynamicRoute]) -> Vec<String> {
+    let mut conflicts = Vec::new();
+
+    let keys = serde_json::from_str::<RoutesKeysFile>(raw_routes_json).map(|f| f.routes.0).unwrap_or_default();
+    let mut counts: Vec<(&str, usize)> = Vec::new();
+    for key in &keys {
+        match counts.iter_mut().find(|(k, _)| k == key) {
+            Some((_, n)) => *n += 1,
+            None => counts.push((key, 1)),
+        }
+    }
+    for (key, n) in counts.into_iter().filter(|(_, n)| *n > 1) {
+        conflicts.push(format!("route '{}' is defined {} times; the last definition wins", key, n));
+    }
+
+    // `:id` and `:userId` match the same paths, so compare params by type only
+    let shape = |pattern: &str| -> String {
+        pattern
+            .trim_matches('/')
+            .split('/')
+            .map(|seg| match seg.strip_prefix(':') {
+                Some(p) if p.ends_with('*') => ":*".to_string(),
+                Some(p) => format!(":{}", p.split_once('<').map_or("string", |(_, t)| t.trim_end_matches('>'))),
+                None => seg.to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join("/")
+    };
+    for (i, later) in dynamic_routes.iter().enumerate() {
+        if let Some(earlier) = dynamic_routes[..i]
+            .iter()
+            .find(|r| r.all_methods().any(|m| later.serves(m)) && shape(&r.pattern) == shape(&later.pattern))
+        {
+            conflicts.push(format!(
+                "dynamic routes '{} {}' (→ {}) and '{} {}' (→ {}) overlap; '{} {}' wins",
+                earlier.method_label(), earlier.pattern, earlier.action,
+                later.method_label(), later.pattern, later.action,
+                earlier.method_label(), earlier.pattern
+            ));
+        }
+    }
+
+    conflicts
+}
+
+/// Directory `scan_actions` loads bundles from.
+pub fn actions_root(root: &PathBuf) -> PathBuf {
+    match find_actions_dir(root) {
         Some(d) => d,
         None => {
             let ad = resolve_actions_dir();
             if ad.exists() { ad } else { root.clone() }
         }
-    };
+    }
+}
+
+/// Action name for a bundle inside `dir`: its relative path without extension.
+pub fn action_name(dir: &Path, path: &Path) -> Option<String> {
+    let ext = path.extension().and_then(|s| s.to_str()).unwrap_or("");
+    if ext != "js" && ext != "jsbundle" { return None; }
+
+    let rel = path.strip_prefix(dir).ok()?;
+    let name = rel.with_extension("")
+        .to_string_lossy()
+        .replace('\\', "/");
+    if name.is_empty() { None } else { Some(name) }
+}
+
+pub fn scan_actions(root: &PathBuf) -> HashMap<String, PathBuf> {
+    let mut map = HashMap::new();
+    
+    let dir = actions_root(root);
 
     if dir.exists() {
         for entry in walkdir::WalkDir::new(&dir).into_iter().flatten() {
             let path = entry.path();
             if !path.is_file() { continue; }
             
-            let ext = path.extension().and_then(|s| s.to_str()).unwrap_or("");
-            if ext != "js" && ext != "jsbundle" { continue; }
-            
-            // Generate action name from relative path
-            if let Ok(rel) = path.strip_prefix(&dir) {
-                let name = rel.with_extension("")
-                    .to_string_lossy()
-                    .replace('\\', "/");
-                
-                if !name.is_empty() {
-                    map.insert(name, path.to_path_buf());
-                }
+            if let Some(name) = action_name(&dir, path) {
+                map.insert(name, path.to_path_buf());
             }
         }
     }
     
     map
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn route(method: &str, pattern: &str, action: &str) -> DynamicRoute {
+        serde_json::from_value(serde_json::json!({ "method": method, "pattern": pattern, "action": action })).unwrap()
+    }
+
+    #[test]
+    fn duplicate_exact_routes_are_reported_once_with_a_count() {
+        let raw = r#"{ "routes": {
+            "GET:/a": { "type": "action", "value": "a" },
+            "GET:/b": { "type": "action", "value": "b" },
+            "GET:/a": { "type": "action", "value": "a2" },
+            "GET:/a": { "type": "action", "value": "a3" }
+        } }"#;
+        assert_eq!(find_route_conflicts(raw, &[]), vec!["route 'GET:/a' is defined 3 times; the last definition wins"]);
+    }
+
+    #[test]
+    fn param_names_do_not_tell_dynamic_routes_apart() {
+        let routes = [route("GET", "/users/:id", "by_id"), route("GET", "/users/:userId", "by_user_id")];
+        assert_eq!(
+            find_route_conflicts("{}", &routes),
+            vec!["dynamic routes 'GET /users/:id' (→ by_id) and 'GET /users/:userId' (→ by_user_id) overlap; 'GET /users/:id' wins"]
+        );
+    }
+
+    #[test]
+    fn param_types_and_methods_tell_dynamic_routes_apart() {
+        let routes = [
+            route("GET", "/users/:id<number>", "by_number"),
+            route("GET", "/users/:name", "by_name"),
+            route("POST", "/users/:id<number>", "update"),
+        ];
+        assert!(find_route_conflicts("{}", &routes).is_empty());
+    }
+
+    #[test]
+    fn extra_methods_take_part_in_overlap() {
+        let mut multi = route("GET", "/items/:id", "items");
+        multi.methods = vec!["POST".to_string()];
+        let routes = [multi, route("post", "/items/:key", "create")];
+        assert_eq!(find_route_conflicts("{}", &routes).len(), 1);
+    }
+
+    #[test]
+    fn unreadable_routes_json_only_skips_the_exact_check() {
+        let routes = [route("GET", "/files/:path*", "a"), route("GET", "/files/:rest*", "b")];
+        assert_eq!(find_route_conflicts("not json", &routes).len(), 1);
+    }
+}
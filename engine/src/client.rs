@@ -0,0 +1,53 @@
+//! Client Address Resolution
+//!
+//! `req.ip` / `req.protocol` and access logs use the TCP peer address. Behind
+//! a reverse proxy that address is the proxy's, so with `__config.trustProxy`
+//! the `X-Forwarded-For` / `X-Forwarded-Proto` entries are used instead.
+//!
+//! Each proxy appends the address it received the request from, so only the
+//! right-hand entries were written by proxies we trust; anything to their
+//! left came from the client. `trustProxy: true` trusts one proxy and takes
+//! the right-most entry; a number `n` trusts `n` proxies and takes the entry
+//! `n` from the right.
+
+use axum::extract::ConnectInfo;
+use axum::http::{Extensions, HeaderMap};
+use gravity::ClientInfo;
+use std::net::{IpAddr, SocketAddr};
+
+/// `__config.trustProxy` as a number of trusted proxy hops; 0 when off.
+pub fn trusted_hops(value: &serde_json::Value) -> usize {
+    match value {
+        serde_json::Value::Bool(true) => 1,
+        serde_json::Value::Number(n) => n.as_u64().unwrap_or(0) as usize,
+        _ => 0,
+    }
+}
+
+/// `trusted_hops` proxies in front of us; 0 uses the peer address only.
+pub fn resolve(headers: &HeaderMap, extensions: &Extensions, trusted_hops: usize) -> ClientInfo {
+    let peer = extensions.get::<ConnectInfo<SocketAddr>>().map(|ConnectInfo(addr)| addr.ip());
+    if trusted_hops == 0 {
+        return ClientInfo { ip: peer, https: false };
+    }
+
+    let forwarded_ip = header(headers, "x-forwarded-for")
+        .and_then(|v| from_right(v, trusted_hops))
+        .and_then(|ip| ip.parse::<IpAddr>().ok());
+    let https = header(headers, "x-forwarded-proto")
+        .and_then(|v| from_right(v, trusted_hops))
+        .is_some_and(|proto| proto.eq_ignore_ascii_case("https"));
+
+    ClientInfo { ip: forwarded_ip.or(peer), https }
+}
+
+/// The entry `hops` from the right of a comma-separated list, or the
+/// left-most one when the list is shorter.
+fn from_right(list: &str, hops: usize) -> Option<&str> {
+    let entries: Vec<&str> = list.split(',').map(str::trim).collect();
+    entries.get(entries.len().saturating_sub(hops)).copied()
+}
+
+fn header<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers.get(name).and_then(|v| v.to_str().ok())
+}
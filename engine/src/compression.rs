@@ -0,0 +1,273 @@
+//! Response Compression
+//!
+//! Enabled by `__config.compression` in routes.json — `true` for the
+//! defaults, or `{ "threshold": 1024, "codecs": ["br", "gzip"] }`, plus
+//! `level: { br, gzip }` and a `types` allowlist of content types.
+//!
+//! Action responses are compressed per request when the client's
+//! `Accept-Encoding` allows it and the body is at least `threshold` bytes.
+//! FastPath bodies never change, so they are compressed once at startup and
+//! the hot path only picks the matching variant.
+
+use axum::body::{to_bytes, Body};
+use axum::http::{header, HeaderValue, Response};
+use bytes::Bytes;
+use flate2::write::GzEncoder;
+use http_body::Body as _;
+use serde::Deserialize;
+use serde_json::Value;
+use std::io::Write;
+
+use crate::static_files::accepts;
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+pub enum Codec {
+    #[serde(rename = "br")]
+    Brotli,
+    #[serde(rename = "gzip", alias = "gz")]
+    Gzip,
+}
+
+impl Codec {
+    /// `Content-Encoding` token.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Codec::Brotli => "br",
+            Codec::Gzip => "gzip",
+        }
+    }
+
+    pub fn compress(self, data: &[u8], level: u32) -> Option<Vec<u8>> {
+        match self {
+            Codec::Brotli => {
+                let mut writer = brotli::CompressorWriter::new(Vec::new(), 4096, level.min(11), 22);
+                writer.write_all(data).ok()?;
+                Some(writer.into_inner())
+            }
+            Codec::Gzip => {
+                let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::new(level.min(9)));
+                encoder.write_all(data).ok()?;
+                encoder.finish().ok()
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct CompressionConfig {
+    /// Minimum body size in bytes worth compressing.
+    #[serde(default = "default_threshold", alias = "minBytes", alias = "min_bytes")]
+    pub threshold: usize,
+    /// Codecs in preference order.
+    #[serde(default = "default_codecs")]
+    pub codecs: Vec<Codec>,
+    #[serde(default)]
+    pub level: Levels,
+    /// Content types to compress: exact types, or prefixes ending in `/`
+    /// (`"text/"`). Empty compresses everything not already compressed.
+    #[serde(default)]
+    pub types: Vec<String>,
+}
+
+/// Per-codec compression level. The defaults favour CPU over ratio since
+/// action responses are compressed on every request.
+#[derive(Clone, Debug, Deserialize)]
+pub struct Levels {
+    /// Brotli quality, 0-11.
+    #[serde(default = "default_brotli_level")]
+    pub br: u32,
+    /// Gzip level, 0-9.
+    #[serde(default = "default_gzip_level", alias = "gz")]
+    pub gzip: u32,
+}
+
+impl Default for Levels {
+    fn default() -> Self {
+        Self { br: default_brotli_level(), gzip: default_gzip_level() }
+    }
+}
+
+fn default_brotli_level() -> u32 {
+    5
+}
+
+fn default_gzip_level() -> u32 {
+    6
+}
+
+fn default_threshold() -> usize {
+    1024
+}
+
+fn default_codecs() -> Vec<Codec> {
+    vec![Codec::Brotli, Codec::Gzip]
+}
+
+impl CompressionConfig {
+    /// Parse `__config.compression`: `true` or an options object.
+    pub fn from_config(value: &Value) -> Option<Self> {
+        match value {
+            Value::Bool(true) => serde_json::from_value(Value::Object(Default::default())).ok(),
+            Value::Object(_) => serde_json::from_value(value.clone()).ok(),
+            _ => None,
+        }
+    }
+
+    /// First configured codec the client accepts.
+    pub fn negotiate(&self, accept_encoding: Option<&str>) -> Option<Codec> {
+        self.codecs.iter().copied().find(|c| accepts(accept_encoding, c.as_str()))
+    }
+
+    fn compress(&self, codec: Codec, data: &[u8]) -> Option<Vec<u8>> {
+        let level = match codec {
+            Codec::Brotli => self.level.br,
+            Codec::Gzip => self.level.gzip,
+        };
+        codec.compress(data, level)
+    }
+
+    /// Worth compressing: not an already-compressed media type, and in the
+    /// `types` allowlist when one is configured.
+    fn compresses_type(&self, content_type: Option<&str>) -> bool {
+        let ct = content_type.unwrap_or("").split(';').next().unwrap_or("").trim().to_ascii_lowercase();
+        if is_precompressed_type(&ct) {
+            return false;
+        }
+        self.types.is_empty()
+            || self.types.iter().any(|t| {
+                let t = t.to_ascii_lowercase();
+                if t.ends_with('/') { ct.starts_with(&t) } else { ct == t }
+            })
+    }
+
+    /// Compress a finished response in place of the original. Streaming
+    /// bodies (unknown length, trailers), small bodies, bodies that are
+    /// already encoded and types not worth compressing pass through untouched.
+    pub async fn compress_response(&self, response: Response<Body>, accept_encoding: Option<&str>) -> Response<Body> {
+        let Some(codec) = self.negotiate(accept_encoding) else { return response };
+
+        let headers = response.headers();
+        let status = response.status();
+        let size = response.body().size_hint().exact();
+        let skip = status.is_informational()
+            || status == axum::http::StatusCode::NO_CONTENT
+            || status == axum::http::StatusCode::NOT_MODIFIED
+            || headers.contains_key(header::CONTENT_ENCODING)
+            || headers.contains_key(header::TRAILER)
+            || !self.compresses_type(headers.get(header::CONTENT_TYPE).and_then(|v| v.to_str().ok()))
+            || size.is_none_or(|s| (s as usize) < self.threshold);
+        if skip {
+            return response;
+        }
+
+        let (mut parts, body) = response.into_parts();
+        let Ok(bytes) = to_bytes(body, usize::MAX).await else {
+            return Response::from_parts(parts, Body::empty());
+        };
+        let Some(compressed) = self.compress(codec, &bytes) else {
+            return Response::from_parts(parts, Body::from(bytes));
+        };
+
+        parts.headers.insert(header::CONTENT_ENCODING, HeaderValue::from_static(codec.as_str()));
+        parts.headers.append(header::VARY, HeaderValue::from_static("accept-encoding"));
+        parts.headers.remove(header::CONTENT_LENGTH);
+        Response::from_parts(parts, Body::from(compressed))
+    }
+
+    /// Pre-compressed variants of a fixed body, in preference order. Empty
+    /// when the body is below the threshold or of a type not worth compressing.
+    pub fn variants(&self, body: &Bytes, content_type: &str) -> Vec<(Codec, Bytes)> {
+        if body.len() < self.threshold || !self.compresses_type(Some(content_type)) {
+            return Vec::new();
+        }
+        self.codecs
+            .iter()
+            .filter_map(|&codec| self.compress(codec, body).map(|data| (codec, Bytes::from(data))))
+            .collect()
+    }
+}
+
+/// Media types that are already compressed; re-compressing only costs CPU.
+/// Takes the bare, lowercased type.
+fn is_precompressed_type(ct: &str) -> bool {
+    (ct.starts_with("image/") && ct != "image/svg+xml")
+        || ct.starts_with("video/")
+        || ct.starts_with("audio/")
+        || ct == "text/event-stream"
+        || matches!(
+            ct,
+            "application/zip"
+                | "application/gzip"
+                | "application/x-gzip"
+                | "application/x-brotli"
+                | "application/pdf"
+                | "font/woff"
+                | "font/woff2"
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn json_response(body: String) -> Response<Body> {
+        Response::builder().header(header::CONTENT_TYPE, "application/json").body(Body::from(body)).unwrap()
+    }
+
+    #[test]
+    fn from_config_defaults() {
+        let config = CompressionConfig::from_config(&json!(true)).unwrap();
+        assert_eq!(config.threshold, 1024);
+        assert_eq!(config.codecs, vec![Codec::Brotli, Codec::Gzip]);
+        assert_eq!((config.level.br, config.level.gzip), (5, 6));
+        assert!(config.types.is_empty());
+
+        assert!(CompressionConfig::from_config(&json!(false)).is_none());
+        assert!(CompressionConfig::from_config(&Value::Null).is_none());
+    }
+
+    #[test]
+    fn from_config_options_and_aliases() {
+        let config = CompressionConfig::from_config(&json!({ "minBytes": 64, "codecs": ["gz"], "level": { "gz": 9 }, "types": ["text/"] })).unwrap();
+        assert_eq!(config.threshold, 64);
+        assert_eq!(config.codecs, vec![Codec::Gzip]);
+        assert_eq!(config.level.gzip, 9);
+        assert!(config.compresses_type(Some("text/html; charset=utf-8")));
+        assert!(!config.compresses_type(Some("application/json")));
+    }
+
+    #[test]
+    fn negotiate_follows_codec_order_and_refusals() {
+        let config = CompressionConfig::from_config(&json!(true)).unwrap();
+        assert_eq!(config.negotiate(Some("gzip, br")), Some(Codec::Brotli));
+        assert_eq!(config.negotiate(Some("br;q=0, gzip")), Some(Codec::Gzip));
+        assert_eq!(config.negotiate(Some("identity")), None);
+        assert_eq!(config.negotiate(None), None);
+    }
+
+    #[test]
+    fn already_compressed_types_are_skipped() {
+        let config = CompressionConfig::from_config(&json!(true)).unwrap();
+        assert!(!config.compresses_type(Some("image/png")));
+        assert!(config.compresses_type(Some("image/svg+xml")));
+        assert!(!config.compresses_type(Some("text/event-stream")));
+    }
+
+    #[tokio::test]
+    async fn compresses_large_bodies_only() {
+        let config = CompressionConfig::from_config(&json!(true)).unwrap();
+        let large = format!("[{}]", vec!["1"; 2000].join(","));
+
+        let response = config.compress_response(json_response(large.clone()), Some("gzip")).await;
+        assert_eq!(response.headers()[header::CONTENT_ENCODING], "gzip");
+        assert_eq!(response.headers()[header::VARY], "accept-encoding");
+        let compressed = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let mut decoded = String::new();
+        std::io::Read::read_to_string(&mut flate2::read::GzDecoder::new(&compressed[..]), &mut decoded).unwrap();
+        assert_eq!(decoded, large);
+
+        let small = config.compress_response(json_response("[1]".to_string()), Some("gzip")).await;
+        assert!(!small.headers().contains_key(header::CONTENT_ENCODING));
+    }
+}
@@ -0,0 +1,173 @@
+//! CORS
+//!
+//! Driven by `__config.cors` in routes.json:
+//!
+//! ```json
+//! { "origins": ["https://app.example.com"], "methods": ["GET", "POST"],
+//!   "headers": ["content-type"], "credentials": true, "maxAge": 600 }
+//! ```
+//!
+//! Preflight `OPTIONS` requests are answered with 204 before routing, and
+//! `Access-Control-Allow-*` headers are added to every other response.
+
+use axum::body::Body;
+use axum::http::{header, HeaderMap, HeaderValue, Response, StatusCode};
+use serde::Deserialize;
+
+const DEFAULT_METHODS: &str = "GET, POST, PUT, PATCH, DELETE, OPTIONS";
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum CorsOrigins {
+    /// `"*"` (any origin) or a single origin
+    One(String),
+    List(Vec<String>),
+}
+
+#[derive(Deserialize)]
+pub struct CorsConfig {
+    #[serde(default = "any_origin")]
+    pub origins: CorsOrigins,
+    #[serde(default)]
+    pub methods: Vec<String>,
+    #[serde(default)]
+    pub headers: Vec<String>,
+    #[serde(default)]
+    pub credentials: bool,
+    #[serde(default, alias = "maxAge")]
+    pub max_age: Option<u64>,
+}
+
+fn any_origin() -> CorsOrigins {
+    CorsOrigins::One("*".to_string())
+}
+
+impl CorsConfig {
+    /// Value for `Access-Control-Allow-Origin`, or `None` if the origin is not allowed.
+    /// Credentialed requests can't use `*`, so the request origin is echoed instead.
+    fn allow_origin(&self, origin: Option<&HeaderValue>) -> Option<HeaderValue> {
+        let wildcard = matches!(&self.origins, CorsOrigins::One(o) if o == "*");
+        if wildcard && !self.credentials {
+            return Some(HeaderValue::from_static("*"));
+        }
+        let origin = origin?;
+        let allowed = wildcard
+            || match &self.origins {
+                CorsOrigins::One(o) => o.as_bytes() == origin.as_bytes(),
+                CorsOrigins::List(list) => list.iter().any(|o| o.as_bytes() == origin.as_bytes()),
+            };
+        allowed.then(|| origin.clone())
+    }
+
+    fn apply_common(&self, headers: &mut HeaderMap, origin: Option<&HeaderValue>) -> bool {
+        let Some(allow) = self.allow_origin(origin) else { return false };
+        if allow != "*" {
+            headers.append(header::VARY, HeaderValue::from_static("origin"));
+        }
+        headers.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, allow);
+        if self.credentials {
+            headers.insert(header::ACCESS_CONTROL_ALLOW_CREDENTIALS, HeaderValue::from_static("true"));
+        }
+        true
+    }
+
+    /// Whether this request is a CORS preflight.
+    pub fn is_preflight(method: &str, headers: &HeaderMap) -> bool {
+        method == "OPTIONS" && headers.contains_key(header::ACCESS_CONTROL_REQUEST_METHOD)
+    }
+
+    /// 204 answer to a preflight. Disallowed origins get a bare 204 without
+    /// CORS headers, which the browser treats as a rejection.
+    pub fn preflight(&self, req_headers: &HeaderMap) -> Response<Body> {
+        let mut resp = Response::builder().status(StatusCode::NO_CONTENT).body(Body::empty()).unwrap();
+        let origin = req_headers.get(header::ORIGIN);
+        let headers = resp.headers_mut();
+        if !self.apply_common(headers, origin) {
+            return resp;
+        }
+
+        let methods = if self.methods.is_empty() { DEFAULT_METHODS.to_string() } else { self.methods.join(", ") };
+        if let Ok(v) = HeaderValue::from_str(&methods) {
+            headers.insert(header::ACCESS_CONTROL_ALLOW_METHODS, v);
+        }
+
+        // Without an explicit list, allow whatever the browser asked for
+        let allow_headers = if self.headers.is_empty() {
+            req_headers.get(header::ACCESS_CONTROL_REQUEST_HEADERS).cloned()
+        } else {
+            HeaderValue::from_str(&self.headers.join(", ")).ok()
+        };
+        if let Some(v) = allow_headers {
+            headers.insert(header::ACCESS_CONTROL_ALLOW_HEADERS, v);
+        }
+
+        if let Some(age) = self.max_age {
+            headers.insert(header::ACCESS_CONTROL_MAX_AGE, HeaderValue::from(age));
+        }
+        resp
+    }
+
+    /// Add CORS headers to an actual (non-preflight) response.
+    pub fn apply(&self, resp: &mut Response<Body>, origin: Option<&HeaderValue>) {
+        self.apply_common(resp.headers_mut(), origin);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(json: serde_json::Value) -> CorsConfig {
+        serde_json::from_value(json).unwrap()
+    }
+
+    fn preflight_headers(origin: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::ORIGIN, HeaderValue::from_str(origin).unwrap());
+        headers.insert(header::ACCESS_CONTROL_REQUEST_METHOD, HeaderValue::from_static("POST"));
+        headers.insert(header::ACCESS_CONTROL_REQUEST_HEADERS, HeaderValue::from_static("x-token"));
+        headers
+    }
+
+    #[test]
+    fn defaults_allow_any_origin() {
+        let cors = config(serde_json::json!({}));
+        let resp = cors.preflight(&preflight_headers("https://a.example"));
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+        assert_eq!(resp.headers()[header::ACCESS_CONTROL_ALLOW_ORIGIN], "*");
+        assert_eq!(resp.headers()[header::ACCESS_CONTROL_ALLOW_METHODS], DEFAULT_METHODS);
+        assert_eq!(resp.headers()[header::ACCESS_CONTROL_ALLOW_HEADERS], "x-token");
+        assert!(!resp.headers().contains_key(header::VARY));
+    }
+
+    #[test]
+    fn allowlist_echoes_matching_origins_only() {
+        let cors = config(serde_json::json!({ "origins": ["https://app.example"], "methods": ["GET"], "headers": ["content-type"], "maxAge": 600 }));
+        let resp = cors.preflight(&preflight_headers("https://app.example"));
+        assert_eq!(resp.headers()[header::ACCESS_CONTROL_ALLOW_ORIGIN], "https://app.example");
+        assert_eq!(resp.headers()[header::VARY], "origin");
+        assert_eq!(resp.headers()[header::ACCESS_CONTROL_ALLOW_METHODS], "GET");
+        assert_eq!(resp.headers()[header::ACCESS_CONTROL_ALLOW_HEADERS], "content-type");
+        assert_eq!(resp.headers()[header::ACCESS_CONTROL_MAX_AGE], "600");
+
+        let rejected = cors.preflight(&preflight_headers("https://evil.example"));
+        assert_eq!(rejected.status(), StatusCode::NO_CONTENT);
+        assert!(!rejected.headers().contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN));
+    }
+
+    #[test]
+    fn credentials_echo_the_origin_instead_of_a_wildcard() {
+        let cors = config(serde_json::json!({ "origins": "*", "credentials": true }));
+        let mut resp = Response::new(Body::empty());
+        cors.apply(&mut resp, Some(&HeaderValue::from_static("https://a.example")));
+        assert_eq!(resp.headers()[header::ACCESS_CONTROL_ALLOW_ORIGIN], "https://a.example");
+        assert_eq!(resp.headers()[header::ACCESS_CONTROL_ALLOW_CREDENTIALS], "true");
+    }
+
+    #[test]
+    fn is_preflight_needs_the_request_method_header() {
+        assert!(CorsConfig::is_preflight("OPTIONS", &preflight_headers("https://a.example")));
+        assert!(!CorsConfig::is_preflight("OPTIONS", &HeaderMap::new()));
+        assert!(!CorsConfig::is_preflight("POST", &preflight_headers("https://a.example")));
+    }
+}
@@ -0,0 +1,117 @@
+//! Action Error Responses
+//!
+//! A failed action (it threw, its result couldn't be serialized, or the
+//! worker failed) answers 500. The body follows `Accept`: a client that
+//! prefers `text/plain` over JSON gets the bare message, everyone else the
+//! JSON object (`{ "error": ..., "action"?: ... }`).
+//!
+//! `__config.exposeErrors: false` replaces the message with a generic one in
+//! production, so stack traces and internals don't reach clients; the full
+//! error is still logged by the isolate.
+
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Json, Response},
+};
+use serde_json::Value;
+
+const GENERIC: &str = "Internal Server Error";
+
+pub fn response(result: Value, accept: Option<&str>, expose: bool) -> Response {
+    let result = if expose { result } else { serde_json::json!({ "error": GENERIC }) };
+    if !prefers_text(accept) {
+        return (StatusCode::INTERNAL_SERVER_ERROR, Json(result)).into_response();
+    }
+    let message = match result.get("error") {
+        Some(Value::String(s)) => s.clone(),
+        Some(other) => other.to_string(),
+        None => GENERIC.to_string(),
+    };
+    (StatusCode::INTERNAL_SERVER_ERROR, [(axum::http::header::CONTENT_TYPE, "text/plain; charset=utf-8")], message).into_response()
+}
+
+/// Whether `Accept` ranks `text/plain` above `application/json`. No header,
+/// or a tie, keeps JSON.
+fn prefers_text(accept: Option<&str>) -> bool {
+    let Some(accept) = accept else { return false };
+    quality(accept, "text", "plain") > quality(accept, "application", "json")
+}
+
+/// `q` of the most specific range in `accept` that covers `kind/sub`
+/// (`kind/sub` over `kind/*` over `*/*`, as RFC 9110 ranks them); 0 when
+/// none does.
+fn quality(accept: &str, kind: &str, sub: &str) -> f32 {
+    accept
+        .split(',')
+        .filter_map(|range| {
+            let mut pieces = range.split(';');
+            let (k, s) = pieces.next()?.trim().split_once('/')?;
+            let specificity = match (k, s) {
+                ("*", "*") => 0,
+                (k, "*") if k.eq_ignore_ascii_case(kind) => 1,
+                (k, s) if k.eq_ignore_ascii_case(kind) && s.eq_ignore_ascii_case(sub) => 2,
+                _ => return None,
+            };
+            let q = pieces
+                .find_map(|p| p.trim().strip_prefix("q=").and_then(|q| q.trim().parse::<f32>().ok()))
+                .unwrap_or(1.0);
+            Some((specificity, q))
+        })
+        .fold(None, |best: Option<(u8, f32)>, (specificity, q)| match best {
+            Some((b, bq)) if b > specificity || (b == specificity && bq >= q) => best,
+            _ => Some((specificity, q)),
+        })
+        .map_or(0.0, |(_, q)| q)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http_body_util::BodyExt;
+
+    async fn body(response: Response) -> String {
+        String::from_utf8(response.into_body().collect().await.unwrap().to_bytes().to_vec()).unwrap()
+    }
+
+    #[test]
+    fn prefers_text_only_when_ranked_higher() {
+        assert!(!prefers_text(None));
+        assert!(!prefers_text(Some("application/json")));
+        assert!(!prefers_text(Some("*/*")));
+        assert!(!prefers_text(Some("text/plain, application/json")));
+        assert!(prefers_text(Some("text/plain")));
+        assert!(prefers_text(Some("text/*, application/json;q=0.5")));
+        assert!(prefers_text(Some("application/json;q=0.2, TEXT/PLAIN;q=0.9")));
+        assert!(!prefers_text(Some("text/html")));
+    }
+
+    #[test]
+    fn most_specific_range_sets_the_quality() {
+        // text/plain;q=0.1 outranks the text/* that would otherwise win
+        assert!(!prefers_text(Some("text/*;q=0.9, text/plain;q=0.1, application/json;q=0.5")));
+        assert!(prefers_text(Some("*/*, application/json;q=0.1")));
+        assert!(!prefers_text(Some("*/*;q=0.8, text/plain;q=0")));
+        assert_eq!(quality("text/*;q=0.3, */*", "text", "plain"), 0.3);
+        assert_eq!(quality("application/json", "text", "plain"), 0.0);
+    }
+
+    #[tokio::test]
+    async fn json_by_default() {
+        let response = response(serde_json::json!({ "error": "boom", "action": "a" }), None, true);
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(serde_json::from_str::<Value>(&body(response).await).unwrap(), serde_json::json!({ "error": "boom", "action": "a" }));
+    }
+
+    #[tokio::test]
+    async fn plain_text_when_preferred() {
+        let response = response(serde_json::json!({ "error": "boom" }), Some("text/plain"), true);
+        assert_eq!(response.headers()[axum::http::header::CONTENT_TYPE], "text/plain; charset=utf-8");
+        assert_eq!(body(response).await, "boom");
+    }
+
+    #[tokio::test]
+    async fn hidden_errors_are_generic() {
+        let response = response(serde_json::json!({ "error": "at secret.js:1" }), None, false);
+        assert_eq!(body(response).await, r#"{"error":"Internal Server Error"}"#);
+    }
+}
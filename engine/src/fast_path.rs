@@ -14,7 +14,9 @@
 //! Dependencies:
 //! Requires `oxc` crate with "semantic" feature.
 
+use axum::http::HeaderValue;
 use bytes::Bytes;
+use serde::Deserialize;
 use std::collections::HashMap;
 use std::fs;
 use std::path::Path;
@@ -26,6 +28,8 @@ use oxc::parser::Parser;
 use oxc::semantic::SemanticBuilder;
 use oxc::span::SourceType;
 
+use crate::compression::{Codec, CompressionConfig};
+
 /// A pre-computed HTTP response for a static action.
 #[derive(Clone, Debug)]
 pub struct StaticResponse {
@@ -33,6 +37,10 @@ pub struct StaticResponse {
     pub content_type: &'static str,
     pub status: u16,
     pub extra_headers: Vec<(String, String)>,
+    /// Compressed copies of `body` built at startup, in preference order.
+    pub encoded: Vec<(Codec, Bytes)>,
+    /// Weak ETag of `body`, set by `enable_etags` for opted-in JSON actions.
+    pub etag: Option<HeaderValue>,
 }
 
 impl PartialEq for StaticResponse {
@@ -52,7 +60,7 @@ struct ResponseOptions {
 }
 
 /// Registry of actions that have been detected as static.
-#[derive(Clone)]
+#[derive(Clone, Default)]
 pub struct FastPathRegistry {
     actions: HashMap<String, StaticResponse>,
 }
@@ -126,6 +134,26 @@ impl FastPathRegistry {
         self.actions.get(action_name)
     }
 
+    /// Pre-compress every static body once so requests only pick a variant.
+    pub fn precompress(&mut self, config: &CompressionConfig) {
+        for resp in self.actions.values_mut() {
+            if resp.extra_headers.iter().any(|(k, _)| k.eq_ignore_ascii_case("content-encoding")) {
+                continue;
+            }
+            resp.encoded = config.variants(&resp.body, resp.content_type);
+        }
+    }
+
+    /// Bake a weak ETag into every 200 JSON response whose action opted in
+    /// (`__config.etag` or the route's `etag` option).
+    pub fn enable_etags(&mut self, wants: impl Fn(&str) -> bool) {
+        for (name, resp) in self.actions.iter_mut() {
+            if resp.status == 200 && resp.content_type.starts_with("application/json") && wants(name) {
+                resp.etag = HeaderValue::from_str(&weak_etag(&resp.body)).ok();
+            }
+        }
+    }
+
     /// Number of registered fast-path actions.
     pub fn len(&self) -> usize {
         self.actions.len()
@@ -134,13 +162,44 @@ impl FastPathRegistry {
 
 impl StaticResponse {
     /// Convert to an Axum response. Uses Bytes::clone() which is O(1) ref-count bump.
+    /// Serves the first pre-compressed variant the client accepts, if any, and
+    /// answers 304 when `If-None-Match` matches the baked ETag.
     #[inline(always)]
-    pub fn to_axum_response(&self) -> axum::response::Response<axum::body::Body> {
+    pub fn to_axum_response(
+        &self,
+        accept_encoding: Option<&str>,
+        if_none_match: Option<&HeaderValue>,
+    ) -> axum::response::Response<axum::body::Body> {
         let mut builder = axum::response::Response::builder()
-            .status(self.status)
-            .header("content-type", self.content_type)
             .header("server", "TitanPL");
 
+        if let Some(etag) = &self.etag {
+            builder = builder.header("etag", etag);
+            if if_none_match.map_or(false, |inm| etag_matches(inm, etag)) {
+                return builder
+                    .status(304u16)
+                    .body(axum::body::Body::empty())
+                    .unwrap();
+            }
+        }
+
+        builder = builder
+            .status(self.status)
+            .header("content-type", self.content_type);
+
+        let mut body = &self.body;
+        if !self.encoded.is_empty() {
+            builder = builder.header("vary", "accept-encoding");
+            if let Some((codec, data)) = self
+                .encoded
+                .iter()
+                .find(|(codec, _)| crate::static_files::accepts(accept_encoding, codec.as_str()))
+            {
+                builder = builder.header("content-encoding", codec.as_str());
+                body = data;
+            }
+        }
+
         for (key, val) in &self.extra_headers {
             let lower = key.to_lowercase();
             if lower == "content-type" || lower == "server" {
@@ -150,16 +209,50 @@ impl StaticResponse {
         }
 
         builder
-            .body(axum::body::Body::from(self.body.clone()))
+            .body(axum::body::Body::from(body.clone()))
             .unwrap()
     }
 }
 
+/// Caching policy for reply routes, from a route's `cache` option or
+/// `__config.replyCache` (global default).
+///
+/// Accepts `60` (max-age seconds), `"public, max-age=60"` (raw header),
+/// or `{ maxAge, cacheControl }`.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(untagged)]
+pub enum ReplyCache {
+    MaxAge(u64),
+    Header(String),
+    Options {
+        #[serde(default, alias = "maxAge")]
+        max_age: Option<u64>,
+        #[serde(default, alias = "cacheControl")]
+        cache_control: Option<String>,
+    },
+}
+
+impl ReplyCache {
+    /// Resolve to a Cache-Control header value.
+    pub fn cache_control(&self) -> Option<String> {
+        match self {
+            ReplyCache::MaxAge(secs) => Some(format!("public, max-age={}", secs)),
+            ReplyCache::Header(h) => Some(h.clone()),
+            ReplyCache::Options { max_age, cache_control } => cache_control
+                .clone()
+                .or_else(|| max_age.map(|secs| format!("public, max-age={}", secs))),
+        }
+    }
+}
+
 /// A pre-computed response for static reply routes (t.get("/").reply("ok")).
 #[derive(Clone, Debug)]
 pub struct PrecomputedRoute {
     pub body: Bytes,
-    pub content_type: &'static str,
+    pub content_type: HeaderValue,
+    /// Baked at startup when caching is configured for this route
+    pub cache_control: Option<HeaderValue>,
+    pub etag: Option<HeaderValue>,
 }
 
 impl PrecomputedRoute {
@@ -168,7 +261,9 @@ impl PrecomputedRoute {
         let body = serde_json::to_vec(val).unwrap_or_default();
         Self {
             body: Bytes::from(body),
-            content_type: "application/json",
+            content_type: HeaderValue::from_static("application/json"),
+            cache_control: None,
+            etag: None,
         }
     }
 
@@ -176,22 +271,89 @@ impl PrecomputedRoute {
     pub fn from_text(text: &str) -> Self {
         Self {
             body: Bytes::from(text.to_string()),
-            content_type: "text/plain; charset=utf-8",
+            content_type: HeaderValue::from_static("text/plain; charset=utf-8"),
+            cache_control: None,
+            etag: None,
         }
     }
 
+    /// Replace the default content type (routes.json `contentType`).
+    /// Values that are not valid header values keep the default; startup
+    /// warns about them.
+    pub fn with_content_type(mut self, content_type: Option<&str>) -> Self {
+        if let Some(value) = content_type.and_then(|ct| HeaderValue::from_str(ct).ok()) {
+            self.content_type = value;
+        }
+        self
+    }
+
+    /// Attach a Cache-Control header and a content-derived ETag.
+    /// The ETag is a hash of the body, so it is stable across restarts.
+    pub fn with_cache(mut self, cache: Option<&ReplyCache>) -> Self {
+        if let Some(cc) = cache.and_then(|c| c.cache_control()) {
+            self.cache_control = HeaderValue::from_str(&cc).ok();
+            self.etag = HeaderValue::from_str(&body_etag(&self.body)).ok();
+        }
+        self
+    }
+
     /// Convert to Axum response. O(1) body clone via Bytes refcount.
+    /// Answers 304 when `If-None-Match` matches the baked ETag.
     #[inline(always)]
-    pub fn to_axum_response(&self) -> axum::response::Response<axum::body::Body> {
-        axum::response::Response::builder()
+    pub fn to_axum_response(
+        &self,
+        if_none_match: Option<&HeaderValue>,
+    ) -> axum::response::Response<axum::body::Body> {
+        let mut builder = axum::response::Response::builder()
+            .header("server", "TitanPL");
+
+        if let Some(cc) = &self.cache_control {
+            builder = builder.header("cache-control", cc);
+        }
+
+        if let Some(etag) = &self.etag {
+            builder = builder.header("etag", etag);
+            if if_none_match.map_or(false, |inm| etag_matches(inm, etag)) {
+                return builder
+                    .status(304u16)
+                    .body(axum::body::Body::empty())
+                    .unwrap();
+            }
+        }
+
+        builder
             .status(200u16)
-            .header("content-type", self.content_type)
-            .header("server", "TitanPL")
+            .header("content-type", &self.content_type)
             .body(axum::body::Body::from(self.body.clone()))
             .unwrap()
     }
 }
 
+/// Strong ETag derived from the response body: 64-bit FNV-1a, which unlike
+/// `DefaultHasher` is fixed, so tags survive restarts, upgrades and agree
+/// across instances behind a load balancer.
+pub fn body_etag(body: &[u8]) -> String {
+    let hash = body.iter().fold(0xcbf2_9ce4_8422_2325_u64, |hash, &byte| (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01b3));
+    format!("\"{:016x}\"", hash)
+}
+
+/// Weak ETag for bodies that are semantically, not byte-for-byte, stable
+/// (serialized action results, served under any content encoding).
+pub fn weak_etag(body: &[u8]) -> String {
+    format!("W/{}", body_etag(body))
+}
+
+/// Check an `If-None-Match` header (`*`, a single tag, or a list) against an ETag.
+/// Uses weak comparison, as RFC 9110 requires for `If-None-Match`.
+pub fn etag_matches(if_none_match: &HeaderValue, etag: &HeaderValue) -> bool {
+    let Ok(inm) = if_none_match.to_str() else { return false };
+    let Ok(tag) = etag.to_str() else { return false };
+    let tag = tag.trim_start_matches("W/");
+    inm.split(',')
+        .map(|t| t.trim())
+        .any(|t| t == "*" || t.trim_start_matches("W/") == tag)
+}
+
 /// Maximum recursion depth for static expression evaluation.
 const MAX_EVAL_DEPTH: usize = 16;
 
@@ -282,11 +444,16 @@ fn analyze_response_call<'a>(
         arg => arg.as_expression().unwrap(),
     };
 
-    // Second argument: options { headers: {...}, status: N } (optional)
+    // Second argument: options { headers: {...}, status: N } or a bare status,
+    // third: headers for the positional (status, headers) form (optional)
     let opts_expr = call.arguments.get(1).and_then(|arg| match arg {
         Argument::SpreadElement(_) => None,
         arg => arg.as_expression(),
     });
+    let headers_expr = call.arguments.get(2).and_then(|arg| match arg {
+        Argument::SpreadElement(_) => None,
+        arg => arg.as_expression(),
+    });
 
     // Evaluate the body statically
     let body_value = match eval_static(body_expr, semantic, 0) {
@@ -299,8 +466,16 @@ fn analyze_response_call<'a>(
 
     // Evaluate options if present
     let options = if let Some(opts) = opts_expr {
+        let third = match headers_expr.map(|h| eval_static(h, semantic, 0)) {
+            Some(Some(v)) => Some(v),
+            Some(None) => {
+                *has_dynamic = true;
+                return;
+            }
+            None => None,
+        };
         match eval_static(opts, semantic, 0) {
-            Some(v) => extract_response_options(&v),
+            Some(v) => extract_response_options(&v, third.as_ref()),
             None => {
                 *has_dynamic = true;
                 return;
@@ -353,6 +528,8 @@ fn analyze_response_call<'a>(
         content_type,
         status: options.status,
         extra_headers: options.headers,
+        encoded: Vec::new(),
+        etag: None,
     });
 }
 
@@ -410,7 +587,13 @@ fn eval_static<'a>(
                         let val = eval_static(&p.value, semantic, depth + 1)?;
                         map.insert(key, val);
                     }
-                    ObjectPropertyKind::SpreadProperty(_) => return None,
+                    // `{ ...base, extra }` with a static object source; later keys win
+                    ObjectPropertyKind::SpreadProperty(spread) => {
+                        match eval_static(&spread.argument, semantic, depth + 1)? {
+                            Value::Object(source) => map.extend(source),
+                            _ => return None,
+                        }
+                    }
                 }
             }
             Some(Value::Object(map))
@@ -422,7 +605,13 @@ fn eval_static<'a>(
 
             for elem in &arr.elements {
                 match elem {
-                    ArrayExpressionElement::SpreadElement(_) => return None,
+                    // `[...[1, 2], 3]`: splice in a static array source; other iterables stay dynamic
+                    ArrayExpressionElement::SpreadElement(spread) => {
+                        match eval_static(&spread.argument, semantic, depth + 1)? {
+                            Value::Array(items) => vec.extend(items),
+                            _ => return None,
+                        }
+                    }
                     ArrayExpressionElement::Elision(_) => {
                         vec.push(Value::Null); // holes become null in JSON
                     }
@@ -524,6 +713,86 @@ fn eval_static<'a>(
             eval_static(&paren.expression, semantic, depth)
         }
 
+        // Math constants (Math.PI, Math.E, ...)
+        Expression::StaticMemberExpression(member) => {
+            if !is_global(&member.object, "Math", semantic) {
+                return None;
+            }
+            math_constant(member.property.name.as_str()).and_then(number_to_json)
+        }
+
+        // Whitelisted pure Math calls with static numeric arguments, and
+        // Object.freeze(x), which returns x and can only make it more constant
+        Expression::CallExpression(call) => {
+            let Expression::StaticMemberExpression(callee) = &call.callee else {
+                return None;
+            };
+            if call.optional {
+                return None;
+            }
+            if is_global(&callee.object, "Object", semantic) {
+                return match (callee.property.name.as_str(), &call.arguments[..]) {
+                    ("freeze", [arg]) => eval_static(arg.as_expression()?, semantic, depth + 1),
+                    _ => None,
+                };
+            }
+            if !is_global(&callee.object, "Math", semantic) {
+                return None;
+            }
+            let mut args = Vec::with_capacity(call.arguments.len());
+            for arg in &call.arguments {
+                match eval_static(arg.as_expression()?, semantic, depth + 1)? {
+                    Value::Number(n) => args.push(n.as_f64()?),
+                    _ => return None,
+                }
+            }
+            math_call(callee.property.name.as_str(), &args).and_then(number_to_json)
+        }
+
+        _ => None,
+    }
+}
+
+/// Whether `expr` names the global `name` (no local binding shadows it).
+fn is_global(expr: &Expression<'_>, name: &str, semantic: &oxc::semantic::Semantic<'_>) -> bool {
+    let Expression::Identifier(ident) = expr else {
+        return false;
+    };
+    if ident.name.as_str() != name {
+        return false;
+    }
+    ident
+        .reference_id
+        .get()
+        .is_some_and(|ref_id| semantic.scoping().get_reference(ref_id).symbol_id().is_none())
+}
+
+fn math_constant(name: &str) -> Option<f64> {
+    use std::f64::consts;
+    Some(match name {
+        "PI" => consts::PI,
+        "E" => consts::E,
+        "LN2" => consts::LN_2,
+        "LN10" => consts::LN_10,
+        "LOG2E" => consts::LOG2_E,
+        "LOG10E" => consts::LOG10_E,
+        "SQRT2" => consts::SQRT_2,
+        "SQRT1_2" => consts::FRAC_1_SQRT_2,
+        _ => return None,
+    })
+}
+
+/// Pure Math functions whose Rust equivalents match JS exactly. Anything else
+/// (Math.random, Math.round's half-up rule, ...) stays dynamic.
+fn math_call(name: &str, args: &[f64]) -> Option<f64> {
+    match (name, args) {
+        ("abs", [x, ..]) => Some(x.abs()),
+        ("floor", [x, ..]) => Some(x.floor()),
+        ("ceil", [x, ..]) => Some(x.ceil()),
+        ("trunc", [x, ..]) => Some(x.trunc()),
+        // Empty max/min are -Infinity/Infinity, which number_to_json rejects
+        ("max", _) => args.iter().copied().reduce(f64::max),
+        ("min", _) => args.iter().copied().reduce(f64::min),
         _ => None,
     }
 }
@@ -686,12 +955,29 @@ fn number_to_json(v: f64) -> Option<serde_json::Value> {
 }
 
 /// Extract ResponseOptions (status + headers) from a serde_json::Value.
-fn extract_response_options(val: &serde_json::Value) -> ResponseOptions {
+fn extract_response_options(val: &serde_json::Value, third: Option<&serde_json::Value>) -> ResponseOptions {
     let mut opts = ResponseOptions {
         status: 200,
         headers: Vec::new(),
     };
 
+    // The third argument adds headers in both forms, as in the runtime.
+    if let Some(h_obj) = third.and_then(|h| h.as_object()) {
+        for (key, val) in h_obj {
+            if let Some(v_str) = val.as_str() {
+                opts.headers.push((key.clone(), v_str.to_string()));
+            }
+        }
+    }
+
+    // Positional form: t.response.html(page, 503, { ... })
+    if let Some(n) = val.as_u64() {
+        if n >= 100 && n <= 599 {
+            opts.status = n as u16;
+        }
+        return opts;
+    }
+
     let obj = match val.as_object() {
         Some(o) => o,
         None => return opts,
@@ -715,5 +1001,56 @@ fn extract_response_options(val: &serde_json::Value) -> ResponseOptions {
         }
     }
 
+    // `retryAfter`: delay in seconds or an HTTP date; an explicit header wins.
+    let retry_after = match obj.get("retryAfter") {
+        Some(serde_json::Value::Number(n)) => n.as_u64().map(|s| s.to_string()),
+        Some(serde_json::Value::String(s)) if !s.is_empty() => Some(s.clone()),
+        _ => None,
+    };
+    if let Some(value) = retry_after {
+        if !opts.headers.iter().any(|(k, _)| k.eq_ignore_ascii_case("retry-after")) {
+            opts.headers.push(("Retry-After".to_string(), value));
+        }
+    }
+
     opts
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(if_none_match: &str, etag: &str) -> bool {
+        etag_matches(&HeaderValue::from_str(if_none_match).unwrap(), &HeaderValue::from_str(etag).unwrap())
+    }
+
+    #[test]
+    fn body_etag_is_stable_and_quoted() {
+        let etag = body_etag(b"{\"ok\":true}");
+        assert_eq!(etag, body_etag(b"{\"ok\":true}"));
+        assert_ne!(etag, body_etag(b"{\"ok\":false}"));
+        assert!(etag.starts_with('"') && etag.ends_with('"') && etag.len() == 18);
+        // FNV-1a test vectors: the tag must not change between builds
+        assert_eq!(body_etag(b""), "\"cbf29ce484222325\"");
+        assert_eq!(body_etag(b"a"), "\"af63dc4c8601ec8c\"");
+    }
+
+    #[test]
+    fn etag_matches_lists_wildcards_and_weak_tags() {
+        assert!(matches("\"abc\"", "\"abc\""));
+        assert!(matches("\"x\", \"abc\"", "\"abc\""));
+        assert!(matches("*", "\"abc\""));
+        assert!(matches("W/\"abc\"", "\"abc\""));
+        assert!(!matches("\"abd\"", "\"abc\""));
+        assert!(!matches("", "\"abc\""));
+    }
+
+    #[test]
+    fn weak_etag_matches_its_strong_form_both_ways() {
+        let weak = weak_etag(b"[1,2,3]");
+        assert_eq!(weak, format!("W/{}", body_etag(b"[1,2,3]")));
+        assert!(matches(&weak, &weak));
+        assert!(matches(&body_etag(b"[1,2,3]"), &weak));
+        assert!(!matches(&weak_etag(b"[1,2]"), &weak));
+    }
+}
@@ -0,0 +1,77 @@
+//! Dev Hot Reload
+//!
+//! With `__config.dev: true` the actions directory is watched and a rebuilt
+//! bundle is recompiled into every isolate through
+//! `RuntimeManager::reload_action`, so editing an action no longer needs a
+//! server restart. Workers keep the old version if the new one fails to
+//! compile, and hold a reload back while a drifted request still runs it.
+
+use notify::{EventKind, RecursiveMode, Watcher};
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::mpsc;
+use std::time::Duration;
+
+use gravity::RuntimeManager;
+use gravity::utils::LogLevel;
+
+use crate::action_management::action_name;
+
+/// Bundlers write a file in several steps; wait for the burst to settle.
+const DEBOUNCE: Duration = Duration::from_millis(100);
+
+pub fn watch(dir: PathBuf, runtime: Arc<RuntimeManager>) {
+    let spawned = std::thread::Builder::new()
+        .name("titan-hot-reload".to_string())
+        .spawn(move || {
+            let (tx, rx) = mpsc::channel();
+            let mut watcher = match notify::recommended_watcher(tx) {
+                Ok(w) => w,
+                Err(e) => return log(LogLevel::Warn, &format!("hot reload disabled: {}", e)),
+            };
+            if let Err(e) = watcher.watch(&dir, RecursiveMode::Recursive) {
+                return log(LogLevel::Warn, &format!("hot reload disabled, cannot watch {}: {}", dir.display(), e));
+            }
+            log(LogLevel::Info, &format!("watching {} for action changes", dir.display()));
+
+            while let Ok(event) = rx.recv() {
+                let mut changed = BTreeSet::new();
+                collect(event, &mut changed);
+                while let Ok(event) = rx.recv_timeout(DEBOUNCE) {
+                    collect(event, &mut changed);
+                }
+                for path in changed {
+                    reload(&dir, &path, &runtime);
+                }
+            }
+        });
+    if let Err(e) = spawned {
+        log(LogLevel::Warn, &format!("hot reload disabled: {}", e));
+    }
+}
+
+fn collect(event: notify::Result<notify::Event>, changed: &mut BTreeSet<PathBuf>) {
+    if let Ok(event) = event {
+        if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+            changed.extend(event.paths);
+        }
+    }
+}
+
+fn reload(dir: &Path, path: &Path, runtime: &RuntimeManager) {
+    let Some(name) = action_name(dir, path) else { return };
+    // Removed files and half-written (truncated) bundles keep the loaded version
+    match std::fs::read_to_string(path) {
+        Ok(source) if !source.trim().is_empty() => {
+            runtime.reload_action(name, source);
+            // `__config.cache` entries were rendered by the old version
+            gravity::extensions::response_cache().clear();
+        }
+        _ => {}
+    }
+}
+
+fn log(level: LogLevel, message: &str) {
+    gravity::utils::log(level, "hot_reload", serde_json::json!({ "message": message }));
+}
@@ -0,0 +1,115 @@
+//! Bind Address
+//!
+//! `__config.host` (default `0.0.0.0`), overridden by the `TITAN_HOST`
+//! environment variable, picks the interface the server listens on:
+//! `127.0.0.1` keeps it off the network, `::` listens on every IPv6 (and,
+//! depending on the OS, IPv4) interface. `unix:/tmp/titan.sock` listens on a
+//! Unix domain socket instead of TCP, for a reverse proxy on the same host;
+//! the port is ignored then.
+
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::path::PathBuf;
+
+pub enum BindAddr {
+    Tcp(SocketAddr),
+    Unix(PathBuf),
+}
+
+impl BindAddr {
+    /// Validate `host` and `port` up front, so a typo stops startup with a
+    /// message naming the bad value instead of a bare bind error.
+    pub fn parse(host: &str, port: u64) -> Result<Self, String> {
+        let host = host.trim();
+        if let Some(path) = host.strip_prefix("unix:") {
+            if path.is_empty() {
+                return Err("invalid host \"unix:\": expected a socket path, e.g. unix:/tmp/titan.sock".to_string());
+            }
+            if cfg!(not(unix)) {
+                return Err(format!("invalid host \"{}\": Unix sockets are not supported on this platform", host));
+            }
+            return Ok(BindAddr::Unix(PathBuf::from(path)));
+        }
+
+        let port = u16::try_from(port).map_err(|_| format!("invalid port {}: must be between 0 and 65535", port))?;
+        let ip = match host {
+            "" => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+            "localhost" => IpAddr::V4(Ipv4Addr::LOCALHOST),
+            _ => host
+                .trim_start_matches('[')
+                .trim_end_matches(']')
+                .parse()
+                .map_err(|_| format!("invalid host \"{}\": expected an IP address (0.0.0.0, 127.0.0.1, ::1) or unix:/path/to.sock", host))?,
+        };
+        Ok(BindAddr::Tcp(SocketAddr::new(ip, port)))
+    }
+
+    /// Where to point a browser or proxy at. Unspecified addresses print as
+    /// `localhost`, which is what the listener is reachable under locally.
+    pub fn url(&self) -> String {
+        match self {
+            BindAddr::Tcp(addr) if addr.ip().is_unspecified() => format!("http://localhost:{}", addr.port()),
+            BindAddr::Tcp(addr) => format!("http://{}", addr),
+            BindAddr::Unix(path) => format!("unix:{}", path.display()),
+        }
+    }
+}
+
+impl fmt::Display for BindAddr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BindAddr::Tcp(addr) => write!(f, "{}", addr),
+            BindAddr::Unix(path) => write!(f, "unix:{}", path.display()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tcp(host: &str, port: u64) -> SocketAddr {
+        match BindAddr::parse(host, port) {
+            Ok(BindAddr::Tcp(addr)) => addr,
+            Ok(other) => panic!("{host} parsed as {other}"),
+            Err(e) => panic!("{host}: {e}"),
+        }
+    }
+
+    fn error(host: &str, port: u64) -> String {
+        match BindAddr::parse(host, port) {
+            Ok(addr) => panic!("{host} parsed as {addr}"),
+            Err(e) => e,
+        }
+    }
+
+    #[test]
+    fn parses_ip_hosts() {
+        assert_eq!(tcp("", 3000).to_string(), "0.0.0.0:3000");
+        assert_eq!(tcp(" 127.0.0.1 ", 8080).to_string(), "127.0.0.1:8080");
+        assert_eq!(tcp("localhost", 80).to_string(), "127.0.0.1:80");
+        assert_eq!(tcp("::", 3000).to_string(), "[::]:3000");
+        assert_eq!(tcp("[::1]", 3000).to_string(), "[::1]:3000");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn parses_unix_sockets() {
+        let addr = BindAddr::parse("unix:/tmp/titan.sock", 3000).unwrap_or_else(|e| panic!("{e}"));
+        assert!(matches!(&addr, BindAddr::Unix(path) if path == std::path::Path::new("/tmp/titan.sock")));
+        assert_eq!(addr.url(), "unix:/tmp/titan.sock");
+    }
+
+    #[test]
+    fn rejects_bad_values() {
+        assert!(error("unix:", 3000).contains("expected a socket path"));
+        assert_eq!(error("0.0.0.0", 70000), "invalid port 70000: must be between 0 and 65535");
+        assert!(error("my-host", 3000).starts_with("invalid host \"my-host\""));
+    }
+
+    #[test]
+    fn url_names_unspecified_hosts_localhost() {
+        assert_eq!(BindAddr::Tcp(tcp("0.0.0.0", 3000)).url(), "http://localhost:3000");
+        assert_eq!(BindAddr::Tcp(tcp("127.0.0.1", 3000)).url(), "http://127.0.0.1:3000");
+    }
+}
@@ -0,0 +1,306 @@
+//! Request Logging
+//!
+//! Every per-request log line goes through `RequestLog::emit`, so the output
+//! format is decided in one place: colored text for local dev, or one JSON
+//! object per line when `__config.logFormat` is `"json"`. Lines below the
+//! `TITAN_LOG_LEVEL` threshold are dropped before anything is formatted.
+//!
+//! `"common"` / `"combined"` replace those lines with Apache-style access
+//! logs (`AccessLog`), written for every request in dev and production alike
+//! so GoAccess, AWStats and similar tools can read them.
+
+use chrono::{DateTime, Local};
+use gravity::utils::{blue, gray, green, log_enabled, log_with, red, white, yellow, LogLevel};
+use serde_json::json;
+use std::time::Duration;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AccessLogFormat {
+    /// `ip - - [time] "request" status bytes`
+    Common,
+    /// Common plus `"referer" "user-agent"`
+    Combined,
+}
+
+impl AccessLogFormat {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "common" => Some(Self::Common),
+            "combined" => Some(Self::Combined),
+            _ => None,
+        }
+    }
+}
+
+/// One access log line. Missing values are written as `-`, as CLF expects.
+pub struct AccessLog<'a> {
+    pub ip: Option<std::net::IpAddr>,
+    pub time: DateTime<Local>,
+    pub method: &'a str,
+    /// Path plus query string, as requested
+    pub target: &'a str,
+    pub version: &'a str,
+    pub status: u16,
+    /// Body size when known up front; streamed bodies log `-`
+    pub bytes: Option<u64>,
+    pub referer: Option<&'a str>,
+    pub user_agent: Option<&'a str>,
+}
+
+impl AccessLog<'_> {
+    pub fn line(&self, format: AccessLogFormat) -> String {
+        let ip = self.ip.map_or_else(|| "-".to_string(), |ip| ip.to_string());
+        let bytes = self.bytes.filter(|b| *b > 0).map_or_else(|| "-".to_string(), |b| b.to_string());
+        let mut line = format!(
+            "{} - - [{}] \"{} {} {}\" {} {}",
+            ip,
+            self.time.format("%d/%b/%Y:%H:%M:%S %z"),
+            self.method,
+            escape(self.target),
+            self.version,
+            self.status,
+            bytes
+        );
+        if format == AccessLogFormat::Combined {
+            line.push_str(&format!(
+                " \"{}\" \"{}\"",
+                self.referer.map_or_else(|| "-".to_string(), escape),
+                self.user_agent.map_or_else(|| "-".to_string(), escape)
+            ));
+        }
+        line
+    }
+
+    pub fn emit(&self, format: AccessLogFormat) {
+        println!("{}", self.line(format));
+    }
+}
+
+/// The request's `X-Request-Id`: the incoming one when it's a plausible id
+/// (1-128 visible ASCII characters, as set by a proxy or client), otherwise a
+/// new UUID. Anything else could forge or break log lines.
+pub fn request_id(incoming: Option<&axum::http::HeaderValue>) -> axum::http::HeaderValue {
+    incoming
+        .filter(|v| (1..=128).contains(&v.len()) && v.as_bytes().iter().all(|b| b.is_ascii_graphic()))
+        .cloned()
+        .unwrap_or_else(|| {
+            let id = uuid::Uuid::new_v4().simple().to_string();
+            axum::http::HeaderValue::from_str(&id).expect("uuid is a valid header value")
+        })
+}
+
+/// Keep client-controlled strings from breaking out of their quoted field.
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+pub enum Outcome<'a> {
+    /// Precomputed `.reply()` route
+    Reply,
+    /// Fast-path action served from a pre-built response
+    FastPath,
+    /// Fast-path hit reached via the full routing path
+    Static,
+    /// Action response served from `__config.cache`
+    Cached,
+    /// File from the static directory
+    File,
+    /// Streamed to an upstream by a `proxy` route
+    Proxy { status: u16 },
+    /// WebSocket upgrade
+    Upgrade { socket_id: &'a str },
+    NotFound,
+    /// Every worker's queue was full; answered 503
+    Overloaded,
+    /// Action failed; `drift` marks whether it suspended before failing
+    Error { drift: bool },
+    /// Action executed in V8; `timing` is `(active_ms, drift_ms)` when it drifted,
+    /// `queue_ms` is the wait for a worker before execution began
+    Action { action: &'a str, dynamic: bool, timing: Option<(f64, f64)>, queue_ms: f64 },
+}
+
+pub struct RequestLog<'a> {
+    /// `X-Request-Id`, shared with the action's `req.requestId` and isolate logs
+    pub request_id: &'a str,
+    pub method: &'a str,
+    pub path: &'a str,
+    pub outcome: Outcome<'a>,
+    pub elapsed: Duration,
+}
+
+impl Outcome<'_> {
+    pub fn level(&self) -> LogLevel {
+        match self {
+            Outcome::Error { .. } => LogLevel::Error,
+            Outcome::NotFound | Outcome::Overloaded => LogLevel::Warn,
+            _ => LogLevel::Info,
+        }
+    }
+}
+
+impl RequestLog<'_> {
+    pub fn emit(&self) {
+        let level = self.outcome.level();
+        // Checked first so nothing is formatted for a dropped line
+        if !log_enabled(level) {
+            return;
+        }
+        log_with(level, "request", self.fields(), || self.pretty());
+    }
+
+    fn fields(&self) -> serde_json::Value {
+        let mut fields = json!({
+            "requestId": self.request_id,
+            "method": self.method,
+            "path": self.path,
+            "durationMs": self.elapsed.as_secs_f64() * 1000.0,
+        });
+        let outcome = match &self.outcome {
+            Outcome::Reply => "reply",
+            Outcome::FastPath => "fastpath",
+            Outcome::Static => "static",
+            Outcome::Cached => "cached",
+            Outcome::File => "file",
+            Outcome::Proxy { status } => {
+                fields["status"] = json!(status);
+                "proxy"
+            }
+            Outcome::Upgrade { socket_id } => {
+                fields["socketId"] = json!(socket_id);
+                "upgrade"
+            }
+            Outcome::NotFound => "not_found",
+            Outcome::Overloaded => "overloaded",
+            Outcome::Error { drift } => {
+                fields["drift"] = json!(drift);
+                "error"
+            }
+            Outcome::Action { action, dynamic, timing, queue_ms } => {
+                fields["action"] = json!(action);
+                fields["route"] = json!(if *dynamic { "dynamic" } else { "exact" });
+                fields["queueMs"] = json!(queue_ms);
+                if let Some((active, drift)) = timing {
+                    fields["activeMs"] = json!(active);
+                    fields["driftMs"] = json!(drift);
+                }
+                "action"
+            }
+        };
+        fields["outcome"] = json!(outcome);
+        fields
+    }
+
+    fn pretty(&self) -> String {
+        let route = format!("{} {}", self.method, self.path);
+        let took = gray(&format!("in {:.2?} [{}]", self.elapsed, self.request_id));
+        match &self.outcome {
+            Outcome::Reply => format!("{} {} {} {}", blue("[Titan]"), green(&route), white("→ reply"), took),
+            Outcome::FastPath => format!("{} {} {} {}", blue("[Titan]"), green(&route), white("→ fastpath"), took),
+            Outcome::Static => format!("{} {} {} {}", blue("[Titan FastPath]"), white(&route), green("→ static"), took),
+            Outcome::Cached => format!("{} {} {} {}", blue("[Titan]"), green(&route), white("→ cached"), took),
+            Outcome::File => format!("{} {} {} {}", blue("[Titan]"), green(&route), white("→ file"), took),
+            Outcome::Proxy { status } => format!("{} {} {} {}", blue("[Titan]"), green(&route), white(&format!("→ proxy {}", status)), took),
+            Outcome::Upgrade { socket_id } => format!(
+                "{} {} {} {}",
+                blue("[Titan]"),
+                yellow(&format!("WS {}", self.path)),
+                white("→ upgrade"),
+                gray(&format!("(id: {}) [{}]", socket_id, self.request_id))
+            ),
+            Outcome::NotFound => format!("{} {} {} {}", blue("[Titan]"), white(&route), white("→ 404"), took),
+            Outcome::Overloaded => format!("{} {} {} {}", blue("[Titan]"), yellow(&route), yellow("→ 503 (queues full)"), took),
+            Outcome::Error { drift } => format!("{} {} {} {}", prefix(*drift), red(&route), red("→ error"), took),
+            Outcome::Action { action, dynamic, timing, queue_ms } => {
+                // Sub-millisecond waits are noise; anything longer means workers are saturated
+                let timing_info = match timing {
+                    Some((active, drift)) => gray(&format!("(queue: {:.2}ms, active: {:.2}ms, drift: {:.2}ms) in {:.2?} [{}]", queue_ms, active, drift, self.elapsed, self.request_id)),
+                    None if *queue_ms >= 1.0 => gray(&format!("(queue: {:.2}ms) in {:.2?} [{}]", queue_ms, self.elapsed, self.request_id)),
+                    None => took,
+                };
+                if *dynamic {
+                    format!("{} {} {} {} {} {}", prefix(timing.is_some()), green(&route), white("→"), green(action), white("(dynamic)"), timing_info)
+                } else {
+                    format!("{} {} {} {} {}", prefix(timing.is_some()), white(&route), white("→"), yellow(action), timing_info)
+                }
+            }
+        }
+    }
+}
+
+fn prefix(drift: bool) -> String {
+    if drift {
+        format!("{} {}", blue("[Titan"), blue("Drift]"))
+    } else {
+        blue("[Titan]").to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry<'a>() -> AccessLog<'a> {
+        AccessLog {
+            ip: Some("203.0.113.7".parse().unwrap()),
+            time: DateTime::parse_from_rfc3339("2024-03-05T14:07:09Z").unwrap().with_timezone(&Local),
+            method: "GET",
+            target: "/users?page=2",
+            version: "HTTP/1.1",
+            status: 200,
+            bytes: Some(512),
+            referer: Some("https://example.com/"),
+            user_agent: Some("curl/8.5.0"),
+        }
+    }
+
+    fn stamp(log: &AccessLog) -> String {
+        log.time.format("%d/%b/%Y:%H:%M:%S %z").to_string()
+    }
+
+    #[test]
+    fn common_line() {
+        let log = entry();
+        assert_eq!(log.line(AccessLogFormat::Common), format!("203.0.113.7 - - [{}] \"GET /users?page=2 HTTP/1.1\" 200 512", stamp(&log)));
+    }
+
+    #[test]
+    fn combined_line_adds_referer_and_user_agent() {
+        let log = entry();
+        assert_eq!(
+            log.line(AccessLogFormat::Combined),
+            format!("203.0.113.7 - - [{}] \"GET /users?page=2 HTTP/1.1\" 200 512 \"https://example.com/\" \"curl/8.5.0\"", stamp(&log))
+        );
+    }
+
+    #[test]
+    fn missing_values_are_dashes() {
+        let log = AccessLog { ip: None, bytes: Some(0), referer: None, user_agent: None, status: 204, ..entry() };
+        assert_eq!(log.line(AccessLogFormat::Combined), format!("- - - [{}] \"GET /users?page=2 HTTP/1.1\" 204 - \"-\" \"-\"", stamp(&log)));
+        let streamed = AccessLog { bytes: None, ..entry() };
+        assert!(streamed.line(AccessLogFormat::Common).ends_with(" 200 -"));
+    }
+
+    #[test]
+    fn client_strings_cannot_break_out_of_their_field() {
+        let log = AccessLog { target: "/a\" 500 0 \"x", user_agent: Some("evil\\\"agent"), ..entry() };
+        let line = log.line(AccessLogFormat::Combined);
+        assert!(line.contains("\"GET /a\\\" 500 0 \\\"x HTTP/1.1\" 200 512"));
+        assert!(line.ends_with("\"evil\\\\\\\"agent\""));
+    }
+
+    #[test]
+    fn format_names() {
+        assert_eq!(AccessLogFormat::parse("common"), Some(AccessLogFormat::Common));
+        assert_eq!(AccessLogFormat::parse("combined"), Some(AccessLogFormat::Combined));
+        assert_eq!(AccessLogFormat::parse("json"), None);
+    }
+
+    #[test]
+    fn request_id_keeps_plausible_ids_only() {
+        let incoming = axum::http::HeaderValue::from_static("req-42");
+        assert_eq!(request_id(Some(&incoming)), "req-42");
+        let spaced = axum::http::HeaderValue::from_static("a b");
+        assert_eq!(request_id(Some(&spaced)).len(), 32);
+        assert_eq!(request_id(None).len(), 32);
+    }
+}
@@ -5,7 +5,8 @@ use axum::{
     extract::{State, FromRequestParts, Request as AxumRequest, ws::{WebSocketUpgrade, WebSocket, Message}},
     http::{StatusCode, HeaderValue},
     response::{IntoResponse, Json},
-    routing::any,
+    routing::{any, get},
+    serve::ListenerExt,
 };
 use dashmap::DashMap;
 use tokio::sync::mpsc;
@@ -13,18 +14,47 @@ use futures_util::{StreamExt, SinkExt};
 use serde_json::Value;
 use smallvec::SmallVec;
 use std::time::Instant;
+use subtle::ConstantTimeEq;
 use std::{collections::HashMap, fs, path::PathBuf, sync::Arc};
 use tokio::net::TcpListener;
 
 mod action_management;
+mod client;
+mod compression;
+mod cors;
+mod errors;
 mod fast_path;
-
-use gravity::{RuntimeManager, WsMessage};
+mod hot_reload;
+mod listen;
+mod logging;
+mod metrics;
+mod multipart;
+mod proxy;
+mod query;
+mod response_cache;
+mod serve;
+mod spill;
+mod startup;
+mod static_files;
+mod streaming;
+
+use gravity::{RequestContext, RuntimeManager, WsMessage};
+use gravity::utils::{log, log_with, LogLevel};
 use gravity::extensions;
-use gravity::utils::{blue, gray, green, red, white, yellow};
 use gravity::native_host;
-use action_management::{RouteVal, DynamicRoute, match_dynamic_route, scan_actions};
-use fast_path::{FastPathRegistry, PrecomputedRoute};
+use action_management::{RouteVal, DynamicRoute, TrailingSlash, actions_root, find_route_conflicts, match_dynamic_route, scan_actions, toggle_trailing_slash};
+use compression::CompressionConfig;
+use cors::CorsConfig;
+use multipart::MultipartLimits;
+use response_cache::ResponseCache;
+use serve::ConnectionConfig;
+use fast_path::{FastPathRegistry, PrecomputedRoute, ReplyCache, etag_matches, weak_etag};
+use listen::BindAddr;
+use logging::{AccessLog, AccessLogFormat, Outcome, RequestLog};
+use metrics::{MatchedRoute, Metrics, UNMATCHED};
+use startup::StartupSummary;
+use static_files::{SpaFallback, StaticFiles};
+use streaming::{ChannelBody, TrailerBody, WithTrailers};
 
 /// Global allocator: mimalloc for ~5-15% better allocation throughput.
 #[global_allocator]
@@ -41,26 +71,278 @@ struct AppState {
     precomputed: Arc<HashMap<String, PrecomputedRoute>>,
     /// When true: disable per-request logging and timings injection
     production_mode: bool,
+    /// `__config.compression` — Accept-Encoding negotiation for action responses
+    compression: Option<Arc<CompressionConfig>>,
+    /// `__config.cache` — per-route TTLs for serving action responses without V8
+    response_cache: Option<Arc<ResponseCache>>,
+    /// Shared client for `proxy` routes
+    proxy_client: reqwest::Client,
+    /// `__config.maxBodyBytes` — larger request bodies get 413 before reaching V8
+    max_body_bytes: usize,
+    /// `__config.bodySpillBytes` — bodies past this many bytes continue in a temp file
+    body_spill_bytes: Option<usize>,
+    /// `__config.bodylessMethods` — methods whose body is only read when framed
+    /// by Content-Length or Transfer-Encoding (default GET, HEAD)
+    bodyless_methods: Arc<Vec<String>>,
+    /// `__config.multipart` — upload size limits for `req.formData`
+    multipart_limits: MultipartLimits,
+    /// `__config.cors` — preflights and Access-Control-Allow-* headers
+    cors: Option<Arc<CorsConfig>>,
+    /// Directories served for unmatched GET/HEAD requests (`__config.static`,
+    /// `__config.staticDir`), longest prefix first
+    static_files: Arc<Vec<StaticFiles>>,
+    /// `__config.spa` — app shell for unmatched GET/HEAD requests under its prefix
+    spa: Option<Arc<SpaFallback>>,
+    /// `__config.trailingSlash` — whether `/users/` reaches the `/users` route
+    trailing_slash: TrailingSlash,
+    /// `__config.etag` — weak ETag and 304 handling for every JSON action response
+    etag: bool,
+    /// `__config.trustProxy` — proxy hops whose X-Forwarded-* entries give `req.ip` / `req.protocol`
+    trust_proxy: usize,
+    /// `__config.logFormat: "common" | "combined"` — access log lines instead of request logs
+    access_log: Option<AccessLogFormat>,
+    /// `__config.metrics` — counters and histograms served at `/metrics`
+    metrics: Option<Arc<Metrics>>,
+    /// `__config.exposeErrors` — `false` hides action error messages in production
+    expose_errors: bool,
+    /// Status for actions that return `undefined` (`__config.undefinedStatus`, default 204)
+    undefined_status: StatusCode,
+    /// `__config.stringAsJson` — send a bare string result as a JSON string instead of text/plain
+    string_as_json: bool,
+    /// `TITAN_HEAPDUMP_TOKEN` — bearer token `/__heapdump` requires when set
+    heapdump_token: Option<Arc<str>>,
     /// Active WebSocket channels (Gravity compatible)
     ws_sockets: Arc<DashMap<String, mpsc::UnboundedSender<WsMessage>>>,
 }
 
 async fn root_route(state: State<AppState>, req: AxumRequest) -> impl IntoResponse {
-    handler(state, req).await
+    with_access_log(state, req).await
 }
 
 async fn dynamic_route(state: State<AppState>, req: AxumRequest) -> impl IntoResponse {
-    handler(state, req).await
+    with_access_log(state, req).await
 }
 
-/// Main request handler — optimized with early fast-path bailout.
-async fn handler(State(state): State<AppState>, req: AxumRequest) -> impl IntoResponse {
+/// Liveness: the server is up and accepting connections.
+async fn healthz() -> impl IntoResponse {
+    (StatusCode::OK, "ok")
+}
+
+/// Readiness: every isolate in the pool has finished loading actions.
+async fn readyz(State(state): State<AppState>) -> impl IntoResponse {
+    if state.runtime.is_ready() {
+        (StatusCode::OK, "ready")
+    } else {
+        (StatusCode::SERVICE_UNAVAILABLE, "not ready")
+    }
+}
+
+/// V8 heap snapshot of one isolate, `/__heapdump?isolate=N` (N is the worker's
+/// 0-based position in the pool). Save the body as `.heapsnapshot` and load it
+/// in Chrome DevTools' Memory tab. Mounted in dev mode (`TITAN_DEV=1`), or in
+/// production when `TITAN_HEAPDUMP_TOKEN` is set, which then has to be sent as
+/// a bearer token.
+async fn heapdump_route(State(state): State<AppState>, req: AxumRequest) -> axum::response::Response {
+    if let Some(token) = state.heapdump_token.as_deref() {
+        let bearer = req.headers().get(axum::http::header::AUTHORIZATION).and_then(|v| v.as_bytes().strip_prefix(b"Bearer "));
+        // Constant time, so response timing doesn't leak how much of a guess matched
+        if !bearer.is_some_and(|b| bool::from(b.ct_eq(token.as_bytes()))) {
+            return (StatusCode::UNAUTHORIZED, "Unauthorized").into_response();
+        }
+    }
+
+    let isolate = req
+        .uri()
+        .query()
+        .and_then(|q| q.split('&').find_map(|pair| pair.strip_prefix("isolate=")))
+        .map_or(Some(0), |n| n.parse::<usize>().ok());
+    let Some(rx) = isolate.and_then(|i| state.runtime.heap_snapshot(i)) else {
+        let message = format!("isolate must be between 0 and {}", state.runtime.pool_size().saturating_sub(1));
+        return (StatusCode::BAD_REQUEST, message).into_response();
+    };
+
+    let filename = format!("titan-isolate-{}-{}.heapsnapshot", isolate.unwrap_or(0), chrono::Utc::now().format("%Y%m%dT%H%M%S"));
+    axum::http::Response::builder()
+        .header(axum::http::header::CONTENT_TYPE, "application/json")
+        .header(axum::http::header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", filename))
+        .header(axum::http::header::CACHE_CONTROL, "no-store")
+        .body(Body::new(ChannelBody::new(rx)))
+        .unwrap()
+}
+
+/// Prometheus scrape endpoint (`__config.metrics`).
+async fn metrics_route(State(state): State<AppState>) -> impl IntoResponse {
+    let body = state.metrics.as_ref().map(|m| m.render(&state.runtime)).unwrap_or_default();
+    ([(axum::http::header::CONTENT_TYPE, "text/plain; version=0.0.4")], body)
+}
+
+/// Write a Common/Combined Log Format line once the response is final.
+async fn with_access_log(state: State<AppState>, req: AxumRequest) -> axum::response::Response {
+    let Some(format) = state.access_log else {
+        return with_metrics(state, req).await;
+    };
+    use axum::http::header;
+
+    let time = chrono::Local::now();
+    let ip = client::resolve(req.headers(), req.extensions(), state.trust_proxy).ip;
+    let method = req.method().to_string();
+    let target = req.uri().path_and_query().map_or_else(|| req.uri().path().to_string(), |pq| pq.to_string());
+    let version = format!("{:?}", req.version());
+    let referer = req.headers().get(header::REFERER).and_then(|v| v.to_str().ok()).map(String::from);
+    let user_agent = req.headers().get(header::USER_AGENT).and_then(|v| v.to_str().ok()).map(String::from);
+
+    let response = with_metrics(state, req).await;
+    let bytes = response
+        .headers()
+        .get(header::CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse().ok())
+        .or_else(|| http_body::Body::size_hint(response.body()).exact());
+    AccessLog {
+        ip,
+        time,
+        method: &method,
+        target: &target,
+        version: &version,
+        status: response.status().as_u16(),
+        bytes,
+        referer: referer.as_deref(),
+        user_agent: user_agent.as_deref(),
+    }
+    .emit(format);
+    response
+}
+
+/// Count the request under its route pattern once the response is final.
+/// Exact routes are labelled by their path; dynamic actions tag the
+/// response with the pattern they matched.
+async fn with_metrics(state: State<AppState>, req: AxumRequest) -> axum::response::Response {
+    let Some(metrics) = state.metrics.clone() else {
+        return with_cors(state, req).await;
+    };
     let method = req.method().as_str().to_uppercase();
     let path = req.uri().path().to_string();
-    let strict_key = format!("{}:{}", method, path);
+    let exact = state.routes.contains_key(&format!("{}:{}", route_method(&state, &method, &path), path))
+        || state.routes.contains_key(&path)
+        || state.routes.contains_key(&format!("WS:{}", path));
+
+    let mut response = with_cors(state, req).await;
+    let route = match response.extensions_mut().remove::<MatchedRoute>() {
+        Some(MatchedRoute(route)) => route,
+        None if exact => path,
+        None => UNMATCHED.to_string(),
+    };
+    metrics.record_request(&method, &route, response.status().as_u16());
+    response
+}
+
+/// Answer CORS preflights before routing and decorate every other response.
+async fn with_cors(state: State<AppState>, req: AxumRequest) -> axum::response::Response {
+    let Some(cors) = state.cors.clone() else {
+        return handler(state, req).await.into_response();
+    };
+    if CorsConfig::is_preflight(req.method().as_str(), req.headers()) {
+        return cors.preflight(req.headers());
+    }
+    let origin = req.headers().get(axum::http::header::ORIGIN).cloned();
+    let mut response = handler(state, req).await.into_response();
+    cors.apply(&mut response, origin.as_ref());
+    response
+}
+
+/// Tags the request with its `X-Request-Id` (kept from the client or
+/// generated), seen by the action as `req.requestId`, in every log line for
+/// it and echoed on the response.
+///
+/// `HEAD` without a route of its own is answered like the matching `GET`
+/// (action, FastPath, reply or static file) and sent without the body.
+async fn handler(state: State<AppState>, mut req: AxumRequest) -> axum::response::Response {
+    let request_id = logging::request_id(req.headers().get(X_REQUEST_ID));
+    req.headers_mut().insert(X_REQUEST_ID, request_id.clone());
+
+    if req.method() != axum::http::Method::HEAD {
+        let mut response = route_request(state, req).await.into_response();
+        response.headers_mut().insert(X_REQUEST_ID, request_id);
+        return response;
+    }
+    let (mut parts, body) = route_request(state, req).await.into_response().into_parts();
+    parts.headers.insert(X_REQUEST_ID, request_id);
+    // What a GET would have sent; a streamed body of unknown length has none
+    if !parts.headers.contains_key(axum::http::header::CONTENT_LENGTH) {
+        if let Some(len) = http_body::Body::size_hint(&body).exact() {
+            parts.headers.insert(axum::http::header::CONTENT_LENGTH, HeaderValue::from(len));
+        }
+    }
+    axum::response::Response::from_parts(parts, Body::empty())
+}
+
+/// The routing method: `GET` for a `HEAD` request no `HEAD` route claims.
+fn route_method<'a>(state: &AppState, method: &'a str, path: &str) -> &'a str {
+    let own_route = || {
+        state.routes.contains_key(&format!("HEAD:{}", path))
+            || match_dynamic_route("HEAD", path, state.dynamic_routes.as_slice()).is_some()
+    };
+    if method == "HEAD" && !own_route() { "GET" } else { method }
+}
+
+/// Whether `path` has a route for `method`: exact, dynamic or WebSocket.
+fn has_route(state: &AppState, method: &str, path: &str) -> bool {
+    let method = route_method(state, method, path);
+    state.routes.contains_key(&format!("{}:{}", method, path))
+        || state.routes.contains_key(path)
+        || state.routes.contains_key(&format!("WS:{}", path))
+        || match_dynamic_route(method, path, state.dynamic_routes.as_slice()).is_some()
+        || match_dynamic_route("WS", path, state.dynamic_routes.as_slice()).is_some()
+}
+
+const X_REQUEST_ID: &str = "x-request-id";
+
+/// Main request handler — optimized with early fast-path bailout.
+async fn route_request(State(state): State<AppState>, req: AxumRequest) -> impl IntoResponse {
+    let method = req.method().as_str().to_uppercase();
+    let mut path = req.uri().path().to_string();
+    let request_id = req.headers().get(X_REQUEST_ID).and_then(|v| v.to_str().ok()).unwrap_or_default().to_string();
+
+    // `__config.trailingSlash`, applied once before any lookup. Paths with a
+    // route (or no route in either form) are left alone, so static
+    // directories keep their `/dir/` index pages.
+    match state.trailing_slash {
+        TrailingSlash::Strict => {}
+        TrailingSlash::Ignore => {
+            if !has_route(&state, &method, &path) {
+                if let Some(other) = toggle_trailing_slash(&path).filter(|p| has_route(&state, &method, p)) {
+                    path = other;
+                }
+            }
+        }
+        TrailingSlash::Redirect => {
+            let declared = state.routes.contains_key(&format!("{}:{}", route_method(&state, &method, &path), path))
+                || state.routes.contains_key(&path);
+            if path.len() > 1 && path.ends_with('/') && !declared {
+                // Collapse leading slashes: `//evil.com/` must not become a
+                // protocol-relative `Location: //evil.com`
+                let canonical = toggle_trailing_slash(&path).map(|p| format!("/{}", p.trim_start_matches('/')));
+                if let Some(trimmed) = canonical.filter(|p| has_route(&state, &method, p)) {
+                    let location = match req.uri().query() {
+                        Some(q) => format!("{}?{}", trimmed, q),
+                        None => trimmed,
+                    };
+                    // 308 keeps the method and body of non-GET requests
+                    let status = if method == "GET" || method == "HEAD" { StatusCode::MOVED_PERMANENTLY } else { StatusCode::PERMANENT_REDIRECT };
+                    return match HeaderValue::from_str(&location) {
+                        Ok(location) => (status, [(axum::http::header::LOCATION, location)]).into_response(),
+                        Err(_) => (StatusCode::BAD_REQUEST, "Invalid path").into_response(),
+                    };
+                }
+            }
+        }
+    }
+
+    let route_method = route_method(&state, &method, &path);
+    let strict_key = format!("{}:{}", route_method, path);
 
     let start = Instant::now();
-    let log_enabled = !state.production_mode;
+    let log_enabled = !state.production_mode && state.access_log.is_none();
 
     if let Some(route) = state
         .routes
@@ -73,11 +355,12 @@ async fn handler(State(state): State<AppState>, req: AxumRequest) -> impl IntoRe
             // Precomputed reply routes
             "json" | "text" => {
                 if let Some(precomputed) = state.precomputed.get(&strict_key) {
+                    let if_none_match = req.headers().get(axum::http::header::IF_NONE_MATCH);
                     if state.production_mode {
-                        return precomputed.to_axum_response();
+                        return precomputed.to_axum_response(if_none_match);
                     }
 
-                    let mut response = precomputed.to_axum_response();
+                    let mut response = precomputed.to_axum_response(if_none_match);
                     let elapsed = start.elapsed();
 
                     response.headers_mut().insert(
@@ -88,13 +371,7 @@ async fn handler(State(state): State<AppState>, req: AxumRequest) -> impl IntoRe
                     );
 
                     if log_enabled {
-                        println!(
-                            "{} {} {} {}",
-                            blue("[Titan]"),
-                            green(&format!("{} {}", method, path)),
-                            white("→ reply"),
-                            gray(&format!("in {:.2?}", elapsed))
-                        );
+                        RequestLog { request_id: &request_id, method: &method, path: &path, outcome: Outcome::Reply, elapsed }.emit();
                     }
 
                     return response;
@@ -109,6 +386,16 @@ async fn handler(State(state): State<AppState>, req: AxumRequest) -> impl IntoRe
                 }
             }
 
+            // Streaming reverse proxy — body and response are never buffered
+            "proxy" => {
+                let upstream = route.value.as_str().unwrap_or("").to_string();
+                let response = proxy::forward(&state.proxy_client, &upstream, req).await;
+                if log_enabled {
+                    RequestLog { request_id: &request_id, method: &method, path: &path, outcome: Outcome::Proxy { status: response.status().as_u16() }, elapsed: start.elapsed() }.emit();
+                }
+                return response;
+            }
+
             // WebSocket routes
             "websocket" => {
                 let (mut parts, _body) = req.into_parts();
@@ -117,13 +404,7 @@ async fn handler(State(state): State<AppState>, req: AxumRequest) -> impl IntoRe
                 let state_clone = state.clone();
 
                 if log_enabled {
-                    println!(
-                        "{} {} {} {}",
-                        blue("[Titan]"),
-                        yellow(&format!("WS {}", path)),
-                        white("→ upgrade"),
-                        gray(&format!("(id: {})", socket_id))
-                    );
+                    RequestLog { request_id: &request_id, method: &method, path: &path, outcome: Outcome::Upgrade { socket_id: &socket_id }, elapsed: start.elapsed() }.emit();
                 }
 
                 return match WebSocketUpgrade::from_request_parts(&mut parts, &state).await {
@@ -134,16 +415,19 @@ async fn handler(State(state): State<AppState>, req: AxumRequest) -> impl IntoRe
                 };
             }
 
-            // Action routes (Fast path check)
+            // Action routes (Fast path check). Middleware has to run in V8, so
+            // routes that declare any never take the fast path.
             "action" => {
                 let action_name = route.value.as_str().unwrap_or("");
 
-                if let Some(static_resp) = state.fast_paths.get(action_name) {
+                if let Some(static_resp) = state.fast_paths.get(action_name).filter(|_| route.middleware.is_empty()) {
+                    let accept = req.headers().get(axum::http::header::ACCEPT_ENCODING).and_then(|v| v.to_str().ok());
+                    let if_none_match = req.headers().get(axum::http::header::IF_NONE_MATCH);
                     if state.production_mode {
-                        return static_resp.to_axum_response();
+                        return static_resp.to_axum_response(accept, if_none_match);
                     }
 
-                    let mut response = static_resp.to_axum_response();
+                    let mut response = static_resp.to_axum_response(accept, if_none_match);
                     let elapsed = start.elapsed();
 
                     response.headers_mut().insert(
@@ -154,13 +438,7 @@ async fn handler(State(state): State<AppState>, req: AxumRequest) -> impl IntoRe
                     );
 
                     if log_enabled {
-                        println!(
-                            "{} {} {} {}",
-                            blue("[Titan]"),
-                            green(&format!("{} {}", method, path)),
-                            white("→ fastpath"),
-                            gray(&format!("in {:.2?}", elapsed))
-                        );
+                        RequestLog { request_id: &request_id, method: &method, path: &path, outcome: Outcome::FastPath, elapsed }.emit();
                     }
 
                     return response;
@@ -174,13 +452,7 @@ async fn handler(State(state): State<AppState>, req: AxumRequest) -> impl IntoRe
                     }
                     let elapsed = start.elapsed();
                     if log_enabled {
-                        println!(
-                            "{} {} {} {}",
-                            blue("[Titan]"),
-                            green(&format!("{} {}", method, path)),
-                            white("→ reply"),
-                            gray(&format!("in {:.2?}", elapsed))
-                        );
+                        RequestLog { request_id: &request_id, method: &method, path: &path, outcome: Outcome::Reply, elapsed }.emit();
                     }
                     return s.to_string().into_response();
                 }
@@ -189,38 +461,70 @@ async fn handler(State(state): State<AppState>, req: AxumRequest) -> impl IntoRe
     }
 
     let start = Instant::now();
-    let log_enabled = !state.production_mode;
+    let log_enabled = !state.production_mode && state.access_log.is_none();
 
-    let query_pairs: Vec<(String, String)> = req
-        .uri()
-        .query()
-        .map(|q| {
-            q.split('&')
-                .filter_map(|pair| {
-                    let mut it = pair.splitn(2, '=');
-                    Some((it.next()?.to_string(), it.next().unwrap_or("").to_string()))
-                })
-                .collect()
-        })
-        .unwrap_or_default();
-    let query_map: HashMap<String, String> = query_pairs.into_iter().collect();
+    let raw_query = req.uri().query().map(str::to_string);
+    let query_pairs = query::pairs(raw_query.as_deref());
 
     let (mut parts, body) = req.into_parts();
+    let client = client::resolve(&parts.headers, &parts.extensions, state.trust_proxy);
+    let early_hints = parts.extensions.get::<serve::EarlyHints>().map(|hints| hints.0.clone());
     let headers_map: HashMap<String, String> = parts
         .headers
         .iter()
         .map(|(k, v)| (k.to_string(), v.to_str().unwrap_or("").to_string()))
         .collect();
 
-    let body_bytes = match to_bytes(body, usize::MAX).await {
-        Ok(b) => b,
-        Err(_) => return (StatusCode::BAD_REQUEST, "Failed to read request body").into_response(),
+    // Reject declared oversize bodies without reading them (multipart has its own limits).
+    // hyper has already answered 400 to a malformed or conflicting Content-Length and
+    // frames the body by it: a body cut short fails the read below, extra bytes are
+    // parsed as the next request. So a body that arrives matches its declared length.
+    let boundary = headers_map.get("content-type").and_then(|ct| multipart::boundary(ct));
+    let declared_len = headers_map.get("content-length").and_then(|v| v.trim().parse::<usize>().ok());
+    if boundary.is_none() && declared_len.is_some_and(|len| len > state.max_body_bytes) {
+        return (StatusCode::PAYLOAD_TOO_LARGE, "Payload Too Large").into_response();
+    }
+
+    // Body-less methods skip the read entirely unless the client framed a body
+    let framed = declared_len.is_some_and(|len| len > 0) || headers_map.contains_key("transfer-encoding");
+    let skip_body = !framed && state.bodyless_methods.contains(&method);
+
+    // Multipart bodies are parsed while streaming instead of buffered whole
+    let mut body_file = None;
+    let (body_bytes, form) = match (boundary, state.body_spill_bytes) {
+        _ if skip_body => (bytes::Bytes::new(), None),
+        (Some(boundary), _) => match multipart::parse(body, boundary, state.multipart_limits).await {
+            Ok(form) => (bytes::Bytes::new(), Some(Arc::new(form))),
+            Err((status, message)) => return (status, message).into_response(),
+        },
+        (None, Some(threshold)) => {
+            match spill::read(body, threshold, state.max_body_bytes).await {
+                Ok(spill::ReadBody::Memory(b)) => (b, None),
+                Ok(spill::ReadBody::Spilled(file)) => {
+                    body_file = Some(file);
+                    (bytes::Bytes::new(), None)
+                }
+                Err((status, message)) => return (status, message).into_response(),
+            }
+        }
+        (None, None) => match to_bytes(body, state.max_body_bytes).await {
+            Ok(b) => (b, None),
+            Err(e) if e.into_inner().downcast_ref::<http_body_util::LengthLimitError>().is_some() => {
+                return (StatusCode::PAYLOAD_TOO_LARGE, "Payload Too Large").into_response();
+            }
+            Err(_) => return (StatusCode::BAD_REQUEST, "Failed to read request body").into_response(),
+        },
     };
 
     let mut params: HashMap<String, String> = HashMap::new();
     let mut action_name: Option<String> = None;
     let mut route_kind = "none";
     let mut route_label = String::from("not_found");
+    // Route pattern for metrics labels; bounded by routes.json, unlike `path`
+    let mut route_pattern = path.clone();
+    let mut route_etag = false;
+    let mut middleware: Option<Arc<[String]>> = None;
+    let mut query_schema: Option<&query::QuerySchema> = None;
 
     let route = state
         .routes
@@ -228,7 +532,12 @@ async fn handler(State(state): State<AppState>, req: AxumRequest) -> impl IntoRe
         .or_else(|| state.routes.get(&path));
     if let Some(route) = route {
         route_kind = "exact";
+        route_etag = route.etag;
         if route.r#type == "action" {
+            if !route.middleware.is_empty() {
+                middleware = Some(Arc::from(route.middleware.as_slice()));
+            }
+            query_schema = route.query_schema.as_ref();
             let name = route.value.as_str().unwrap_or("unknown").to_string();
             route_label = name.clone();
             action_name = Some(name);
@@ -236,20 +545,27 @@ async fn handler(State(state): State<AppState>, req: AxumRequest) -> impl IntoRe
     }
 
     if action_name.is_none() {
-        if let Some((action, p)) =
-            match_dynamic_route(&method, &path, state.dynamic_routes.as_slice())
+        if let Some((route, p)) =
+            match_dynamic_route(route_method, &path, state.dynamic_routes.as_slice())
         {
             route_kind = "dynamic";
-            route_label = action.clone();
-            action_name = Some(action);
+            route_label = route.action.clone();
+            route_pattern = route.pattern.clone();
+            route_etag = route.etag;
+            if !route.middleware.is_empty() {
+                middleware = Some(Arc::from(route.middleware.as_slice()));
+            }
+            query_schema = route.query_schema.as_ref();
+            action_name = Some(route.action.clone());
             params = p;
         } else {
-            if let Some((action, p)) =
+            if let Some((route, p)) =
                 match_dynamic_route("WS", &path, state.dynamic_routes.as_slice())
             {
                 route_kind = "websocket_dynamic";
-                route_label = action.clone();
-                action_name = Some(action);
+                route_label = route.action.clone();
+                route_pattern = route.pattern.clone();
+                action_name = Some(route.action.clone());
                 params = p;
             }
         }
@@ -260,46 +576,94 @@ async fn handler(State(state): State<AppState>, req: AxumRequest) -> impl IntoRe
         let state_clone = state.clone();
         let action_name = action_name.unwrap();
 
-        return match WebSocketUpgrade::from_request_parts(&mut parts, &state).await {
+        let mut response = match WebSocketUpgrade::from_request_parts(&mut parts, &state).await {
             Ok(ws_upgrade) => ws_upgrade.on_upgrade(move |socket| {
                 handle_websocket(socket, socket_id, action_name, state_clone)
             }).into_response(),
             Err(rejection) => rejection.into_response(),
         };
+        response.extensions_mut().insert(MatchedRoute(route_pattern));
+        return response;
     }
 
     let action_name = match action_name {
         Some(a) => a,
         None => {
+            if method == "GET" || method == "HEAD" {
+                for files in state.static_files.iter() {
+                    if let Some(resp) = files.serve(&path, &headers_map).await {
+                        if log_enabled {
+                            RequestLog { request_id: &request_id, method: &method, path: &path, outcome: Outcome::File, elapsed: start.elapsed() }.emit();
+                        }
+                        return resp;
+                    }
+                }
+                let if_none_match = headers_map.get("if-none-match").and_then(|v| HeaderValue::from_str(v).ok());
+                if let Some(resp) = state.spa.as_ref().and_then(|spa| spa.serve(&path, if_none_match.as_ref())) {
+                    if log_enabled {
+                        RequestLog { request_id: &request_id, method: &method, path: &path, outcome: Outcome::File, elapsed: start.elapsed() }.emit();
+                    }
+                    return resp;
+                }
+            }
             if log_enabled {
-                println!(
-                    "{} {} {} {}",
-                    blue("[Titan]"),
-                    white(&format!("{} {}", method, path)),
-                    white("→ 404"),
-                    gray(&format!("in {:.2?}", start.elapsed()))
-                );
+                RequestLog { request_id: &request_id, method: &method, path: &path, outcome: Outcome::NotFound, elapsed: start.elapsed() }.emit();
             }
             return (StatusCode::NOT_FOUND, "Not Found").into_response();
         }
     };
 
-    if let Some(static_resp) = state.fast_paths.get(&action_name) {
+    // Typed by the route's querySchema; a value that does not fit never reaches the action
+    let query_vec: SmallVec<[(String, Value); 4]> = match query::build(query_pairs, query_schema) {
+        Ok(query) => query.into_iter().collect(),
+        Err(message) => return (StatusCode::BAD_REQUEST, message).into_response(),
+    };
+
+    if let Some(static_resp) = state.fast_paths.get(&action_name).filter(|_| middleware.is_none()) {
         if log_enabled {
-            println!(
-                "{} {} {} {}",
-                blue("[Titan FastPath]"),
-                white(&format!("{} {}", method, path)),
-                green("→ static"),
-                gray(&format!("in {:.2?}", start.elapsed()))
-            );
+            RequestLog { request_id: &request_id, method: &method, path: &path, outcome: Outcome::Static, elapsed: start.elapsed() }.emit();
         }
-        return static_resp.to_axum_response();
+        let if_none_match = headers_map.get("if-none-match").and_then(|v| HeaderValue::from_str(v).ok());
+        let mut response = static_resp.to_axum_response(headers_map.get("accept-encoding").map(String::as_str), if_none_match.as_ref());
+        response.extensions_mut().insert(MatchedRoute(route_pattern));
+        return response;
     }
 
+    let accept_encoding = headers_map.get("accept-encoding").cloned();
+    let accept = headers_map.get("accept").cloned();
+    let if_none_match = headers_map.get("if-none-match").cloned();
+
+    // `__config.cache`: a fresh copy is served without entering the isolate
+    let cache_slot = state
+        .response_cache
+        .as_ref()
+        .filter(|_| middleware.is_none() && (method == "GET" || method == "HEAD"))
+        .and_then(|cache| cache.ttl(&route_pattern))
+        .map(|ttl| (ResponseCache::key(&path, raw_query.as_deref()), ttl));
+    if let Some(mut response) = cache_slot.as_ref().and_then(|(key, _)| ResponseCache::get(key)) {
+        if state.etag || route_etag {
+            response = with_etag(response, if_none_match.as_deref()).await;
+        }
+        if let Some(compression) = state.compression.as_ref() {
+            response = compression.compress_response(response, accept_encoding.as_deref()).await;
+        }
+        if !state.production_mode {
+            let elapsed = start.elapsed();
+            response.headers_mut().insert(
+                "Server-Timing",
+                format!("cache;dur={:.2}", elapsed.as_secs_f64() * 1000.0)
+                    .parse()
+                    .unwrap(),
+            );
+            if log_enabled {
+                RequestLog { request_id: &request_id, method: &method, path: &path, outcome: Outcome::Cached, elapsed }.emit();
+            }
+        }
+        response.extensions_mut().insert(MatchedRoute(route_pattern));
+        return response;
+    }
     let headers_vec: SmallVec<[(String, String); 8]> = headers_map.into_iter().collect();
     let params_vec: SmallVec<[(String, String); 4]> = params.into_iter().collect();
-    let query_vec: SmallVec<[(String, String); 4]> = query_map.into_iter().collect();
 
     let body_arg = if !body_bytes.is_empty() {
         Some(body_bytes)
@@ -307,108 +671,268 @@ async fn handler(State(state): State<AppState>, req: AxumRequest) -> impl IntoRe
         None
     };
 
-    let (result_json, timings) = state
+    let executed = state
         .runtime
-        .execute(
+        .try_execute(
             action_name.clone(),
-            method.clone(),
-            path.clone(),
-            body_arg,
-            headers_vec,
-            params_vec,
-            query_vec,
+            RequestContext {
+                method: method.clone(),
+                path: path.clone(),
+                body: body_arg,
+                form,
+                body_file,
+                middleware,
+                client: Some(client),
+                early_hints,
+                headers: headers_vec,
+                params: params_vec,
+                query: query_vec,
+            },
         )
-        .await
-        .unwrap_or_else(|e| (serde_json::json!({"error": e}), vec![]));
+        .await;
+    let (mut result_json, timings) = match executed {
+        Ok(done) => done,
+        // Every worker's queue is full (`__config.maxQueueDepth`): shed load instead of waiting
+        Err(gravity::ExecuteError::Overloaded) => {
+            if log_enabled {
+                RequestLog { request_id: &request_id, method: &method, path: &path, outcome: Outcome::Overloaded, elapsed: start.elapsed() }.emit();
+            }
+            let mut response = (StatusCode::SERVICE_UNAVAILABLE, [(axum::http::header::RETRY_AFTER, "1")], "Service Unavailable").into_response();
+            response.extensions_mut().insert(MatchedRoute(route_pattern));
+            return response;
+        }
+        Err(gravity::ExecuteError::Failed(e)) => (serde_json::json!({"error": e}), vec![]),
+    };
+
+    if let Some(metrics) = state.metrics.as_ref() {
+        metrics.record_action(&route_pattern, start.elapsed().as_secs_f64() * 1000.0, &timings);
+    }
 
     if let Some(err) = result_json.get("error") {
         if log_enabled {
-            let prefix = if !timings.is_empty() {
-                format!("{} {}", blue("[Titan"), blue("Drift]"))
-            } else {
-                blue("[Titan]").to_string()
-            };
-            println!(
-                "{} {} {} {}",
-                prefix,
-                red(&format!("{} {}", method, path)),
-                red("→ error"),
-                gray(&format!("in {:.2?}", start.elapsed()))
-            );
+            let drift = timings.iter().any(|(n, _)| n != "queue");
+            RequestLog { request_id: &request_id, method: &method, path: &path, outcome: Outcome::Error { drift }, elapsed: start.elapsed() }.emit();
         }
-        return (StatusCode::INTERNAL_SERVER_ERROR, Json(result_json)).into_response();
+        let expose = state.expose_errors || !state.production_mode;
+        let mut response = errors::response(result_json, accept.as_deref(), expose);
+        response.extensions_mut().insert(MatchedRoute(route_pattern));
+        return response;
     }
 
-    let mut response = if let Some(is_resp) = result_json.get("_isResponse") {
-        if is_resp.as_bool().unwrap_or(false) {
-            let status_u16 = result_json.get("status").and_then(|v| v.as_u64()).unwrap_or(200) as u16;
-            let status = StatusCode::from_u16(status_u16).unwrap_or(StatusCode::OK);
-            let mut builder = axum::http::Response::builder().status(status);
-
-            if let Some(hmap) = result_json.get("headers").and_then(|v| v.as_object()) {
-                for (k, v) in hmap {
-                    if let Some(vs) = v.as_str() {
-                        builder = builder.header(k, vs);
-                    }
+    let mut streamed = false;
+    let mut response = if result_json.get("_isUndefined").is_some() {
+        // `undefined` → no content; any other configured status keeps the legacy `null` body
+        if state.undefined_status == StatusCode::NO_CONTENT {
+            StatusCode::NO_CONTENT.into_response()
+        } else {
+            (state.undefined_status, Json(Value::Null)).into_response()
+        }
+    } else if let (Value::String(text), false) = (&mut result_json, state.string_as_json) {
+        // `return "hello"` → the text itself
+        ([(axum::http::header::CONTENT_TYPE, "text/plain; charset=utf-8")], std::mem::take(text)).into_response()
+    } else if let Some(Value::String(text)) = result_json.get_mut("_rawJson").map(Value::take) {
+        // A plain object/array result, already JSON text
+        ([(axum::http::header::CONTENT_TYPE, "application/json")], text).into_response()
+    } else if result_json.get("_isResponse").and_then(Value::as_bool).unwrap_or(false) {
+        let status_u16 = result_json.get("status").and_then(|v| v.as_u64()).unwrap_or(200) as u16;
+        let status = StatusCode::from_u16(status_u16).unwrap_or(StatusCode::OK);
+        let mut builder = axum::http::Response::builder().status(status);
+
+        if let Some(hmap) = result_json.get("headers").and_then(|v| v.as_object()) {
+            for (k, v) in hmap {
+                if let Some(vs) = v.as_str() {
+                    builder = builder.header(k, vs);
                 }
             }
+        }
 
-            let mut is_redirect = false;
-            if let Some(location) = result_json.get("redirect") {
-                if let Some(url) = location.as_str() {
-                    let mut final_status_u16 = status.as_u16();
-                    if !(300..400).contains(&final_status_u16) { final_status_u16 = 302; }
-                    builder = builder.status(StatusCode::from_u16(final_status_u16).unwrap_or(StatusCode::FOUND)).header("Location", url);
-                    is_redirect = true;
-                }
+        // Appended, not inserted: each t.cookies.set / clear is its own Set-Cookie header
+        if let Some(cookies) = result_json.get("cookies").and_then(|v| v.as_array()) {
+            for line in cookies.iter().filter_map(|v| v.as_str()) {
+                builder = builder.header(axum::http::header::SET_COOKIE, line);
             }
+        }
 
-            let body_text = if is_redirect { "".to_string() } else {
-                match result_json.get("body") {
-                    Some(Value::String(s)) => s.clone(),
-                    Some(v) => v.to_string(),
-                    None => "".to_string(),
+        let mut is_redirect = false;
+        if let Some(location) = result_json.get("redirect") {
+            if let Some(url) = location.as_str() {
+                let mut final_status_u16 = status.as_u16();
+                if !(300..400).contains(&final_status_u16) { final_status_u16 = 302; }
+                builder = builder.status(StatusCode::from_u16(final_status_u16).unwrap_or(StatusCode::FOUND)).header("Location", url);
+                is_redirect = true;
+            }
+        }
+
+        // Already serialized by t.response.json / text / html: moved out and sent as-is
+        let body_text = if is_redirect { "".to_string() } else {
+            match result_json.get_mut("body").map(Value::take) {
+                Some(Value::String(s)) => s,
+                Some(v) => v.to_string(),
+                None => "".to_string(),
+            }
+        };
+
+        // Trailers (t.response.trailer) — sent after the body over chunked encoding
+        let trailers: Vec<(String, String)> = result_json
+            .get("trailers")
+            .and_then(|v| v.as_object())
+            .map(|t| t.iter().filter_map(|(k, v)| v.as_str().map(|vs| (k.clone(), vs.to_string()))).collect())
+            .unwrap_or_default();
+
+        if let Some(file) = result_json.get("file").and_then(|v| v.as_str()) {
+            // t.response.file: read from disk here, never buffered
+            streamed = true;
+            let mut response = static_files::send_file(builder, file, result_json.get("download").and_then(|v| v.as_str())).await;
+            if !trailers.is_empty() && response.status().is_success() {
+                // Chunked instead of a Content-Length, so the trailers fit after the file
+                let names = trailers.iter().map(|(k, _)| k.as_str()).collect::<Vec<_>>().join(", ");
+                let headers = response.headers_mut();
+                headers.remove(axum::http::header::CONTENT_LENGTH);
+                if let Ok(names) = HeaderValue::from_str(&names) {
+                    headers.insert(axum::http::header::TRAILER, names);
                 }
-            };
-            builder.body(Body::from(body_text)).unwrap()
+                response = response.map(|body| Body::new(WithTrailers::new(body, &trailers)));
+            }
+            response
+        } else if let Some(stream_id) = result_json.get("_stream").and_then(|v| v.as_u64()) {
+            // t.response.jsonArray: the head arrives first, the body as the action
+            // writes it; the head announced the trailers (see `_streamItems`)
+            streamed = true;
+            match gravity::extensions::take_response_stream(stream_id) {
+                Some(stream) => builder.body(Body::new(ChannelBody::from_stream(stream))).unwrap_or_else(|_| invalid_response()),
+                None => builder.body(Body::empty()).unwrap_or_else(|_| invalid_response()),
+            }
+        } else if !trailers.is_empty() && !is_redirect {
+            let names = trailers.iter().map(|(k, _)| k.as_str()).collect::<Vec<_>>().join(", ");
+            builder = builder.header("Trailer", names);
+            builder.body(Body::new(TrailerBody::new(bytes::Bytes::from(body_text), &trailers))).unwrap_or_else(|_| invalid_response())
         } else {
-            Json(result_json).into_response()
+            builder.body(Body::from(body_text)).unwrap_or_else(|_| invalid_response())
         }
     } else {
         Json(result_json).into_response()
     };
 
+    // Cached uncompressed, so every hit negotiates its own encoding
+    if let Some((key, ttl)) = cache_slot.filter(|_| !streamed) {
+        response = ResponseCache::store(key, ttl, response).await;
+    }
+
+    // Hash before compression so the tag is the same for every encoding.
+    // Both buffer the whole body, which would defeat a streamed one.
+    if (state.etag || route_etag) && !streamed {
+        response = with_etag(response, if_none_match.as_deref()).await;
+    }
+
+    if let Some(compression) = state.compression.as_ref().filter(|_| !streamed) {
+        response = compression.compress_response(response, accept_encoding.as_deref()).await;
+    }
+
     if !state.production_mode && !timings.is_empty() {
-        let server_timing = timings.iter().enumerate().map(|(i, (name, duration))| format!("{}_{};dur={:.2}", name, i, duration)).collect::<Vec<_>>().join(", ");
+        let server_timing = timings
+            .iter()
+            .enumerate()
+            .map(|(i, (name, duration))| {
+                if name == "queue" {
+                    format!("queue;dur={:.2}", duration)
+                } else {
+                    format!("{}_{};dur={:.2}", name, i, duration)
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
         response.headers_mut().insert("Server-Timing", server_timing.parse().unwrap_or_else(|_| HeaderValue::from_static("")));
     }
 
-    if log_enabled {
+    // Hot success path: skip the timing sums entirely when info lines are off
+    if log_enabled && gravity::utils::log_enabled(gravity::utils::LogLevel::Info) {
         let total_elapsed = start.elapsed();
         let total_elapsed_ms = total_elapsed.as_secs_f64() * 1000.0;
         let total_drift_ms: f64 = timings.iter().filter(|(n, _)| n == "drift" || n == "drift_error").map(|(_, d)| d).sum();
-        let compute_ms = (total_elapsed_ms - total_drift_ms).max(0.0);
-
-        let prefix = if !timings.is_empty() { format!("{} {}", blue("[Titan"), blue("Drift]")) } else { blue("[Titan]").to_string() };
-        let timing_info = if !timings.is_empty() { gray(&format!("(active: {:.2}ms, drift: {:.2}ms) in {:.2?}", compute_ms, total_drift_ms, total_elapsed)) } else { gray(&format!("in {:.2?}", total_elapsed)) };
-
-        match route_kind {
-            "dynamic" => println!("{} {} {} {} {} {}", prefix, green(&format!("{} {}", method, path)), white("→"), green(&route_label), white("(dynamic)"), timing_info),
-            "exact" => println!("{} {} {} {} {}", prefix, white(&format!("{} {}", method, path)), white("→"), yellow(&route_label), timing_info),
-            _ => {}
+        let queue_ms: f64 = timings.iter().filter(|(n, _)| n == "queue").map(|(_, d)| d).sum();
+        let compute_ms = (total_elapsed_ms - total_drift_ms - queue_ms).max(0.0);
+
+        let drifted = timings.iter().any(|(n, _)| n != "queue");
+        let timing = drifted.then_some((compute_ms, total_drift_ms));
+
+        if route_kind == "dynamic" || route_kind == "exact" {
+            RequestLog {
+                request_id: &request_id,
+                method: &method,
+                path: &path,
+                outcome: Outcome::Action { action: &route_label, dynamic: route_kind == "dynamic", timing, queue_ms },
+                elapsed: total_elapsed,
+            }
+            .emit();
         }
     }
 
+    response.extensions_mut().insert(MatchedRoute(route_pattern));
     response
 }
 
+/// Every middleware name referenced by an exact or dynamic route.
+fn route_middleware<'a>(
+    routes: &'a HashMap<String, RouteVal>,
+    dynamic_routes: &'a [DynamicRoute],
+) -> impl Iterator<Item = &'a str> {
+    routes
+        .values()
+        .flat_map(|route| route.middleware.iter())
+        .chain(dynamic_routes.iter().flat_map(|route| route.middleware.iter()))
+        .map(String::as_str)
+}
+
+/// An action response whose status or headers `http` rejects (say a CR/LF
+/// in a header value) answers 500 instead of panicking the handler.
+fn invalid_response() -> axum::response::Response {
+    (StatusCode::INTERNAL_SERVER_ERROR, "Invalid response headers").into_response()
+}
+
+/// Tag a 200 JSON action response with a weak ETag of its body, or turn it
+/// into a 304 when `If-None-Match` already holds that tag.
+async fn with_etag(response: axum::response::Response, if_none_match: Option<&str>) -> axum::response::Response {
+    use axum::http::header;
+
+    let is_json = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|ct| ct.starts_with("application/json"));
+    if response.status() != StatusCode::OK
+        || !is_json
+        || response.headers().contains_key(header::ETAG)
+        || response.headers().contains_key(header::TRAILER)
+    {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let Ok(bytes) = to_bytes(body, usize::MAX).await else {
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    };
+    let Ok(etag) = HeaderValue::from_str(&weak_etag(&bytes)) else {
+        return axum::response::Response::from_parts(parts, Body::from(bytes));
+    };
+    let matched = if_none_match
+        .and_then(|v| HeaderValue::from_str(v).ok())
+        .is_some_and(|inm| etag_matches(&inm, &etag));
+    parts.headers.insert(header::ETAG, etag);
+    if matched {
+        parts.status = StatusCode::NOT_MODIFIED;
+        parts.headers.remove(header::CONTENT_LENGTH);
+        return axum::response::Response::from_parts(parts, Body::empty());
+    }
+    axum::response::Response::from_parts(parts, Body::from(bytes))
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
-    dotenvy::dotenv().ok();
+    let dotenv_loaded = dotenvy::dotenv().is_ok();
 
     let args: Vec<String> = std::env::args().collect();
     if args.len() >= 3 && args[1] == "native-host" {
-        native_host::run_native_host(&args[2]).await;
+        native_host::run_native_host(&args[2], args.get(3).map(String::as_str)).await;
         return Ok(());
     }
 
@@ -422,49 +946,289 @@ async fn main() -> Result<()> {
         std::process::exit(1);
     }
 
-    let production_mode = std::env::var("TITAN_DEV").unwrap_or_default() != "1";
+    let production_mode = !gravity::utils::dev_mode();
     let routes_path = dist_dir.join("routes.json");
     let raw = fs::read_to_string(&routes_path).unwrap_or_else(|_| "{}".to_string());
     let json: Value = serde_json::from_str(&raw).unwrap_or_default();
 
+    // Accepts the camelCase spelling too, as written by hand in route configs
+    let log_format = json["__config"]["logFormat"].as_str().or_else(|| json["__config"]["log_format"].as_str());
+    gravity::utils::set_json_logs(log_format == Some("json"));
+    let access_log = log_format.and_then(AccessLogFormat::parse);
+    let trust_proxy = client::trusted_hops(if json["__config"]["trustProxy"].is_null() { &json["__config"]["trust_proxy"] } else { &json["__config"]["trustProxy"] });
+    if let Some(level) = std::env::var("TITAN_LOG_LEVEL").ok().as_deref().and_then(gravity::utils::LogLevel::parse) {
+        gravity::utils::set_log_level(level);
+    }
+
+    // Default for drift(op) without its own `{ timeout }`
+    if let Some(ms) = json["__config"]["driftTimeoutMs"].as_u64().or_else(|| json["__config"]["drift_timeout_ms"].as_u64()) {
+        gravity::extensions::builtins::system::set_default_drift_timeout(ms);
+    }
+
+    // Bundles that failed to compile are retried from disk on later requests
+    if let Some(ms) = json["__config"]["compileRetryMs"].as_u64().or_else(|| json["__config"]["compile_retry_ms"].as_u64()) {
+        gravity::extensions::set_compile_retry_interval(ms);
+    }
+
+    // Environment variables actions may read (t.env / process.env); none unless listed
+    let env_allowlist = json["__config"]["env"].as_array().map(|names| {
+        names.iter().filter_map(|n| n.as_str().map(str::to_string)).collect::<Vec<_>>()
+    });
+    if env_allowlist.is_none() && dotenv_loaded {
+        let message = "the environment is no longer exposed to actions in full: list the variables they read in __config.env (TITAN_PUBLIC_* are always exposed)";
+        log(LogLevel::Warn, "env", serde_json::json!({ "message": message }));
+    }
+    gravity::extensions::builtins::system::set_env_allowlist(env_allowlist.unwrap_or_default());
+
+    // Requests each worker may have queued before new ones get 503
+    if let Some(depth) = json["__config"]["maxQueueDepth"].as_u64().or_else(|| json["__config"]["max_queue_depth"].as_u64()) {
+        gravity::runtime::set_max_queue_depth(depth as usize);
+    }
+
+    // Deepest action result (and t.* argument) serialization will walk
+    if let Some(depth) = json["__config"]["maxResultDepth"].as_u64().or_else(|| json["__config"]["max_result_depth"].as_u64()) {
+        gravity::extensions::set_max_json_depth(depth as usize);
+    }
+
+    // Worker panic policy; crash reports land in the project root
+    let on_panic = json["__config"]["onPanic"]
+        .as_str()
+        .or_else(|| json["__config"]["on_panic"].as_str())
+        .and_then(gravity::panic::PanicPolicy::parse)
+        .unwrap_or(gravity::panic::PanicPolicy::Recover);
+    gravity::panic::install(on_panic, dist_dir.parent().map(PathBuf::from).unwrap_or_default());
+
     let port = std::env::var("PORT").ok().and_then(|p| p.parse::<u64>().ok()).or_else(|| json["__config"]["port"].as_u64()).unwrap_or(3000);
+    let host = std::env::var("TITAN_HOST").ok().or_else(|| json["__config"]["host"].as_str().map(str::to_string)).unwrap_or_else(|| "0.0.0.0".to_string());
+    let bind_addr = match BindAddr::parse(&host, port) {
+        Ok(addr) => addr,
+        Err(e) => {
+            eprintln!("Error: {} (from TITAN_HOST / __config.host / PORT)", e);
+            std::process::exit(1);
+        }
+    };
     let thread_count = json["__config"]["threads"].as_u64();
     let routes_json = json["routes"].clone();
     let map: HashMap<String, RouteVal> = serde_json::from_value(routes_json).unwrap_or_default();
     let dynamic_routes: Vec<DynamicRoute> = serde_json::from_value(json["__dynamic_routes"].clone()).unwrap_or_default();
 
+    // Shadowed routes are config mistakes: warn, or refuse to start under strictRoutes
+    let conflicts = find_route_conflicts(&raw, &dynamic_routes);
+    let strict_routes = json["__config"]["strictRoutes"].as_bool().or_else(|| json["__config"]["strict_routes"].as_bool()).unwrap_or(false);
+    for conflict in &conflicts {
+        log(if strict_routes { LogLevel::Error } else { LogLevel::Warn }, "route_conflict", serde_json::json!({ "message": conflict }));
+    }
+    if strict_routes && !conflicts.is_empty() {
+        eprintln!("Error: {} conflicting route definition(s) in {:?} (strictRoutes is enabled)", conflicts.len(), routes_path);
+        std::process::exit(1);
+    }
+
     let project_root = dist_dir.clone();
     extensions::load_project_extensions(project_root.clone());
 
+    // Global caching default for reply routes (per-route `cache` wins)
+    let reply_cache = if json["__config"]["replyCache"].is_null() { &json["__config"]["reply_cache"] } else { &json["__config"]["replyCache"] };
+    let reply_cache: Option<ReplyCache> = serde_json::from_value(reply_cache.clone()).ok();
+
     let mut precomputed = HashMap::new();
     for (key, route) in &map {
-        match route.r#type.as_str() {
-            "json" => { precomputed.insert(key.clone(), PrecomputedRoute::from_json(&route.value)); }
-            "text" => { if let Some(s) = route.value.as_str() { precomputed.insert(key.clone(), PrecomputedRoute::from_text(s)); } }
-            _ => {}
+        let cache = route.cache.as_ref().or(reply_cache.as_ref());
+        let content_type = route.content_type.as_deref().filter(|ct| {
+            let valid = HeaderValue::from_str(ct).is_ok();
+            if !valid {
+                let message = format!("route '{}' has an invalid contentType {:?}; serving it with the default", key, ct);
+                log(LogLevel::Warn, "content_type", serde_json::json!({ "route": key, "contentType": ct, "message": message }));
+            }
+            valid
+        });
+        let built = match route.r#type.as_str() {
+            // With an explicit content type a string value is served as the literal body
+            "json" if content_type.is_none() || !route.value.is_string() => Some(PrecomputedRoute::from_json(&route.value)),
+            "json" | "text" => route.value.as_str().map(PrecomputedRoute::from_text),
+            _ => None,
+        };
+        if let Some(route) = built {
+            precomputed.insert(key.clone(), route.with_content_type(content_type).with_cache(cache));
         }
     }
 
+    // Hot reload replaces actions at runtime, so nothing may be served from a startup snapshot
+    let hot_reload = json["__config"]["dev"].as_bool().unwrap_or(false);
+
     let actions_dir = dist_dir.join("actions");
-    let fast_paths = FastPathRegistry::build(&actions_dir);
+    let mut fast_paths = if hot_reload { FastPathRegistry::default() } else { FastPathRegistry::build(&actions_dir) };
+
+    // ETags: everywhere with `__config.etag`, otherwise per route (`etag: true`)
+    let etag = json["__config"]["etag"].as_bool().unwrap_or(false);
+    let trailing_slash = json["__config"]["trailingSlash"]
+        .as_str()
+        .or_else(|| json["__config"]["trailing_slash"].as_str())
+        .and_then(TrailingSlash::parse)
+        .unwrap_or_default();
+    let etag_actions: std::collections::HashSet<String> = map
+        .values()
+        .filter(|route| route.etag && route.r#type == "action")
+        .filter_map(|route| route.value.as_str().map(String::from))
+        .chain(dynamic_routes.iter().filter(|route| route.etag).map(|route| route.action.clone()))
+        .collect();
+    fast_paths.enable_etags(|name| etag || etag_actions.contains(name));
+
+    let compression = CompressionConfig::from_config(&json["__config"]["compression"]);
+    let response_cache = ResponseCache::from_config(&json["__config"]["cache"]).map(Arc::new);
+    if let Some(config) = compression.as_ref() {
+        fast_paths.precompress(config);
+    }
 
     let threads = match thread_count {
         Some(t) if t > 0 => t as usize,
         _ => num_cpus::get() * 2,
     };
 
-    let stack_mb = json["__config"]["stack_mb"].as_u64().unwrap_or(8);
+    let stack_mb = json["__config"]["stackMb"].as_u64().or_else(|| json["__config"]["stack_mb"].as_u64()).unwrap_or(8);
     let stack_size = (stack_mb as usize) * 1024 * 1024;
 
-    let runtime_manager = Arc::new(RuntimeManager::new(project_root.clone(), threads, stack_size));
+    let cpu_affinity = json["__config"]["cpuAffinity"].as_bool().or_else(|| json["__config"]["cpu_affinity"].as_bool()).unwrap_or(false);
+
+    let runtime_manager = Arc::new(RuntimeManager::new(project_root.clone(), threads, stack_size, cpu_affinity));
 
     // Load Actions into workers
     let action_files = scan_actions(&project_root);
+    let missing_middleware: std::collections::BTreeSet<&str> = route_middleware(&map, &dynamic_routes)
+        .filter(|name| !action_files.contains_key(*name))
+        .collect();
+    for name in missing_middleware {
+        let message = format!("middleware '{}' has no action file; routes using it will fail", name);
+        log(LogLevel::Warn, "missing_middleware", serde_json::json!({ "middleware": name, "message": message }));
+    }
     for (name, path) in action_files {
         if let Ok(code) = fs::read_to_string(&path) {
+            extensions::action_sources().insert(name.clone(), path);
             runtime_manager.load_action(name, code);
         }
     }
+    runtime_manager.mark_loaded();
+
+    if hot_reload {
+        hot_reload::watch(actions_root(&project_root), runtime_manager.clone());
+    }
+
+    // Elastic pool: `threads` is the baseline, `pool.max` the ceiling
+    let pool = &json["__config"]["pool"];
+    let pool_max = pool["max"].as_u64().map_or(threads, |max| max as usize);
+    if pool_max > threads {
+        let cooldown_ms = pool["cooldownMs"].as_u64().or_else(|| pool["cooldown_ms"].as_u64()).unwrap_or(30_000);
+        runtime_manager.clone().start_autoscaler(pool_max, std::time::Duration::from_millis(cooldown_ms));
+    }
+
+    // Built-in probes: `health: false` drops both, `{ healthz, readyz }` moves or disables each
+    let health = &json["__config"]["health"];
+    let probe_path = |key: &str, default: &str| -> Option<String> {
+        if health.as_bool() == Some(false) {
+            return None;
+        }
+        match &health[key] {
+            Value::Bool(false) => None,
+            Value::String(p) if p.starts_with('/') && p != "/" => Some(p.clone()),
+            _ => Some(default.to_string()),
+        }
+    };
+    let healthz_path = probe_path("healthz", "/healthz");
+    let readyz_path = probe_path("readyz", "/readyz");
+
+    // Heap snapshots expose everything an isolate holds: dev mode, or a token in production
+    let heapdump_token: Option<Arc<str>> = std::env::var("TITAN_HEAPDUMP_TOKEN").ok().filter(|t| !t.is_empty()).map(Arc::from);
+    let heapdump = !production_mode || heapdump_token.is_some();
+
+    let max_body_bytes = json["__config"]["maxBodyBytes"]
+        .as_u64()
+        .or_else(|| json["__config"]["max_body_bytes"].as_u64())
+        .unwrap_or(10 * 1024 * 1024) as usize;
+    let body_spill_bytes = json["__config"]["bodySpillBytes"]
+        .as_u64()
+        .or_else(|| json["__config"]["body_spill_bytes"].as_u64())
+        .filter(|&bytes| bytes > 0 && (bytes as usize) < max_body_bytes)
+        .map(|bytes| bytes as usize);
+    let bodyless_methods: Vec<String> = json["__config"]["bodylessMethods"]
+        .as_array()
+        .or_else(|| json["__config"]["bodyless_methods"].as_array())
+        .map(|methods| methods.iter().filter_map(|m| m.as_str()).map(|m| m.to_uppercase()).collect())
+        .unwrap_or_else(|| vec!["GET".to_string(), "HEAD".to_string()]);
+    let multipart_limits: MultipartLimits = serde_json::from_value(json["__config"]["multipart"].clone()).unwrap_or_default();
+
+    let cors: Option<Arc<CorsConfig>> = serde_json::from_value(json["__config"]["cors"].clone()).ok().map(Arc::new);
+
+    // Static directories are resolved next to dist/ (the project or build root)
+    let static_base = dist_dir.parent().map(PathBuf::from).unwrap_or_default();
+    let mut static_files: Vec<StaticFiles> = Vec::new();
+    if let Some(mounts) = json["__config"]["static"].as_object() {
+        for (prefix, dir) in mounts {
+            if let Some(files) = dir.as_str().and_then(|dir| StaticFiles::new(&static_base.join(dir), prefix)) {
+                static_files.push(files);
+            }
+        }
+    }
+    if let Some(dir) = json["__config"]["staticDir"].as_str().or_else(|| json["__config"]["static_dir"].as_str()) {
+        let prefix = json["__config"]["staticPrefix"].as_str().or_else(|| json["__config"]["static_prefix"].as_str()).unwrap_or("/");
+        if let Some(files) = StaticFiles::new(&static_base.join(dir), prefix) {
+            static_files.push(files);
+        }
+    }
+    // `/assets` must be tried before a `/` mount that would also claim it
+    static_files.sort_by_key(|files| std::cmp::Reverse(files.prefix().len()));
+    let spa = if json["__config"]["spa"].is_object() {
+        let spa = SpaFallback::from_config(&json["__config"]["spa"], &static_base);
+        if spa.is_none() {
+            let fallback = &json["__config"]["spa"]["fallback"];
+            let message = format!("spa fallback {} is missing or unreadable; the SPA fallback is disabled", fallback);
+            log(LogLevel::Warn, "spa_fallback_missing", serde_json::json!({ "fallback": fallback, "message": message }));
+        }
+        spa.map(Arc::new)
+    } else {
+        None
+    };
+
+    let metrics = json["__config"]["metrics"].as_bool().unwrap_or(false).then(|| Arc::new(Metrics::default()));
+
+    let summary = StartupSummary::enabled(&json["__config"], production_mode).then(|| StartupSummary {
+        port,
+        threads,
+        stack_mb,
+        exact_routes: map.len(),
+        dynamic_routes: dynamic_routes.len(),
+        precomputed_routes: precomputed.len(),
+        fast_path_actions: fast_paths.len(),
+        extensions: extensions::external::loaded_extension_names(),
+        features: vec![
+            ("dev_mode", !production_mode),
+            ("reply_cache", reply_cache.is_some()),
+            ("static_files", !static_files.is_empty()),
+            ("spa_fallback", spa.is_some()),
+            ("cors", cors.is_some()),
+            ("compression", compression.is_some()),
+            ("response_cache", response_cache.is_some()),
+            ("cpu_affinity", cpu_affinity),
+            ("elastic_pool", pool_max > threads),
+            ("metrics", metrics.is_some()),
+            ("heapdump", heapdump),
+            ("hot_reload", hot_reload),
+        ],
+    });
+
+    let connections = ConnectionConfig::from_config(&json["__config"]);
+
+    let shutdown_grace = std::time::Duration::from_millis(
+        json["__config"]["shutdownGraceMs"].as_u64().or_else(|| json["__config"]["shutdown_grace_ms"].as_u64()).unwrap_or(10_000),
+    );
+
+    let undefined_status = json["__config"]["undefinedStatus"]
+        .as_u64()
+        .or_else(|| json["__config"]["undefined_status"].as_u64())
+        .and_then(|s| StatusCode::from_u16(s as u16).ok())
+        .unwrap_or(StatusCode::NO_CONTENT);
+
+    let string_as_json = json["__config"]["stringAsJson"].as_bool().or_else(|| json["__config"]["string_as_json"].as_bool()).unwrap_or(false);
+
+    let expose_errors = json["__config"]["exposeErrors"].as_bool().or_else(|| json["__config"]["expose_errors"].as_bool()).unwrap_or(true);
 
     let state = AppState {
         routes: Arc::new(map),
@@ -473,29 +1237,151 @@ async fn main() -> Result<()> {
         fast_paths: Arc::new(fast_paths),
         precomputed: Arc::new(precomputed),
         production_mode,
+        compression: compression.map(Arc::new),
+        response_cache,
+        proxy_client: proxy::client(),
+        max_body_bytes,
+        body_spill_bytes,
+        bodyless_methods: Arc::new(bodyless_methods),
+        multipart_limits,
+        cors,
+        static_files: Arc::new(static_files),
+        spa,
+        trailing_slash,
+        etag,
+        trust_proxy,
+        access_log,
+        metrics,
+        expose_errors,
+        undefined_status,
+        string_as_json,
+        heapdump_token,
         ws_sockets: Arc::new(DashMap::new()),
     };
 
     extensions::WS_CHANNELS.get_or_init(|| state.ws_sockets.clone());
     extensions::TASK_RUNTIME.get_or_init(|| state.runtime.clone());
+    let state_runtime = state.runtime.clone();
+
+    // Probes are registered ahead of the fallback so they never reach action lookup
+    let mut app = Router::new().route("/", any(root_route));
+    if let Some(path) = healthz_path.as_deref() {
+        app = app.route(path, get(healthz));
+    }
+    if let Some(path) = readyz_path.as_deref().filter(|p| Some(*p) != healthz_path.as_deref()) {
+        app = app.route(path, get(readyz));
+    }
+    if state.metrics.is_some() {
+        app = app.route("/metrics", get(metrics_route));
+    }
+    if heapdump {
+        app = app.route("/__heapdump", get(heapdump_route));
+    }
+    let app = app.fallback(any(dynamic_route)).with_state(state);
+
+    // Graceful shutdown: stop accepting on SIGTERM/Ctrl-C, then give in-flight
+    // requests (including ones suspended on drift) the grace period to finish
+    let runtime = state_runtime;
+    let stop = Arc::new(tokio::sync::Notify::new());
+    let stop_signal = stop.clone();
+    let server = match &bind_addr {
+        BindAddr::Tcp(addr) => {
+            let listener = TcpListener::bind(*addr).await.map_err(|e| anyhow::anyhow!("failed to bind {}: {}", addr, e))?;
+            let tcp_nodelay = connections.tcp_nodelay;
+            let listener = listener.tap_io(move |tcp| {
+                let _ = tcp.set_nodelay(tcp_nodelay);
+            });
+            tokio::spawn(serve::serve(listener, app, connections, async move { stop_signal.notified().await }))
+        }
+        #[cfg(unix)]
+        BindAddr::Unix(path) => {
+            // A socket left behind by a previous run would make bind fail
+            if std::fs::symlink_metadata(path).is_ok_and(|m| std::os::unix::fs::FileTypeExt::is_socket(&m.file_type())) {
+                let _ = std::fs::remove_file(path);
+            }
+            let listener = tokio::net::UnixListener::bind(path).map_err(|e| anyhow::anyhow!("failed to bind unix:{}: {}", path.display(), e))?;
+            tokio::spawn(serve::serve(listener, app, connections, async move { stop_signal.notified().await }))
+        }
+        #[cfg(not(unix))]
+        BindAddr::Unix(_) => unreachable!("rejected by BindAddr::parse"),
+    };
+
+    log_with(
+        LogLevel::Info,
+        "listening",
+        serde_json::json!({ "address": bind_addr.to_string(), "port": port, "threads": threads, "stackMb": stack_mb, "dev": !production_mode }),
+        || format!("\x1b[38;5;39mTitan server running at:\x1b[0m {}  \x1b[90m(Threads: {}, Stack: {}MB{})\x1b[0m", bind_addr.url(), threads, stack_mb, if production_mode { "" } else { ", Dev Mode" }),
+    );
+    if let Some(summary) = summary {
+        summary.print();
+    }
 
-    let app = Router::new()
-        .route("/", any(root_route))
-        .fallback(any(dynamic_route))
-        .with_state(state);
+    shutdown_signal().await;
+    let in_flight = runtime.in_flight();
+    log_with(LogLevel::Info, "shutdown", serde_json::json!({ "inFlight": in_flight, "graceMs": shutdown_grace.as_millis() as u64 }), || {
+        format!("{} shutting down, draining {} in-flight request(s)", gravity::utils::blue("[Titan]"), in_flight)
+    });
+    stop.notify_one();
 
-    let listener = TcpListener::bind(format!("0.0.0.0:{}", port)).await?;
-    println!("\x1b[38;5;39mTitan server running at:\x1b[0m http://localhost:{}  \x1b[90m(Threads: {}, Stack: {}MB{})\x1b[0m", port, threads, stack_mb, if production_mode { "" } else { ", Dev Mode" });
+    let drained_cleanly = matches!(tokio::time::timeout(shutdown_grace, server).await, Ok(Ok(Ok(()))));
+    let force_closed = runtime.in_flight();
+    let drained = in_flight.saturating_sub(force_closed);
+    log_with(LogLevel::Info, "shutdown_complete", serde_json::json!({ "drained": drained, "forceClosed": force_closed }), || {
+        format!("{} shutdown complete: {} drained, {} force-closed", gravity::utils::blue("[Titan]"), drained, force_closed)
+    });
+
+    if let BindAddr::Unix(path) = &bind_addr {
+        let _ = std::fs::remove_file(path);
+    }
 
-    axum::serve(listener, app).await?;
+    // Workers only get a clean exit when nothing is left mid-replay
+    if drained_cleanly && force_closed == 0 {
+        let _ = tokio::task::spawn_blocking(move || runtime.shutdown()).await;
+    }
     Ok(())
 }
 
+/// Resolves on Ctrl-C, or SIGTERM on Unix (what orchestrators send).
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        let _ = tokio::signal::ctrl_c().await;
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut sig) => {
+                sig.recv().await;
+            }
+            Err(_) => std::future::pending::<()>().await,
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+}
+
+/// A WebSocket event as the action sees it: `WS /ws`, with the socket id
+/// and event name as headers.
+fn ws_event(id: &str, event: &str, body: Option<bytes::Bytes>) -> RequestContext {
+    RequestContext {
+        method: "WS".to_string(),
+        path: "/ws".to_string(),
+        body,
+        headers: smallvec::smallvec![("socketId".to_string(), id.to_string()), ("event".to_string(), event.to_string())],
+        ..Default::default()
+    }
+}
+
 async fn handle_websocket(socket: WebSocket, id: String, action: String, state: AppState) {
     let (tx, mut rx) = mpsc::unbounded_channel();
     state.ws_sockets.insert(id.clone(), tx);
 
-    let _ = state.runtime.execute(action.clone(), "WS".to_string(), "/ws".to_string(), None, smallvec::smallvec![("socketId".to_string(), id.clone()), ("event".to_string(), "open".to_string())], smallvec::smallvec![], smallvec::smallvec![]).await;
+    let _ = state.runtime.execute(action.clone(), ws_event(&id, "open", None)).await;
 
     let (mut sender, mut receiver) = socket.split();
     let id_clone = id.clone();
@@ -519,7 +1405,7 @@ async fn handle_websocket(socket: WebSocket, id: String, action: String, state:
         while let Some(Ok(msg)) = receiver.next().await {
             match msg {
                 Message::Text(t) => {
-                    let _ = state_clone.runtime.execute(action_clone.clone(), "WS".to_string(), "/ws".to_string(), Some(bytes::Bytes::from(t.as_str().to_string())), smallvec::smallvec![("socketId".to_string(), id_clone.clone()), ("event".to_string(), "message".to_string())], smallvec::smallvec![], smallvec::smallvec![]).await;
+                    let _ = state_clone.runtime.execute(action_clone.clone(), ws_event(&id_clone, "message", Some(bytes::Bytes::from(t.as_str().to_string())))).await;
                 }
                 Message::Close(_) => break,
                 _ => {}
@@ -529,5 +1415,5 @@ async fn handle_websocket(socket: WebSocket, id: String, action: String, state:
 
     tokio::select! { _ = (&mut send_task) => recv_task.abort(), _ = (&mut recv_task) => send_task.abort(), };
     state.ws_sockets.remove(&id);
-    let _ = state.runtime.execute(action, "WS".to_string(), "/ws".to_string(), None, smallvec::smallvec![("socketId".to_string(), id), ("event".to_string(), "close".to_string())], smallvec::smallvec![], smallvec::smallvec![]).await;
+    let _ = state.runtime.execute(action, ws_event(&id, "close", None)).await;
 }
@@ -0,0 +1,154 @@
+//! Prometheus Metrics
+//!
+//! With `__config.metrics: true` the server answers `/metrics` in the
+//! Prometheus text format. Requests are labelled by the matched route
+//! pattern (`/users/:id`), never the raw path, so cardinality is bounded by
+//! the routes in `routes.json`; anything unrouted shares `route="unmatched"`.
+//! Action and drift durations come from the same `timings` the worker
+//! reports for `Server-Timing`.
+
+use dashmap::DashMap;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use gravity::RuntimeManager;
+
+/// Upper bounds in seconds, shared by every histogram.
+const BUCKETS: [f64; 13] = [0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+/// Route label for requests no route claimed (404s, static files, early rejections).
+pub const UNMATCHED: &str = "unmatched";
+
+/// Route pattern the handler matched, carried on the response so the
+/// request counter can label it after CORS and compression have run.
+#[derive(Clone)]
+pub struct MatchedRoute(pub String);
+
+#[derive(Default)]
+struct Histogram {
+    buckets: [AtomicU64; BUCKETS.len()],
+    count: AtomicU64,
+    /// Sum in microseconds; f64 has no atomic add
+    sum_micros: AtomicU64,
+}
+
+impl Histogram {
+    fn observe(&self, seconds: f64) {
+        for (bucket, bound) in self.buckets.iter().zip(BUCKETS) {
+            if seconds <= bound {
+                bucket.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.sum_micros.fetch_add((seconds * 1_000_000.0) as u64, Ordering::Relaxed);
+    }
+
+    fn render(&self, out: &mut String, name: &str, labels: &str) {
+        let sep = if labels.is_empty() { "" } else { "," };
+        for (bucket, bound) in self.buckets.iter().zip(BUCKETS) {
+            let _ = writeln!(out, "{}_bucket{{{}{}le=\"{}\"}} {}", name, labels, sep, bound, bucket.load(Ordering::Relaxed));
+        }
+        let count = self.count.load(Ordering::Relaxed);
+        let _ = writeln!(out, "{}_bucket{{{}{}le=\"+Inf\"}} {}", name, labels, sep, count);
+        let braces = if labels.is_empty() { String::new() } else { format!("{{{}}}", labels) };
+        let sum = self.sum_micros.load(Ordering::Relaxed) as f64 / 1_000_000.0;
+        let _ = writeln!(out, "{}_sum{} {}", name, braces, sum);
+        let _ = writeln!(out, "{}_count{} {}", name, braces, count);
+    }
+}
+
+#[derive(Default)]
+pub struct Metrics {
+    /// (method, route, status) → count
+    requests: DashMap<(String, String, u16), AtomicU64>,
+    /// route → V8 time excluding drift and queue wait
+    action_seconds: DashMap<String, Histogram>,
+    /// route → time suspended on drift operations
+    drift_seconds: DashMap<String, Histogram>,
+    /// Wait for a free worker before execution began
+    queue_seconds: Histogram,
+}
+
+impl Metrics {
+    pub fn record_request(&self, method: &str, route: &str, status: u16) {
+        // Clients can send any method token; keep the label set closed
+        let method = match method {
+            "GET" | "HEAD" | "POST" | "PUT" | "PATCH" | "DELETE" | "OPTIONS" => method,
+            _ => "OTHER",
+        };
+        let key = (method.to_string(), route.to_string(), status);
+        if let Some(counter) = self.requests.get(&key) {
+            counter.fetch_add(1, Ordering::Relaxed);
+            return;
+        }
+        self.requests.entry(key).or_default().fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Split an action's worker timings into compute, drift and queue time.
+    pub fn record_action(&self, route: &str, total_ms: f64, timings: &[(String, f64)]) {
+        let drift_ms: f64 = timings.iter().filter(|(n, _)| n == "drift" || n == "drift_error").map(|(_, d)| d).sum();
+        let queue_ms: f64 = timings.iter().filter(|(n, _)| n == "queue").map(|(_, d)| d).sum();
+        let compute_ms = (total_ms - drift_ms - queue_ms).max(0.0);
+
+        self.action_seconds.entry(route.to_string()).or_default().observe(compute_ms / 1000.0);
+        if drift_ms > 0.0 {
+            self.drift_seconds.entry(route.to_string()).or_default().observe(drift_ms / 1000.0);
+        }
+        self.queue_seconds.observe(queue_ms / 1000.0);
+    }
+
+    /// Prometheus text exposition format (version 0.0.4).
+    pub fn render(&self, runtime: &RuntimeManager) -> String {
+        let mut out = String::new();
+
+        out.push_str("# HELP titan_requests_total HTTP requests by method, route pattern and status.\n");
+        out.push_str("# TYPE titan_requests_total counter\n");
+        let mut requests: Vec<_> = self.requests.iter().map(|e| (e.key().clone(), e.value().load(Ordering::Relaxed))).collect();
+        requests.sort();
+        for ((method, route, status), count) in requests {
+            let _ = writeln!(
+                out,
+                "titan_requests_total{{method=\"{}\",route=\"{}\",status=\"{}\"}} {}",
+                escape(&method),
+                escape(&route),
+                status,
+                count
+            );
+        }
+
+        for (name, help, histograms) in [
+            ("titan_action_duration_seconds", "Action execution time in V8, excluding drift and queue wait.", &self.action_seconds),
+            ("titan_drift_duration_seconds", "Time actions spent suspended on drift operations.", &self.drift_seconds),
+        ] {
+            let _ = writeln!(out, "# HELP {} {}", name, help);
+            let _ = writeln!(out, "# TYPE {} histogram", name);
+            let mut routes: Vec<String> = histograms.iter().map(|e| e.key().clone()).collect();
+            routes.sort();
+            for route in routes {
+                if let Some(histogram) = histograms.get(&route) {
+                    histogram.render(&mut out, name, &format!("route=\"{}\"", escape(&route)));
+                }
+            }
+        }
+
+        out.push_str("# HELP titan_queue_duration_seconds Wait for a free worker before an action started.\n");
+        out.push_str("# TYPE titan_queue_duration_seconds histogram\n");
+        self.queue_seconds.render(&mut out, "titan_queue_duration_seconds", "");
+
+        let size = runtime.pool_size();
+        let busy = runtime.busy_workers().min(size);
+        out.push_str("# HELP titan_workers Isolate pool workers by state.\n");
+        out.push_str("# TYPE titan_workers gauge\n");
+        let _ = writeln!(out, "titan_workers{{state=\"busy\"}} {}", busy);
+        let _ = writeln!(out, "titan_workers{{state=\"free\"}} {}", size - busy);
+        out.push_str("# HELP titan_requests_in_flight Actions executing or suspended on a drift.\n");
+        out.push_str("# TYPE titan_requests_in_flight gauge\n");
+        let _ = writeln!(out, "titan_requests_in_flight {}", runtime.in_flight());
+
+        out
+    }
+}
+
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
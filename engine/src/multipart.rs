@@ -0,0 +1,89 @@
+//! Multipart Form Parsing
+//!
+//! `multipart/form-data` bodies are parsed straight off the request stream
+//! with `multer`, so uploads are never collected into one string first. The
+//! result reaches actions as `req.formData`.
+//!
+//! Limits come from `__config.multipart` (`maxFileBytes`, `maxTotalBytes`);
+//! exceeding either answers 413.
+
+use axum::body::Body;
+use axum::http::StatusCode;
+use gravity::extensions::{FormData, FormFile};
+use serde::Deserialize;
+
+#[derive(Clone, Copy, Deserialize)]
+pub struct MultipartLimits {
+    /// Per part (file or field) limit in bytes.
+    #[serde(default = "default_max_file", alias = "maxFileBytes")]
+    pub max_file_bytes: u64,
+    /// Whole body limit in bytes.
+    #[serde(default = "default_max_total", alias = "maxTotalBytes")]
+    pub max_total_bytes: u64,
+}
+
+fn default_max_file() -> u64 {
+    10 * 1024 * 1024
+}
+
+fn default_max_total() -> u64 {
+    50 * 1024 * 1024
+}
+
+impl Default for MultipartLimits {
+    fn default() -> Self {
+        Self { max_file_bytes: default_max_file(), max_total_bytes: default_max_total() }
+    }
+}
+
+/// Boundary of a `multipart/form-data` content type, or `None` for any other body.
+pub fn boundary(content_type: &str) -> Option<String> {
+    if !content_type.trim_start().to_ascii_lowercase().starts_with("multipart/form-data") {
+        return None;
+    }
+    multer::parse_boundary(content_type).ok()
+}
+
+/// Stream the body into fields and files. Errors carry the status to answer with.
+pub async fn parse(body: Body, boundary: String, limits: MultipartLimits) -> Result<FormData, (StatusCode, String)> {
+    let constraints = multer::Constraints::new().size_limit(
+        multer::SizeLimit::new()
+            .whole_stream(limits.max_total_bytes)
+            .per_field(limits.max_file_bytes),
+    );
+    let mut multipart = multer::Multipart::with_constraints(body.into_data_stream(), boundary, constraints);
+    let mut form = FormData::default();
+
+    loop {
+        let field = match multipart.next_field().await {
+            Ok(Some(field)) => field,
+            Ok(None) => break,
+            Err(e) => return Err(reject(e)),
+        };
+        let name = field.name().unwrap_or("").to_string();
+        match field.file_name().map(str::to_string) {
+            Some(filename) => {
+                let content_type = field
+                    .content_type()
+                    .map(|m| m.to_string())
+                    .unwrap_or_else(|| "application/octet-stream".to_string());
+                let bytes = field.bytes().await.map_err(reject)?;
+                form.files.push(FormFile { name, filename, content_type, bytes });
+            }
+            None => {
+                let value = field.text().await.map_err(reject)?;
+                form.fields.push((name, value));
+            }
+        }
+    }
+
+    Ok(form)
+}
+
+fn reject(err: multer::Error) -> (StatusCode, String) {
+    let status = match err {
+        multer::Error::FieldSizeExceeded { .. } | multer::Error::StreamSizeExceeded { .. } => StatusCode::PAYLOAD_TOO_LARGE,
+        _ => StatusCode::BAD_REQUEST,
+    };
+    (status, err.to_string())
+}
@@ -0,0 +1,108 @@
+//! Streaming Reverse Proxy
+//!
+//! `t.get("/upload").proxy("https://upstream/upload")` writes a `proxy`
+//! route. The request body is piped to the upstream as it arrives and the
+//! upstream response is piped back the same way, so uploads and downloads
+//! of any size pass through in constant memory. V8 is never involved.
+
+use axum::body::Body;
+use axum::extract::Request as AxumRequest;
+use axum::http::{header, HeaderMap, HeaderName, Response, StatusCode};
+use axum::response::IntoResponse;
+
+/// Connection-scoped headers that must not be forwarded (RFC 9110 §7.6.1).
+const HOP_BY_HOP: [HeaderName; 7] = [
+    header::CONNECTION,
+    HeaderName::from_static("keep-alive"),
+    header::PROXY_AUTHENTICATE,
+    header::PROXY_AUTHORIZATION,
+    header::TE,
+    header::TRANSFER_ENCODING,
+    header::UPGRADE,
+];
+
+/// Client shared by all proxy routes. Decompression stays off so encoded
+/// upstream bodies pass through byte for byte.
+pub fn client() -> reqwest::Client {
+    reqwest::Client::builder()
+        .no_gzip()
+        .no_brotli()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .expect("proxy client")
+}
+
+/// `headers` without the hop-by-hop ones, including any the sender declared
+/// connection-scoped by listing them in `Connection`.
+fn forwardable(headers: &HeaderMap) -> HeaderMap {
+    let mut out = headers.clone();
+    let listed = headers
+        .get_all(header::CONNECTION)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .filter_map(|name| HeaderName::from_bytes(name.trim().as_bytes()).ok());
+    for name in listed {
+        out.remove(name);
+    }
+    for name in HOP_BY_HOP.iter() {
+        out.remove(name);
+    }
+    out.remove(header::TRAILER);
+    out
+}
+
+/// Forward `req` to `upstream` (its query string is appended) and stream the answer back.
+pub async fn forward(client: &reqwest::Client, upstream: &str, req: AxumRequest) -> Response<Body> {
+    let (parts, body) = req.into_parts();
+    let url = match parts.uri.query() {
+        Some(q) if upstream.contains('?') => format!("{}&{}", upstream, q),
+        Some(q) => format!("{}?{}", upstream, q),
+        None => upstream.to_string(),
+    };
+
+    let mut headers = forwardable(&parts.headers);
+    // reqwest derives Host from the upstream URL
+    headers.remove(header::HOST);
+
+    let upstream_resp = client
+        .request(parts.method, &url)
+        .headers(headers)
+        .body(reqwest::Body::wrap_stream(body.into_data_stream()))
+        .send()
+        .await;
+
+    let upstream_resp = match upstream_resp {
+        Ok(r) => r,
+        Err(e) => return (StatusCode::BAD_GATEWAY, format!("Upstream request failed: {}", e)).into_response(),
+    };
+
+    let mut builder = Response::builder().status(upstream_resp.status());
+    if let Some(h) = builder.headers_mut() {
+        *h = forwardable(upstream_resp.headers());
+    }
+    builder
+        .body(Body::from_stream(upstream_resp.bytes_stream()))
+        .unwrap_or_else(|_| Response::new(Body::empty()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    #[test]
+    fn forwardable_drops_headers_named_in_connection() {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::CONNECTION, HeaderValue::from_static("keep-alive, X-Session-Hop"));
+        headers.append(header::CONNECTION, HeaderValue::from_static("x-other-hop"));
+        headers.insert("x-session-hop", HeaderValue::from_static("1"));
+        headers.insert("x-other-hop", HeaderValue::from_static("2"));
+        headers.insert("keep-alive", HeaderValue::from_static("timeout=5"));
+        headers.insert(header::CONTENT_TYPE, HeaderValue::from_static("text/plain"));
+
+        let out = forwardable(&headers);
+        assert_eq!(out.len(), 1);
+        assert_eq!(out[header::CONTENT_TYPE], "text/plain");
+    }
+}
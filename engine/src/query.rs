@@ -0,0 +1,248 @@
+//! Query Strings
+//!
+//! `req.query` keys and values are percent-decoded, with `+` as a space as in
+//! HTML forms; a repeated key (`?tag=a&tag=b`) collects into an array instead
+//! of keeping only one of them.
+//!
+//! A route's `querySchema` in routes.json (`{ "page": "number", "tags":
+//! "string[]" }`) coerces the listed keys before the action runs: `string`,
+//! `number`, `integer` or `boolean`, with `[]` for a list. A value that does
+//! not fit answers 400 naming the key; keys the schema leaves out stay
+//! strings.
+
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Kind {
+    String,
+    Number,
+    Integer,
+    Boolean,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct FieldType {
+    kind: Kind,
+    list: bool,
+}
+
+impl FieldType {
+    fn parse(spec: &str) -> Option<Self> {
+        let spec = spec.trim();
+        let (name, list) = match spec.strip_suffix("[]") {
+            Some(name) => (name, true),
+            None => (spec, false),
+        };
+        let kind = match name {
+            "string" => Kind::String,
+            "number" => Kind::Number,
+            "integer" => Kind::Integer,
+            "boolean" => Kind::Boolean,
+            _ => return None,
+        };
+        Some(Self { kind, list })
+    }
+
+    fn name(&self) -> &'static str {
+        match self.kind {
+            Kind::String => "a string",
+            Kind::Number => "a number",
+            Kind::Integer => "an integer",
+            Kind::Boolean => "true or false",
+        }
+    }
+}
+
+/// A route's `querySchema`. Entries with an unknown type are dropped with a
+/// warning at startup, leaving that key a plain string.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(from = "HashMap<String, String>")]
+pub struct QuerySchema {
+    fields: HashMap<String, FieldType>,
+}
+
+impl From<HashMap<String, String>> for QuerySchema {
+    fn from(raw: HashMap<String, String>) -> Self {
+        let mut fields = HashMap::with_capacity(raw.len());
+        for (key, spec) in raw {
+            match FieldType::parse(&spec) {
+                Some(ty) => {
+                    fields.insert(key, ty);
+                }
+                None => gravity::utils::log(
+                    gravity::utils::LogLevel::Warn,
+                    "query_schema",
+                    serde_json::json!({ "message": format!("unknown type '{}' for query key '{}'; left as a string", spec, key) }),
+                ),
+            }
+        }
+        Self { fields }
+    }
+}
+
+/// Split a raw query string into decoded `(key, value)` pairs in order,
+/// keeping repeats. Splitting happens first, so an encoded `%26` stays inside
+/// its value.
+pub fn pairs(query: Option<&str>) -> Vec<(String, String)> {
+    query
+        .map(|q| {
+            q.split('&')
+                .filter(|pair| !pair.is_empty())
+                .map(|pair| {
+                    let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+                    (percent_decode(key, true), percent_decode(value, true))
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Decode `%XX` escapes (and `+` as a space when `plus_as_space`). A `%` not
+/// followed by two hex digits is kept as-is, and bytes that don't form valid
+/// UTF-8 become U+FFFD.
+pub fn percent_decode(input: &str, plus_as_space: bool) -> String {
+    if !input.contains(['%', '+']) {
+        return input.to_string();
+    }
+    let hex = |b: u8| (b as char).to_digit(16).map(|d| d as u8);
+    let bytes = input.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' => match (bytes.get(i + 1).copied().and_then(hex), bytes.get(i + 2).copied().and_then(hex)) {
+                (Some(hi), Some(lo)) => {
+                    out.push(hi << 4 | lo);
+                    i += 3;
+                    continue;
+                }
+                _ => out.push(b'%'),
+            },
+            b'+' if plus_as_space => out.push(b' '),
+            b => out.push(b),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// `req.query` entries: one per key, repeats as arrays, coerced by `schema`.
+/// `Err` carries the message for a 400.
+pub fn build(pairs: Vec<(String, String)>, schema: Option<&QuerySchema>) -> Result<Vec<(String, Value)>, String> {
+    let mut grouped: Vec<(String, Vec<String>)> = Vec::with_capacity(pairs.len());
+    for (key, value) in pairs {
+        match grouped.iter_mut().find(|(k, _)| *k == key) {
+            Some((_, values)) => values.push(value),
+            None => grouped.push((key, vec![value])),
+        }
+    }
+
+    grouped
+        .into_iter()
+        .map(|(key, mut values)| {
+            let value = match schema.and_then(|s| s.fields.get(&key)) {
+                Some(ty) => coerce(&key, values, *ty)?,
+                None if values.len() == 1 => Value::String(values.pop().unwrap_or_default()),
+                None => Value::Array(values.into_iter().map(Value::String).collect()),
+            };
+            Ok((key, value))
+        })
+        .collect()
+}
+
+fn coerce(key: &str, values: Vec<String>, ty: FieldType) -> Result<Value, String> {
+    if !ty.list && values.len() > 1 {
+        return Err(format!("Query parameter '{}' expects a single value, got {}", key, values.len()));
+    }
+    let mut out = Vec::with_capacity(values.len());
+    for raw in values {
+        let value = match ty.kind {
+            Kind::String => Some(Value::String(raw.clone())),
+            Kind::Number => raw.parse::<f64>().ok().and_then(serde_json::Number::from_f64).map(Value::Number),
+            Kind::Integer => raw.parse::<i64>().ok().map(Value::from),
+            Kind::Boolean => match raw.as_str() {
+                "true" | "1" => Some(Value::Bool(true)),
+                "false" | "0" => Some(Value::Bool(false)),
+                _ => None,
+            },
+        };
+        match value {
+            Some(v) => out.push(v),
+            None => return Err(format!("Query parameter '{}' must be {}, got '{}'", key, ty.name(), raw)),
+        }
+    }
+    Ok(if ty.list { Value::Array(out) } else { out.pop().unwrap_or(Value::Null) })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn schema(fields: &[(&str, &str)]) -> QuerySchema {
+        fields.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect::<HashMap<_, _>>().into()
+    }
+
+    fn query(raw: &str, schema: Option<&QuerySchema>) -> Result<Vec<(String, Value)>, String> {
+        build(pairs(Some(raw)), schema)
+    }
+
+    #[test]
+    fn pairs_decode_and_keep_repeats() {
+        assert_eq!(
+            pairs(Some("a=1&name=J%C3%BCrgen+M&a=2&&flag&q=x%26y")),
+            vec![
+                ("a".to_string(), "1".to_string()),
+                ("name".to_string(), "Jürgen M".to_string()),
+                ("a".to_string(), "2".to_string()),
+                ("flag".to_string(), String::new()),
+                ("q".to_string(), "x&y".to_string()),
+            ]
+        );
+        assert!(pairs(None).is_empty());
+    }
+
+    #[test]
+    fn percent_decode_keeps_broken_escapes() {
+        assert_eq!(percent_decode("100%", true), "100%");
+        assert_eq!(percent_decode("%zz+%41", true), "%zz A");
+        assert_eq!(percent_decode("a+b", false), "a+b");
+        assert_eq!(percent_decode("%FF", true), "\u{FFFD}");
+    }
+
+    #[test]
+    fn repeated_keys_become_arrays() {
+        assert_eq!(
+            query("tag=a&tag=b&page=2", None).unwrap(),
+            vec![("tag".to_string(), json!(["a", "b"])), ("page".to_string(), json!("2"))]
+        );
+    }
+
+    #[test]
+    fn schema_coerces_listed_keys() {
+        let schema = schema(&[("page", "integer"), ("ratio", "number"), ("on", "boolean"), ("ids", "integer[]")]);
+        let built: HashMap<String, Value> = query("page=3&ratio=0.5&on=1&ids=1&ids=2&other=x", Some(&schema)).unwrap().into_iter().collect();
+        assert_eq!(built["page"], json!(3));
+        assert_eq!(built["ratio"], json!(0.5));
+        assert_eq!(built["on"], json!(true));
+        assert_eq!(built["ids"], json!([1, 2]));
+        assert_eq!(built["other"], json!("x"));
+    }
+
+    #[test]
+    fn schema_mismatches_name_the_key() {
+        let schema = schema(&[("page", "integer"), ("on", "boolean")]);
+        assert_eq!(query("page=two", Some(&schema)).unwrap_err(), "Query parameter 'page' must be an integer, got 'two'");
+        assert_eq!(query("on=yes", Some(&schema)).unwrap_err(), "Query parameter 'on' must be true or false, got 'yes'");
+        assert_eq!(query("page=1&page=2", Some(&schema)).unwrap_err(), "Query parameter 'page' expects a single value, got 2");
+    }
+
+    #[test]
+    fn unknown_schema_types_are_dropped() {
+        let schema = schema(&[("page", "float"), ("tags", " string[] ")]);
+        assert!(!schema.fields.contains_key("page"));
+        assert!(schema.fields["tags"].list);
+    }
+}
@@ -0,0 +1,105 @@
+//! Response Cache
+//!
+//! Enabled per route by `__config.cache` in routes.json, mapping a route as
+//! written there to a TTL in milliseconds:
+//! `{ "/products": 5000, "/products/:id": 30000 }`.
+//!
+//! GET and HEAD responses of those routes are stored by path and query
+//! string and served within the TTL without entering V8. Only 2xx responses
+//! without `Set-Cookie` or trailers are kept. Routes with middleware are
+//! never cached, because middleware has to run for every request. Entries live in
+//! `gravity::extensions::response_cache()` so an action can drop them with
+//! `t.cache.invalidate(prefix)`.
+
+use axum::body::{to_bytes, Body};
+use axum::http::{header, Response};
+use gravity::extensions::{response_cache, CachedResponse};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Expired entries are swept on insert once the map grows past this.
+const SWEEP_THRESHOLD: usize = 10_000;
+
+#[derive(Debug, Default)]
+pub struct ResponseCache {
+    ttls: HashMap<String, Duration>,
+}
+
+impl ResponseCache {
+    /// `None` unless at least one route has a positive TTL.
+    pub fn from_config(value: &Value) -> Option<Self> {
+        let ttls: HashMap<String, Duration> = value
+            .as_object()?
+            .iter()
+            .filter_map(|(route, ttl)| ttl.as_u64().filter(|ms| *ms > 0).map(|ms| (route.clone(), Duration::from_millis(ms))))
+            .collect();
+        (!ttls.is_empty()).then_some(Self { ttls })
+    }
+
+    /// TTL for a matched route pattern (exact path or `/users/:id`).
+    pub fn ttl(&self, route: &str) -> Option<Duration> {
+        self.ttls.get(route).copied()
+    }
+
+    pub fn key(path: &str, query: Option<&str>) -> String {
+        match query {
+            Some(q) if !q.is_empty() => format!("{}?{}", path, q),
+            _ => path.to_string(),
+        }
+    }
+
+    /// A fresh entry as a response. O(1) body clone via Bytes refcount.
+    pub fn get(key: &str) -> Option<Response<Body>> {
+        let cache = response_cache();
+        let entry = cache.get(key).map(|e| Arc::clone(e.value()))?;
+        if entry.expires <= Instant::now() {
+            cache.remove_if(key, |_, e| e.expires <= Instant::now());
+            return None;
+        }
+
+        let mut builder = Response::builder().status(entry.status);
+        for (name, value) in &entry.headers {
+            builder = builder.header(name, value);
+        }
+        builder.body(Body::from(entry.body.clone())).ok()
+    }
+
+    /// Keep a copy of `response` for `ttl` if it qualifies, and hand it back
+    /// with its body buffered.
+    pub async fn store(key: String, ttl: Duration, response: Response<Body>) -> Response<Body> {
+        let headers = response.headers();
+        if !response.status().is_success()
+            || headers.contains_key(header::SET_COOKIE)
+            || headers.contains_key(header::TRAILER)
+        {
+            return response;
+        }
+
+        let (parts, body) = response.into_parts();
+        let Ok(bytes) = to_bytes(body, usize::MAX).await else {
+            return Response::from_parts(parts, Body::empty());
+        };
+
+        let entry = CachedResponse {
+            status: parts.status.as_u16(),
+            headers: parts
+                .headers
+                .iter()
+                .filter_map(|(k, v)| v.to_str().ok().map(|vs| (k.to_string(), vs.to_string())))
+                .collect(),
+            body: bytes.clone(),
+            expires: Instant::now() + ttl,
+        };
+
+        let cache = response_cache();
+        if cache.len() >= SWEEP_THRESHOLD {
+            let now = Instant::now();
+            cache.retain(|_, e| e.expires > now);
+        }
+        cache.insert(key, Arc::new(entry));
+
+        Response::from_parts(parts, Body::from(bytes))
+    }
+}
@@ -0,0 +1,92 @@
+//! Request Body Spilling
+//!
+//! Enabled by `__config.bodySpillBytes` in routes.json. Bodies up to that
+//! many bytes are buffered in memory as usual. Past it, the first
+//! `bodySpillBytes` stay in memory and the remainder is streamed to a temp
+//! file, so a large upload never sits in memory whole. Actions read it through
+//! `req.bodyFile`; the file is removed once the request is done with it.
+//!
+//! `maxBodyBytes` still applies to the whole body.
+
+use axum::body::Body;
+use axum::http::StatusCode;
+use bytes::{Bytes, BytesMut};
+use futures_util::StreamExt;
+use gravity::extensions::SpilledBody;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::io::AsyncWriteExt;
+
+pub enum ReadBody {
+    Memory(Bytes),
+    Spilled(Arc<SpilledBody>),
+}
+
+/// Read `body`, spilling everything past `threshold` bytes to a temp file.
+pub async fn read(body: Body, threshold: usize, limit: usize) -> Result<ReadBody, (StatusCode, &'static str)> {
+    let mut stream = body.into_data_stream();
+    let mut head = BytesMut::new();
+    let mut spill: Option<(tokio::fs::File, PathBuf)> = None;
+    let mut total = 0usize;
+
+    while let Some(chunk) = stream.next().await {
+        let Ok(mut chunk) = chunk else {
+            return fail(spill, (StatusCode::BAD_REQUEST, "Failed to read request body")).await;
+        };
+        total += chunk.len();
+        if total > limit {
+            return fail(spill, (StatusCode::PAYLOAD_TOO_LARGE, "Payload Too Large")).await;
+        }
+
+        if spill.is_none() {
+            if head.len() + chunk.len() <= threshold {
+                head.extend_from_slice(&chunk);
+                continue;
+            }
+            head.extend_from_slice(&chunk.split_to(threshold - head.len()));
+            let path = std::env::temp_dir().join(format!("titan-body-{}", uuid::Uuid::new_v4()));
+            match create_private(&path).await {
+                Ok(file) => spill = Some((file, path)),
+                Err(_) => return Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to buffer request body")),
+            }
+        }
+
+        if let Some((file, _)) = spill.as_mut() {
+            if file.write_all(&chunk).await.is_err() {
+                return fail(spill, (StatusCode::INTERNAL_SERVER_ERROR, "Failed to buffer request body")).await;
+            }
+        }
+    }
+
+    match spill {
+        None => Ok(ReadBody::Memory(head.freeze())),
+        Some((mut file, path)) => {
+            if file.flush().await.is_err() {
+                return fail(Some((file, path)), (StatusCode::INTERNAL_SERVER_ERROR, "Failed to buffer request body")).await;
+            }
+            Ok(ReadBody::Spilled(Arc::new(SpilledBody { head: head.freeze(), path, len: total as u64 })))
+        }
+    }
+}
+
+/// A new file only this user can read. `create_new` refuses an existing path,
+/// so a file or symlink planted at the name in the shared temp dir is never
+/// written through.
+async fn create_private(path: &std::path::Path) -> std::io::Result<tokio::fs::File> {
+    let mut options = tokio::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    options.mode(0o600);
+    options.open(path).await
+}
+
+async fn fail(
+    spill: Option<(tokio::fs::File, PathBuf)>,
+    error: (StatusCode, &'static str),
+) -> Result<ReadBody, (StatusCode, &'static str)> {
+    if let Some((file, path)) = spill {
+        drop(file);
+        let _ = tokio::fs::remove_file(path).await;
+    }
+    Err(error)
+}
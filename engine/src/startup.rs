@@ -0,0 +1,62 @@
+//! Startup Diagnostics
+//!
+//! Structured summary of what the server loaded at boot, so operators can
+//! confirm `__config` took effect. Printed when `__config.startupSummary`
+//! is `true` (always in dev mode), as pretty text or JSON depending on
+//! `__config.logFormat` (see `gravity::utils::json_logs`).
+
+use gravity::utils::{blue, gray, green, log_with, white, LogLevel};
+use serde::Serialize;
+
+#[derive(Serialize)]
+pub struct StartupSummary {
+    pub port: u64,
+    pub threads: usize,
+    pub stack_mb: u64,
+    pub exact_routes: usize,
+    pub dynamic_routes: usize,
+    pub precomputed_routes: usize,
+    pub fast_path_actions: usize,
+    pub extensions: Vec<String>,
+    pub features: Vec<(&'static str, bool)>,
+}
+
+impl StartupSummary {
+    /// Whether the summary should be printed for this config.
+    pub fn enabled(config: &serde_json::Value, production_mode: bool) -> bool {
+        !production_mode || config["startupSummary"].as_bool().or_else(|| config["startup_summary"].as_bool()).unwrap_or(false)
+    }
+
+    pub fn print(&self) {
+        let features: serde_json::Map<String, serde_json::Value> = self
+            .features
+            .iter()
+            .map(|(name, on)| (name.to_string(), serde_json::Value::Bool(*on)))
+            .collect();
+        let mut out = serde_json::to_value(self).unwrap_or_default();
+        out["features"] = serde_json::Value::Object(features);
+        log_with(LogLevel::Info, "startup", serde_json::json!({ "summary": out }), || self.pretty());
+    }
+
+    fn pretty(&self) -> String {
+        let row = |label: &str, value: String| format!("\n  {} {}", gray(&format!("{:<20}", label)), white(&value));
+
+        let mut text = format!("{} {}", blue("[Titan]"), white("Startup summary"));
+        text += &row("Port", self.port.to_string());
+        text += &row("Threads / isolates", format!("{} (stack {}MB)", self.threads, self.stack_mb));
+        text += &row("Exact routes", format!("{} ({} precomputed)", self.exact_routes, self.precomputed_routes));
+        text += &row("Dynamic routes", self.dynamic_routes.to_string());
+        text += &row("Fast-path actions", self.fast_path_actions.to_string());
+        text += &row(
+            "Extensions",
+            if self.extensions.is_empty() {
+                "0".to_string()
+            } else {
+                format!("{} ({})", self.extensions.len(), self.extensions.join(", "))
+            },
+        );
+        let enabled: Vec<&str> = self.features.iter().filter(|(_, on)| *on).map(|(n, _)| *n).collect();
+        text += &row("Features", if enabled.is_empty() { "none".to_string() } else { green(&enabled.join(", ")) });
+        text
+    }
+}
@@ -0,0 +1,271 @@
+//! Static File Serving
+//!
+//! Serves files from the directories mounted by `__config.static`
+//! (`{ "/assets": "./public" }`) or `__config.staticDir`, for requests no
+//! route claims. Pre-compressed siblings (`app.js.br`, `app.js.gz`) written
+//! by frontend build tools are served as-is when the client accepts that
+//! encoding, so assets are never compressed per request.
+//!
+//! Every file carries an `ETag` and `Last-Modified` derived from its metadata;
+//! matching `If-None-Match` / `If-Modified-Since` requests get a 304.
+//!
+//! `__config.spa` (`{ "prefix": "/app", "fallback": "./public/index.html" }`)
+//! answers unmatched GET/HEAD requests under `prefix` with the fallback page,
+//! so client-side routes like `/app/users/42` load the app shell. Paths whose
+//! last segment has an extension (`/app/missing.js`) still get a 404.
+
+use axum::body::Body;
+use axum::http::{header, HeaderValue, Response, StatusCode};
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use crate::fast_path::{body_etag, etag_matches, PrecomputedRoute};
+
+/// Preference order for pre-compressed variants: (Content-Encoding, file suffix).
+const ENCODINGS: [(&str, &str); 2] = [("br", "br"), ("gzip", "gz")];
+
+/// IMF-fixdate, the only format `Last-Modified` is sent in.
+const HTTP_DATE: &str = "%a, %d %b %Y %H:%M:%S GMT";
+
+pub struct StaticFiles {
+    root: PathBuf,
+    prefix: String,
+}
+
+impl StaticFiles {
+    /// `None` if the directory does not exist.
+    pub fn new(root: &Path, prefix: &str) -> Option<Self> {
+        let root = root.canonicalize().ok()?;
+        if !root.is_dir() {
+            return None;
+        }
+        let prefix = format!("/{}", prefix.trim_matches('/'));
+        Some(Self { root, prefix })
+    }
+
+    /// URL prefix this directory is mounted at.
+    pub fn prefix(&self) -> &str {
+        &self.prefix
+    }
+
+    /// Resolve `path` inside the static root and build the response, or
+    /// `None` when no file matches (caller falls through to 404).
+    /// `headers` are the lowercased request headers.
+    pub async fn serve(&self, path: &str, headers: &HashMap<String, String>) -> Option<Response<Body>> {
+        let rel = path.strip_prefix(self.prefix.trim_end_matches('/'))?;
+        if !rel.is_empty() && !rel.starts_with('/') {
+            return None;
+        }
+        let mut rel = rel.trim_start_matches('/').to_string();
+        if rel.is_empty() || rel.ends_with('/') {
+            rel.push_str("index.html");
+        }
+
+        // Lexical guard first, then canonicalize to catch symlinks out of the root
+        if Path::new(&rel).components().any(|c| !matches!(c, Component::Normal(_))) {
+            return None;
+        }
+        let file = self.root.join(&rel).canonicalize().ok()?;
+        if !file.starts_with(&self.root) || !file.is_file() {
+            return None;
+        }
+
+        let accept_encoding = headers.get("accept-encoding").map(String::as_str);
+        let mut chosen = (file.clone(), None);
+        for (encoding, suffix) in ENCODINGS {
+            if !accepts(accept_encoding, encoding) {
+                continue;
+            }
+            let mut variant = file.clone().into_os_string();
+            variant.push(".");
+            variant.push(suffix);
+            // The variant can be a symlink of its own, so it gets the same containment check
+            let Ok(variant) = PathBuf::from(variant).canonicalize() else { continue };
+            if variant.starts_with(&self.root) && variant.is_file() {
+                chosen = (variant, Some(encoding));
+                break;
+            }
+        }
+        let (served, encoding) = chosen;
+
+        let meta = tokio::fs::metadata(&served).await.ok()?;
+        let modified: Option<DateTime<Utc>> = meta.modified().ok().map(DateTime::from);
+        let mtime = meta.modified().ok().and_then(|m| m.duration_since(UNIX_EPOCH).ok()).map_or(0, |d| d.as_secs());
+        let etag = HeaderValue::from_str(&format!("\"{:x}-{:x}\"", meta.len(), mtime)).ok()?;
+
+        let mut builder = Response::builder()
+            .header(header::CONTENT_TYPE, HeaderValue::from_static(content_type(&file)))
+            .header(header::VARY, HeaderValue::from_static("accept-encoding"))
+            .header(header::ETAG, etag.clone());
+        if let Some(modified) = modified {
+            builder = builder.header(header::LAST_MODIFIED, modified.format(HTTP_DATE).to_string());
+        }
+        if let Some(encoding) = encoding {
+            builder = builder.header(header::CONTENT_ENCODING, HeaderValue::from_static(encoding));
+        }
+
+        if not_modified(headers, &etag, modified) {
+            return builder.status(StatusCode::NOT_MODIFIED).body(Body::empty()).ok();
+        }
+
+        let bytes = tokio::fs::read(&served).await.ok()?;
+        builder.status(StatusCode::OK).body(Body::from(bytes)).ok()
+    }
+}
+
+/// The SPA shell, read once at startup and served like a reply route.
+pub struct SpaFallback {
+    prefix: String,
+    page: PrecomputedRoute,
+}
+
+impl SpaFallback {
+    /// `None` unless `fallback` names a readable file under `base`.
+    /// `prefix` defaults to `/`.
+    pub fn from_config(value: &serde_json::Value, base: &Path) -> Option<Self> {
+        let file = base.join(value["fallback"].as_str()?);
+        let html = std::fs::read_to_string(&file).ok()?;
+        let prefix = format!("/{}", value["prefix"].as_str().unwrap_or("/").trim_matches('/'));
+
+        let mut page = PrecomputedRoute::from_text(&html).with_content_type(Some("text/html; charset=utf-8"));
+        // Revalidated on every load, so a deploy's new asset hashes are picked up
+        page.cache_control = Some(HeaderValue::from_static("no-cache"));
+        page.etag = HeaderValue::from_str(&body_etag(&page.body)).ok();
+        Some(Self { prefix, page })
+    }
+
+    /// The fallback page for `path`, or `None` when it lies outside the
+    /// prefix or looks like a file.
+    pub fn serve(&self, path: &str, if_none_match: Option<&HeaderValue>) -> Option<Response<Body>> {
+        let rel = path.strip_prefix(self.prefix.trim_end_matches('/'))?;
+        if !rel.is_empty() && !rel.starts_with('/') {
+            return None;
+        }
+        if rel.rsplit('/').next().is_some_and(|segment| segment.contains('.')) {
+            return None;
+        }
+        Some(self.page.to_axum_response(if_none_match))
+    }
+}
+
+/// `t.response.file(path, { download })`: `path` resolved under the project
+/// root by the rules `t.read` uses, streamed from disk with a Content-Length.
+/// `builder` carries the action's status and headers; a Content-Type among
+/// them wins over the one guessed from the extension. Missing files and
+/// paths outside the root both answer 404.
+pub async fn send_file(mut builder: axum::http::response::Builder, path: &str, download: Option<&str>) -> Response<Body> {
+    let opened = match gravity::extensions::builtins::fs::resolve_in_project(path) {
+        Some(file) => tokio::fs::File::open(&file).await.ok().map(|handle| (file, handle)),
+        None => None,
+    };
+    let Some((file, handle)) = opened else {
+        return not_found();
+    };
+    let Some(meta) = handle.metadata().await.ok().filter(|m| m.is_file()) else {
+        return not_found();
+    };
+
+    if !builder.headers_ref().is_some_and(|h| h.contains_key(header::CONTENT_TYPE)) {
+        builder = builder.header(header::CONTENT_TYPE, HeaderValue::from_static(content_type(&file)));
+    }
+    if let Some(name) = download {
+        builder = builder.header(header::CONTENT_DISPOSITION, attachment(name));
+    }
+
+    let chunks = futures_util::stream::unfold(Some(handle), |handle| async move {
+        let mut handle = handle?;
+        let mut buf = bytes::BytesMut::with_capacity(64 * 1024);
+        match tokio::io::AsyncReadExt::read_buf(&mut handle, &mut buf).await {
+            Ok(0) => None,
+            Ok(_) => Some((Ok(buf.freeze()), Some(handle))),
+            Err(e) => Some((Err(e), None)),
+        }
+    });
+    builder
+        .header(header::CONTENT_LENGTH, meta.len())
+        .body(Body::from_stream(chunks))
+        .unwrap_or_else(|_| not_found())
+}
+
+fn not_found() -> Response<Body> {
+    let mut response = Response::new(Body::from("Not Found"));
+    *response.status_mut() = StatusCode::NOT_FOUND;
+    response
+}
+
+/// `attachment; filename="..."`, plus RFC 5987 `filename*` for names that
+/// are not plain ASCII.
+fn attachment(name: &str) -> String {
+    let fallback: String = name
+        .chars()
+        .map(|c| if c.is_ascii() && !c.is_ascii_control() && c != '"' && c != '\\' { c } else { '_' })
+        .collect();
+    if fallback == name {
+        return format!("attachment; filename=\"{}\"", name);
+    }
+    let encoded: String = name
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'.' | b'-' | b'_' | b'~' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect();
+    format!("attachment; filename=\"{}\"; filename*=UTF-8''{}", fallback, encoded)
+}
+
+/// `If-None-Match` wins when present; otherwise `If-Modified-Since` is
+/// compared at whole-second precision, as HTTP dates carry no more.
+fn not_modified(headers: &HashMap<String, String>, etag: &HeaderValue, modified: Option<DateTime<Utc>>) -> bool {
+    if let Some(inm) = headers.get("if-none-match") {
+        return HeaderValue::from_str(inm).is_ok_and(|inm| etag_matches(&inm, etag));
+    }
+    let since = headers
+        .get("if-modified-since")
+        .and_then(|v| DateTime::parse_from_rfc2822(v).ok());
+    match (since, modified) {
+        (Some(since), Some(modified)) => modified.timestamp() <= since.timestamp(),
+        _ => false,
+    }
+}
+
+/// Whether an `Accept-Encoding` header allows `encoding` (explicit `q=0` refuses it).
+pub(crate) fn accepts(accept_encoding: Option<&str>, encoding: &str) -> bool {
+    let Some(header) = accept_encoding else { return false };
+    header.split(',').any(|part| {
+        let mut pieces = part.split(';');
+        let name = pieces.next().unwrap_or("").trim();
+        let refused = pieces.any(|p| {
+            p.trim()
+                .strip_prefix("q=")
+                .and_then(|q| q.trim().parse::<f32>().ok())
+                .is_some_and(|q| q == 0.0)
+        });
+        (name.eq_ignore_ascii_case(encoding) || name == "*") && !refused
+    })
+}
+
+fn content_type(path: &Path) -> &'static str {
+    let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("").to_ascii_lowercase();
+    match ext.as_str() {
+        "html" | "htm" => "text/html; charset=utf-8",
+        "css" => "text/css; charset=utf-8",
+        "js" | "mjs" => "text/javascript; charset=utf-8",
+        "json" | "map" => "application/json",
+        "txt" => "text/plain; charset=utf-8",
+        "xml" => "application/xml",
+        "svg" => "image/svg+xml",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "avif" => "image/avif",
+        "ico" => "image/x-icon",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        "wasm" => "application/wasm",
+        "pdf" => "application/pdf",
+        _ => "application/octet-stream",
+    }
+}
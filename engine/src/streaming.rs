@@ -0,0 +1,209 @@
+//! Streaming Response Bodies
+//!
+//! Custom `http_body::Body` implementations for responses that cannot be
+//! expressed as a single buffered `Body::from(...)`.
+//!
+//! - `TrailerBody`: emits the action body followed by HTTP trailers
+//!   (`t.response.trailer(name, value)`).
+//! - `ChannelBody`: forwards chunks a worker writes while the action is still
+//!   running (`t.response.jsonArray(items)`), then the trailers it set.
+//! - `WithTrailers`: any other body (`t.response.file`) followed by trailers.
+
+use axum::http::{HeaderMap, HeaderName, HeaderValue};
+use bytes::Bytes;
+use gravity::extensions::{ResponseStream, StreamTrailers};
+use http_body::{Body as HttpBody, Frame, SizeHint};
+use std::convert::Infallible;
+use std::pin::Pin;
+use std::task::{Context, Poll, ready};
+use tokio::sync::mpsc::Receiver;
+
+/// Trailer pairs as a header map; pairs that aren't valid headers are dropped.
+fn trailer_map(trailers: &[(String, String)]) -> HeaderMap {
+    let mut map = HeaderMap::with_capacity(trailers.len());
+    for (k, v) in trailers {
+        if let (Ok(name), Ok(val)) = (HeaderName::from_bytes(k.as_bytes()), HeaderValue::from_str(v)) {
+            map.append(name, val);
+        }
+    }
+    map
+}
+
+/// A body that yields its data frame, then a single trailers frame.
+///
+/// The size hint is deliberately left open so hyper falls back to chunked
+/// transfer encoding — trailers cannot be sent with a fixed Content-Length.
+pub struct TrailerBody {
+    data: Option<Bytes>,
+    trailers: Option<HeaderMap>,
+}
+
+impl TrailerBody {
+    pub fn new(data: Bytes, trailers: &[(String, String)]) -> Self {
+        Self {
+            data: Some(data),
+            trailers: Some(trailer_map(trailers)),
+        }
+    }
+}
+
+impl HttpBody for TrailerBody {
+    type Data = Bytes;
+    type Error = Infallible;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Bytes>, Infallible>>> {
+        if let Some(data) = self.data.take() {
+            if !data.is_empty() {
+                return Poll::Ready(Some(Ok(Frame::data(data))));
+            }
+        }
+        if let Some(trailers) = self.trailers.take() {
+            return Poll::Ready(Some(Ok(Frame::trailers(trailers))));
+        }
+        Poll::Ready(None)
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.data.is_none() && self.trailers.is_none()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        SizeHint::default()
+    }
+}
+
+/// A body fed by a worker through `t._stream_write`. It ends when the action
+/// finishes and the worker drops the sending half, with a trailers frame if
+/// the action set any; dropping the body (client gone) makes further writes
+/// fail fast instead of blocking the worker.
+pub struct ChannelBody {
+    rx: Receiver<Bytes>,
+    trailers: Option<StreamTrailers>,
+}
+
+impl ChannelBody {
+    pub fn new(rx: Receiver<Bytes>) -> Self {
+        Self { rx, trailers: None }
+    }
+
+    /// The body of a streamed action response, trailers included.
+    pub fn from_stream(stream: ResponseStream) -> Self {
+        Self { rx: stream.rx, trailers: Some(stream.trailers) }
+    }
+}
+
+impl HttpBody for ChannelBody {
+    type Data = Bytes;
+    type Error = Infallible;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Bytes>, Infallible>>> {
+        if let Some(data) = ready!(self.rx.poll_recv(cx)) {
+            return Poll::Ready(Some(Ok(Frame::data(data))));
+        }
+        // The sender is gone, so the worker has already stored the trailers
+        let trailers = self.trailers.take().map(|slot| std::mem::take(&mut *slot.lock().unwrap()));
+        Poll::Ready(trailers.filter(|t| !t.is_empty()).map(|t| Ok(Frame::trailers(trailer_map(&t)))))
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        SizeHint::default()
+    }
+}
+
+/// `inner`'s frames followed by a trailers frame. The size hint is left open
+/// for the same reason as [`TrailerBody`]'s.
+pub struct WithTrailers<B> {
+    inner: B,
+    trailers: Option<HeaderMap>,
+}
+
+impl<B> WithTrailers<B> {
+    pub fn new(inner: B, trailers: &[(String, String)]) -> Self {
+        Self { inner, trailers: Some(trailer_map(trailers)) }
+    }
+}
+
+impl<B> HttpBody for WithTrailers<B>
+where
+    B: HttpBody<Data = Bytes> + Unpin,
+{
+    type Data = Bytes;
+    type Error = B::Error;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Bytes>, B::Error>>> {
+        if let Some(frame) = ready!(Pin::new(&mut self.inner).poll_frame(cx)) {
+            return Poll::Ready(Some(frame));
+        }
+        Poll::Ready(self.trailers.take().map(|t| Ok(Frame::trailers(t))))
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        SizeHint::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http_body_util::BodyExt;
+
+    fn pairs(list: &[(&str, &str)]) -> Vec<(String, String)> {
+        list.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[tokio::test]
+    async fn trailer_body_sends_data_then_trailers() {
+        let collected = TrailerBody::new(Bytes::from("hello"), &pairs(&[("x-checksum", "abc")])).collect().await.unwrap();
+        assert_eq!(collected.trailers().unwrap()["x-checksum"], "abc");
+        assert_eq!(collected.to_bytes(), "hello");
+    }
+
+    #[test]
+    fn trailer_map_drops_invalid_pairs() {
+        let map = trailer_map(&pairs(&[("x-ok", "1"), ("bad name", "2"), ("x-bad", "a\r\nb")]));
+        assert_eq!(map.len(), 1);
+        assert_eq!(map["x-ok"], "1");
+    }
+
+    #[tokio::test]
+    async fn channel_body_sends_trailers_after_the_channel_closes() {
+        let (tx, rx) = tokio::sync::mpsc::channel(4);
+        let trailers = StreamTrailers::default();
+        let body = ChannelBody::from_stream(ResponseStream { rx, trailers: trailers.clone() });
+        tx.send(Bytes::from("[1,")).await.unwrap();
+        tx.send(Bytes::from("2]")).await.unwrap();
+        *trailers.lock().unwrap() = pairs(&[("x-count", "2")]);
+        drop(tx);
+
+        let collected = body.collect().await.unwrap();
+        assert_eq!(collected.trailers().unwrap()["x-count"], "2");
+        assert_eq!(collected.to_bytes(), "[1,2]");
+    }
+
+    #[tokio::test]
+    async fn channel_body_without_trailers_just_ends() {
+        let (tx, rx) = tokio::sync::mpsc::channel(1);
+        tx.send(Bytes::from("x")).await.unwrap();
+        drop(tx);
+        let collected = ChannelBody::from_stream(ResponseStream { rx, trailers: StreamTrailers::default() }).collect().await.unwrap();
+        assert!(collected.trailers().is_none());
+        assert_eq!(collected.to_bytes(), "x");
+    }
+
+    #[tokio::test]
+    async fn with_trailers_follows_the_inner_body() {
+        let inner = axum::body::Body::from("file contents");
+        let collected = WithTrailers::new(inner, &pairs(&[("x-digest", "sha-256=x")])).collect().await.unwrap();
+        assert_eq!(collected.trailers().unwrap()["x-digest"], "sha-256=x");
+        assert_eq!(collected.to_bytes(), "file contents");
+    }
+}
@@ -0,0 +1,42 @@
+//! Common and Combined Log Format access logs.
+
+mod common;
+
+use common::*;
+use serde_json::json;
+
+fn access_line(server: &Server, needle: &str) -> String {
+    server.wait_for_log(needle).lines().find(|line| line.contains(needle)).unwrap().to_string()
+}
+
+#[test]
+fn combined_format_logs_one_line_per_request() {
+    let server = App::new()
+        .config("log_format", json!("combined"))
+        .route("GET", "/hi", json!({ "type": "text", "value": "hello" }))
+        .start();
+
+    let res = client()
+        .get(server.url("/hi?x=1"))
+        .header("Referer", "http://ref.example/")
+        .header("User-Agent", "tester/1.0")
+        .send()
+        .unwrap();
+    assert_eq!(res.text().unwrap(), "hello");
+
+    let line = access_line(&server, "\"GET /hi?x=1 HTTP/1.1\"");
+    assert!(line.starts_with("127.0.0.1 - - ["), "{}", line);
+    assert!(line.ends_with("] \"GET /hi?x=1 HTTP/1.1\" 200 5 \"http://ref.example/\" \"tester/1.0\""), "{}", line);
+}
+
+#[test]
+fn common_format_omits_referer_and_user_agent() {
+    let server = App::new().config("log_format", json!("common")).start();
+
+    let res = client().get(server.url("/missing")).header("User-Agent", "tester/1.0").send().unwrap();
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+    let line = access_line(&server, "\"GET /missing HTTP/1.1\" 404");
+    assert!(line.starts_with("127.0.0.1 - - ["), "{}", line);
+    assert!(!line.contains("tester/1.0"), "{}", line);
+}
@@ -0,0 +1,439 @@
+//! The `t.*` runtime APIs as actions see them.
+
+mod common;
+
+use common::*;
+use serde_json::{json, Value};
+
+#[test]
+fn fetch_rejects_a_body_past_max_body_bytes() {
+    let server = App::new()
+        .route("GET", "/big", json!({ "type": "text", "value": "x".repeat(4096) }))
+        .action("GET", "/limited", "limited", r#"export default function (req) {
+            const url = "http://" + req.headers.host + "/big";
+            const small = drift(t.fetch(url, { maxBodyBytes: 1024 }));
+            const large = drift(t.fetch(url, { maxBodyBytes: 8192 }));
+            return { small: small.error || null, large: large.body.length };
+        }"#)
+        .start();
+
+    assert_eq!(server.get("/limited").json::<Value>().unwrap(), json!({ "small": "body too large", "large": 4096 }));
+}
+
+#[test]
+fn password_hashes_verify_and_reject_the_wrong_password() {
+    let server = App::new()
+        .action("GET", "/passwords", "passwords", r#"export default function (req) {
+            const argon = t.password.hash("hunter2", { algorithm: "argon2id", memory: 8192, cost: 1 });
+            const bcrypt = t.password.hash("hunter2", { cost: 4 });
+            return {
+                argonPrefix: argon.slice(0, 10),
+                bcryptPrefix: bcrypt.slice(0, 4),
+                argonOk: t.password.verify("hunter2", argon),
+                argonWrong: t.password.verify("hunter3", argon),
+                bcryptOk: t.password.verify("hunter2", bcrypt),
+                bcryptWrong: t.password.verify("hunter3", bcrypt),
+            };
+        }"#)
+        .start();
+
+    assert_eq!(
+        server.get("/passwords").json::<Value>().unwrap(),
+        json!({
+            "argonPrefix": "$argon2id$",
+            "bcryptPrefix": "$2b$",
+            "argonOk": true,
+            "argonWrong": false,
+            "bcryptOk": true,
+            "bcryptWrong": false,
+        })
+    );
+}
+
+#[test]
+fn jwt_rs256_verifies_with_the_public_key_only_under_rs256() {
+    let private = serde_json::to_string(include_str!("fixtures/jwt_rs256.key")).unwrap();
+    let public = serde_json::to_string(include_str!("fixtures/jwt_rs256.pub")).unwrap();
+    let server = App::new()
+        .action("GET", "/jwt", "jwt", &format!(r#"export default function (req) {{
+            const attempt = (fn) => {{ try {{ return fn(); }} catch (e) {{ return "rejected"; }} }};
+            const token = t.jwt.sign({{ sub: req.query.sub }}, {private}, {{ algorithm: "RS256" }});
+            const hs = t.jwt.sign({{ sub: req.query.sub }}, "shared-secret");
+            return {{
+                header: JSON.parse(t.encode.base64Decode(token.split(".")[0], "utf8")).alg,
+                claims: t.jwt.verify(token, {public}, {{ algorithms: ["RS256"] }}),
+                asHs256: attempt(() => t.jwt.verify(token, {public})),
+                hsHeader: JSON.parse(t.encode.base64Decode(hs.split(".")[0], "utf8")).alg,
+                hsClaims: t.jwt.verify(hs, "shared-secret"),
+                hsAsRs256: attempt(() => t.jwt.verify(hs, {public}, {{ algorithms: ["RS256"] }})),
+            }};
+        }}"#))
+        .start();
+
+    assert_eq!(
+        server.get("/jwt?sub=u1").json::<Value>().unwrap(),
+        json!({
+            "header": "RS256",
+            "claims": { "sub": "u1" },
+            "asHs256": "rejected",
+            "hsHeader": "HS256",
+            "hsClaims": { "sub": "u1" },
+            "hsAsRs256": "rejected",
+        })
+    );
+}
+
+#[test]
+fn jwt_eddsa_round_trips_and_mixed_key_families_are_rejected() {
+    let private = serde_json::to_string(include_str!("fixtures/jwt_eddsa.key")).unwrap();
+    let public = serde_json::to_string(include_str!("fixtures/jwt_eddsa.pub")).unwrap();
+    let server = App::new()
+        .action("GET", "/jwt", "jwtEddsa", &format!(r#"export default function (req) {{
+            const attempt = (fn) => {{ try {{ return fn(); }} catch (e) {{ return String(e.message || e); }} }};
+            const token = t.jwt.sign({{ sub: "u2" }}, {private}, {{ algorithm: "EdDSA" }});
+            return {{
+                header: JSON.parse(t.encode.base64Decode(token.split(".")[0], "utf8")).alg,
+                claims: t.jwt.verify(token, {public}, {{ algorithms: ["EdDSA"] }}),
+                mixed: attempt(() => t.jwt.verify(token, {public}, {{ algorithms: ["EdDSA", "HS256"] }})),
+            }};
+        }}"#))
+        .start();
+
+    let body: Value = server.get("/jwt").json().unwrap();
+    assert_eq!(body["header"], json!("EdDSA"));
+    assert_eq!(body["claims"], json!({ "sub": "u2" }));
+    assert!(body["mixed"].as_str().unwrap().contains("same kind of key"), "{}", body);
+}
+
+#[test]
+fn binary_reads_round_trip_byte_for_byte() {
+    let bytes: Vec<u8> = (0..=255).collect();
+    let server = App::new()
+        .file("data.bin", &bytes)
+        .action("GET", "/bytes", "bytes", r#"export default function (req) {
+            const viaDrift = drift(t.read("data.bin", { encoding: "binary" }));
+            const viaSync = t.readSync("data.bin", { encoding: "binary" });
+            return { drift: Array.from(viaDrift), sync: Array.from(viaSync), isBytes: viaSync instanceof Uint8Array };
+        }"#)
+        .start();
+
+    let body: Value = server.get("/bytes").json().unwrap();
+    assert_eq!(body["drift"], json!(bytes));
+    assert_eq!(body["sync"], json!(bytes));
+    assert_eq!(body["isBytes"], json!(true));
+}
+
+#[test]
+fn write_refuses_paths_outside_the_project_root() {
+    let server = App::new()
+        .action("GET", "/write", "writeFiles", r#"export default function (req) {
+            const attempt = (path) => {
+                try { return t.write(path, "x"); } catch (e) { return String(e.message || e); }
+            };
+            return {
+                written: t.write("reports/out.txt", "hello"),
+                parent: attempt("../escape.txt"),
+                nested: attempt("reports/../../escape.txt"),
+                absolute: attempt("/tmp/titan-escape.txt"),
+            };
+        }"#)
+        .start();
+
+    let body: Value = server.get("/write").json().unwrap();
+    assert_eq!(body["written"], json!(5));
+    assert_eq!(std::fs::read_to_string(server.dir.join("reports/out.txt")).unwrap(), "hello");
+    for key in ["parent", "nested", "absolute"] {
+        let message = body[key].as_str().unwrap();
+        assert!(message.contains("must be a relative path inside the project root"), "{}: {}", key, message);
+    }
+    assert!(!server.dir.parent().unwrap().join("escape.txt").exists());
+}
+
+#[cfg(unix)]
+#[test]
+fn write_refuses_symlinks_out_of_the_project_root() {
+    let server = App::new()
+        .action("GET", "/write", "writeLinks", r#"export default function (req) {
+            const attempt = (path) => {
+                try { return t.write(path, "x"); } catch (e) { return String(e.message || e); }
+            };
+            return { dir: attempt("linked/new/out.txt"), leaf: attempt("leaf.txt") };
+        }"#)
+        .start();
+
+    let outside = server.project.join("outside");
+    std::fs::create_dir_all(&outside).unwrap();
+    std::os::unix::fs::symlink(&outside, server.dir.join("linked")).unwrap();
+    std::os::unix::fs::symlink(outside.join("leaf.txt"), server.dir.join("leaf.txt")).unwrap();
+
+    let body: Value = server.get("/write").json().unwrap();
+    assert!(body["dir"].as_str().unwrap().contains("escapes the project root"), "{}", body);
+    assert!(body["leaf"].as_str().unwrap().contains("is a symlink"), "{}", body);
+    assert!(!outside.join("new").exists());
+    assert!(!outside.join("leaf.txt").exists());
+}
+
+#[test]
+fn headers_are_read_case_insensitively() {
+    let server = App::new()
+        .action("POST", "/headers", "headers", r#"export default function (req) {
+            return {
+                lower: req.headers["content-type"],
+                mixed: req.headers["Content-Type"] || null,
+                viaHeader: req.header("content-type"),
+                viaHeaderMixed: req.header("CONTENT-TYPE"),
+            };
+        }"#)
+        .start();
+
+    let res = client().post(server.url("/headers")).header("Content-Type", "text/csv").body("a,b").send().unwrap();
+    assert_eq!(
+        res.json::<Value>().unwrap(),
+        json!({ "lower": "text/csv", "mixed": null, "viaHeader": "text/csv", "viaHeaderMixed": "text/csv" })
+    );
+}
+
+#[test]
+fn client_ip_and_protocol_honor_forwarded_headers_only_when_trusted() {
+    let whoami = |app: App| {
+        let server = app.action("GET", "/whoami", "whoami", "export default function (req) { return { ip: req.ip, protocol: req.protocol }; }").start();
+        client()
+            .get(server.url("/whoami"))
+            .header("X-Forwarded-For", "203.0.113.7, 10.0.0.1")
+            .header("X-Forwarded-Proto", "https")
+            .send()
+            .unwrap()
+            .json::<Value>()
+            .unwrap()
+    };
+
+    assert_eq!(whoami(App::new()), json!({ "ip": "127.0.0.1", "protocol": "http" }));
+    // One trusted proxy: its own entry, not the client-written one to its left
+    assert_eq!(whoami(App::new().config("trustProxy", json!(true))), json!({ "ip": "10.0.0.1", "protocol": "https" }));
+    assert_eq!(whoami(App::new().config("trustProxy", json!(2))), json!({ "ip": "203.0.113.7", "protocol": "https" }));
+}
+
+#[test]
+fn query_expand_parses_the_list_and_drives_includes() {
+    let server = App::new()
+        .dynamic("GET", "/posts/:id", "post", r#"export default function (req) {
+            const post = { id: req.params.id, expand: t.query.expand() };
+            if (t.query.expands("author")) post.author = { name: "Ada" };
+            return t.query.include(post, { comments: () => [{ text: "hi" }] });
+        }"#)
+        .start();
+
+    assert_eq!(
+        server.get("/posts/7?expand=a,b").json::<Value>().unwrap(),
+        json!({ "id": "7", "expand": ["a", "b"] })
+    );
+    assert_eq!(
+        server.get("/posts/7?expand=author,%20comments.author,author").json::<Value>().unwrap(),
+        json!({ "id": "7", "expand": ["author", "comments.author"], "author": { "name": "Ada" }, "comments": [{ "text": "hi" }] })
+    );
+    assert_eq!(server.get("/posts/7").json::<Value>().unwrap(), json!({ "id": "7", "expand": [] }));
+}
+
+#[test]
+fn cache_response_shares_results_under_the_declared_key() {
+    let server = App::new()
+        .dynamic("GET", "/users/:id", "user", r#"export default function (req) {
+            return t.cache.response("user:" + req.params.id, 60000, () => ({ id: req.params.id, computedFor: req.headers["x-caller"] }));
+        }"#)
+        .start();
+
+    let fetch = |path: &str, caller: &str| client().get(server.url(path)).header("X-Caller", caller).send().unwrap().json::<Value>().unwrap();
+    assert_eq!(fetch("/users/1", "alice"), json!({ "id": "1", "computedFor": "alice" }));
+    // Other headers, same key: the producer doesn't run again
+    assert_eq!(fetch("/users/1", "bob"), json!({ "id": "1", "computedFor": "alice" }));
+    assert_eq!(fetch("/users/2", "bob"), json!({ "id": "2", "computedFor": "bob" }));
+}
+
+#[test]
+fn cache_response_accepts_an_infinite_ttl() {
+    let server = App::new()
+        .action("GET", "/forever", "forever", r#"export default function (req) {
+            return t.cache.response("forever", Infinity, () => ({ at: req.headers["x-caller"] }));
+        }"#)
+        .action("GET", "/huge", "huge", r#"export default function (req) {
+            return t.cache.response("huge", 1e20, () => ({ at: req.headers["x-caller"] }));
+        }"#)
+        .start();
+
+    let fetch = |path: &str, caller: &str| client().get(server.url(path)).header("X-Caller", caller).send().unwrap().json::<Value>().unwrap();
+    for path in ["/forever", "/huge"] {
+        assert_eq!(fetch(path, "alice"), json!({ "at": "alice" }));
+        // Cached, and the server is still up
+        assert_eq!(fetch(path, "bob"), json!({ "at": "alice" }));
+    }
+}
+
+#[test]
+fn services_log_is_tagged_with_the_request() {
+    let server = App::new()
+        .config("log_format", json!("json"))
+        .action("GET", "/tagged", "tagged", r#"export default function (req) {
+            const { log, cache } = t.services;
+            log("hello", { n: 1 });
+            return { sameLog: t.services.log === log, cache: cache === t.cache };
+        }"#)
+        .start();
+
+    assert_eq!(server.get("/tagged").json::<Value>().unwrap(), json!({ "sameLog": true, "cache": true }));
+    let log = server.wait_for_log("hello {\\\"n\\\":1}");
+    let line: Value = log
+        .lines()
+        .filter_map(|line| serde_json::from_str::<Value>(line).ok())
+        .find(|line| line["event"] == "t.log")
+        .unwrap();
+    let message = line["message"].as_str().unwrap();
+    assert!(message.starts_with("[GET /tagged #") && message.ends_with(r#"] hello {"n":1}"#), "{}", message);
+}
+
+#[test]
+fn cookies_set_and_clear_each_add_a_set_cookie_header() {
+    let server = App::new()
+        .action("GET", "/login", "login", r#"export default function (req) {
+            t.cookies.set("session", "a b", { httpOnly: true, sameSite: "lax", maxAge: 3600 });
+            t.cookies.set("embed", "1", { domain: "example.com", partitioned: true });
+            t.cookies.clear("legacy");
+            return { path: req.path };
+        }"#)
+        .start();
+
+    let res = server.get("/login");
+    let cookies: Vec<&str> = res.headers().get_all("set-cookie").iter().map(|v| v.to_str().unwrap()).collect();
+    assert_eq!(
+        cookies,
+        [
+            "session=a%20b; Max-Age=3600; Path=/; HttpOnly; SameSite=Lax",
+            "embed=1; Domain=example.com; Path=/; Secure; Partitioned",
+            "legacy=; Max-Age=0; Expires=Thu, 01 Jan 1970 00:00:00 GMT; Path=/",
+        ]
+    );
+}
+
+#[test]
+fn cookies_reject_names_and_attributes_that_would_break_the_header() {
+    let server = App::new()
+        .action("GET", "/bad", "badCookies", r#"export default function (req) {
+            const attempt = (...args) => {
+                try { t.cookies.set(...args); return "set"; } catch (e) { return String(e.message || e); }
+            };
+            return {
+                name: attempt("a;b", "1"),
+                domain: attempt("ok", "1", { domain: "example.com; Secure" }),
+                path: attempt("ok", "1", { path: "/\r\nX-Injected: 1" }),
+            };
+        }"#)
+        .action("GET", "/header", "badHeader", r#"export default function (req) {
+            return t.response.text("hi", { headers: { "X-Bad": "a\r\nb" } });
+        }"#)
+        .start();
+
+    let body: Value = server.get("/bad").json().unwrap();
+    assert!(body["name"].as_str().unwrap().contains("invalid cookie name"), "{}", body);
+    assert!(body["domain"].as_str().unwrap().contains("invalid domain"), "{}", body);
+    assert!(body["path"].as_str().unwrap().contains("invalid path"), "{}", body);
+
+    // A header http rejects answers 500 and the worker keeps serving
+    assert_eq!(server.get("/header").status(), StatusCode::INTERNAL_SERVER_ERROR);
+    assert_eq!(server.get("/bad").status(), StatusCode::OK);
+}
+
+const SIGNUP: &str = r#"export default function (req) {
+    const body = t.validate(req.body, {
+        type: "object",
+        required: ["email", "age"],
+        properties: { email: { type: "string", pattern: "@" }, age: { type: "integer", minimum: 18 } },
+    });
+    return { ok: body.email };
+}"#;
+
+#[test]
+fn validation_errors_carry_the_schema_in_dev_only() {
+    let invalid = json!({ "email": "nope", "age": 12.5 });
+    let dev = App::new().env("TITAN_DEV", "1").action("POST", "/signup", "signup", SIGNUP).start();
+    let res = client().post(dev.url("/signup")).json(&invalid).send().unwrap();
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    let error = res.json::<Value>().unwrap()["error"].clone();
+    assert_eq!(error["code"], json!("VALIDATION_FAILED"));
+    assert_eq!(
+        error["details"]["errors"],
+        json!([
+            { "path": "email", "expected": "match /@/", "received": "nope" },
+            { "path": "age", "expected": "integer", "received": "number" },
+        ])
+    );
+    assert_eq!(error["schema"]["required"], json!(["email", "age"]));
+    assert_eq!(
+        client().post(dev.url("/signup")).json(&json!({ "email": "a@b", "age": 30 })).send().unwrap().json::<Value>().unwrap(),
+        json!({ "ok": "a@b" })
+    );
+
+    let prod = App::new().action("POST", "/signup", "signup", SIGNUP).start();
+    let res = client().post(prod.url("/signup")).json(&invalid).send().unwrap();
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(res.json::<Value>().unwrap(), json!({ "error": { "code": "VALIDATION_FAILED", "message": "Invalid request" } }));
+}
+
+#[test]
+fn encode_round_trips_base64_url_safe_base64_and_hex() {
+    let server = App::new()
+        .action("GET", "/encode", "encode", r#"export default function (req) {
+            const bytes = new Uint8Array([0, 250, 251, 252, 253, 254, 255]);
+            const attempt = (fn) => { try { return fn(); } catch (e) { return e.message; } };
+            return {
+                base64: t.encode.base64(req.query.s),
+                url: t.encode.base64(bytes, { url: true }),
+                std: t.encode.base64(bytes),
+                text: t.encode.base64Decode(t.encode.base64(req.query.s), "utf8"),
+                urlBack: Array.from(t.encode.base64Decode(t.encode.base64(bytes, { url: true }))),
+                hex: t.encode.hex(bytes),
+                hexBack: Array.from(t.encode.hexDecode(t.encode.hex(bytes))),
+                badBase64: attempt(() => t.encode.base64Decode("not*base64")),
+                badHex: attempt(() => t.encode.hexDecode("abc")),
+            };
+        }"#)
+        .start();
+
+    let res: Value = server.get("/encode?s=h%C3%A9llo").json().unwrap();
+    assert_eq!(res["base64"], json!("aMOpbGxv"));
+    assert_eq!(res["text"], json!("héllo"));
+    assert_eq!(res["url"], json!("APr7_P3-_w"));
+    assert_eq!(res["std"], json!("APr7/P3+/w=="));
+    assert_eq!(res["urlBack"], json!([0, 250, 251, 252, 253, 254, 255]));
+    assert_eq!(res["hex"], json!("00fafbfcfdfeff"));
+    assert_eq!(res["hexBack"], json!([0, 250, 251, 252, 253, 254, 255]));
+    assert!(res["badBase64"].as_str().unwrap().starts_with("t.encode.base64Decode(): invalid base64"), "{}", res);
+    assert_eq!(res["badHex"], json!("t.encode.hexDecode(): invalid hex: odd number of digits"));
+}
+
+/// `8-4-4-4-12` lowercase hex with the given version digit and an RFC 4122 variant.
+fn is_uuid(id: &str, version: char) -> bool {
+    let groups: Vec<&str> = id.split('-').collect();
+    groups.iter().map(|g| g.len()).eq([8, 4, 4, 4, 12])
+        && id.chars().all(|c| c == '-' || c.is_ascii_digit() || ('a'..='f').contains(&c))
+        && groups[2].starts_with(version)
+        && groups[3].starts_with(['8', '9', 'a', 'b'])
+}
+
+#[test]
+fn uuid_v4_has_the_uuid_shape_and_v7_sorts_by_creation() {
+    let server = App::new()
+        .action("GET", "/ids", "ids", r#"export default function (req) {
+            const v7 = [];
+            for (let i = 0; i < 200; i++) v7.push(t.uuid.v7());
+            return { path: req.path, v4: [t.uuid.v4(), t.uuid.v4()], v7 };
+        }"#)
+        .start();
+
+    let res: Value = server.get("/ids").json().unwrap();
+    let v4: Vec<&str> = res["v4"].as_array().unwrap().iter().map(|v| v.as_str().unwrap()).collect();
+    assert!(v4.iter().all(|id| is_uuid(id, '4')), "{:?}", v4);
+    assert_ne!(v4[0], v4[1]);
+
+    let v7: Vec<&str> = res["v7"].as_array().unwrap().iter().map(|v| v.as_str().unwrap()).collect();
+    assert!(v7.iter().all(|id| is_uuid(id, '7')), "{:?}", v7);
+    assert!(v7.windows(2).all(|pair| pair[0] < pair[1]), "not strictly increasing: {:?}", v7);
+}
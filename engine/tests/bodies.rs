@@ -0,0 +1,202 @@
+//! Request bodies: parsing by Content-Type, size limits and spilling.
+
+mod common;
+
+use common::*;
+use serde_json::{json, Value};
+use std::io::{Read, Write};
+
+const DESCRIBE_BODY: &str = r#"export default function (req) {
+    const body = req.body;
+    const kind = body instanceof Uint8Array ? "bytes" : typeof body;
+    return { kind, body: kind === "bytes" ? Array.from(body) : body, rawLength: req.rawBody ? req.rawBody.byteLength : null };
+}"#;
+
+fn post(server: &Server, content_type: &str, body: &'static [u8]) -> Response {
+    client().post(server.url("/echo")).header("Content-Type", content_type).body(body).send().unwrap()
+}
+
+#[test]
+fn body_is_parsed_by_content_type() {
+    let server = App::new().action("POST", "/echo", "echo", DESCRIBE_BODY).start();
+
+    let res: Value = post(&server, "application/json", br#"{"a":[1,2]}"#).json().unwrap();
+    assert_eq!((res["kind"].clone(), res["body"].clone()), (json!("object"), json!({ "a": [1, 2] })));
+
+    let res: Value = post(&server, "application/x-www-form-urlencoded", b"name=Ada+Lovelace&lang=en%2DGB").json().unwrap();
+    assert_eq!((res["kind"].clone(), res["body"].clone()), (json!("object"), json!({ "name": "Ada Lovelace", "lang": "en-GB" })));
+
+    let res: Value = post(&server, "text/plain; charset=utf-8", b"hello").json().unwrap();
+    assert_eq!((res["kind"].clone(), res["body"].clone()), (json!("string"), json!("hello")));
+
+    let res: Value = post(&server, "application/octet-stream", b"\x00\xff\x10").json().unwrap();
+    assert_eq!((res["kind"].clone(), res["body"].clone()), (json!("bytes"), json!([0, 255, 16])));
+    assert_eq!(res["rawLength"], json!(3));
+}
+
+#[test]
+fn malformed_json_is_a_400() {
+    let server = App::new().action("POST", "/echo", "echo", DESCRIBE_BODY).start();
+
+    let res = post(&server, "application/json", b"{\"a\": ");
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    let body: Value = res.json().unwrap();
+    assert_eq!(body["error"]["code"], json!("invalid_json"));
+    assert!(body["error"]["message"].as_str().unwrap().starts_with("Malformed JSON body"));
+}
+
+#[test]
+fn body_one_byte_over_max_body_bytes_is_rejected() {
+    let server = App::new()
+        .config("maxBodyBytes", json!(1024))
+        .action("POST", "/echo", "echo", DESCRIBE_BODY)
+        .start();
+
+    let at_limit = client().post(server.url("/echo")).header("Content-Type", "text/plain").body("x".repeat(1024)).send().unwrap();
+    assert_eq!(at_limit.status(), StatusCode::OK);
+
+    let over = client().post(server.url("/echo")).header("Content-Type", "text/plain").body("x".repeat(1025)).send().unwrap();
+    assert_eq!(over.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+    // A chunked body has no length up front; the limit applies while reading
+    let chunked = server.raw(&format!(
+        "POST /echo HTTP/1.1\r\nHost: localhost\r\nContent-Type: text/plain\r\nTransfer-Encoding: chunked\r\nConnection: close\r\n\r\n3e8\r\n{}\r\n19\r\n{}\r\n0\r\n\r\n",
+        "x".repeat(1000),
+        "x".repeat(25)
+    ));
+    assert!(chunked.starts_with("HTTP/1.1 413"), "{}", chunked);
+}
+
+#[test]
+fn get_reads_a_body_only_when_one_is_framed() {
+    let server = App::new().action("GET", "/echo", "echo", DESCRIBE_BODY).start();
+
+    // No Content-Length or Transfer-Encoding on a kept-alive connection: answered without waiting on a body
+    let bare = server.raw("GET /echo HTTP/1.1\r\nHost: localhost\r\nContent-Type: text/plain\r\nConnection: close\r\n\r\n");
+    assert!(bare.starts_with("HTTP/1.1 200"), "{}", bare);
+    assert!(bare.contains(r#""rawLength":0"#) || bare.contains(r#""rawLength":null"#), "{}", bare);
+
+    let res: Value = client().get(server.url("/echo")).header("Content-Type", "text/plain").body("in a GET").send().unwrap().json().unwrap();
+    assert_eq!((res["kind"].clone(), res["body"].clone()), (json!("string"), json!("in a GET")));
+
+    let chunked = server.raw("GET /echo HTTP/1.1\r\nHost: localhost\r\nContent-Type: text/plain\r\nTransfer-Encoding: chunked\r\nConnection: close\r\n\r\n5\r\nhello\r\n0\r\n\r\n");
+    assert!(chunked.contains(r#""body":"hello""#), "{}", chunked);
+}
+
+#[test]
+fn content_length_must_match_the_body() {
+    let server = App::new().action("POST", "/echo", "echo", DESCRIBE_BODY).start();
+
+    let res: Value = post(&server, "text/plain", b"hello").json().unwrap();
+    assert_eq!(res["body"], json!("hello"));
+
+    // Declares 10 bytes, sends 5, then ends the request side of the connection
+    let mut stream = std::net::TcpStream::connect(("127.0.0.1", server.port)).unwrap();
+    stream.set_read_timeout(Some(std::time::Duration::from_secs(10))).unwrap();
+    stream
+        .write_all(b"POST /echo HTTP/1.1\r\nHost: localhost\r\nContent-Type: text/plain\r\nContent-Length: 10\r\n\r\nhello")
+        .unwrap();
+    stream.shutdown(std::net::Shutdown::Write).unwrap();
+    let mut short = String::new();
+    let _ = stream.read_to_string(&mut short);
+    assert!(short.starts_with("HTTP/1.1 400"), "{}", short);
+    assert!(!short.contains("\"body\""), "{}", short);
+
+    for length in ["-5", "abc", "99999999999999999999999", "5, 6"] {
+        let response = server.raw(&format!(
+            "POST /echo HTTP/1.1\r\nHost: localhost\r\nContent-Type: text/plain\r\nContent-Length: {}\r\nConnection: close\r\n\r\nhello",
+            length
+        ));
+        assert!(response.starts_with("HTTP/1.1 400"), "{} -> {}", length, response);
+    }
+}
+
+#[test]
+fn large_json_bodies_parse_like_small_ones() {
+    let server = App::new().action("POST", "/echo", "echo", DESCRIBE_BODY).start();
+
+    // Well past the size where the `simd-json` feature takes over parsing
+    let rows: Vec<Value> = (0..400)
+        .map(|i| json!({ "id": i, "name": format!("row \"{}\" ✓ \u{1F600}", i), "ratio": i as f64 + 0.25, "tags": ["a", null, true], "nested": { "escaped": "tab\there\\n" } }))
+        .collect();
+    for sent in [json!(rows[..2]), json!(rows)] {
+        let res: Value = client().post(server.url("/echo")).json(&sent).send().unwrap().json().unwrap();
+        assert_eq!(res["kind"], json!("object"));
+        assert_eq!(res["body"], sent);
+    }
+
+    let mut truncated = serde_json::to_vec(&rows).unwrap();
+    truncated.pop();
+    let res = client().post(server.url("/echo")).header("Content-Type", "application/json").body(truncated).send().unwrap();
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(res.json::<Value>().unwrap()["error"]["code"], json!("invalid_json"));
+}
+
+#[test]
+fn bodies_past_the_spill_threshold_go_to_a_temp_file() {
+    let tmp = std::env::temp_dir().join(format!("titan-spill-test-{}", std::process::id()));
+    std::fs::create_dir_all(&tmp).unwrap();
+    let server = App::new()
+        .config("bodySpillBytes", json!(1024))
+        .env("TMPDIR", tmp.to_str().unwrap())
+        .action("POST", "/upload", "upload", r#"export default function (req) {
+            if (!req.bodyFile) return { spilled: false, size: req.rawBody.byteLength, text: req.body };
+            return { spilled: true, size: req.bodyFile.size, text: req.bodyFile.text(), tail: req.bodyFile.text(1020, 5) };
+        }"#)
+        .start();
+
+    let send = |body: String| -> Value {
+        client().post(server.url("/upload")).header("Content-Type", "text/plain").body(body).send().unwrap().json().unwrap()
+    };
+    let at_threshold = "a".repeat(1024);
+    assert_eq!(send(at_threshold.clone()), json!({ "spilled": false, "size": 1024, "text": at_threshold }));
+
+    let over = format!("{}bcdef", "a".repeat(1020));
+    assert_eq!(send(over.clone()), json!({ "spilled": true, "size": 1025, "text": over, "tail": "bcdef" }));
+
+    // The temp file goes once the request is done with it
+    let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+    while std::fs::read_dir(&tmp).unwrap().next().is_some() {
+        assert!(std::time::Instant::now() < deadline, "temp file left behind in {:?}", tmp);
+        std::thread::sleep(std::time::Duration::from_millis(50));
+    }
+    drop(server);
+    let _ = std::fs::remove_dir_all(&tmp);
+}
+
+/// A `multipart/form-data` body with one text field and one file.
+fn form(file: &[u8]) -> Vec<u8> {
+    let mut body = b"--XyZ\r\nContent-Disposition: form-data; name=\"title\"\r\n\r\nhello\r\n".to_vec();
+    body.extend_from_slice(b"--XyZ\r\nContent-Disposition: form-data; name=\"avatar\"; filename=\"a.png\"\r\nContent-Type: image/png\r\n\r\n");
+    body.extend_from_slice(file);
+    body.extend_from_slice(b"\r\n--XyZ--\r\n");
+    body
+}
+
+#[test]
+fn multipart_uploads_reach_the_action_as_form_data() {
+    let server = App::new()
+        .config("multipart", json!({ "maxFileBytes": 16 }))
+        .action("POST", "/upload", "upload", r#"export default function (req) {
+            const { fields, files } = req.formData;
+            return { fields, files: files.map((f) => ({ name: f.name, filename: f.filename, contentType: f.contentType, bytes: Array.from(f.bytes) })) };
+        }"#)
+        .start();
+
+    let send = |file: &[u8]| {
+        client()
+            .post(server.url("/upload"))
+            .header("Content-Type", "multipart/form-data; boundary=XyZ")
+            .body(form(file))
+            .send()
+            .unwrap()
+    };
+    assert_eq!(
+        send(&[0, 1, 13, 10, 255]).json::<Value>().unwrap(),
+        json!({
+            "fields": { "title": "hello" },
+            "files": [{ "name": "avatar", "filename": "a.png", "contentType": "image/png", "bytes": [0, 1, 13, 10, 255] }],
+        })
+    );
+    assert_eq!(send(&[7; 17]).status(), StatusCode::PAYLOAD_TOO_LARGE);
+}
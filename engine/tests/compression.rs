@@ -0,0 +1,84 @@
+//! `__config.compression`: thresholds, content types and levels.
+
+mod common;
+
+use common::*;
+use serde_json::json;
+use std::io::{Read, Write};
+
+/// Text that compresses, but not trivially, so levels give different output.
+const TEXT: &str = "export default function (req) {
+    let out = req.path;
+    for (let i = 0; i < 2000; i++) out += ' ' + ((i * 7919) % 1009).toString(36);
+    return t.response.text(out, { headers: { 'Content-Type': req.query.type || 'text/plain' } });
+}";
+
+fn gzip(data: &[u8], level: u32) -> Vec<u8> {
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::new(level));
+    encoder.write_all(data).unwrap();
+    encoder.finish().unwrap()
+}
+
+fn fetch(server: &Server, path: &str) -> Response {
+    client().get(server.url(path)).header("Accept-Encoding", "gzip").send().unwrap()
+}
+
+#[test]
+fn compresses_large_text_at_the_configured_level_only() {
+    let server = App::new()
+        .config("compression", json!({ "minBytes": 512, "codecs": ["gzip"], "level": { "gzip": 1 } }))
+        .action("GET", "/small", "small", "export default function (req) { return { path: req.path }; }")
+        .action("GET", "/text", "text", TEXT)
+        .start();
+
+    let small = fetch(&server, "/small");
+    assert!(small.headers().get("content-encoding").is_none());
+    assert_eq!(small.text().unwrap(), r#"{"path":"/small"}"#);
+
+    let image = fetch(&server, "/text?type=image/png");
+    assert!(image.headers().get("content-encoding").is_none());
+    let plain = image.bytes().unwrap();
+    assert!(plain.len() > 4096);
+
+    let text = fetch(&server, "/text");
+    assert_eq!(text.headers()["content-encoding"], "gzip");
+    assert_eq!(text.headers()["vary"], "accept-encoding");
+    let compressed = text.bytes().unwrap();
+    assert_eq!(compressed.as_ref(), gzip(&plain, 1).as_slice(), "level 1 output");
+    assert_ne!(compressed.as_ref(), gzip(&plain, 9).as_slice());
+}
+
+fn decode(codec: &str, data: &[u8]) -> String {
+    let mut out = String::new();
+    match codec {
+        "br" => brotli::Decompressor::new(data, 4096).read_to_string(&mut out).unwrap(),
+        _ => flate2::read::GzDecoder::new(data).read_to_string(&mut out).unwrap(),
+    };
+    out
+}
+
+#[test]
+fn negotiates_brotli_or_gzip_for_actions_and_fast_path_bodies() {
+    let fixed = "titan ".repeat(400);
+    let server = App::new()
+        .config("compression", json!(true))
+        .action("GET", "/rows", "rows", r#"export default function (req) {
+            return Array.from({ length: 200 }, (_, i) => ({ id: i, path: req.path }));
+        }"#)
+        .action("GET", "/fixed", "fixed", &format!("export default function (req) {{ return t.response.text({}); }}", serde_json::to_string(&fixed).unwrap()))
+        .start();
+
+    let plain = client().get(server.url("/rows")).send().unwrap();
+    assert!(plain.headers().get("content-encoding").is_none());
+    let rows = plain.text().unwrap();
+    assert!(rows.len() > 1024);
+
+    for path in ["/rows", "/fixed"] {
+        let expected = if path == "/rows" { rows.clone() } else { fixed.clone() };
+        for (accept, codec) in [("gzip, deflate, br", "br"), ("gzip", "gzip")] {
+            let res = client().get(server.url(path)).header("Accept-Encoding", accept).send().unwrap();
+            assert_eq!(res.headers()["content-encoding"], codec, "{} with {}", path, accept);
+            assert_eq!(decode(codec, &res.bytes().unwrap()), expected, "{} with {}", path, accept);
+        }
+    }
+}
@@ -0,0 +1,61 @@
+//! `__config.cors`: preflights and Access-Control-Allow-* headers.
+
+mod common;
+
+use common::*;
+use reqwest::header::{
+    ACCESS_CONTROL_ALLOW_CREDENTIALS, ACCESS_CONTROL_ALLOW_HEADERS, ACCESS_CONTROL_ALLOW_METHODS,
+    ACCESS_CONTROL_ALLOW_ORIGIN, ACCESS_CONTROL_MAX_AGE, VARY,
+};
+use serde_json::json;
+
+const APP: &str = "https://app.example.com";
+
+fn preflight(server: &Server, origin: &str) -> reqwest::blocking::Response {
+    client()
+        .request(reqwest::Method::OPTIONS, server.url("/items"))
+        .header("Origin", origin)
+        .header("Access-Control-Request-Method", "POST")
+        .header("Access-Control-Request-Headers", "content-type")
+        .send()
+        .unwrap()
+}
+
+#[test]
+fn allowlisted_origins_get_preflights_and_echoed_headers() {
+    let server = App::new()
+        .config("cors", json!({ "origins": [APP], "methods": ["GET", "POST"], "headers": ["content-type"], "credentials": true, "maxAge": 600 }))
+        .action("POST", "/items", "items", "export default function (req) { return { path: req.path }; }")
+        .start();
+
+    let res = preflight(&server, APP);
+    assert_eq!(res.status(), StatusCode::NO_CONTENT);
+    assert_eq!(res.headers()[ACCESS_CONTROL_ALLOW_ORIGIN], APP);
+    assert_eq!(res.headers()[ACCESS_CONTROL_ALLOW_METHODS], "GET, POST");
+    assert_eq!(res.headers()[ACCESS_CONTROL_ALLOW_HEADERS], "content-type");
+    assert_eq!(res.headers()[ACCESS_CONTROL_ALLOW_CREDENTIALS], "true");
+    assert_eq!(res.headers()[ACCESS_CONTROL_MAX_AGE], "600");
+
+    // Other origins get a bare 204, which the browser treats as a refusal
+    let res = preflight(&server, "https://evil.example.com");
+    assert_eq!(res.status(), StatusCode::NO_CONTENT);
+    assert!(!res.headers().contains_key(ACCESS_CONTROL_ALLOW_ORIGIN));
+
+    let res = client().post(server.url("/items")).header("Origin", APP).send().unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.headers()[ACCESS_CONTROL_ALLOW_ORIGIN], APP);
+    assert_eq!(res.headers()[ACCESS_CONTROL_ALLOW_CREDENTIALS], "true");
+    assert_eq!(res.headers()[VARY], "origin");
+}
+
+#[test]
+fn wildcard_origins_allow_any_caller() {
+    let server = App::new()
+        .config("cors", json!({ "origins": "*" }))
+        .route("GET", "/items", json!({ "type": "json", "value": [] }))
+        .start();
+
+    let res = client().get(server.url("/items")).header("Origin", "https://other.example").send().unwrap();
+    assert_eq!(res.headers()[ACCESS_CONTROL_ALLOW_ORIGIN], "*");
+    assert!(!res.headers().contains_key(VARY));
+}
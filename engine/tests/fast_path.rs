@@ -0,0 +1,96 @@
+//! Which actions FastPath answers without running them.
+
+mod common;
+
+use common::*;
+use serde_json::{json, Value};
+
+/// `fast_path_actions` from the startup summary.
+fn fast_path_actions(app: App) -> (Server, u64) {
+    let server = app.config("startup_summary", json!(true)).config("log_format", json!("json")).start();
+    let log = server.wait_for_log("\"event\":\"startup\"");
+    let count = log
+        .lines()
+        .filter_map(|line| serde_json::from_str::<Value>(line).ok())
+        .find(|line| line["event"] == "startup")
+        .unwrap()["summary"]["fast_path_actions"]
+        .as_u64()
+        .unwrap();
+    (server, count)
+}
+
+#[test]
+fn pure_math_calls_are_static_and_random_is_not() {
+    let (server, count) = fast_path_actions(
+        App::new()
+            .action("GET", "/math", "math", r#"export default function (req) {
+                return t.response.json({ pi: Math.PI, max: Math.max(1, 5, 3), min: Math.min(2, -1), floor: Math.floor(2.7), abs: Math.abs(-4) });
+            }"#)
+            .action("GET", "/random", "random", "export default function (req) { return t.response.json({ r: Math.random() }); }"),
+    );
+
+    assert_eq!(count, 1, "only /math is static");
+    assert_eq!(
+        server.get("/math").json::<Value>().unwrap(),
+        json!({ "pi": std::f64::consts::PI, "max": 5, "min": -1, "floor": 2, "abs": 4 })
+    );
+    let draws: Vec<f64> = (0..3).map(|_| server.get("/random").json::<Value>().unwrap()["r"].as_f64().unwrap()).collect();
+    assert!(draws.windows(2).any(|pair| pair[0] != pair[1]), "{:?}", draws);
+}
+
+#[test]
+fn object_freeze_is_as_static_as_its_argument() {
+    let (server, count) = fast_path_actions(
+        App::new()
+            .action("GET", "/plain", "plain", "export default function (req) { return t.response.json({ a: 1 }); }")
+            .action("GET", "/frozen", "frozen", "export default function (req) { return t.response.json(Object.freeze({ a: 1 })); }")
+            .action("GET", "/assigned", "assigned", "export default function (req) { return t.response.json(Object.assign({}, { a: 1 })); }"),
+    );
+
+    assert_eq!(count, 2, "Object.assign stays dynamic");
+    let plain = server.get("/plain");
+    let plain_type = plain.headers()["content-type"].clone();
+    let plain_body = plain.text().unwrap();
+    let frozen = server.get("/frozen");
+    assert_eq!(frozen.headers()["content-type"], plain_type);
+    assert_eq!(frozen.text().unwrap(), plain_body);
+    assert_eq!(server.get("/assigned").json::<Value>().unwrap(), json!({ "a": 1 }));
+}
+
+#[test]
+fn spreads_of_static_arrays_and_objects_are_static() {
+    let (server, count) = fast_path_actions(
+        App::new()
+            .action("GET", "/array", "array", "export default function (req) { return t.response.json([...[1, 2], 3]); }")
+            .action("GET", "/object", "object", "const base = { a: 1 }; export default function (req) { return t.response.json({ ...base, b: 2 }); }")
+            .action("GET", "/dynamic", "dynamic", "export default function (req) { return t.response.json([...req.path.split('/'), 3]); }"),
+    );
+
+    assert_eq!(count, 2, "a spread of request data stays dynamic");
+    assert_eq!(server.get("/array").json::<Value>().unwrap(), json!([1, 2, 3]));
+    assert_eq!(server.get("/object").json::<Value>().unwrap(), json!({ "a": 1, "b": 2 }));
+    assert_eq!(server.get("/dynamic").json::<Value>().unwrap(), json!(["", "dynamic", 3]));
+}
+
+#[test]
+fn static_error_pages_keep_their_status_and_retry_after() {
+    let (server, count) = fast_path_actions(
+        App::new()
+            .action("GET", "/maintenance", "maintenance", r#"export default function (req) {
+                return t.response.html("<h1>Back soon</h1>", { status: 503, retryAfter: 120 });
+            }"#)
+            .action("GET", "/gone", "gone", r#"export default function (req) { return t.response.json({ error: "gone" }, 410); }"#),
+    );
+
+    assert_eq!(count, 2);
+    let maintenance = server.get("/maintenance");
+    assert_eq!(maintenance.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(maintenance.headers()["retry-after"], "120");
+    assert!(maintenance.headers()["content-type"].to_str().unwrap().starts_with("text/html"));
+    assert_eq!(maintenance.text().unwrap(), "<h1>Back soon</h1>");
+
+    let gone = server.get("/gone");
+    assert_eq!(gone.status(), StatusCode::GONE);
+    assert!(gone.headers().get("retry-after").is_none());
+    assert_eq!(gone.json::<Value>().unwrap(), json!({ "error": "gone" }));
+}
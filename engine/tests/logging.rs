@@ -0,0 +1,55 @@
+//! Request log lines and `t.log` output.
+
+mod common;
+
+use common::*;
+use serde_json::{json, Value};
+
+/// The JSON lines (`log_format: "json"`) the server printed.
+fn json_lines(log: &str) -> Vec<Value> {
+    log.lines().filter_map(|line| serde_json::from_str(line).ok()).collect()
+}
+
+#[test]
+fn json_format_writes_request_lines_and_t_log_as_json() {
+    let server = App::new()
+        .config("log_format", json!("json"))
+        .action("GET", "/hello", "hello", r#"export default function (req) { t.log("hi from", req.path); return { ok: true }; }"#)
+        .start();
+
+    assert_eq!(server.get("/hello").json::<Value>().unwrap(), json!({ "ok": true }));
+    let log = server.wait_for_log("\"outcome\":\"action\"");
+    let lines = json_lines(&log);
+
+    let request = lines.iter().find(|line| line["outcome"] == "action").unwrap();
+    assert_eq!(request["level"], json!("info"));
+    assert_eq!(request["action"], json!("hello"));
+    assert_eq!(request["method"], json!("GET"));
+    assert_eq!(request["path"], json!("/hello"));
+    assert!(request["durationMs"].is_f64(), "{}", request);
+
+    let t_log = lines.iter().find(|line| line["event"] == "t.log").unwrap();
+    assert_eq!(t_log["action"], json!("hello"));
+    assert!(t_log["message"].as_str().unwrap().ends_with("hi from /hello"), "{}", t_log);
+
+    // No colored text slips through for the request
+    assert!(log.lines().filter(|line| line.contains("/hello")).all(|line| !line.contains('\x1b')), "{}", log);
+}
+
+#[test]
+fn error_level_logs_only_failed_requests() {
+    let server = App::new()
+        .env("TITAN_LOG_LEVEL", "error")
+        .action("GET", "/ok", "ok", "export default function (req) { return { path: req.path }; }")
+        .action("GET", "/boom", "boom", "export default function (req) { throw new Error('boom at ' + req.path); }")
+        .start();
+
+    assert_eq!(server.get("/ok").json::<Value>().unwrap(), json!({ "path": "/ok" }));
+    assert_eq!(server.get("/missing").status(), StatusCode::NOT_FOUND);
+    assert_eq!(server.get("/boom").status(), StatusCode::INTERNAL_SERVER_ERROR);
+
+    let log = server.wait_for_log("GET /boom");
+    // The banner is an info line too; success and 404 lines don't print either
+    assert!(!log.contains("Titan server running at"), "{}", log);
+    assert!(!log.contains("GET /ok") && !log.contains("GET /missing"), "{}", log);
+}
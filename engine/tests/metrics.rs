@@ -0,0 +1,43 @@
+//! `__config.metrics`: the Prometheus `/metrics` endpoint.
+
+mod common;
+
+use common::*;
+use serde_json::json;
+
+#[test]
+fn metrics_count_requests_by_route_pattern() {
+    let server = App::new()
+        .config("metrics", json!(true))
+        .route("GET", "/pong", json!({ "type": "text", "value": "pong" }))
+        .dynamic("GET", "/users/:id", "user", "export default function (req) { return { id: req.params.id }; }")
+        .action("GET", "/drifty", "drifty", r#"export default function (req) {
+            return { body: drift(t.fetch("http://" + req.headers.host + "/pong")).body };
+        }"#)
+        .start();
+
+    for path in ["/users/1", "/users/2", "/drifty"] {
+        assert_eq!(server.get(path).status(), StatusCode::OK);
+    }
+    assert_eq!(server.get("/nope").status(), StatusCode::NOT_FOUND);
+
+    let metrics = server.get("/metrics").text().unwrap();
+    for line in [
+        r#"titan_requests_total{method="GET",route="/users/:id",status="200"} 2"#,
+        r#"titan_requests_total{method="GET",route="/drifty",status="200"} 1"#,
+        r#"titan_requests_total{method="GET",route="unmatched",status="404"} 1"#,
+        r#"titan_action_duration_seconds_count{route="/users/:id"} 2"#,
+        r#"titan_drift_duration_seconds_count{route="/drifty"} 1"#,
+        r#"titan_workers{state="free"} 1"#,
+    ] {
+        assert!(metrics.lines().any(|l| l == line), "missing {:?} in:\n{}", line, metrics);
+    }
+    // Raw paths never become labels
+    assert!(!metrics.contains("/users/1") && !metrics.contains("/nope"), "{}", metrics);
+}
+
+#[test]
+fn metrics_are_off_by_default() {
+    let server = App::new().start();
+    assert_eq!(server.get("/metrics").status(), StatusCode::NOT_FOUND);
+}
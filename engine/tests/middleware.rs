@@ -0,0 +1,106 @@
+//! Code that runs around actions: extension hooks and route middleware.
+
+mod common;
+
+use common::*;
+use serde_json::{json, Value};
+
+#[test]
+fn extension_before_hook_blocks_requests_without_a_header() {
+    let server = App::new()
+        .file(".ext/gate/titan.json", r#"{ "name": "gate", "entry": "index.js", "hooks": { "before": "check" } }"#)
+        .file(".ext/gate/index.js", r#"t.gate = {
+            check(req) {
+                if (!req.headers["x-api-key"]) return t.response.json({ error: "missing api key" }, 401);
+            }
+        };"#)
+        .action("GET", "/secret", "secret", "export default function (req) { return { secret: 42 }; }")
+        .start();
+
+    let blocked = server.get("/secret");
+    assert_eq!(blocked.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(blocked.json::<Value>().unwrap(), json!({ "error": "missing api key" }));
+
+    let allowed = client().get(server.url("/secret")).header("X-Api-Key", "k").send().unwrap();
+    assert_eq!(allowed.status(), StatusCode::OK);
+    assert_eq!(allowed.json::<Value>().unwrap(), json!({ "secret": 42 }));
+}
+
+#[test]
+fn before_hook_answering_from_cache_skips_the_action() {
+    let server = App::new()
+        .file(".ext/page-cache/titan.json", r#"{ "name": "page-cache", "entry": "index.js", "hooks": { "before": "lookup", "after": "store" } }"#)
+        .file(".ext/page-cache/index.js", r#"t["page-cache"] = {
+            lookup(req, ctx) {
+                if (req.path !== "/page") return;
+                ctx.cacheKey = "page:" + req.path;
+                const hit = t.shareContext.get(ctx.cacheKey);
+                if (hit !== null) return hit;
+            },
+            store(req, result, ctx) {
+                if (ctx.cacheKey) t.shareContext.set(ctx.cacheKey, result);
+            }
+        };"#)
+        .action("GET", "/page", "page", r#"export default function (req) {
+            const runs = (t.shareContext.get("runs") || 0) + 1;
+            t.shareContext.set("runs", runs);
+            return { path: req.path, renderedOnRun: runs };
+        }"#)
+        .action("GET", "/runs", "runs", "export default function (req) { return { runs: t.shareContext.get(\"runs\"), path: req.path }; }")
+        .start();
+
+    for _ in 0..3 {
+        assert_eq!(server.get("/page").json::<Value>().unwrap(), json!({ "path": "/page", "renderedOnRun": 1 }));
+    }
+    assert_eq!(server.get("/runs").json::<Value>().unwrap(), json!({ "runs": 1, "path": "/runs" }));
+}
+
+#[test]
+fn route_middleware_runs_in_order_and_can_short_circuit() {
+    let server = App::new()
+        .route("GET", "/me", json!({ "type": "action", "value": "me", "middleware": ["auth", "stamp"] }))
+        .route("GET", "/broken", json!({ "type": "action", "value": "me", "middleware": ["missing"] }))
+        .script("auth", r#"export default function (req, ctx) {
+            if (req.headers["x-token"] !== "secret") return t.response.json({ error: "unauthorized" }, 401);
+            ctx.user = "ada";
+            ctx.trail = ["auth"];
+        }"#)
+        .script("stamp", "export default function (req, ctx) { ctx.trail.push('stamp'); }")
+        .script("me", "export default function (req, ctx) { return { user: ctx.user, trail: ctx.trail, shared: req.context === ctx }; }")
+        .start();
+
+    let denied = server.get("/me");
+    assert_eq!(denied.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(denied.json::<Value>().unwrap(), json!({ "error": "unauthorized" }));
+
+    let allowed = client().get(server.url("/me")).header("X-Token", "secret").send().unwrap();
+    assert_eq!(allowed.json::<Value>().unwrap(), json!({ "user": "ada", "trail": ["auth", "stamp"], "shared": true }));
+
+    let broken = server.get("/broken");
+    assert_eq!(broken.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    assert_eq!(broken.json::<Value>().unwrap()["error"], json!("Middleware 'missing' not found"));
+}
+
+#[test]
+fn async_route_middleware_is_awaited() {
+    let server = App::new()
+        .route("GET", "/me", json!({ "type": "action", "value": "me", "middleware": ["auth", "stamp"] }))
+        .route("GET", "/rejects", json!({ "type": "action", "value": "me", "middleware": ["reject"] }))
+        .script("auth", r#"export default async function (req, ctx) {
+            await Promise.resolve();
+            if (req.headers["x-token"] !== "secret") return t.response.json({ error: "unauthorized" }, 401);
+            ctx.user = "ada";
+        }"#)
+        .script("stamp", "export default function (req, ctx) { ctx.stamped = ctx.user; }")
+        .script("reject", "export default async function () { throw new Error('no session'); }")
+        .script("me", "export default function (req, ctx) { return { user: ctx.user, stamped: ctx.stamped }; }")
+        .start();
+
+    assert_eq!(server.get("/me").status(), StatusCode::UNAUTHORIZED);
+    let allowed = client().get(server.url("/me")).header("X-Token", "secret").send().unwrap();
+    assert_eq!(allowed.json::<Value>().unwrap(), json!({ "user": "ada", "stamped": "ada" }));
+
+    let rejected = server.get("/rejects");
+    assert_eq!(rejected.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    assert_eq!(rejected.json::<Value>().unwrap()["error"], json!("no session"));
+}
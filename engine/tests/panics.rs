@@ -0,0 +1,50 @@
+//! `__config.onPanic`, driven by an action calling `t._debug_panic` (bound
+//! under `TITAN_DEBUG_PANIC`).
+
+mod common;
+
+use common::*;
+use serde_json::{json, Value};
+
+fn app(policy: &str) -> App {
+    App::new()
+        .config("onPanic", json!(policy))
+        .env("TITAN_DEBUG_PANIC", "1")
+        // Suspended on a drift when the worker panics, so the request is still open
+        .action("GET", "/boom", "boom", r#"export default function (req) {
+            t._debug_panic("panic requested by the boom action");
+            return drift(t.fetch("http://127.0.0.1:9/"));
+        }"#)
+        .action("GET", "/ping", "ping", "export default function (req) { return { pong: true }; }")
+}
+
+#[test]
+fn recover_fails_the_request_and_keeps_serving() {
+    let server = app("recover").start();
+
+    let boom = server.get("/boom");
+    assert_eq!(boom.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    assert!(boom.text().unwrap().contains("Worker panicked"));
+    assert!(server.log().contains("panicked"));
+
+    // Same single worker, on a fresh isolate
+    assert_eq!(server.get("/ping").json::<Value>().unwrap(), json!({ "pong": true }));
+}
+
+#[test]
+fn abort_exits_the_process_with_a_crash_report() {
+    let mut server = app("abort").start();
+
+    assert!(client().get(server.url("/boom")).send().is_err(), "no response from an aborted process");
+    let status = server.wait_exit();
+    assert!(!status.success(), "{:?}", status);
+
+    let reports: Vec<_> = std::fs::read_dir(&server.project)
+        .unwrap()
+        .flatten()
+        .filter(|entry| entry.file_name().to_string_lossy().starts_with("titan-crash-"))
+        .collect();
+    assert_eq!(reports.len(), 1);
+    let report = std::fs::read_to_string(reports[0].path()).unwrap();
+    assert!(report.contains("panic requested by the boom action"), "{}", report);
+}
@@ -0,0 +1,93 @@
+//! `proxy` routes against a mock upstream.
+
+mod common;
+
+use common::*;
+use serde_json::json;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+
+const UPLOAD: u64 = 64 * 1024 * 1024;
+const DOWNLOAD: u64 = 48 * 1024 * 1024;
+
+/// One-request upstream: counts the request body it receives and answers
+/// with `download` bytes plus the count in `X-Received`.
+fn upstream(download: u64) -> u16 {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    std::thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let mut content_length = None;
+        let mut chunked = false;
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            let line = line.trim_end().to_ascii_lowercase();
+            if line.is_empty() {
+                break;
+            }
+            if let Some(len) = line.strip_prefix("content-length:") {
+                content_length = len.trim().parse::<u64>().ok();
+            }
+            chunked |= line.starts_with("transfer-encoding:") && line.contains("chunked");
+        }
+
+        let received = if chunked {
+            let mut total = 0;
+            loop {
+                let mut size = String::new();
+                reader.read_line(&mut size).unwrap();
+                let size = u64::from_str_radix(size.trim().split(';').next().unwrap(), 16).unwrap();
+                total += std::io::copy(&mut (&mut reader).take(size), &mut std::io::sink()).unwrap();
+                let mut crlf = String::new();
+                reader.read_line(&mut crlf).unwrap();
+                if size == 0 {
+                    break total;
+                }
+            }
+        } else {
+            std::io::copy(&mut (&mut reader).take(content_length.unwrap_or(0)), &mut std::io::sink()).unwrap()
+        };
+
+        let mut stream = stream;
+        write!(stream, "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nX-Received: {}\r\nConnection: close\r\n\r\n", download, received).unwrap();
+        std::io::copy(&mut std::io::repeat(b'd').take(download), &mut stream).unwrap();
+    });
+    port
+}
+
+/// Peak resident set size of `pid` in KiB.
+#[cfg(target_os = "linux")]
+fn peak_rss_kib(pid: u32) -> u64 {
+    let status = std::fs::read_to_string(format!("/proc/{}/status", pid)).unwrap();
+    status
+        .lines()
+        .find_map(|l| l.strip_prefix("VmHWM:"))
+        .and_then(|v| v.trim().trim_end_matches("kB").trim().parse().ok())
+        .unwrap()
+}
+
+#[test]
+fn large_upload_and_download_stream_through() {
+    let port = upstream(DOWNLOAD);
+    let server = App::new()
+        .route("POST", "/upload", json!({ "type": "proxy", "value": format!("http://127.0.0.1:{}/sink", port) }))
+        .start();
+    #[cfg(target_os = "linux")]
+    let peak_before = peak_rss_kib(server.pid());
+
+    let body = reqwest::blocking::Body::sized(std::io::repeat(b'u').take(UPLOAD), UPLOAD);
+    let mut res = client().post(server.url("/upload")).body(body).send().unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.headers()["x-received"], UPLOAD.to_string().as_str());
+    let downloaded = std::io::copy(&mut res, &mut std::io::sink()).unwrap();
+    assert_eq!(downloaded, DOWNLOAD);
+
+    // Buffering either direction would add at least 48 MB
+    #[cfg(target_os = "linux")]
+    {
+        let grown_mib = (peak_rss_kib(server.pid()) - peak_before) / 1024;
+        assert!(grown_mib < 24, "peak RSS grew by {} MiB", grown_mib);
+    }
+}
@@ -0,0 +1,51 @@
+//! `req.query` and `req.params`: repeats, decoding and `querySchema` coercion.
+
+mod common;
+
+use common::*;
+use serde_json::{json, Value};
+
+const ECHO_QUERY: &str = "export default function (req) { return { query: req.query, params: req.params || {} }; }";
+
+#[test]
+fn repeated_keys_become_arrays_and_stay_strings_without_a_schema() {
+    let server = App::new().action("GET", "/echo", "echo", ECHO_QUERY).start();
+
+    let res: Value = server.get("/echo?tag=a&page=2&tag=b&tag=c").json().unwrap();
+    assert_eq!(res["query"], json!({ "tag": ["a", "b", "c"], "page": "2" }));
+}
+
+#[test]
+fn query_schema_coerces_values_and_rejects_malformed_ones() {
+    let server = App::new()
+        .route("GET", "/search", json!({ "type": "action", "value": "search", "querySchema": { "page": "number", "tags": "string[]", "exact": "boolean" } }))
+        .script("search", ECHO_QUERY)
+        .start();
+
+    let res: Value = server.get("/search?page=3&tags=x&exact=true&other=1&other=2").json().unwrap();
+    assert_eq!(res["query"], json!({ "page": 3, "tags": ["x"], "exact": true, "other": ["1", "2"] }));
+
+    let res: Value = server.get("/search?tags=x&tags=y").json().unwrap();
+    assert_eq!(res["query"], json!({ "tags": ["x", "y"] }));
+
+    let bad = server.get("/search?page=two");
+    assert_eq!(bad.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(bad.text().unwrap(), "Query parameter 'page' must be a number, got 'two'");
+
+    let repeated = server.get("/search?page=1&page=2");
+    assert_eq!(repeated.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(repeated.text().unwrap(), "Query parameter 'page' expects a single value, got 2");
+}
+
+#[test]
+fn params_and_query_are_percent_decoded() {
+    let server = App::new().dynamic("GET", "/users/:name", "user", ECHO_QUERY).start();
+
+    let res: Value = server.get("/users/john%20doe?q=a%26b%3Dc&city=S%C3%A3o+Paulo&na%C3%AFve=1&plus=1%2B1").json().unwrap();
+    assert_eq!(res["params"], json!({ "name": "john doe" }));
+    assert_eq!(res["query"], json!({ "q": "a&b=c", "city": "São Paulo", "naïve": "1", "plus": "1+1" }));
+
+    let res: Value = server.get("/users/%E6%97%A5%E6%9C%AC?bad=%FF%FE").json().unwrap();
+    assert_eq!(res["params"], json!({ "name": "日本" }));
+    assert_eq!(res["query"], json!({ "bad": "\u{FFFD}\u{FFFD}" }));
+}
@@ -0,0 +1,59 @@
+//! Precomputed `.reply()` routes.
+
+mod common;
+
+use common::*;
+use serde_json::json;
+
+#[test]
+fn reply_route_with_max_age_revalidates_with_its_etag() {
+    let server = App::new()
+        .route("GET", "/version", json!({ "type": "json", "value": { "version": "1.2.3" }, "cache": 60 }))
+        .start();
+
+    let first = server.get("/version");
+    assert_eq!(first.status(), StatusCode::OK);
+    assert_eq!(first.headers()["cache-control"], "public, max-age=60");
+    let etag = first.headers()["etag"].clone();
+    assert_eq!(server.get("/version").headers()["etag"], etag, "ETag is stable");
+
+    let revalidated = client().get(server.url("/version")).header("If-None-Match", etag.clone()).send().unwrap();
+    assert_eq!(revalidated.status(), StatusCode::NOT_MODIFIED);
+    assert_eq!(revalidated.headers()["etag"], etag);
+    assert!(revalidated.text().unwrap().is_empty());
+}
+
+#[test]
+fn reply_routes_serve_their_declared_content_type() {
+    let server = App::new()
+        .route("GET", "/page", json!({ "type": "text", "value": "<h1>Hi</h1>", "contentType": "text/html; charset=utf-8" }))
+        .route("GET", "/config.yaml", json!({ "type": "json", "value": "name: titan\n", "contentType": "application/yaml" }))
+        .route("GET", "/data", json!({ "type": "json", "value": { "a": 1 }, "contentType": "application/vnd.api+json" }))
+        .start();
+
+    let page = server.get("/page");
+    assert_eq!(page.headers()["content-type"], "text/html; charset=utf-8");
+    assert_eq!(page.text().unwrap(), "<h1>Hi</h1>");
+
+    let yaml = server.get("/config.yaml");
+    assert_eq!(yaml.headers()["content-type"], "application/yaml");
+    assert_eq!(yaml.text().unwrap(), "name: titan\n");
+
+    let data = server.get("/data");
+    assert_eq!(data.headers()["content-type"], "application/vnd.api+json");
+    assert_eq!(data.text().unwrap(), r#"{"a":1}"#);
+}
+
+#[test]
+fn invalid_content_type_keeps_the_default_and_warns() {
+    let server = App::new()
+        .route("GET", "/bad", json!({ "type": "json", "value": "{\"a\":1}", "contentType": "text/plain\r\nX-Injected: 1" }))
+        .start();
+
+    let bad = server.get("/bad");
+    assert_eq!(bad.headers()["content-type"], "application/json");
+    assert!(bad.headers().get("x-injected").is_none());
+    // Without a usable content type the string is a JSON value, not a literal body
+    assert_eq!(bad.text().unwrap(), r#""{\"a\":1}""#);
+    assert!(server.log().contains("invalid contentType"), "{}", server.log());
+}
@@ -0,0 +1,261 @@
+//! How action results become HTTP responses.
+
+mod common;
+
+use common::*;
+use serde_json::{json, Value};
+
+fn empty_results(app: App) -> App {
+    app.action("GET", "/undefined", "returnsUndefined", "export default function (req) { return undefined; }")
+        .action("GET", "/null", "returnsNull", "export default function (req) { return null; }")
+        .action("GET", "/empty", "returnsEmpty", "export default function (req) { return {}; }")
+}
+
+#[test]
+fn undefined_null_and_empty_object_are_distinct() {
+    let server = empty_results(App::new()).start();
+
+    let undefined = server.get("/undefined");
+    assert_eq!(undefined.status(), StatusCode::NO_CONTENT);
+    assert!(undefined.text().unwrap().is_empty());
+
+    let null = server.get("/null");
+    assert_eq!(null.status(), StatusCode::OK);
+    assert_eq!(null.text().unwrap(), "null");
+
+    let empty = server.get("/empty");
+    assert_eq!(empty.status(), StatusCode::OK);
+    assert_eq!(empty.json::<Value>().unwrap(), json!({}));
+}
+
+#[test]
+fn undefined_status_is_configurable() {
+    let server = empty_results(App::new().config("undefined_status", json!(200))).start();
+
+    let undefined = server.get("/undefined");
+    assert_eq!(undefined.status(), StatusCode::OK);
+    assert_eq!(undefined.text().unwrap(), "null");
+}
+
+#[test]
+fn fail_answers_with_its_status_and_error_envelope() {
+    let server = App::new()
+        .dynamic("GET", "/users/:id", "user", r#"export default function (req) {
+            t.fail("USER_NOT_FOUND", "No such user", { status: 404, details: { id: req.params.id } });
+        }"#)
+        .start();
+
+    let res = server.get("/users/7");
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    assert_eq!(
+        res.json::<Value>().unwrap(),
+        json!({ "error": { "code": "USER_NOT_FOUND", "message": "No such user", "details": { "id": "7" } } })
+    );
+}
+
+#[test]
+fn etag_routes_answer_304_when_the_body_is_unchanged() {
+    let code = "export default function (req) { return { version: req.query.v || 1 }; }";
+    let server = App::new()
+        .route("GET", "/tagged", json!({ "type": "action", "value": "tagged", "etag": true }))
+        .script("tagged", code)
+        .action("GET", "/plain", "plain", code)
+        .start();
+
+    let first = server.get("/tagged");
+    assert_eq!(first.status(), StatusCode::OK);
+    let etag = first.headers()["etag"].to_str().unwrap().to_string();
+    assert!(etag.starts_with("W/\""), "{}", etag);
+
+    let revalidate = |path: &str| client().get(server.url(path)).header("If-None-Match", &etag).send().unwrap();
+    let unchanged = revalidate("/tagged");
+    assert_eq!(unchanged.status(), StatusCode::NOT_MODIFIED);
+    assert_eq!(unchanged.headers()["etag"], etag.as_str());
+    assert_eq!(unchanged.text().unwrap(), "");
+
+    let changed = revalidate("/tagged?v=2");
+    assert_eq!(changed.status(), StatusCode::OK);
+    assert_ne!(changed.headers()["etag"], etag.as_str());
+    assert_eq!(changed.json::<Value>().unwrap(), json!({ "version": "2" }));
+
+    // Opt-in: other routes never get a tag
+    assert!(server.get("/plain").headers().get("etag").is_none());
+}
+
+#[test]
+fn early_hints_precede_the_final_response() {
+    let server = App::new()
+        .action("GET", "/page", "page", r#"export default function (req) {
+            t.response.earlyHints({ links: [{ href: "/app.css", as: "style" }, "</app.js>; rel=preload; as=script"] });
+            return t.response.html("<h1>" + req.path + "</h1>");
+        }"#)
+        .start();
+
+    let response = server.raw("GET /page HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n");
+    let (hints, rest) = response.split_once("\r\n\r\n").unwrap();
+    assert_eq!(
+        hints,
+        "HTTP/1.1 103 Early Hints\r\nLink: </app.css>; rel=preload; as=style\r\nLink: </app.js>; rel=preload; as=script",
+        "{}",
+        response
+    );
+    assert!(rest.starts_with("HTTP/1.1 200 OK\r\n"), "{}", response);
+    assert!(rest.to_ascii_lowercase().contains("link: </app.css>; rel=preload; as=style, </app.js>; rel=preload; as=script"), "{}", rest);
+    assert!(rest.ends_with("<h1>/page</h1>"), "{}", rest);
+}
+
+#[test]
+fn sets_and_maps_serialize_as_arrays_objects_and_pairs() {
+    let server = App::new()
+        .action("GET", "/set", "returnsSet", "export default function (req) { return { tags: new Set(['a', 'b', 'a', req.path]) }; }")
+        .action("GET", "/map", "returnsMap", "export default function (req) { return new Map([['path', req.path], [1, 'one']]); }")
+        .action("GET", "/pairs", "returnsPairs", r#"export default function (req) {
+            return t.response.json({ m: new Map([['path', req.path], [1, 'one']]) }, { maps: "pairs" });
+        }"#)
+        .start();
+
+    assert_eq!(server.get("/set").json::<Value>().unwrap(), json!({ "tags": ["a", "b", "/set"] }));
+    assert_eq!(server.get("/map").json::<Value>().unwrap(), json!({ "path": "/map", "1": "one" }));
+    assert_eq!(server.get("/pairs").json::<Value>().unwrap(), json!({ "m": [["path", "/pairs"], [1, "one"]] }));
+}
+
+#[test]
+fn dates_serialize_as_iso_strings() {
+    let server = App::new()
+        .action("GET", "/dated", "dated", r#"export default function (req) {
+            const createdAt = new Date(Date.UTC(2024, 0, 2, 3, 4, 5, 6));
+            return { path: req.path, createdAt, nested: [{ at: createdAt }], stringified: JSON.parse(JSON.stringify({ createdAt })).createdAt };
+        }"#)
+        .start();
+
+    assert_eq!(
+        server.get("/dated").json::<Value>().unwrap(),
+        json!({
+            "path": "/dated",
+            "createdAt": "2024-01-02T03:04:05.006Z",
+            "nested": [{ "at": "2024-01-02T03:04:05.006Z" }],
+            "stringified": "2024-01-02T03:04:05.006Z"
+        })
+    );
+}
+
+#[test]
+fn missing_action_bundle_is_a_structured_500() {
+    let server = App::new()
+        .route("GET", "/gone", json!({ "type": "action", "value": "gone" }))
+        .action("GET", "/ping", "ping", "export default function (req) { return { path: req.path }; }")
+        .start();
+
+    let res = server.get("/gone");
+    assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    assert_eq!(res.json::<Value>().unwrap(), json!({ "error": "Action bundle not found", "action": "gone" }));
+    assert_eq!(server.get("/ping").json::<Value>().unwrap(), json!({ "path": "/ping" }));
+}
+
+#[test]
+fn bundle_exporting_the_wrong_name_explains_the_mismatch() {
+    let server = App::new()
+        .action("GET", "/", "home", "module.exports = { handler: function (req) { return { path: req.path }; }, VERSION: 2 };")
+        .start();
+
+    let expected = "Action 'home' does not export a function named 'home'; the bundle exports: handler";
+    assert!(server.log().contains(expected), "{}", server.log());
+    let res = server.get("/");
+    assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    assert_eq!(res.json::<Value>().unwrap(), json!({ "error": expected, "action": "home" }));
+}
+
+const NESTED: &str = r#"export default function (req) {
+    let value = { leaf: req.path };
+    for (let i = 1; i < Number(req.query.depth); i++) value = { child: value };
+    return value;
+}"#;
+
+#[test]
+fn results_nested_past_the_cap_are_a_clean_500() {
+    let server = App::new()
+        .action("GET", "/nested", "nested", NESTED)
+        .action("GET", "/cycle", "cycle", "export default function (req) { const a = { path: req.path }; a.self = a; return a; }")
+        .start();
+
+    assert_eq!(server.get("/nested?depth=200").status(), StatusCode::OK);
+    let deep = server.get("/nested?depth=100000");
+    assert_eq!(deep.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    assert_eq!(deep.json::<Value>().unwrap(), json!({ "error": "Result nests deeper than 256 levels (__config.maxResultDepth)" }));
+    assert_eq!(server.get("/cycle").status(), StatusCode::INTERNAL_SERVER_ERROR);
+    // The worker survived both
+    assert_eq!(server.get("/nested?depth=1").json::<Value>().unwrap(), json!({ "leaf": "/nested" }));
+}
+
+#[test]
+fn max_result_depth_is_configurable() {
+    let server = App::new().config("maxResultDepth", json!(4)).action("GET", "/nested", "nested", NESTED).start();
+
+    assert_eq!(
+        server.get("/nested?depth=4").json::<Value>().unwrap(),
+        json!({ "child": { "child": { "child": { "leaf": "/nested" } } } })
+    );
+    let deep = server.get("/nested?depth=5");
+    assert_eq!(deep.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    assert_eq!(deep.json::<Value>().unwrap(), json!({ "error": "Result nests deeper than 4 levels (__config.maxResultDepth)" }));
+}
+
+const GREETING: &str = "export default function (req) { return \"hello \" + req.query.name; }";
+
+#[test]
+fn bare_string_results_are_plain_text_unless_string_as_json() {
+    let server = App::new().action("GET", "/greet", "greet", GREETING).start();
+    let res = server.get("/greet?name=ada");
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.headers()["content-type"], "text/plain; charset=utf-8");
+    assert_eq!(res.text().unwrap(), "hello ada");
+
+    let legacy = App::new().config("stringAsJson", json!(true)).action("GET", "/greet", "greet", GREETING).start();
+    let res = legacy.get("/greet?name=ada");
+    assert_eq!(res.headers()["content-type"], "application/json");
+    assert_eq!(res.text().unwrap(), "\"hello ada\"");
+}
+
+#[test]
+fn t_error_answers_with_the_status_it_carries() {
+    let server = App::new()
+        .dynamic("POST", "/orders/:id", "order", r#"export default function (req) {
+            if (req.params.id !== "1") t.error(404, "No such order", { id: req.params.id });
+            t.error(422, "Quantity must be positive");
+        }"#)
+        .start();
+
+    let missing = client().post(server.url("/orders/7")).send().unwrap();
+    assert_eq!(missing.status(), StatusCode::NOT_FOUND);
+    assert_eq!(
+        missing.json::<Value>().unwrap(),
+        json!({ "error": { "code": "HTTP_404", "message": "No such order", "details": { "id": "7" } } })
+    );
+
+    let invalid = client().post(server.url("/orders/1")).send().unwrap();
+    assert_eq!(invalid.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(invalid.json::<Value>().unwrap(), json!({ "error": { "code": "HTTP_422", "message": "Quantity must be positive" } }));
+}
+
+#[test]
+fn numbers_keep_integers_and_big_ids_keep_every_digit() {
+    let server = App::new()
+        .action("GET", "/number", "number", "export default function (req) { return Number(req.query.v); }")
+        .action("GET", "/ids", "ids", r#"export default function (req) {
+            return { path: req.path, id: BigInt("9007199254740993"), max: BigInt("18446744073709551615"), huge: BigInt("123456789012345678901234567890"), neg: -7, half: 1.5 };
+        }"#)
+        .start();
+
+    for (raw, expected) in [("42", "42"), ("-7", "-7"), ("42.0", "42"), ("1.5", "1.5"), ("9007199254740992", "9007199254740992")] {
+        let res = server.get(&format!("/number?v={}", raw));
+        assert_eq!(res.headers()["content-type"], "application/json");
+        assert_eq!(res.text().unwrap(), expected, "{}", raw);
+    }
+
+    let ids: Value = serde_json::from_str(&server.get("/ids").text().unwrap()).unwrap();
+    assert_eq!(ids["id"].as_u64(), Some(9007199254740993));
+    assert_eq!(ids["max"].as_u64(), Some(u64::MAX));
+    assert_eq!(ids["huge"], json!("123456789012345678901234567890"));
+    assert_eq!(ids["neg"].as_i64(), Some(-7));
+    assert_eq!(ids["half"].as_f64(), Some(1.5));
+}
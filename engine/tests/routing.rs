@@ -0,0 +1,126 @@
+//! Route matching: trailing slashes, methods and the built-in probes.
+
+mod common;
+
+use common::*;
+use serde_json::{json, Value};
+
+const ECHO_PATH: &str = "export default function (req) { return { path: req.path, method: req.method, params: req.params || {} }; }";
+
+fn users_app(mode: &str) -> App {
+    App::new()
+        .config("trailingSlash", json!(mode))
+        .action("GET", "/users", "listUsers", ECHO_PATH)
+        .action("POST", "/users", "createUser", ECHO_PATH)
+        .dynamic("GET", "/users/:id", "getUser", ECHO_PATH)
+}
+
+#[test]
+fn strict_trailing_slash_keeps_the_forms_apart() {
+    let server = users_app("strict").start();
+
+    assert_eq!(server.get("/users").json::<Value>().unwrap()["path"], json!("/users"));
+    assert_eq!(server.get("/users/").status(), StatusCode::NOT_FOUND);
+    // Dynamic patterns match either form in every mode
+    assert_eq!(server.get("/users/7/").json::<Value>().unwrap()["params"], json!({ "id": "7" }));
+}
+
+#[test]
+fn ignore_trailing_slash_reaches_the_declared_route() {
+    let server = users_app("ignore").start();
+
+    assert_eq!(server.get("/users/").json::<Value>().unwrap(), json!({ "path": "/users", "method": "GET", "params": {} }));
+    let created = client().post(server.url("/users/")).send().unwrap();
+    assert_eq!(created.json::<Value>().unwrap()["method"], json!("POST"));
+    assert_eq!(server.get("/users/7/").json::<Value>().unwrap()["params"], json!({ "id": "7" }));
+    assert_eq!(server.get("/nothing/").status(), StatusCode::NOT_FOUND);
+}
+
+#[test]
+fn redirect_trailing_slash_points_at_the_canonical_path() {
+    let server = users_app("redirect").start();
+
+    let get = server.get("/users/?page=2");
+    assert_eq!(get.status(), StatusCode::MOVED_PERMANENTLY);
+    assert_eq!(get.headers()["location"], "/users?page=2");
+
+    let post = client().post(server.url("/users/")).send().unwrap();
+    assert_eq!(post.status(), StatusCode::PERMANENT_REDIRECT);
+    assert_eq!(post.headers()["location"], "/users");
+
+    let dynamic = server.get("/users/7/");
+    assert_eq!(dynamic.status(), StatusCode::MOVED_PERMANENTLY);
+    assert_eq!(dynamic.headers()["location"], "/users/7");
+
+    assert_eq!(server.get("/users").status(), StatusCode::OK);
+    assert_eq!(server.get("/nothing/").status(), StatusCode::NOT_FOUND);
+}
+
+#[test]
+fn redirect_trailing_slash_never_points_off_site() {
+    let server = App::new()
+        .config("trailingSlash", json!("redirect"))
+        .dynamic("GET", "/:slug", "page", ECHO_PATH)
+        .start();
+
+    let res = server.raw("GET //evil.com/ HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n");
+    let location = res.lines().find_map(|l| l.strip_prefix("location: ")).unwrap_or_else(|| panic!("no location:\n{}", res));
+    assert_eq!(location, "/evil.com");
+}
+
+#[test]
+fn dynamic_routes_match_each_method_and_multi_method_entries() {
+    let server = App::new()
+        .dynamic("PATCH", "/items/:id", "patchItem", ECHO_PATH)
+        .dynamic("DELETE", "/items/:id", "deleteItem", ECHO_PATH)
+        .dynamic("options", "/items/:id", "itemOptions", ECHO_PATH)
+        .dynamic_route(json!({ "method": "GET", "methods": ["POST", "purge"], "pattern": "/carts/:id", "action": "cart" }))
+        .script("cart", ECHO_PATH)
+        .start();
+
+    let send = |method: &str, path: &str| {
+        client().request(reqwest::Method::from_bytes(method.as_bytes()).unwrap(), server.url(path)).send().unwrap()
+    };
+    for method in ["PATCH", "DELETE", "OPTIONS"] {
+        let res = send(method, "/items/9");
+        assert_eq!(res.status(), StatusCode::OK, "{}", method);
+        assert_eq!(res.json::<Value>().unwrap(), json!({ "path": "/items/9", "method": method, "params": { "id": "9" } }));
+    }
+    assert_eq!(send("GET", "/items/9").status(), StatusCode::NOT_FOUND);
+    assert_eq!(send("PUT", "/items/9").status(), StatusCode::NOT_FOUND);
+
+    for method in ["GET", "POST", "PURGE"] {
+        let res = send(method, "/carts/3");
+        assert_eq!(res.status(), StatusCode::OK, "{}", method);
+        assert_eq!(res.json::<Value>().unwrap()["method"], json!(method));
+    }
+    assert_eq!(send("DELETE", "/carts/3").status(), StatusCode::NOT_FOUND);
+}
+
+#[test]
+fn probes_answer_without_actions_and_can_be_moved_or_disabled() {
+    let server = App::new().start();
+    assert_eq!(server.get("/healthz").text().unwrap(), "ok");
+    assert_eq!(server.get("/readyz").text().unwrap(), "ready");
+
+    let server = App::new()
+        .config("health", json!({ "healthz": "/live", "readyz": false }))
+        .action("GET", "/readyz", "readyz", ECHO_PATH)
+        .start();
+    assert_eq!(server.get("/live").text().unwrap(), "ok");
+    assert_eq!(server.get("/healthz").status(), StatusCode::NOT_FOUND);
+    // A disabled probe leaves the path to the app's own routes
+    assert_eq!(server.get("/readyz").json::<Value>().unwrap()["path"], json!("/readyz"));
+}
+
+#[test]
+fn http2_prior_knowledge_is_served_alongside_http1() {
+    let server = users_app("strict").start();
+
+    let h2 = Client::builder().http2_prior_knowledge().build().unwrap();
+    let res = h2.get(server.url("/users")).send().unwrap();
+    assert_eq!(res.version(), reqwest::Version::HTTP_2);
+    assert_eq!(res.json::<Value>().unwrap()["path"], json!("/users"));
+
+    assert_eq!(server.get("/users").version(), reqwest::Version::HTTP_11);
+}
@@ -0,0 +1,392 @@
+//! Action execution on the isolate pool: request isolation, drift replay,
+//! worker failures and scheduling.
+
+mod common;
+
+use common::*;
+use serde_json::{json, Value};
+
+#[test]
+fn globals_set_by_one_request_are_gone_for_the_next() {
+    // One worker, so both requests run on the same isolate
+    let server = App::new()
+        .action("GET", "/set", "setLeak", "export default function (req) { globalThis.leak = 1; return { leak: globalThis.leak }; }")
+        .action("GET", "/hide", "hideLeak", "export default function (req) { Object.defineProperty(globalThis, 'hidden', { value: 1, configurable: true }); return { hidden: globalThis.hidden }; }")
+        .action("GET", "/read", "readLeak", "export default function (req) { return { leak: typeof globalThis.leak, hidden: typeof globalThis.hidden }; }")
+        .start();
+
+    assert_eq!(server.get("/set").json::<Value>().unwrap(), json!({ "leak": 1 }));
+    // Non-enumerable globals are removed too
+    assert_eq!(server.get("/hide").json::<Value>().unwrap(), json!({ "hidden": 1 }));
+    assert_eq!(server.get("/read").json::<Value>().unwrap(), json!({ "leak": "undefined", "hidden": "undefined" }));
+}
+
+/// Bumps `key` in t.share before and between two drifts.
+fn side_effects(directive: &str, key: &str) -> String {
+    format!(
+        r#"{directive}
+export default function (req) {{
+    const bump = () => t.share.set("{key}", (t.share.get("{key}") || 0) + 1);
+    const url = "http://" + req.headers.host + "/pong";
+    bump();
+    drift(t.fetch(url));
+    bump();
+    drift(t.fetch(url));
+    bump();
+    return {{ effects: t.share.get("{key}") }};
+}}"#
+    )
+}
+
+#[test]
+fn no_replay_actions_run_each_side_effect_once() {
+    let server = App::new()
+        .route("GET", "/pong", json!({ "type": "text", "value": "pong" }))
+        .action("GET", "/linear", "linear", &side_effects("// @titan-no-replay", "linear"))
+        .action("GET", "/replayed", "replayed", &side_effects("", "replayed"))
+        .start();
+
+    assert_eq!(server.get("/linear").json::<Value>().unwrap(), json!({ "effects": 3 }));
+    // Replay re-runs the code before each completed drift
+    assert_eq!(server.get("/replayed").json::<Value>().unwrap(), json!({ "effects": 6 }));
+}
+
+/// `Cpus_allowed_list` of each `titan-worker-N` thread, by worker name.
+#[cfg(target_os = "linux")]
+fn worker_cpus(pid: u32) -> std::collections::BTreeMap<String, String> {
+    let mut workers = std::collections::BTreeMap::new();
+    for task in std::fs::read_dir(format!("/proc/{}/task", pid)).unwrap().flatten() {
+        let name = std::fs::read_to_string(task.path().join("comm")).unwrap_or_default().trim().to_string();
+        if !name.starts_with("titan-worker-") {
+            continue;
+        }
+        let status = std::fs::read_to_string(task.path().join("status")).unwrap_or_default();
+        if let Some(cpus) = status.lines().find_map(|l| l.strip_prefix("Cpus_allowed_list:")) {
+            workers.insert(name, cpus.trim().to_string());
+        }
+    }
+    workers
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn cpu_affinity_pins_workers_to_distinct_cores() {
+    let cores = std::thread::available_parallelism().map_or(1, |n| n.get()).min(2);
+    let server = App::new()
+        .config("threads", json!(cores))
+        .config("cpuAffinity", json!(true))
+        .action("GET", "/ping", "ping", "export default function (req) { return { pong: true }; }")
+        .start();
+
+    let workers = worker_cpus(server.pid());
+    assert_eq!(workers.len(), cores, "{:?}", workers);
+    for cpus in workers.values() {
+        assert!(cpus.parse::<usize>().is_ok(), "pinned to one core, got {:?}", workers);
+    }
+    let distinct: std::collections::BTreeSet<&String> = workers.values().collect();
+    assert_eq!(distinct.len(), cores, "{:?}", workers);
+    assert_eq!(server.get("/ping").json::<Value>().unwrap(), json!({ "pong": true }));
+}
+
+#[test]
+fn queue_wait_shows_in_server_timing() {
+    let server = App::new()
+        .env("TITAN_DEV", "1")
+        .action("GET", "/slow", "slow", "export default function (req) { const end = Date.now() + 600; while (Date.now() < end) {} return { slow: true }; }")
+        .action("GET", "/fast", "fast", "export default function (req) { return { fast: true }; }")
+        .start();
+
+    // The only worker is busy with /slow, so /fast waits in its queue
+    let slow_url = server.url("/slow");
+    let slow = std::thread::spawn(move || client().get(slow_url).send().unwrap().status());
+    std::thread::sleep(std::time::Duration::from_millis(150));
+    let fast = server.get("/fast");
+    assert_eq!(slow.join().unwrap(), StatusCode::OK);
+
+    let timing = fast.headers()["server-timing"].to_str().unwrap().to_string();
+    let queue_ms: f64 = timing
+        .split(", ")
+        .find_map(|entry| entry.strip_prefix("queue;dur="))
+        .unwrap_or_else(|| panic!("no queue entry in {:?}", timing))
+        .parse()
+        .unwrap();
+    assert!(queue_ms > 100.0, "{}", timing);
+}
+
+#[test]
+fn pool_grows_under_sustained_load_and_shrinks_after() {
+    let server = App::new()
+        .config("pool", json!({ "max": 3, "cooldownMs": 500 }))
+        .action("GET", "/slow", "slow", "export default function (req) { const end = Date.now() + 100; while (Date.now() < end) {} return { path: req.path }; }")
+        .start();
+
+    // Enough concurrent clients to keep every worker's queue non-empty
+    let stop = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+    let load: Vec<_> = (0..8)
+        .map(|_| {
+            let (stop, url) = (stop.clone(), server.url("/slow"));
+            std::thread::spawn(move || {
+                while !stop.load(std::sync::atomic::Ordering::Relaxed) {
+                    assert_eq!(client().get(&url).send().unwrap().status(), StatusCode::OK);
+                }
+            })
+        })
+        .collect();
+    server.wait_for_log("pool grew to 2 workers (max 3)");
+    server.wait_for_log("pool grew to 3 workers (max 3)");
+    stop.store(true, std::sync::atomic::Ordering::Relaxed);
+    load.into_iter().for_each(|thread| thread.join().unwrap());
+
+    server.wait_for_log("pool shrank to 2 workers (baseline 1)");
+    let log = server.wait_for_log("pool shrank to 1 workers (baseline 1)");
+    assert!(!log.contains("pool grew to 4"), "{}", log);
+    assert_eq!(server.get("/slow").json::<Value>().unwrap(), json!({ "path": "/slow" }));
+}
+
+#[test]
+fn system_stats_reports_the_pool_from_inside_an_action() {
+    let server = App::new()
+        .config("threads", json!(2))
+        .action("GET", "/status", "status", "export default function (req) { return { path: req.path, ...t.system.stats() }; }")
+        .start();
+
+    let first: Value = server.get("/status").json().unwrap();
+    assert_eq!(first["totalIsolates"], json!(2));
+    assert_eq!(first["freeIsolates"], json!(1), "the calling isolate is busy: {}", first);
+    assert!(first["inFlightRequests"].as_u64().unwrap() >= 1, "{}", first);
+    let memory = first["memoryMb"].as_f64().unwrap();
+    assert!(memory > 1.0 && memory < 64.0 * 1024.0, "{}", first);
+
+    std::thread::sleep(std::time::Duration::from_millis(300));
+    let second: Value = server.get("/status").json().unwrap();
+    assert!(second["uptimeSeconds"].as_f64().unwrap() > first["uptimeSeconds"].as_f64().unwrap(), "{} then {}", first, second);
+}
+
+#[test]
+fn heapdump_streams_a_parseable_snapshot_behind_its_token() {
+    let plain = App::new().start();
+    assert_eq!(plain.get("/__heapdump?isolate=0").status(), StatusCode::NOT_FOUND, "off in production without a token");
+    drop(plain);
+
+    let server = App::new()
+        .env("TITAN_HEAPDUMP_TOKEN", "s3cret")
+        .action("GET", "/keep", "keep", "export default function (req) { globalThis.kept = 'heap-marker' + req.path; return { ok: true }; }")
+        .start();
+    server.get("/keep");
+
+    assert_eq!(server.get("/__heapdump?isolate=0").status(), StatusCode::UNAUTHORIZED);
+    let dump = |isolate: &str| client().get(server.url(&format!("/__heapdump?isolate={}", isolate))).bearer_auth("s3cret").send().unwrap();
+    assert_eq!(dump("7").status(), StatusCode::BAD_REQUEST);
+
+    let res = dump("0");
+    assert_eq!(res.status(), StatusCode::OK);
+    assert!(res.headers()["content-disposition"].to_str().unwrap().contains(".heapsnapshot"));
+    let snapshot: Value = res.json().unwrap();
+    assert!(snapshot["snapshot"]["meta"]["node_fields"].is_array(), "not a V8 heap snapshot");
+    assert!(snapshot["nodes"].as_array().is_some_and(|nodes| !nodes.is_empty()));
+    assert!(snapshot["strings"].as_array().unwrap().iter().any(|s| s == "heap-marker/keep"));
+    // The isolate goes back to serving requests
+    assert_eq!(server.get("/keep").json::<Value>().unwrap(), json!({ "ok": true }));
+}
+
+#[test]
+fn failed_compilation_recovers_once_the_source_is_fixed() {
+    let server = App::new()
+        .config("compileRetryMs", json!(100))
+        .action("GET", "/fixme", "fixme", "export default function (req) { return { path: req.path ")
+        .start();
+
+    let broken = server.get("/fixme");
+    assert_eq!(broken.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    assert_eq!(broken.json::<Value>().unwrap()["action"], json!("fixme"));
+    server.wait_for_log("Retry 1 of action 'fixme'");
+
+    std::fs::write(server.dir.join("actions/fixme.js"), "export default function (req) { return { path: req.path }; }").unwrap();
+    std::thread::sleep(std::time::Duration::from_millis(150));
+    assert_eq!(server.get("/fixme").json::<Value>().unwrap(), json!({ "path": "/fixme" }));
+    assert!(server.log().contains("of action 'fixme': compiled, action restored"), "{}", server.log());
+}
+
+#[test]
+fn dev_mode_reloads_edited_actions_and_keeps_the_last_good_one() {
+    let server = App::new()
+        .config("dev", json!(true))
+        .action("GET", "/greet", "greet", "export default function (req) { return { version: 1, path: req.path }; }")
+        .start();
+    server.wait_for_log("for action changes");
+    assert_eq!(server.get("/greet").json::<Value>().unwrap(), json!({ "version": 1, "path": "/greet" }));
+
+    let bundle = server.dir.join("actions/greet.js");
+    std::fs::write(&bundle, "export default function (req) { return { version: 2, path: req.path }; }").unwrap();
+    server.wait_for_log("reloaded action 'greet'");
+    assert_eq!(server.get("/greet").json::<Value>().unwrap(), json!({ "version": 2, "path": "/greet" }));
+
+    std::fs::write(&bundle, "export default function (req) { return { version: 3, ").unwrap();
+    server.wait_for_log("keeping the previous version");
+    assert_eq!(server.get("/greet").json::<Value>().unwrap(), json!({ "version": 2, "path": "/greet" }));
+}
+
+/// Upstream that answers every request after `delay`.
+fn slow_upstream(delay: std::time::Duration) -> u16 {
+    use std::io::{Read, Write};
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    std::thread::spawn(move || {
+        for mut stream in listener.incoming().flatten() {
+            std::thread::spawn(move || {
+                let _ = stream.read(&mut [0; 4096]);
+                std::thread::sleep(delay);
+                let _ = stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 4\r\nConnection: close\r\n\r\nlate");
+            });
+        }
+    });
+    port
+}
+
+#[test]
+fn request_ended_mid_drift_ignores_the_late_result() {
+    let port = slow_upstream(std::time::Duration::from_millis(800));
+    let server = App::new()
+        .action("GET", "/slow", "slow", &format!(r#"export default function (req) {{
+            globalThis.leaked = req.path;
+            t.shareContext.set("slowRuns", (t.shareContext.get("slowRuns") || 0) + 1);
+            const res = drift(t.fetch("http://127.0.0.1:{}/"), {{ timeout: 200 }});
+            t.shareContext.set("slowFinished", true);
+            return {{ res }};
+        }}"#, port))
+        .action("GET", "/state", "state", r#"export default function (req) {
+            return { path: req.path, leaked: typeof globalThis.leaked, runs: t.shareContext.get("slowRuns"), finished: t.shareContext.get("slowFinished") };
+        }"#)
+        .start();
+
+    let slow = server.get("/slow");
+    assert_eq!(slow.status(), StatusCode::GATEWAY_TIMEOUT);
+    assert_eq!(slow.json::<Value>().unwrap(), json!({ "error": "drift timeout" }));
+
+    // Past the upstream's answer: the action was neither resumed nor replayed
+    std::thread::sleep(std::time::Duration::from_millis(1200));
+    assert_eq!(
+        server.get("/state").json::<Value>().unwrap(),
+        json!({ "path": "/state", "leaked": "undefined", "runs": 1, "finished": null })
+    );
+}
+
+/// `drift.all` over a slow upstream, this server's /pong and a closed port.
+fn batch(slow: u16, closed: u16, options: &str) -> String {
+    format!(
+        r#"export default function (req) {{
+    const results = drift.all([
+        t.fetch("http://127.0.0.1:{slow}/"),
+        t.fetch("http://" + req.headers.host + "/pong"),
+        t.fetch("http://127.0.0.1:{closed}/"),
+    ]{options});
+    return Array.isArray(results) ? results.map(r => r.error ? "error" : r.body) : {{ index: results.index, failed: !!results.error }};
+}}"#
+    )
+}
+
+#[test]
+fn drift_all_runs_operations_concurrently_in_submission_order() {
+    let slow = slow_upstream(std::time::Duration::from_millis(600));
+    let closed = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+    let server = App::new()
+        .route("GET", "/pong", json!({ "type": "text", "value": "pong" }))
+        .action("GET", "/all", "all", &batch(slow, closed, ""))
+        .action("GET", "/fail-fast", "failFast", &batch(slow, closed, ", { failFast: true }"))
+        .start();
+
+    let started = std::time::Instant::now();
+    let res = server.get("/all");
+    let elapsed = started.elapsed();
+    assert_eq!(res.json::<Value>().unwrap(), json!(["late", "pong", "error"]));
+    // Three slow fetches back to back would take 1.8s
+    assert!(elapsed < std::time::Duration::from_millis(1500), "batch took {:?}", elapsed);
+
+    // The closed port fails at once; failFast answers without waiting for the slow fetch
+    let started = std::time::Instant::now();
+    assert_eq!(server.get("/fail-fast").json::<Value>().unwrap(), json!({ "index": 2, "failed": true }));
+    assert!(started.elapsed() < std::time::Duration::from_millis(500), "failFast took {:?}", started.elapsed());
+}
+
+#[test]
+fn full_worker_queues_shed_requests_with_503() {
+    let server = App::new()
+        .config("maxQueueDepth", json!(1))
+        .action("GET", "/slow", "slow", "export default function (req) { const end = Date.now() + 800; while (Date.now() < end) {} return { path: req.path }; }")
+        .start();
+
+    let burst: Vec<_> = (0..6)
+        .map(|_| {
+            let url = server.url("/slow");
+            std::thread::spawn(move || {
+                let res = client().get(url).send().unwrap();
+                (res.status(), res.headers().get("retry-after").map(|v| v.to_str().unwrap().to_string()))
+            })
+        })
+        .collect();
+    let answers: Vec<_> = burst.into_iter().map(|thread| thread.join().unwrap()).collect();
+
+    let served = answers.iter().filter(|(status, _)| *status == StatusCode::OK).count();
+    let shed: Vec<_> = answers.iter().filter(|(status, _)| *status == StatusCode::SERVICE_UNAVAILABLE).collect();
+    assert!(served >= 1 && !shed.is_empty() && served + shed.len() == answers.len(), "{:?}", answers);
+    assert!(shed.iter().all(|(_, retry_after)| retry_after.as_deref() == Some("1")), "{:?}", answers);
+    assert_eq!(server.get("/slow").status(), StatusCode::OK, "serves again once the queue drains");
+}
+
+#[test]
+fn requests_avoid_the_worker_busy_with_a_slow_one() {
+    let server = App::new()
+        .config("threads", json!(2))
+        .action("GET", "/slow", "slow", "export default function (req) { const end = Date.now() + 1500; while (Date.now() < end) {} return { path: req.path }; }")
+        .action("GET", "/fast", "fast", "export default function (req) { return { path: req.path }; }")
+        .start();
+
+    let slow_url = server.url("/slow");
+    let slow = std::thread::spawn(move || client().get(slow_url).send().unwrap().status());
+    std::thread::sleep(std::time::Duration::from_millis(200));
+
+    // Round-robin would queue every other one behind /slow for over a second
+    let latencies: Vec<_> = (0..6)
+        .map(|_| {
+            let started = std::time::Instant::now();
+            assert_eq!(server.get("/fast").status(), StatusCode::OK);
+            started.elapsed()
+        })
+        .collect();
+    assert_eq!(slow.join().unwrap(), StatusCode::OK);
+    assert!(latencies.iter().all(|took| *took < std::time::Duration::from_millis(500)), "{:?}", latencies);
+}
+
+/// Starts `GET /slow` on another thread, then sends SIGTERM while it runs.
+fn terminate_during_slow_request(server: &mut Server) -> (std::process::ExitStatus, std::thread::JoinHandle<Option<Value>>) {
+    let url = server.url("/slow");
+    let request = std::thread::spawn(move || client().get(url).send().ok().and_then(|res| res.json().ok()));
+    std::thread::sleep(std::time::Duration::from_millis(300));
+    (server.terminate(), request)
+}
+
+const SLOW: &str = "export default function (req) { const end = Date.now() + Number(req.query.ms || 1000); while (Date.now() < end) {} return { path: req.path }; }";
+
+#[test]
+fn sigterm_drains_in_flight_requests_before_exiting() {
+    let mut server = App::new().action("GET", "/slow", "slow", SLOW).start();
+
+    let (status, request) = terminate_during_slow_request(&mut server);
+    assert!(status.success(), "{:?}", status);
+    assert_eq!(request.join().unwrap(), Some(json!({ "path": "/slow" })));
+    assert!(server.log().contains("shutdown complete: 1 drained, 0 force-closed"), "{}", server.log());
+}
+
+#[test]
+fn sigterm_force_closes_requests_past_the_grace_period() {
+    let mut server = App::new()
+        .config("shutdownGraceMs", json!(200))
+        .action("GET", "/slow", "slow", &SLOW.replace("1000", "5000"))
+        .start();
+
+    let started = std::time::Instant::now();
+    let (_, request) = terminate_during_slow_request(&mut server);
+    assert!(started.elapsed() < std::time::Duration::from_secs(3), "took {:?}", started.elapsed());
+    assert_eq!(request.join().unwrap(), None);
+    assert!(server.log().contains("shutdown complete: 0 drained, 1 force-closed"), "{}", server.log());
+}
@@ -0,0 +1,91 @@
+//! What the server reports and refuses at boot.
+
+mod common;
+
+use common::*;
+use serde_json::{json, Value};
+
+/// The JSON lines (`log_format: "json"`) the server printed.
+fn json_lines(log: &str) -> Vec<Value> {
+    log.lines().filter_map(|line| serde_json::from_str(line).ok()).collect()
+}
+
+#[test]
+fn startup_summary_counts_routes_and_extensions() {
+    let server = App::new()
+        .config("startup_summary", json!(true))
+        .config("log_format", json!("json"))
+        .route("GET", "/version", json!({ "type": "json", "value": { "v": 1 } }))
+        .action("GET", "/hello", "hello", "export default function (req) { return { hello: req.query.name }; }")
+        .dynamic("GET", "/users/:id", "user", "export default function (req) { return { id: req.params.id }; }")
+        .file(".ext/greeter/titan.json", r#"{ "name": "greeter", "entry": "index.js" }"#)
+        .file(".ext/greeter/index.js", "t.greeter = { hi: () => 'hi' };")
+        .start();
+
+    let log = server.wait_for_log("\"event\":\"startup\"");
+    let summary = json_lines(&log).into_iter().find(|line| line["event"] == "startup").unwrap()["summary"].clone();
+    assert_eq!(summary["port"], json!(server.port));
+    assert_eq!(summary["threads"], json!(1));
+    assert_eq!(summary["exact_routes"], json!(2));
+    assert_eq!(summary["precomputed_routes"], json!(1));
+    assert_eq!(summary["dynamic_routes"], json!(1));
+    assert_eq!(summary["extensions"], json!(["greeter"]));
+    assert_eq!(summary["features"]["compression"], json!(false));
+}
+
+#[test]
+fn config_keys_accept_camel_case() {
+    let server = App::new()
+        .config("startupSummary", json!(true))
+        .config("logFormat", json!("json"))
+        .config("stackMb", json!(16))
+        .config("undefinedStatus", json!(200))
+        .config("staticDir", json!("public"))
+        .config("replyCache", json!(60))
+        .project_file("public/robots.txt", "User-agent: *")
+        .route("GET", "/version", json!({ "type": "json", "value": { "v": 1 } }))
+        .action("GET", "/nothing", "nothing", "export default function (req) {}")
+        .start();
+
+    let log = server.wait_for_log("\"event\":\"startup\"");
+    let summary = json_lines(&log).into_iter().find(|line| line["event"] == "startup").unwrap()["summary"].clone();
+    assert_eq!(summary["stack_mb"], json!(16));
+    assert_eq!(server.get("/nothing").status(), StatusCode::OK);
+    assert_eq!(server.get("/robots.txt").text().unwrap(), "User-agent: *");
+    assert_eq!(server.get("/version").headers()["cache-control"], "public, max-age=60");
+}
+
+/// `routes.json` with `/a` declared twice, which a JSON map can't express.
+const DUPLICATE_ROUTES: &str = r#"{
+    "routes": {
+        "GET:/a": { "type": "text", "value": "first" },
+        "GET:/a": { "type": "text", "value": "second" }
+    },
+    "__dynamic_routes": [],
+    "__config": { "threads": 1 STRICT }
+}"#;
+
+#[test]
+fn duplicate_routes_are_logged_and_the_last_wins() {
+    let server = App::new().file("routes.json", DUPLICATE_ROUTES.replace(" STRICT", "")).start();
+
+    assert!(server.log().contains("route 'GET:/a' is defined 2 times; the last definition wins"), "{}", server.log());
+    assert_eq!(server.get("/a").text().unwrap(), "second");
+}
+
+#[test]
+fn strict_routes_refuses_to_start_on_a_conflict() {
+    let output = App::new().file("routes.json", DUPLICATE_ROUTES.replace(" STRICT", r#", "strictRoutes": true"#)).exit_output();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("route 'GET:/a' is defined 2 times"), "{}", stderr);
+    assert!(stderr.contains("1 conflicting route definition(s)"), "{}", stderr);
+
+    let overlapping = App::new()
+        .config("strictRoutes", json!(true))
+        .dynamic("GET", "/users/:id", "byId", "export default function (req) { return {}; }")
+        .dynamic("GET", "/users/:name", "byName", "export default function (req) { return {}; }")
+        .exit_output();
+    assert!(!overlapping.status.success());
+    assert!(String::from_utf8_lossy(&overlapping.stderr).contains("overlap"));
+}
@@ -0,0 +1,101 @@
+//! Static directories and the SPA fallback.
+
+mod common;
+
+use common::*;
+use serde_json::json;
+
+#[test]
+fn precompressed_variant_is_served_to_clients_that_accept_it() {
+    let server = App::new()
+        .config("static_dir", json!("public"))
+        .project_file("public/app.js", "console.log('original');")
+        .project_file("public/app.js.br", b"\x0b\x02\x80brotli-bytes\x03")
+        .project_file("public/app.js.gz", b"\x1f\x8bgzip-bytes")
+        .start();
+
+    let br = client().get(server.url("/app.js")).header("Accept-Encoding", "gzip, br").send().unwrap();
+    assert_eq!(br.status(), StatusCode::OK);
+    assert_eq!(br.headers()["content-encoding"], "br");
+    assert_eq!(br.headers()["vary"], "accept-encoding");
+    assert_eq!(br.bytes().unwrap().as_ref(), b"\x0b\x02\x80brotli-bytes\x03");
+
+    let gzip = client().get(server.url("/app.js")).header("Accept-Encoding", "gzip").send().unwrap();
+    assert_eq!(gzip.headers()["content-encoding"], "gzip");
+    assert_eq!(gzip.bytes().unwrap().as_ref(), b"\x1f\x8bgzip-bytes");
+
+    let plain = client().get(server.url("/app.js")).header("Accept-Encoding", "identity").send().unwrap();
+    assert!(plain.headers().get("content-encoding").is_none());
+    assert!(plain.headers()["content-type"].to_str().unwrap().contains("javascript"));
+    assert_eq!(plain.text().unwrap(), "console.log('original');");
+}
+
+#[cfg(unix)]
+#[test]
+fn precompressed_variant_symlinked_outside_the_root_is_ignored() {
+    let server = App::new()
+        .config("static_dir", json!("public"))
+        .project_file("public/app.js", "console.log('original');")
+        .project_file("secret.txt", "do not serve")
+        .start();
+    std::os::unix::fs::symlink(server.project.join("secret.txt"), server.project.join("public/app.js.br")).unwrap();
+
+    let res = client().get(server.url("/app.js")).header("Accept-Encoding", "br").send().unwrap();
+    assert!(res.headers().get("content-encoding").is_none());
+    assert_eq!(res.text().unwrap(), "console.log('original');");
+}
+
+#[test]
+fn mounted_directory_cannot_be_escaped() {
+    let server = App::new()
+        .config("static", json!({ "/assets": "./public" }))
+        .project_file("public/robots.txt", "User-agent: *")
+        .project_file("secret.txt", "do not serve")
+        .start();
+
+    assert_eq!(server.get("/assets/robots.txt").text().unwrap(), "User-agent: *");
+    // Sent raw: HTTP clients would normalize the dot segments away
+    for target in ["/assets/../../etc/passwd", "/assets/../secret.txt", "/assets/%2e%2e/secret.txt", "/assets/..%2fsecret.txt"] {
+        let response = server.raw(&format!("GET {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n", target));
+        assert!(response.starts_with("HTTP/1.1 404"), "{} -> {}", target, response);
+        assert!(!response.contains("do not serve") && !response.contains("root:"), "{} -> {}", target, response);
+    }
+}
+
+#[test]
+fn spa_deep_links_get_the_shell_and_everything_else_is_untouched() {
+    let server = App::new()
+        .config("static_dir", json!("public"))
+        .config("spa", json!({ "prefix": "/app", "fallback": "./public/index.html" }))
+        .project_file("public/index.html", "<div id=\"root\"></div>")
+        .project_file("public/main.js", "render();")
+        .action("GET", "/app/api/users", "users", "export default function (req) { return { path: req.path }; }")
+        .start();
+
+    for deep_link in ["/app", "/app/", "/app/users/42", "/app/settings/profile"] {
+        let res = server.get(deep_link);
+        assert_eq!(res.status(), StatusCode::OK, "{}", deep_link);
+        assert_eq!(res.headers()["content-type"], "text/html; charset=utf-8");
+        assert_eq!(res.headers()["cache-control"], "no-cache");
+        assert_eq!(res.text().unwrap(), "<div id=\"root\"></div>");
+    }
+
+    assert_eq!(server.get("/app/api/users").text().unwrap(), r#"{"path":"/app/api/users"}"#);
+    assert_eq!(server.get("/main.js").text().unwrap(), "render();");
+    // Missing files, other prefixes and non-GET requests are not deep links
+    assert_eq!(server.get("/app/missing.js").status(), StatusCode::NOT_FOUND);
+    assert_eq!(server.get("/other/page").status(), StatusCode::NOT_FOUND);
+    assert_eq!(client().post(server.url("/app/users/42")).send().unwrap().status(), StatusCode::NOT_FOUND);
+}
+
+#[test]
+fn missing_spa_fallback_warns_in_text_mode() {
+    let server = App::new()
+        .config("spa", json!({ "fallback": "./public/index.html" }))
+        .action("GET", "/ok", "ok", "export default function (req) { return { ok: true }; }")
+        .start();
+
+    let log = server.wait_for_log("spa fallback");
+    assert!(log.contains("WARNING:") && log.contains("\"./public/index.html\" is missing or unreadable"), "{}", log);
+    assert_eq!(server.get("/anything").status(), StatusCode::NOT_FOUND);
+}
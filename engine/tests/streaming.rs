@@ -0,0 +1,94 @@
+//! Streamed response bodies: trailers, JSON arrays, SSE and multipart.
+
+mod common;
+
+use common::*;
+use serde_json::Value;
+
+/// Split a raw chunked response into its head and body chunks.
+fn chunks(response: &str) -> (&str, Vec<&str>) {
+    let (head, mut rest) = response.split_once("\r\n\r\n").unwrap();
+    let mut chunks = Vec::new();
+    loop {
+        let (size, after) = rest.split_once("\r\n").unwrap();
+        let size = usize::from_str_radix(size.split(';').next().unwrap(), 16).unwrap();
+        if size == 0 {
+            return (head, chunks);
+        }
+        chunks.push(&after[..size]);
+        rest = &after[size + 2..];
+    }
+}
+
+#[test]
+fn trailers_follow_a_streamed_body() {
+    let server = App::new()
+        .action("GET", "/export", "export", r#"export default function (req) {
+            t.response.trailer("Digest", "sha-256=abc");
+            return t.response.jsonArray([1, 2, 3]);
+        }"#)
+        .start();
+
+    let response = server.raw("GET /export HTTP/1.1\r\nHost: localhost\r\nTE: trailers\r\nConnection: close\r\n\r\n").to_ascii_lowercase();
+    let (head, body) = response.split_once("\r\n\r\n").unwrap();
+    assert!(head.starts_with("http/1.1 200"), "{}", response);
+    assert!(head.contains("transfer-encoding: chunked"), "{}", response);
+    assert!(head.contains("trailer: digest"), "{}", response);
+    let trailer = body.find("digest: sha-256=abc").expect("trailer sent");
+    assert!(body.find("3]").unwrap() < trailer, "trailer before the body: {}", body);
+    assert!(body.ends_with("0\r\ndigest: sha-256=abc\r\n\r\n"), "{}", body);
+}
+
+const ROWS: &str = "Array.from({ length: 1000 }, (_, i) => ({ id: i, name: \"row \" + i, path: req.path }))";
+
+#[test]
+fn json_array_streams_the_same_json_in_several_chunks() {
+    let server = App::new()
+        .action("GET", "/streamed", "streamed", &format!("export default function (req) {{ return t.response.jsonArray({}); }}", ROWS))
+        .action("GET", "/buffered", "buffered", &format!("export default function (req) {{ return t.response.json({}); }}", ROWS))
+        .start();
+
+    let response = server.raw("GET /streamed HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n");
+    let (head, body) = chunks(&response);
+    let head = head.to_ascii_lowercase();
+    assert!(head.contains("transfer-encoding: chunked") && head.contains("content-type: application/json"), "{}", head);
+    assert!(body.len() > 1, "written as one chunk");
+
+    let streamed: Value = serde_json::from_str(&body.concat()).unwrap();
+    let mut buffered: Value = server.get("/buffered").json().unwrap();
+    for row in buffered.as_array_mut().unwrap() {
+        row["path"] = "/streamed".into();
+    }
+    assert_eq!(streamed.as_array().unwrap().len(), 1000);
+    assert_eq!(streamed, buffered);
+}
+
+#[test]
+fn multipart_mixed_frames_each_part_with_a_unique_boundary() {
+    let server = App::new()
+        .action("GET", "/batch", "batch", r#"export default function (req) {
+            return t.response.multipart([
+                { headers: { "Content-Type": "application/json", "Content-ID": "<first>" }, body: { path: req.path } },
+                new Uint8Array([104, 105, 0, 255]),
+            ]);
+        }"#)
+        .start();
+
+    let boundary_of = |res: &Response| {
+        let content_type = res.headers()["content-type"].to_str().unwrap().to_string();
+        content_type.strip_prefix("multipart/mixed; boundary=").unwrap_or_else(|| panic!("{}", content_type)).to_string()
+    };
+    let res = server.get("/batch");
+    let boundary = boundary_of(&res);
+    let body = res.bytes().unwrap();
+    let mut expected = format!(
+        "--{b}\r\nContent-Type: application/json\r\nContent-ID: <first>\r\n\r\n{{\"path\":\"/batch\"}}\r\n--{b}\r\nContent-Type: application/octet-stream\r\n\r\n",
+        b = boundary
+    )
+    .into_bytes();
+    expected.extend_from_slice(b"hi\x00\xff");
+    expected.extend_from_slice(format!("\r\n--{}--\r\n", boundary).as_bytes());
+    assert_eq!(body.as_ref(), expected.as_slice(), "{}", String::from_utf8_lossy(&body));
+
+    assert_ne!(boundary_of(&server.get("/batch")), boundary);
+}
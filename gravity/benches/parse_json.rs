@@ -0,0 +1,53 @@
+//! serde_json vs simd-json on request bodies of growing size, the
+//! comparison behind `SIMD_JSON_MIN_BYTES`:
+//!
+//! ```sh
+//! cargo bench --features simd-json --bench parse_json
+//! ```
+//!
+//! The simd-json timings include the copy into a mutable buffer that
+//! `parse_json` makes, so the crossover is where the threshold belongs.
+
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use serde_json::Value;
+use std::hint::black_box;
+
+/// An array of records like a typical JSON API body, at least `min_bytes` long.
+fn document(min_bytes: usize) -> Vec<u8> {
+    let mut doc = String::from("[");
+    let mut i = 0u64;
+    while doc.len() < min_bytes {
+        if i > 0 {
+            doc.push(',');
+        }
+        doc.push_str(&format!(
+            r#"{{"id":{i},"name":"user-{i}","email":"user{i}@example.com","score":{},"tags":["a","b","c"],"active":{}}}"#,
+            i as f64 * 1.37,
+            i % 2 == 0
+        ));
+        i += 1;
+    }
+    doc.push(']');
+    doc.into_bytes()
+}
+
+fn parse_json(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parse_json");
+    for size in [512, 2 * 1024, 4 * 1024, 8 * 1024, 16 * 1024, 64 * 1024, 256 * 1024] {
+        let doc = document(size);
+        group.throughput(Throughput::Bytes(doc.len() as u64));
+        group.bench_with_input(BenchmarkId::new("serde_json", size), &doc, |b, doc| {
+            b.iter(|| serde_json::from_slice::<Value>(black_box(doc)).unwrap())
+        });
+        group.bench_with_input(BenchmarkId::new("simd_json", size), &doc, |b, doc| {
+            b.iter(|| {
+                let mut owned = black_box(doc).to_vec();
+                simd_json::serde::from_slice::<Value>(&mut owned).unwrap()
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, parse_json);
+criterion_main!(benches);
@@ -0,0 +1,42 @@
+//! How an action's plain-object result becomes the response body: the old
+//! `Value` round trip (`try_v8_to_json`, then the handler's `Json`
+//! serialization) against passing the `JSON.stringify` text on as
+//! `_rawJson` (`v8_to_json_text`):
+//!
+//! ```sh
+//! cargo bench --bench result_json
+//! ```
+
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+use gravity::extensions::{init_v8, try_v8_to_json, v8_to_json_text};
+use std::hint::black_box;
+
+fn result_json(c: &mut Criterion) {
+    init_v8();
+    let isolate = &mut v8::Isolate::new(v8::CreateParams::default());
+    let handle_scope = &mut v8::HandleScope::new(isolate);
+    let context = v8::Context::new(handle_scope, v8::ContextOptions::default());
+    let scope = &mut v8::ContextScope::new(handle_scope, context);
+
+    let mut group = c.benchmark_group("result_json");
+    for keys in [10, 100, 1000] {
+        // A flat object of mixed scalar values, like a wide API record
+        let source = format!(
+            "(() => {{ const o = {{}}; for (let i = 0; i < {keys}; i++) o['key' + i] = i % 3 === 0 ? 'value-' + i : i % 3 === 1 ? i * 1.5 : i % 2 === 0; return o; }})()"
+        );
+        let code = v8::String::new(scope, &source).unwrap();
+        let script = v8::Script::compile(scope, code, None).unwrap();
+        let value = script.run(scope).unwrap();
+
+        group.bench_with_input(BenchmarkId::new("value_tree", keys), &value, |b, value| {
+            b.iter(|| serde_json::to_vec(&try_v8_to_json(scope, black_box(*value)).unwrap()).unwrap())
+        });
+        group.bench_with_input(BenchmarkId::new("json_text", keys), &value, |b, value| {
+            b.iter(|| v8_to_json_text(scope, black_box(*value)).unwrap().unwrap())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, result_json);
+criterion_main!(benches);
@@ -0,0 +1,74 @@
+use v8;
+use base64::{engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD}, Engine as _};
+use hmac::{Hmac, Mac};
+use sha2::{Sha256, Sha384, Sha512};
+use crate::extensions::{v8_str, v8_to_bytes, v8_to_string, throw};
+
+fn mac<M: Mac + hmac::digest::KeyInit>(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut m = <M as Mac>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    m.update(data);
+    m.finalize().into_bytes().to_vec()
+}
+
+/// `"sha256"`, `"SHA-256"` and `"hmac-sha256"` all name the same algorithm.
+fn hmac_digest(algorithm: &str, key: &[u8], data: &[u8]) -> Result<Vec<u8>, String> {
+    let name = algorithm.to_lowercase().replace('-', "");
+    match name.strip_prefix("hmac").unwrap_or(&name) {
+        "sha256" => Ok(mac::<Hmac<Sha256>>(key, data)),
+        "sha384" => Ok(mac::<Hmac<Sha384>>(key, data)),
+        "sha512" => Ok(mac::<Hmac<Sha512>>(key, data)),
+        _ => Err(format!("Unsupported HMAC algorithm '{}' (use sha256, sha384 or sha512)", algorithm)),
+    }
+}
+
+fn encode(bytes: &[u8], encoding: &str) -> Result<String, String> {
+    match encoding {
+        "hex" => Ok(bytes.iter().map(|b| format!("{:02x}", b)).collect()),
+        "base64" => Ok(STANDARD.encode(bytes)),
+        "base64url" => Ok(URL_SAFE_NO_PAD.encode(bytes)),
+        _ => Err(format!("Unsupported encoding '{}' (use hex, base64 or base64url)", encoding)),
+    }
+}
+
+/// `t.crypto.hmac(algorithm, key, data, encoding = "hex")`
+pub fn native_crypto_hmac(scope: &mut v8::HandleScope, args: v8::FunctionCallbackArguments, mut retval: v8::ReturnValue) {
+    let algorithm = v8_to_string(scope, args.get(0));
+    let Some(key) = v8_to_bytes(scope, args.get(1)) else {
+        throw(scope, "t.crypto.hmac(): key must be a string, ArrayBuffer or Uint8Array");
+        return;
+    };
+    let Some(data) = v8_to_bytes(scope, args.get(2)) else {
+        throw(scope, "t.crypto.hmac(): data must be a string, ArrayBuffer or Uint8Array");
+        return;
+    };
+    let enc_val = args.get(3);
+    let encoding = if enc_val.is_string() { v8_to_string(scope, enc_val) } else { "hex".to_string() };
+
+    match hmac_digest(&algorithm, &key, &data).and_then(|digest| encode(&digest, &encoding)) {
+        Ok(out) => retval.set(v8_str(scope, &out).into()),
+        Err(e) => throw(scope, &format!("t.crypto.hmac(): {}", e)),
+    }
+}
+
+/// `t.crypto.timingSafeEqual(a, b)` — compares every byte regardless of where
+/// the first difference is. Only the length can be told apart by timing.
+pub fn native_crypto_timing_safe_equal(scope: &mut v8::HandleScope, args: v8::FunctionCallbackArguments, mut retval: v8::ReturnValue) {
+    let (Some(a), Some(b)) = (v8_to_bytes(scope, args.get(0)), v8_to_bytes(scope, args.get(1))) else {
+        throw(scope, "t.crypto.timingSafeEqual(): arguments must be strings, ArrayBuffers or Uint8Arrays");
+        return;
+    };
+    let diff = a.iter().zip(b.iter()).fold(0u8, |acc, (x, y)| acc | (x ^ y));
+    retval.set_bool(a.len() == b.len() && std::hint::black_box(diff) == 0);
+}
+
+/// `t.uuid.v4()` — random.
+pub fn native_uuid_v4(scope: &mut v8::HandleScope, _args: v8::FunctionCallbackArguments, mut retval: v8::ReturnValue) {
+    retval.set(v8_str(scope, &uuid::Uuid::new_v4().to_string()).into());
+}
+
+/// `t.uuid.v7()` — millisecond timestamp first, so IDs sort by creation time.
+/// The uuid crate keeps a counter so IDs from the same millisecond still
+/// increase within the process.
+pub fn native_uuid_v7(scope: &mut v8::HandleScope, _args: v8::FunctionCallbackArguments, mut retval: v8::ReturnValue) {
+    retval.set(v8_str(scope, &uuid::Uuid::now_v7().to_string()).into());
+}
@@ -1,11 +1,28 @@
 use v8;
 use std::sync::OnceLock;
+use dashmap::DashMap;
 use deadpool_postgres::{Manager, Pool};
 use tokio_postgres::{NoTls, Config};
+use sha2::{Digest, Sha256};
 use crate::extensions::{v8_str, v8_to_string, throw};
 
-// Database connection pool
-pub static DB_POOL: OnceLock<Pool> = OnceLock::new();
+// Database connection pools, keyed by a hash of the connection string so
+// repeated t.db.connect(url) calls reuse the same pool. The URL itself (and
+// its password) stays inside the pool; JS only ever sees the key.
+pub static DB_POOLS: OnceLock<DashMap<String, Pool>> = OnceLock::new();
+
+pub fn db_pools() -> &'static DashMap<String, Pool> {
+    DB_POOLS.get_or_init(DashMap::new)
+}
+
+/// Property on the connection handle carrying its pool key.
+const CONN_KEY: &str = "__titan_conn";
+
+/// The pool key for a connection string.
+fn pool_key(conn_string: &str) -> String {
+    let digest = Sha256::digest(conn_string.as_bytes());
+    digest[..16].iter().map(|b| format!("{:02x}", b)).collect()
+}
 
 pub fn native_db_connect(scope: &mut v8::HandleScope, args: v8::FunctionCallbackArguments, mut retval: v8::ReturnValue) {
     let conn_string = v8_to_string(scope, args.get(0));
@@ -38,18 +55,13 @@ pub fn native_db_connect(scope: &mut v8::HandleScope, args: v8::FunctionCallback
         }
     }
 
-    if DB_POOL.get().is_none() {
-        let cfg: Config = match conn_string.parse() {
-            Ok(c) => c,
-            Err(e) => {
-                println!("{} {} parse error: {}", crate::utils::blue("[Titan DB]"), crate::utils::red("DB:"), e);
-                let msg = format!("t.db.connect(): Invalid connection string: {}", e);
-                let message = v8::String::new(scope, &msg).unwrap_or_else(|| v8::String::new(scope, "Error").unwrap());
-                let exception = v8::Exception::error(scope, message);
-                scope.throw_exception(exception);
-                return;
-            }
-        };
+    // One build per key even when several workers connect at once
+    let key = pool_key(&conn_string);
+    let created = db_pools().entry(key.clone()).or_try_insert_with(|| {
+        let cfg: Config = conn_string.parse().map_err(|e: tokio_postgres::Error| {
+            println!("{} {} parse error: {}", crate::utils::blue("[Titan DB]"), crate::utils::red("DB:"), e);
+            format!("t.db.connect(): Invalid connection string: {}", e)
+        })?;
 
         let pool = if use_ssl {
             let connector = native_tls::TlsConnector::builder()
@@ -69,12 +81,19 @@ pub fn native_db_connect(scope: &mut v8::HandleScope, args: v8::FunctionCallback
                 .build()
                 .unwrap()
         };
-    
-        DB_POOL.set(pool).ok();
+        Ok::<_, String>(pool)
+    }).map(drop);
+    if let Err(msg) = created {
+        throw(scope, &msg);
+        return;
     }
 
     let db_conn_obj = v8::Object::new(scope);
 
+    let conn_key = v8_str(scope, CONN_KEY);
+    let conn_val = v8_str(scope, &key);
+    db_conn_obj.set(scope, conn_key.into(), conn_val.into());
+
     let query_fn = v8::Function::new(scope, native_db_query).unwrap();
     let query_key = v8_str(scope, "query");
     db_conn_obj.set(scope, query_key.into(), query_fn.into());
@@ -89,6 +108,15 @@ pub fn native_db_query(
 ) {
     let sql = v8_to_string(scope, args.get(0));
 
+    // Pool key from the connection handle this query was called on
+    let this_key = v8_str(scope, CONN_KEY);
+    let conn = args
+        .this()
+        .get(scope, this_key.into())
+        .filter(|v| v.is_string())
+        .map(|v| v8_to_string(scope, v))
+        .unwrap_or_else(|| "default".to_string());
+
     // Collect params
     let mut params = Vec::new();
     if args.length() > 1 && args.get(1).is_array() {
@@ -115,7 +143,7 @@ pub fn native_db_query(
     let data_obj = v8::Object::new(scope);
 
     let conn_key = v8_str(scope, "conn");
-    let conn_val = v8_str(scope, "default");
+    let conn_val = v8_str(scope, &conn);
     data_obj.set(scope, conn_key.into(), conn_val.into());
 
     let query_key = v8_str(scope, "query");
@@ -0,0 +1,99 @@
+use v8;
+use base64::{engine::general_purpose::{STANDARD, STANDARD_NO_PAD, URL_SAFE, URL_SAFE_NO_PAD}, Engine as _};
+use crate::extensions::{v8_str, v8_to_bytes, v8_to_string, bytes_to_v8, throw};
+
+/// `{ url: true }` selects the URL-safe alphabet (`-` / `_`, no padding).
+fn url_safe(scope: &mut v8::HandleScope, opts: v8::Local<v8::Value>) -> bool {
+    if !opts.is_object() {
+        return false;
+    }
+    let obj = opts.to_object(scope).unwrap();
+    let url_key = v8_str(scope, "url");
+    obj.get(scope, url_key.into()).is_some_and(|v| v.boolean_value(scope))
+}
+
+/// Decoded bytes as a `Uint8Array`, or a string when `as` is `"utf8"` / `"string"`.
+fn decoded(scope: &mut v8::HandleScope, bytes: Vec<u8>, as_val: v8::Local<v8::Value>, what: &str, mut retval: v8::ReturnValue) {
+    if !as_val.is_string() {
+        retval.set(bytes_to_v8(scope, bytes));
+        return;
+    }
+    match v8_to_string(scope, as_val).as_str() {
+        "utf8" | "utf-8" | "string" => match String::from_utf8(bytes) {
+            Ok(s) => retval.set(v8_str(scope, &s).into()),
+            Err(_) => throw(scope, &format!("{}: decoded bytes are not valid UTF-8", what)),
+        },
+        "bytes" => retval.set(bytes_to_v8(scope, bytes)),
+        other => throw(scope, &format!("{}: unknown output '{}' (use \"bytes\" or \"utf8\")", what, other)),
+    }
+}
+
+/// `t.encode.base64(input, { url? })`
+pub fn native_base64(scope: &mut v8::HandleScope, args: v8::FunctionCallbackArguments, mut retval: v8::ReturnValue) {
+    let Some(bytes) = v8_to_bytes(scope, args.get(0)) else {
+        throw(scope, "t.encode.base64(): input must be a string, ArrayBuffer or Uint8Array");
+        return;
+    };
+    let out = if url_safe(scope, args.get(1)) { URL_SAFE_NO_PAD.encode(bytes) } else { STANDARD.encode(bytes) };
+    retval.set(v8_str(scope, &out).into());
+}
+
+/// `t.encode.base64Decode(str, as?)` — standard or URL-safe alphabet, padded or not.
+pub fn native_base64_decode(scope: &mut v8::HandleScope, args: v8::FunctionCallbackArguments, retval: v8::ReturnValue) {
+    let input = args.get(0);
+    if !input.is_string() {
+        throw(scope, "t.encode.base64Decode(): input must be a string");
+        return;
+    }
+    let text = v8_to_string(scope, input);
+    let text = text.trim();
+    let url = text.contains(['-', '_']);
+    let padded = text.ends_with('=');
+    let result = match (url, padded) {
+        (false, true) => STANDARD.decode(text),
+        (false, false) => STANDARD_NO_PAD.decode(text),
+        (true, true) => URL_SAFE.decode(text),
+        (true, false) => URL_SAFE_NO_PAD.decode(text),
+    };
+    match result {
+        Ok(bytes) => decoded(scope, bytes, args.get(1), "t.encode.base64Decode()", retval),
+        Err(e) => throw(scope, &format!("t.encode.base64Decode(): invalid base64: {}", e)),
+    }
+}
+
+/// `t.encode.hex(input)` — lowercase.
+pub fn native_hex(scope: &mut v8::HandleScope, args: v8::FunctionCallbackArguments, mut retval: v8::ReturnValue) {
+    let Some(bytes) = v8_to_bytes(scope, args.get(0)) else {
+        throw(scope, "t.encode.hex(): input must be a string, ArrayBuffer or Uint8Array");
+        return;
+    };
+    let out: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    retval.set(v8_str(scope, &out).into());
+}
+
+/// `t.encode.hexDecode(str, as?)` — either case.
+pub fn native_hex_decode(scope: &mut v8::HandleScope, args: v8::FunctionCallbackArguments, retval: v8::ReturnValue) {
+    let input = args.get(0);
+    if !input.is_string() {
+        throw(scope, "t.encode.hexDecode(): input must be a string");
+        return;
+    }
+    let text = v8_to_string(scope, input);
+    let text = text.trim();
+    if text.len() % 2 != 0 {
+        throw(scope, "t.encode.hexDecode(): invalid hex: odd number of digits");
+        return;
+    }
+    let mut bytes = Vec::with_capacity(text.len() / 2);
+    for (i, pair) in text.as_bytes().chunks(2).enumerate() {
+        let byte = std::str::from_utf8(pair).ok().and_then(|p| u8::from_str_radix(p, 16).ok());
+        match byte {
+            Some(b) => bytes.push(b),
+            None => {
+                throw(scope, &format!("t.encode.hexDecode(): invalid hex digit at position {}", i * 2));
+                return;
+            }
+        }
+    }
+    decoded(scope, bytes, args.get(1), "t.encode.hexDecode()", retval);
+}
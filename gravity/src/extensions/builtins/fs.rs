@@ -1,6 +1,29 @@
 use v8;
 use crate::extensions::{v8_str, v8_to_string, throw, PROJECT_ROOT};
 
+/// `{ encoding: "binary" }` in a read options argument.
+fn wants_binary(scope: &mut v8::HandleScope, opts: v8::Local<v8::Value>) -> bool {
+    if !opts.is_object() {
+        return false;
+    }
+    let obj = opts.to_object(scope).unwrap();
+    let enc_key = v8_str(scope, "encoding");
+    obj.get(scope, enc_key.into())
+        .filter(|v| v.is_string())
+        .map(|v| v8_to_string(scope, v) == "binary")
+        .unwrap_or(false)
+}
+
+/// `path` resolved against the project root, or `None` when it does not
+/// exist or lands outside the root (through `..`, an absolute path or a
+/// symlink). The boundary every `t.read` variant and `t.response.file` share.
+pub fn resolve_in_project(path: &str) -> Option<std::path::PathBuf> {
+    let root = PROJECT_ROOT.get().cloned().unwrap_or_else(|| std::env::current_dir().unwrap_or_default());
+    let root = root.canonicalize().unwrap_or(root);
+    let target = root.join(path).canonicalize().ok()?;
+    target.starts_with(&root).then_some(target)
+}
+
 pub fn native_read_sync(scope: &mut v8::HandleScope, args: v8::FunctionCallbackArguments, mut retval: v8::ReturnValue) {
     let path_val = args.get(0);
     if !path_val.is_string() {
@@ -8,27 +31,22 @@ pub fn native_read_sync(scope: &mut v8::HandleScope, args: v8::FunctionCallbackA
         return;
     }
     let path_str = v8_to_string(scope, path_val);
+    let binary = wants_binary(scope, args.get(1));
 
-    let root = PROJECT_ROOT.get().cloned().unwrap_or_else(|| std::env::current_dir().unwrap_or_default());
-    let joined = root.join(&path_str);
-    
-    if let Ok(target) = joined.canonicalize() {
-        if target.starts_with(&root.canonicalize().unwrap_or(root.clone())) {
-            match std::fs::read(&target) {
-                Ok(bytes) => {
-                    let content = String::from_utf8_lossy(&bytes);
-                    let v8_content = v8_str(scope, &content);
-                    retval.set(v8_content.into());
-                },
-                Err(_) => {
-                     retval.set(v8::null(scope).into());
-                }
-            }
-        } else {
-             retval.set(v8::null(scope).into());
-        }
-    } else {
-        retval.set(v8::null(scope).into());
+    match resolve_in_project(&path_str).map(std::fs::read) {
+        Some(Ok(bytes)) if binary => {
+            let len = bytes.len();
+            let backing = v8::ArrayBuffer::new_backing_store_from_vec(bytes).make_shared();
+            let ab = v8::ArrayBuffer::with_backing_store(scope, &backing);
+            let arr = v8::Uint8Array::new(scope, ab, 0, len).unwrap();
+            retval.set(arr.into());
+        },
+        Some(Ok(bytes)) => {
+            let content = String::from_utf8_lossy(&bytes);
+            let v8_content = v8_str(scope, &content);
+            retval.set(v8_content.into());
+        },
+        _ => retval.set(v8::null(scope).into()),
     }
 }
 
@@ -39,6 +57,7 @@ pub fn native_read(scope: &mut v8::HandleScope, args: v8::FunctionCallbackArgume
         return;
     }
     let path_str = v8_to_string(scope, path_val);
+    let binary = wants_binary(scope, args.get(1));
 
     let obj = v8::Object::new(scope);
     let op_key = v8_str(scope, "__titanAsync");
@@ -53,6 +72,11 @@ pub fn native_read(scope: &mut v8::HandleScope, args: v8::FunctionCallbackArgume
     let path_k = v8_str(scope, "path");
     let path_v = v8_str(scope, &path_str);
     data_obj.set(scope, path_k.into(), path_v.into());
+    if binary {
+        let enc_k = v8_str(scope, "encoding");
+        let enc_v = v8_str(scope, "binary");
+        data_obj.set(scope, enc_k.into(), enc_v.into());
+    }
     
     let data_key = v8_str(scope, "data");
     obj.set(scope, data_key.into(), data_obj.into());
@@ -60,6 +84,69 @@ pub fn native_read(scope: &mut v8::HandleScope, args: v8::FunctionCallbackArgume
     retval.set(obj.into());
 }
 
+/// `t.write(relPath, contents)` — string or `Uint8Array`, returns bytes written.
+///
+/// The target may not exist yet, so the path is checked lexically (no absolute
+/// paths, no `..`), then the deepest existing ancestor is canonicalized and must
+/// sit inside the project root before any directory is created (catches
+/// symlinked directories). A target that is itself a symlink is refused.
+pub fn native_write(scope: &mut v8::HandleScope, args: v8::FunctionCallbackArguments, mut retval: v8::ReturnValue) {
+    let path_val = args.get(0);
+    if !path_val.is_string() {
+        throw(scope, "t.write(path, contents): path is required");
+        return;
+    }
+    let path_str = v8_to_string(scope, path_val);
+
+    let contents = args.get(1);
+    let bytes: Vec<u8> = if contents.is_string() {
+        v8_to_string(scope, contents).into_bytes()
+    } else if let Ok(view) = v8::Local::<v8::ArrayBufferView>::try_from(contents) {
+        let mut buf = vec![0u8; view.byte_length()];
+        view.copy_contents(&mut buf);
+        buf
+    } else {
+        throw(scope, "t.write(path, contents): contents must be a string or Uint8Array");
+        return;
+    };
+
+    let rel = std::path::Path::new(&path_str);
+    let escapes = rel.components().any(|c| !matches!(c, std::path::Component::Normal(_) | std::path::Component::CurDir));
+    if path_str.is_empty() || escapes {
+        throw(scope, &format!("t.write(): '{}' must be a relative path inside the project root", path_str));
+        return;
+    }
+
+    let root = PROJECT_ROOT.get().cloned().unwrap_or_else(|| std::env::current_dir().unwrap_or_default());
+    let root_canon = root.canonicalize().unwrap_or(root.clone());
+    let target = root.join(rel);
+
+    let parent = target.parent().map(|p| p.to_path_buf()).unwrap_or(root.clone());
+    let inside = |dir: &std::path::Path| dir.canonicalize().map(|p| p.starts_with(&root_canon)).unwrap_or(false);
+    let existing = parent.ancestors().find(|a| a.exists()).unwrap_or(&root);
+    if !inside(existing) {
+        throw(scope, &format!("t.write(): '{}' escapes the project root", path_str));
+        return;
+    }
+    if let Err(e) = std::fs::create_dir_all(&parent) {
+        throw(scope, &format!("t.write(): {}", e));
+        return;
+    }
+    if !inside(&parent) {
+        throw(scope, &format!("t.write(): '{}' escapes the project root", path_str));
+        return;
+    }
+    if std::fs::symlink_metadata(&target).map(|m| m.file_type().is_symlink()).unwrap_or(false) {
+        throw(scope, &format!("t.write(): '{}' is a symlink", path_str));
+        return;
+    }
+
+    match std::fs::write(&target, &bytes) {
+        Ok(()) => retval.set(v8::Number::new(scope, bytes.len() as f64).into()),
+        Err(e) => throw(scope, &format!("t.write(): {}", e)),
+    }
+}
+
 pub fn native_decode_utf8(scope: &mut v8::HandleScope, args: v8::FunctionCallbackArguments, mut retval: v8::ReturnValue) {
     let val = args.get(0);
     if let Ok(u8arr) = v8::Local::<v8::Uint8Array>::try_from(val) {
@@ -1,10 +1,48 @@
 use v8;
-use jsonwebtoken::{encode, decode, Header, EncodingKey, DecodingKey, Validation};
+use jsonwebtoken::{encode, decode, Algorithm, Header, EncodingKey, DecodingKey, Validation};
 use serde_json::Value;
+use std::str::FromStr;
 use std::time::{SystemTime, UNIX_EPOCH};
 use crate::extensions::{v8_str, v8_to_string, throw};
 use crate::utils::parse_expires_in;
 
+/// Case-insensitive; `EdDSA` is the one mixed-case name, so it can't go through `to_uppercase`.
+fn parse_algorithm(name: &str) -> Result<Algorithm, String> {
+    if name.eq_ignore_ascii_case("eddsa") {
+        return Ok(Algorithm::EdDSA);
+    }
+    Algorithm::from_str(&name.to_uppercase()).map_err(|_| format!("Unsupported JWT algorithm '{}'", name))
+}
+
+/// The kind of key an algorithm verifies with; one key can't serve two kinds.
+fn key_family(alg: Algorithm) -> &'static str {
+    match alg {
+        Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512 => "HMAC",
+        Algorithm::ES256 | Algorithm::ES384 => "EC",
+        Algorithm::EdDSA => "EdDSA",
+        _ => "RSA",
+    }
+}
+
+/// HS* algorithms take the key as a shared secret, RS*/PS* an RSA PEM and ES* an EC PEM.
+fn encoding_key(alg: Algorithm, key: &str) -> Result<EncodingKey, String> {
+    match alg {
+        Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512 => Ok(EncodingKey::from_secret(key.as_bytes())),
+        Algorithm::ES256 | Algorithm::ES384 => EncodingKey::from_ec_pem(key.as_bytes()).map_err(|e| e.to_string()),
+        Algorithm::EdDSA => EncodingKey::from_ed_pem(key.as_bytes()).map_err(|e| e.to_string()),
+        _ => EncodingKey::from_rsa_pem(key.as_bytes()).map_err(|e| e.to_string()),
+    }
+}
+
+fn decoding_key(alg: Algorithm, key: &str) -> Result<DecodingKey, String> {
+    match alg {
+        Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512 => Ok(DecodingKey::from_secret(key.as_bytes())),
+        Algorithm::ES256 | Algorithm::ES384 => DecodingKey::from_ec_pem(key.as_bytes()).map_err(|e| e.to_string()),
+        Algorithm::EdDSA => DecodingKey::from_ed_pem(key.as_bytes()).map_err(|e| e.to_string()),
+        _ => DecodingKey::from_rsa_pem(key.as_bytes()).map_err(|e| e.to_string()),
+    }
+}
+
 pub fn native_jwt_sign(scope: &mut v8::HandleScope, args: v8::FunctionCallbackArguments, mut retval: v8::ReturnValue) {
     let payload_val = args.get(0);
     let json_str = v8::json::stringify(scope, payload_val)
@@ -13,9 +51,19 @@ pub fn native_jwt_sign(scope: &mut v8::HandleScope, args: v8::FunctionCallbackAr
     let mut payload: serde_json::Map<String, Value> = serde_json::from_str(&json_str).unwrap_or_default();
     let secret = v8_to_string(scope, args.get(1));
     
+    let mut algorithm = Algorithm::HS256;
     let opts_val = args.get(2);
     if opts_val.is_object() {
         let opts_obj = opts_val.to_object(scope).unwrap();
+        let alg_key = v8_str(scope, "algorithm");
+        if let Some(val) = opts_obj.get(scope, alg_key.into()) {
+            if val.is_string() {
+                match parse_algorithm(&v8_to_string(scope, val)) {
+                    Ok(a) => algorithm = a,
+                    Err(e) => { throw(scope, &e); return; }
+                }
+            }
+        }
         let exp_key = v8_str(scope, "expiresIn");
         if let Some(val) = opts_obj.get(scope, exp_key.into()) {
              let seconds = if val.is_number() {
@@ -30,7 +78,11 @@ pub fn native_jwt_sign(scope: &mut v8::HandleScope, args: v8::FunctionCallbackAr
         }
     }
 
-    let token = encode(&Header::default(), &Value::Object(payload), &EncodingKey::from_secret(secret.as_bytes()));
+    let key = match encoding_key(algorithm, &secret) {
+        Ok(k) => k,
+        Err(e) => { throw(scope, &format!("Invalid JWT signing key: {}", e)); return; }
+    };
+    let token = encode(&Header::new(algorithm), &Value::Object(payload), &key);
     match token {
         Ok(t) => {
             let res = v8_str(scope, &t);
@@ -43,9 +95,45 @@ pub fn native_jwt_sign(scope: &mut v8::HandleScope, args: v8::FunctionCallbackAr
 pub fn native_jwt_verify(scope: &mut v8::HandleScope, args: v8::FunctionCallbackArguments, mut retval: v8::ReturnValue) {
     let token = v8_to_string(scope, args.get(0));
     let secret = v8_to_string(scope, args.get(1));
-    let mut validation = Validation::default();
+
+    // Only the listed algorithms are accepted, so an RS256 public key can never
+    // be replayed as an HS256 secret (alg confusion).
+    let mut algorithms = vec![Algorithm::HS256];
+    let opts_val = args.get(2);
+    if opts_val.is_object() {
+        let opts_obj = opts_val.to_object(scope).unwrap();
+        let algs_key = v8_str(scope, "algorithms");
+        if let Some(val) = opts_obj.get(scope, algs_key.into()) {
+            if val.is_array() {
+                let arr = v8::Local::<v8::Array>::try_from(val).unwrap();
+                let mut parsed = Vec::with_capacity(arr.length() as usize);
+                for i in 0..arr.length() {
+                    let item = arr.get_index(scope, i).unwrap();
+                    match parse_algorithm(&v8_to_string(scope, item)) {
+                        Ok(a) => parsed.push(a),
+                        Err(e) => { throw(scope, &e); return; }
+                    }
+                }
+                if !parsed.is_empty() {
+                    algorithms = parsed;
+                }
+            }
+        }
+    }
+
+    if algorithms.iter().any(|a| key_family(*a) != key_family(algorithms[0])) {
+        throw(scope, "t.jwt.verify(): algorithms must all use the same kind of key (HMAC, RSA, EC or EdDSA)");
+        return;
+    }
+
+    let key = match decoding_key(algorithms[0], &secret) {
+        Ok(k) => k,
+        Err(e) => { throw(scope, &format!("Invalid JWT verification key: {}", e)); return; }
+    };
+    let mut validation = Validation::new(algorithms[0]);
+    validation.algorithms = algorithms;
     validation.validate_exp = false;
-    let data = decode::<Value>(&token, &DecodingKey::from_secret(secret.as_bytes()), &validation);
+    let data = decode::<Value>(&token, &key, &validation);
     match data {
         Ok(d) => {
              let json_str = serde_json::to_string(&d.claims).unwrap();
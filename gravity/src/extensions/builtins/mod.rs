@@ -1,3 +1,5 @@
+pub mod crypto;
+pub mod encode;
 pub mod fs;
 pub mod jwt;
 pub mod password;
@@ -23,6 +25,11 @@ pub fn inject_builtin_extensions(scope: &mut v8::HandleScope, global: v8::Local<
     let read_key = v8_str(scope, "read");
     t_obj.set(scope, read_key.into(), read_fn.into());
 
+    // t.write
+    let write_fn = v8::Function::new(scope, fs::native_write).unwrap();
+    let write_key = v8_str(scope, "write");
+    t_obj.set(scope, write_key.into(), write_fn.into());
+
     // t.decodeUtf8
     let dec_fn = v8::Function::new(scope, fs::native_decode_utf8).unwrap();
     let dec_key = v8_str(scope, "decodeUtf8");
@@ -33,6 +40,19 @@ pub fn inject_builtin_extensions(scope: &mut v8::HandleScope, global: v8::Local<
     let log_key = v8_str(scope, "log");
     t_obj.set(scope, log_key.into(), log_fn.into());
     
+    // t.isReplaying
+    let replaying_fn = v8::Function::new(scope, system::native_is_replaying).unwrap();
+    let replaying_key = v8_str(scope, "isReplaying");
+    t_obj.set(scope, replaying_key.into(), replaying_fn.into());
+
+    // t.system.stats
+    let system_obj = v8::Object::new(scope);
+    let stats_fn = v8::Function::new(scope, system::native_system_stats).unwrap();
+    let stats_key = v8_str(scope, "stats");
+    system_obj.set(scope, stats_key.into(), stats_fn.into());
+    let system_key = v8_str(scope, "system");
+    t_obj.set(scope, system_key.into(), system_obj.into());
+
     // t.fetch (Metadata version for drift)
     let fetch_fn = v8::Function::new(scope, system::native_fetch_meta).unwrap();
     let fetch_key = v8_str(scope, "fetch");
@@ -48,6 +68,27 @@ pub fn inject_builtin_extensions(scope: &mut v8::HandleScope, global: v8::Local<
     let finish_key = v8_str(scope, "_finish_request");
     t_obj.set(scope, finish_key.into(), finish_fn.into());
 
+    // t._stream_open / t._stream_write (t.response.jsonArray, sse, multipart)
+    let stream_open_fn = v8::Function::new(scope, system::native_stream_open).unwrap();
+    let stream_open_key = v8_str(scope, "_stream_open");
+    t_obj.set(scope, stream_open_key.into(), stream_open_fn.into());
+
+    let stream_write_fn = v8::Function::new(scope, system::native_stream_write).unwrap();
+    let stream_write_key = v8_str(scope, "_stream_write");
+    t_obj.set(scope, stream_write_key.into(), stream_write_fn.into());
+
+    // t._early_hints (t.response.earlyHints)
+    let hints_fn = v8::Function::new(scope, system::native_early_hints).unwrap();
+    let hints_key = v8_str(scope, "_early_hints");
+    t_obj.set(scope, hints_key.into(), hints_fn.into());
+
+    // t._debug_panic, only for the onPanic tests
+    if std::env::var_os("TITAN_DEBUG_PANIC").is_some() {
+        let panic_fn = v8::Function::new(scope, system::native_debug_panic).unwrap();
+        let panic_key = v8_str(scope, "_debug_panic");
+        t_obj.set(scope, panic_key.into(), panic_fn.into());
+    }
+
     // t.serialize / t.deserialize
     let ser_fn = v8::Function::new(scope, system::native_serialize).unwrap();
     let ser_key = v8_str(scope, "serialize");
@@ -96,6 +137,51 @@ fn setup_native_utils(scope: &mut v8::HandleScope, t_obj: v8::Local<v8::Object>)
     let jwt_key = v8_str(scope, "jwt");
     t_obj.set(scope, jwt_key.into(), jwt_obj.into());
 
+    // t.crypto
+    let crypto_obj = v8::Object::new(scope);
+    let hmac_fn = v8::Function::new(scope, crypto::native_crypto_hmac).unwrap();
+    let tse_fn = v8::Function::new(scope, crypto::native_crypto_timing_safe_equal).unwrap();
+
+    let hmac_key = v8_str(scope, "hmac");
+    crypto_obj.set(scope, hmac_key.into(), hmac_fn.into());
+    let tse_key = v8_str(scope, "timingSafeEqual");
+    crypto_obj.set(scope, tse_key.into(), tse_fn.into());
+
+    let crypto_key = v8_str(scope, "crypto");
+    t_obj.set(scope, crypto_key.into(), crypto_obj.into());
+
+    // t.uuid
+    let uuid_obj = v8::Object::new(scope);
+    let v4_fn = v8::Function::new(scope, crypto::native_uuid_v4).unwrap();
+    let v7_fn = v8::Function::new(scope, crypto::native_uuid_v7).unwrap();
+
+    let v4_key = v8_str(scope, "v4");
+    uuid_obj.set(scope, v4_key.into(), v4_fn.into());
+    let v7_key = v8_str(scope, "v7");
+    uuid_obj.set(scope, v7_key.into(), v7_fn.into());
+
+    let uuid_key = v8_str(scope, "uuid");
+    t_obj.set(scope, uuid_key.into(), uuid_obj.into());
+
+    // t.encode
+    let encode_obj = v8::Object::new(scope);
+    let b64_fn = v8::Function::new(scope, encode::native_base64).unwrap();
+    let b64_dec_fn = v8::Function::new(scope, encode::native_base64_decode).unwrap();
+    let hex_fn = v8::Function::new(scope, encode::native_hex).unwrap();
+    let hex_dec_fn = v8::Function::new(scope, encode::native_hex_decode).unwrap();
+
+    let b64_key = v8_str(scope, "base64");
+    encode_obj.set(scope, b64_key.into(), b64_fn.into());
+    let b64_dec_key = v8_str(scope, "base64Decode");
+    encode_obj.set(scope, b64_dec_key.into(), b64_dec_fn.into());
+    let hex_key = v8_str(scope, "hex");
+    encode_obj.set(scope, hex_key.into(), hex_fn.into());
+    let hex_dec_key = v8_str(scope, "hexDecode");
+    encode_obj.set(scope, hex_dec_key.into(), hex_dec_fn.into());
+
+    let encode_key = v8_str(scope, "encode");
+    t_obj.set(scope, encode_key.into(), encode_obj.into());
+
     // t.password
     let pw_obj = v8::Object::new(scope);
     let hash_fn = v8::Function::new(scope, password::native_password_hash).unwrap();
@@ -131,6 +217,41 @@ fn setup_native_utils(scope: &mut v8::HandleScope, t_obj: v8::Local<v8::Object>)
     let sc_key = v8_str(scope, "shareContext");
     t_obj.set(scope, sc_key.into(), sc_obj.into());
 
+    // t.share (same store as t.shareContext; writes publish, subscribe lives in titan_core.js)
+    let share_obj = v8::Object::new(scope);
+    let s_get = v8::Function::new(scope, share_context::share_context_get).unwrap();
+    let s_set = v8::Function::new(scope, share_context::share_set).unwrap();
+    let s_del = v8::Function::new(scope, share_context::share_delete).unwrap();
+
+    let s_get_key = v8_str(scope, "get");
+    share_obj.set(scope, s_get_key.into(), s_get.into());
+    let s_set_key = v8_str(scope, "set");
+    share_obj.set(scope, s_set_key.into(), s_set.into());
+    let s_del_key = v8_str(scope, "delete");
+    share_obj.set(scope, s_del_key.into(), s_del.into());
+
+    let share_key = v8_str(scope, "share");
+    t_obj.set(scope, share_key.into(), share_obj.into());
+
+    // t.cache (TTL entries in the shared store; wrapped in titan_core.js)
+    let cache_obj = v8::Object::new(scope);
+    let c_get = v8::Function::new(scope, share_context::cache_get).unwrap();
+    let c_set = v8::Function::new(scope, share_context::cache_set).unwrap();
+    let c_del = v8::Function::new(scope, share_context::cache_delete).unwrap();
+    let c_inv = v8::Function::new(scope, share_context::cache_invalidate).unwrap();
+
+    let c_get_key = v8_str(scope, "_native_get");
+    cache_obj.set(scope, c_get_key.into(), c_get.into());
+    let c_set_key = v8_str(scope, "_native_set");
+    cache_obj.set(scope, c_set_key.into(), c_set.into());
+    let c_del_key = v8_str(scope, "_native_delete");
+    cache_obj.set(scope, c_del_key.into(), c_del.into());
+    let c_inv_key = v8_str(scope, "_native_invalidate");
+    cache_obj.set(scope, c_inv_key.into(), c_inv.into());
+
+    let cache_key = v8_str(scope, "cache");
+    t_obj.set(scope, cache_key.into(), cache_obj.into());
+
     // t.db (Database operations)
     let db_obj = v8::Object::new(scope);
     let db_connect_fn = v8::Function::new(scope, db::native_db_connect).unwrap();
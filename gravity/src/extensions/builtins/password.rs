@@ -1,21 +1,94 @@
 use v8;
 use bcrypt::{hash, verify, DEFAULT_COST};
+use argon2::{Algorithm, Argon2, Params, Version};
+use argon2::password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
 use crate::extensions::{v8_str, v8_to_string, throw};
 
+/// Options accepted by `t.password.hash(pw, opts)`.
+///
+/// `cost` is the bcrypt cost factor, or the argon2 iteration count.
+/// `memory` (KiB) and `parallelism` only apply to argon2.
+struct HashOptions {
+    algorithm: String,
+    cost: Option<u32>,
+    memory: Option<u32>,
+    parallelism: Option<u32>,
+}
+
+fn read_hash_options(scope: &mut v8::HandleScope, val: v8::Local<v8::Value>) -> HashOptions {
+    let mut opts = HashOptions { algorithm: "bcrypt".to_string(), cost: None, memory: None, parallelism: None };
+    if !val.is_object() {
+        return opts;
+    }
+    let obj = val.to_object(scope).unwrap();
+
+    let alg_key = v8_str(scope, "algorithm");
+    if let Some(v) = obj.get(scope, alg_key.into()) {
+        if v.is_string() {
+            opts.algorithm = v8_to_string(scope, v).to_lowercase();
+        }
+    }
+
+    let read_u32 = |scope: &mut v8::HandleScope, name: &str| -> Option<u32> {
+        let key = v8_str(scope, name);
+        let v = obj.get(scope, key.into())?;
+        if v.is_number() { v.uint32_value(scope) } else { None }
+    };
+    opts.cost = read_u32(scope, "cost");
+    opts.memory = read_u32(scope, "memory");
+    opts.parallelism = read_u32(scope, "parallelism");
+    opts
+}
+
+fn hash_password(pw: &str, opts: &HashOptions) -> Result<String, String> {
+    match opts.algorithm.as_str() {
+        "bcrypt" => hash(pw, opts.cost.unwrap_or(DEFAULT_COST)).map_err(|e| e.to_string()),
+        "argon2id" | "argon2" => {
+            let params = Params::new(
+                opts.memory.unwrap_or(Params::DEFAULT_M_COST),
+                opts.cost.unwrap_or(Params::DEFAULT_T_COST),
+                opts.parallelism.unwrap_or(Params::DEFAULT_P_COST),
+                None,
+            ).map_err(|e| format!("t.password.hash(): invalid argon2 options: {}", e))?;
+            let salt = SaltString::generate(&mut OsRng);
+            Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+                .hash_password(pw.as_bytes(), &salt)
+                .map(|h| h.to_string())
+                .map_err(|e| e.to_string())
+        }
+        other => Err(format!("t.password.hash(): unsupported algorithm '{}' (expected 'bcrypt' or 'argon2id')", other)),
+    }
+}
+
+/// Detects the algorithm from the hash prefix so bcrypt hashes keep verifying
+/// after switching new hashes to argon2id.
+fn verify_password(pw: &str, hash_str: &str) -> bool {
+    if hash_str.starts_with("$argon2") {
+        match PasswordHash::new(hash_str) {
+            // Params are encoded in the hash itself
+            Ok(parsed) => Argon2::default().verify_password(pw.as_bytes(), &parsed).is_ok(),
+            Err(_) => false,
+        }
+    } else {
+        verify(pw, hash_str).unwrap_or(false)
+    }
+}
+
 pub fn native_password_hash(scope: &mut v8::HandleScope, args: v8::FunctionCallbackArguments, mut retval: v8::ReturnValue) {
     let pw = v8_to_string(scope, args.get(0));
-    match hash(pw, DEFAULT_COST) {
+    let opts = read_hash_options(scope, args.get(1));
+    match hash_password(&pw, &opts) {
         Ok(h) => {
             let res = v8_str(scope, &h);
             retval.set(res.into());
         },
-        Err(e) => throw(scope, &e.to_string()),
+        Err(e) => throw(scope, &e),
     }
 }
 
 pub fn native_password_verify(scope: &mut v8::HandleScope, args: v8::FunctionCallbackArguments, mut retval: v8::ReturnValue) {
     let pw = v8_to_string(scope, args.get(0));
     let hash_str = v8_to_string(scope, args.get(1));
-    let ok = verify(pw, &hash_str).unwrap_or(false);
+    let ok = verify_password(&pw, &hash_str);
     retval.set(v8::Boolean::new(scope, ok).into());
 }
@@ -1,5 +1,17 @@
 use v8;
+use std::time::{Duration, Instant};
 use crate::extensions::{v8_str, v8_to_string, ShareContextStore};
+use super::system::replaying;
+
+/// Expired `t.cache` entries are swept on insert once the map grows past this.
+const CACHE_SWEEP_THRESHOLD: usize = 10_000;
+
+/// Expiry for a TTL too large for an `Instant` (`Infinity`, `1e20`): about a century.
+const CACHE_FOREVER: Duration = Duration::from_secs(100 * 365 * 24 * 60 * 60);
+
+/// `t.share` changes go out on the broadcast channel as `share:<key>`, with
+/// the new value (`null` once deleted) as payload.
+pub const SHARE_EVENT_PREFIX: &str = "share:";
 
 pub fn share_context_get(scope: &mut v8::HandleScope, args: v8::FunctionCallbackArguments, mut retval: v8::ReturnValue) {
     let key = v8_to_string(scope, args.get(0));
@@ -50,3 +62,96 @@ pub fn share_context_broadcast(scope: &mut v8::HandleScope, args: v8::FunctionCa
         }
     }
 }
+
+/// Tell `t.share.subscribe` waiters about a change. A replayed action already
+/// published on its first run, so nothing goes out twice.
+fn publish_share(scope: &mut v8::HandleScope, key: String, value: serde_json::Value) {
+    if replaying(scope) {
+        return;
+    }
+    let _ = ShareContextStore::get().broadcast_tx.send((format!("{}{}", SHARE_EVENT_PREFIX, key), value));
+}
+
+/// `t.share.set(key, value)`
+pub fn share_set(scope: &mut v8::HandleScope, args: v8::FunctionCallbackArguments, mut _retval: v8::ReturnValue) {
+    let key = v8_to_string(scope, args.get(0));
+    let val_v8 = args.get(1);
+
+    let Some(json_v8) = v8::json::stringify(scope, val_v8) else { return };
+    let json_str = json_v8.to_rust_string_lossy(scope);
+    let Ok(val) = serde_json::from_str::<serde_json::Value>(&json_str) else { return };
+
+    ShareContextStore::get().kv.insert(key.clone(), val.clone());
+    publish_share(scope, key, val);
+}
+
+/// `t.share.delete(key)`
+pub fn share_delete(scope: &mut v8::HandleScope, args: v8::FunctionCallbackArguments, mut _retval: v8::ReturnValue) {
+    let key = v8_to_string(scope, args.get(0));
+    if ShareContextStore::get().kv.remove(&key).is_some() {
+        publish_share(scope, key, serde_json::Value::Null);
+    }
+}
+
+/// `t.cache._native_get(key)` → `{ value }` while the entry is fresh, else `undefined`.
+/// The wrapper object lets a cached `null` be told apart from a miss.
+pub fn cache_get(scope: &mut v8::HandleScope, args: v8::FunctionCallbackArguments, mut retval: v8::ReturnValue) {
+    let key = v8_to_string(scope, args.get(0));
+    let store = ShareContextStore::get();
+    let hit = match store.cache.get(&key) {
+        Some(entry) if entry.1 > Instant::now() => Some(entry.0.to_string()),
+        Some(_) => None,
+        None => return,
+    };
+    let Some(json_str) = hit else {
+        store.cache.remove_if(&key, |_, (_, expires)| *expires <= Instant::now());
+        return;
+    };
+    let wrapped = format!("{{\"value\":{}}}", json_str);
+    let v8_str = v8::String::new(scope, &wrapped).unwrap();
+    if let Some(v8_val) = v8::json::parse(scope, v8_str) {
+        retval.set(v8_val);
+    }
+}
+
+/// `t.cache._native_set(key, value, ttlMs)`
+pub fn cache_set(scope: &mut v8::HandleScope, args: v8::FunctionCallbackArguments, mut _retval: v8::ReturnValue) {
+    let key = v8_to_string(scope, args.get(0));
+    let val_v8 = args.get(1);
+    let ttl_ms = args.get(2).number_value(scope).unwrap_or(0.0);
+    if !(ttl_ms > 0.0) {
+        return;
+    }
+
+    let Some(json_v8) = v8::json::stringify(scope, val_v8) else { return };
+    let json_str = json_v8.to_rust_string_lossy(scope);
+    let Ok(val) = serde_json::from_str(&json_str) else { return };
+
+    let store = ShareContextStore::get();
+    let now = Instant::now();
+    if store.cache.len() >= CACHE_SWEEP_THRESHOLD {
+        store.cache.retain(|_, (_, expires)| *expires > now);
+    }
+    // Panicking here would abort the process: this runs inside a V8 callback
+    let expires = Duration::try_from_secs_f64(ttl_ms / 1000.0)
+        .ok()
+        .and_then(|ttl| now.checked_add(ttl))
+        .or_else(|| now.checked_add(CACHE_FOREVER))
+        .unwrap_or(now);
+    store.cache.insert(key, (val, expires));
+}
+
+/// `t.cache._native_delete(key)`
+pub fn cache_delete(scope: &mut v8::HandleScope, args: v8::FunctionCallbackArguments, mut _retval: v8::ReturnValue) {
+    let key = v8_to_string(scope, args.get(0));
+    ShareContextStore::get().cache.remove(&key);
+}
+
+/// `t.cache._native_invalidate(prefix)` → number of cached route responses dropped.
+pub fn cache_invalidate(scope: &mut v8::HandleScope, args: v8::FunctionCallbackArguments, mut retval: v8::ReturnValue) {
+    let prefix = v8_to_string(scope, args.get(0));
+    let cache = crate::extensions::response_cache();
+    let before = cache.len();
+    cache.retain(|key, _| !key.starts_with(&prefix));
+    retval.set_uint32(before.saturating_sub(cache.len()) as u32);
+}
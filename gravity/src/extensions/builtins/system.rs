@@ -1,14 +1,52 @@
 use v8;
 use v8::{ValueSerializerHelper, ValueDeserializerHelper};
 use std::sync::OnceLock;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
 use serde_json::Value;
-use crate::extensions::{v8_str, v8_to_string, throw, TitanRuntime, TitanAsyncOp};
+use crate::extensions::{v8_str, v8_to_string, v8_to_bytes, throw, TitanRuntime, TitanAsyncOp};
 use crate::utils::{blue, gray, red, parse_expires_in};
-use super::db::DB_POOL;
+use super::db::db_pools;
 use tokio_postgres::types::{Type, ToSql, IsNull};
 use bytes::BytesMut;
+use base64::{engine::general_purpose::STANDARD, Engine as _};
 use std::error::Error;
 
+/// `__config.driftTimeoutMs`; 0 leaves drifts without a default timeout.
+static DEFAULT_DRIFT_TIMEOUT_MS: AtomicU64 = AtomicU64::new(0);
+
+pub fn set_default_drift_timeout(ms: u64) {
+    DEFAULT_DRIFT_TIMEOUT_MS.store(ms, Ordering::Relaxed);
+}
+
+/// `{ timeout }` from drift's options, else the configured default.
+fn drift_timeout(scope: &mut v8::HandleScope, opts: v8::Local<v8::Value>) -> Option<Duration> {
+    let explicit = if opts.is_object() {
+        let opts_obj = opts.to_object(scope).unwrap();
+        let timeout_key = v8_str(scope, "timeout");
+        opts_obj
+            .get(scope, timeout_key.into())
+            .filter(|v| v.is_number())
+            .and_then(|v| v.number_value(scope))
+            .filter(|ms| *ms > 0.0)
+            .map(|ms| ms as u64)
+    } else {
+        None
+    };
+    let ms = explicit.unwrap_or_else(|| DEFAULT_DRIFT_TIMEOUT_MS.load(Ordering::Relaxed));
+    (ms > 0).then(|| Duration::from_millis(ms))
+}
+
+/// Response for a request whose drift timed out.
+pub fn drift_timeout_response() -> serde_json::Value {
+    serde_json::json!({
+        "_isResponse": true,
+        "status": 504,
+        "headers": { "Content-Type": "application/json" },
+        "body": serde_json::json!({ "error": "drift timeout" }).to_string(),
+    })
+}
+
 #[derive(Debug)]
 struct PostgresParam(serde_json::Value);
 
@@ -187,6 +225,11 @@ pub fn native_deserialize(scope: &mut v8::HandleScope, args: v8::FunctionCallbac
 }
 
 pub fn native_log(scope: &mut v8::HandleScope, args: v8::FunctionCallbackArguments, mut _retval: v8::ReturnValue) {
+    // Already printed before the drift this replay is catching up to
+    if replaying(scope) {
+        return;
+    }
+
     let context = scope.get_current_context();
     let global = context.global(scope);
     let action_key = v8_str(scope, "__titan_action");
@@ -200,6 +243,18 @@ pub fn native_log(scope: &mut v8::HandleScope, args: v8::FunctionCallbackArgumen
         "init".to_string()
     };
 
+    // The current request's id (`req.requestId`), to tie the line to its request log
+    let req_key = v8_str(scope, "__titan_req");
+    let request_id = global
+        .get(scope, req_key.into())
+        .filter(|v| v.is_object())
+        .and_then(|req| {
+            let req = req.to_object(scope)?;
+            let rid_key = v8_str(scope, "requestId");
+            req.get(scope, rid_key.into()).filter(|v| v.is_string())
+        })
+        .map(|v| v8_to_string(scope, v));
+
     let mut parts = Vec::new();
     for i in 0..args.length() {
         let val = args.get(i);
@@ -217,21 +272,158 @@ pub fn native_log(scope: &mut v8::HandleScope, args: v8::FunctionCallbackArgumen
         }
     }
     
-    let titan_str = blue("[Gravity]");
-    let log_msg = gray(&format!("\x1b[90mlog({})\x1b[0m\x1b[97m: {}\x1b[0m", action_name, parts.join(" ")));
-    println!(
-        "{} {}",
-        titan_str,
-        log_msg
+    let message = parts.join(" ");
+    crate::utils::log_with(
+        crate::utils::LogLevel::Info,
+        "t.log",
+        serde_json::json!({ "action": action_name, "requestId": request_id, "message": message }),
+        || {
+            let tag = request_id.map(|id| format!(" [{}]", id)).unwrap_or_default();
+            let log_msg = gray(&format!("\x1b[90mlog({}){}\x1b[0m\x1b[97m: {}\x1b[0m", action_name, tag, message));
+            format!("{} {}", blue("[Gravity]"), log_msg)
+        },
     );
 }
 
+/// `(offset, length)` of a `req.bodyFile` read, clamped to the body, and the body itself.
+fn body_file_range(scope: &mut v8::HandleScope, args: &v8::FunctionCallbackArguments) -> Option<(std::sync::Arc<crate::extensions::SpilledBody>, u64, usize)> {
+    let runtime_ptr = scope.get_data(0) as *mut TitanRuntime;
+    if runtime_ptr.is_null() {
+        return None;
+    }
+    let file = unsafe { (*runtime_ptr).body_file.clone() }?;
+    let arg = |scope: &mut v8::HandleScope, i: i32| {
+        let v = args.get(i);
+        if v.is_number() { v.number_value(scope).filter(|n| *n >= 0.0).map(|n| n as u64) } else { None }
+    };
+    let offset = arg(scope, 0).unwrap_or(0).min(file.len);
+    let length = arg(scope, 1).unwrap_or(file.len - offset).min(file.len - offset) as usize;
+    Some((file, offset, length))
+}
+
+/// `req.bodyFile.read(offset?, length?)` → `Uint8Array` of the spilled request body.
+pub fn native_body_read(scope: &mut v8::HandleScope, args: v8::FunctionCallbackArguments, mut retval: v8::ReturnValue) {
+    let Some((file, offset, length)) = body_file_range(scope, &args) else {
+        throw(scope, "req.bodyFile is only readable while its request runs");
+        return;
+    };
+    match file.read(offset, length) {
+        Ok(bytes) => {
+            let len = bytes.len();
+            let backing = v8::ArrayBuffer::new_backing_store_from_vec(bytes).make_shared();
+            let ab = v8::ArrayBuffer::with_backing_store(scope, &backing);
+            if let Some(view) = v8::Uint8Array::new(scope, ab, 0, len) {
+                retval.set(view.into());
+            }
+        }
+        Err(e) => throw(scope, &format!("req.bodyFile.read(): {}", e)),
+    }
+}
+
+/// `req.bodyFile.text(offset?, length?)` → the spilled body decoded as UTF-8.
+pub fn native_body_text(scope: &mut v8::HandleScope, args: v8::FunctionCallbackArguments, mut retval: v8::ReturnValue) {
+    let Some((file, offset, length)) = body_file_range(scope, &args) else {
+        throw(scope, "req.bodyFile is only readable while its request runs");
+        return;
+    };
+    match file.read(offset, length) {
+        Ok(bytes) => {
+            let text = v8_str(scope, &String::from_utf8_lossy(&bytes));
+            retval.set(text.into());
+        }
+        Err(e) => throw(scope, &format!("req.bodyFile.text(): {}", e)),
+    }
+}
+
+/// `t.isReplaying()`: true while a resumed action re-runs code that already
+/// ran before its last completed drift. Guard one-off side effects with it.
+pub fn native_is_replaying(scope: &mut v8::HandleScope, _args: v8::FunctionCallbackArguments, mut retval: v8::ReturnValue) {
+    retval.set_bool(replaying(scope));
+}
+
+/// `t.system.stats()`: load figures for status pages. Reads only atomics, so
+/// a worker never waits on a pool lock the autoscaler may be holding.
+pub fn native_system_stats(scope: &mut v8::HandleScope, _args: v8::FunctionCallbackArguments, mut retval: v8::ReturnValue) {
+    let memory_mb = resident_memory_mb();
+    let stats = match crate::extensions::TASK_RUNTIME.get() {
+        Some(pool) => {
+            let total = pool.pool_size();
+            serde_json::json!({
+                "freeIsolates": total.saturating_sub(pool.busy_workers()),
+                "totalIsolates": total,
+                "inFlightRequests": pool.in_flight(),
+                "uptimeSeconds": pool.uptime().as_secs_f64(),
+                "memoryMb": memory_mb,
+            })
+        }
+        // Outside the server (tgrv scripts) there is no pool to report on
+        None => serde_json::json!({
+            "freeIsolates": 0,
+            "totalIsolates": 0,
+            "inFlightRequests": 0,
+            "uptimeSeconds": 0,
+            "memoryMb": memory_mb,
+        }),
+    };
+    retval.set(crate::extensions::json_to_v8(scope, &stats));
+}
+
+/// Resident set size of the process; `None` where it can't be read cheaply.
+fn resident_memory_mb() -> Option<f64> {
+    #[cfg(target_os = "linux")]
+    {
+        let status = std::fs::read_to_string("/proc/self/status").ok()?;
+        let kb: f64 = status
+            .lines()
+            .find_map(|line| line.strip_prefix("VmRSS:"))?
+            .trim()
+            .trim_end_matches("kB")
+            .trim()
+            .parse()
+            .ok()?;
+        Some((kb / 1024.0 * 10.0).round() / 10.0)
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        None
+    }
+}
+
+pub fn replaying(scope: &mut v8::HandleScope) -> bool {
+    let runtime_ptr = scope.get_data(0) as *mut TitanRuntime;
+    if runtime_ptr.is_null() {
+        return false;
+    }
+    unsafe { (*runtime_ptr).replay_pending > 0 }
+}
+
+/// `__config.env`: names of the environment variables actions may read as
+/// `t.env` / `process.env`. Unset exposes none besides the always-public ones.
+static ENV_ALLOWLIST: std::sync::OnceLock<std::collections::HashSet<String>> = std::sync::OnceLock::new();
+
+/// Exposed without being listed: `TITAN_PUBLIC_*`, and the flags titan_core.js itself reads.
+const ENV_PUBLIC_PREFIX: &str = "TITAN_PUBLIC_";
+const ENV_RUNTIME_FLAGS: &[&str] = &["TITAN_DEV"];
+
+/// Call before the workers start; each isolate builds `t.env` once at init.
+pub fn set_env_allowlist(names: Vec<String>) {
+    let _ = ENV_ALLOWLIST.set(names.into_iter().collect());
+}
+
+fn env_exposed(name: &str) -> bool {
+    name.starts_with(ENV_PUBLIC_PREFIX)
+        || ENV_RUNTIME_FLAGS.contains(&name)
+        || ENV_ALLOWLIST.get().is_some_and(|names| names.contains(name))
+}
+
+/// `t.loadEnv()`: the exposed subset of the process environment, read once
+/// per isolate by titan_core.js into `t.env`.
 pub fn native_load_env(scope: &mut v8::HandleScope, _args: v8::FunctionCallbackArguments, mut retval: v8::ReturnValue) {
     use serde_json::json;
 
     let mut map = serde_json::Map::new();
 
-    for (key, value) in std::env::vars() {
+    for (key, value) in std::env::vars().filter(|(key, _)| env_exposed(key)) {
         map.insert(key, json!(value));
     }
 
@@ -298,6 +490,7 @@ pub fn parse_async_op(scope: &mut v8::HandleScope, op_val: v8::Local<v8::Value>)
             let mut method = "GET".to_string();
             let mut body = None;
             let mut headers = Vec::new();
+            let mut max_body_bytes = None;
             
             let opts_key = v8_str(scope, "opts");
             if let Some(opts_val) = data_obj.get(scope, opts_key.into()) {
@@ -328,9 +521,15 @@ pub fn parse_async_op(scope: &mut v8::HandleScope, op_val: v8::Local<v8::Value>)
                             }
                         }
                     }
+                    let max_key = v8_str(scope, "maxBodyBytes");
+                    if let Some(max_val) = opts_obj.get(scope, max_key.into()) {
+                        if max_val.is_number() {
+                            max_body_bytes = max_val.number_value(scope).map(|n| n as u64);
+                        }
+                    }
                 }
             }
-            Some(TitanAsyncOp::Fetch { url, method, body, headers })
+            Some(TitanAsyncOp::Fetch { url, method, body, headers, max_body_bytes })
         },
 
         "db_query" => {
@@ -358,6 +557,7 @@ pub fn parse_async_op(scope: &mut v8::HandleScope, op_val: v8::Local<v8::Value>)
 
             let mut pool_timeout = None;
             let mut query_timeout = None;
+            let mut statement_timeout = None;
 
             let opts_key = v8_str(scope, "options");
             if let Some(opts_val) = data_obj.get(scope, opts_key.into()) {
@@ -375,6 +575,12 @@ pub fn parse_async_op(scope: &mut v8::HandleScope, op_val: v8::Local<v8::Value>)
                             if let Some(n) = v.number_value(scope) { query_timeout = Some(n as u64); }
                         }
                     }
+                    let st_key = v8_str(scope, "timeoutMs");
+                    if let Some(v) = opts_obj.get(scope, st_key.into()) {
+                        if v.is_number() {
+                            if let Some(n) = v.number_value(scope) { statement_timeout = Some(n as u64); }
+                        }
+                    }
                 }
             }
 
@@ -383,7 +589,8 @@ pub fn parse_async_op(scope: &mut v8::HandleScope, op_val: v8::Local<v8::Value>)
                 query, 
                 params,
                 pool_timeout,
-                query_timeout
+                query_timeout,
+                statement_timeout
             })
         }
 
@@ -391,7 +598,13 @@ pub fn parse_async_op(scope: &mut v8::HandleScope, op_val: v8::Local<v8::Value>)
             let path_key = v8_str(scope, "path");
             let path_obj = data_obj.get(scope, path_key.into())?;
             let path = v8_to_string(scope, path_obj);
-            Some(TitanAsyncOp::FsRead { path })
+            let enc_key = v8_str(scope, "encoding");
+            let binary = data_obj
+                .get(scope, enc_key.into())
+                .filter(|v| v.is_string())
+                .map(|v| v8_to_string(scope, v) == "binary")
+                .unwrap_or(false);
+            Some(TitanAsyncOp::FsRead { path, binary })
         },
         "native_call" => {
             let ext_key = v8_str(scope, "extension");
@@ -416,10 +629,57 @@ pub fn parse_async_op(scope: &mut v8::HandleScope, op_val: v8::Local<v8::Value>)
             }
             Some(TitanAsyncOp::NativeCall { extension, function, params })
         },
+        "share_wait" => {
+            let key_key = v8_str(scope, "key");
+            let key_val = data_obj.get(scope, key_key.into())?;
+            let key = v8_to_string(scope, key_val);
+            let timeout_key = v8_str(scope, "timeoutMs");
+            let timeout_ms = data_obj
+                .get(scope, timeout_key.into())
+                .filter(|v| v.is_number())
+                .and_then(|v| v.number_value(scope))
+                .filter(|ms| *ms > 0.0)
+                .map(|ms| ms as u64);
+            Some(TitanAsyncOp::ShareWait { key, timeout_ms })
+        },
         _ => None
     }
 }
 
+/// Binary payloads cross the JSON drift boundary as `{ data: { __titan_bytes: "<base64>" } }`.
+const BYTES_KEY: &str = "__titan_bytes";
+
+/// Replace base64-tagged `data` fields in a parsed drift result with real
+/// `Uint8Array`s. Batch results are checked per item.
+fn attach_binary_data(scope: &mut v8::HandleScope, res: &Value, val: v8::Local<v8::Value>) {
+    match res {
+        Value::Array(items) if val.is_array() => {
+            let arr = v8::Local::<v8::Array>::try_from(val).unwrap();
+            for (i, item) in items.iter().enumerate() {
+                if let Some(v) = arr.get_index(scope, i as u32) {
+                    attach_binary_data(scope, item, v);
+                }
+            }
+        }
+        Value::Object(map) if val.is_object() => {
+            let Some(b64) = map.get("data").and_then(|d| d.get(BYTES_KEY)).and_then(|b| b.as_str()) else {
+                return;
+            };
+            if let Ok(bytes) = STANDARD.decode(b64) {
+                let len = bytes.len();
+                let backing = v8::ArrayBuffer::new_backing_store_from_vec(bytes).make_shared();
+                let ab = v8::ArrayBuffer::with_backing_store(scope, &backing);
+                if let Some(u8_arr) = v8::Uint8Array::new(scope, ab, 0, len) {
+                    let obj = val.to_object(scope).unwrap();
+                    let data_key = v8_str(scope, "data");
+                    obj.set(scope, data_key.into(), u8_arr.into());
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
 pub fn native_drift_call(scope: &mut v8::HandleScope, mut args: v8::FunctionCallbackArguments, mut retval: v8::ReturnValue) {
     let runtime_ptr = unsafe { args.get_isolate() }.get_data(0) as *mut TitanRuntime;
     let runtime = unsafe { &mut *runtime_ptr };
@@ -428,14 +688,29 @@ pub fn native_drift_call(scope: &mut v8::HandleScope, mut args: v8::FunctionCall
     
     let (async_op, op_type) = if arg0.is_array() {
         let arr = v8::Local::<v8::Array>::try_from(arg0).unwrap();
-        let mut ops = Vec::new();
+        let mut ops = Vec::with_capacity(arr.length() as usize);
         for i in 0..arr.length() {
             let op_val = arr.get_index(scope, i).unwrap();
-            if let Some(op) = parse_async_op(scope, op_val) {
-                ops.push(op);
+            match parse_async_op(scope, op_val) {
+                Some(op) => ops.push(op),
+                None => {
+                    // Dropping the item would shift every later result slot
+                    throw(scope, &format!("drift.all(): item {} is not an async operation", i));
+                    return;
+                }
+            }
+        }
+
+        let mut fail_fast = false;
+        let opts = args.get(1);
+        if opts.is_object() {
+            let opts_obj = opts.to_object(scope).unwrap();
+            let ff_key = v8_str(scope, "failFast");
+            if let Some(v) = opts_obj.get(scope, ff_key.into()) {
+                fail_fast = v.boolean_value(scope);
             }
         }
-        (TitanAsyncOp::Batch(ops), "batch".to_string())
+        (TitanAsyncOp::Batch { ops, fail_fast }, "batch".to_string())
     } else {
         match parse_async_op(scope, arg0) {
             Some(op) => {
@@ -443,6 +718,7 @@ pub fn native_drift_call(scope: &mut v8::HandleScope, mut args: v8::FunctionCall
                     TitanAsyncOp::Fetch { .. } => "fetch",
                     TitanAsyncOp::DbQuery { .. } => "db_query",
                     TitanAsyncOp::FsRead { .. } => "fs_read",
+                    TitanAsyncOp::ShareWait { .. } => "share_wait",
                     _ => "unknown"
                 };
                 (op, t.to_string())
@@ -468,19 +744,70 @@ pub fn native_drift_call(scope: &mut v8::HandleScope, mut args: v8::FunctionCall
         } else { 0 }
     };
 
-    runtime.drift_counter += 1;
-    let drift_id = runtime.drift_counter;
-    
-    if req_id != 0 {
-        runtime.drift_to_request.insert(drift_id, req_id);
+    // --- NO-REPLAY MODE ---
+    // Block this worker on the op and hand the result straight back, so code
+    // before the drift never re-runs. Pins the isolate for the request duration.
+    let no_replay = {
+        let context = scope.get_current_context();
+        let global = context.global(scope);
+        let act_key = v8_str(scope, "__titan_action");
+        global
+            .get(scope, act_key.into())
+            .filter(|v| v.is_string())
+            .map(|v| runtime.no_replay_actions.contains(&v8_to_string(scope, v)))
+            .unwrap_or(false)
+    };
+    let timeout = drift_timeout(scope, args.get(1));
+    if no_replay {
+        let start = std::time::Instant::now();
+        let result = runtime.tokio_handle.block_on(async {
+            match timeout {
+                Some(limit) => tokio::time::timeout(limit, run_async_operation(async_op)).await.ok(),
+                None => Some(run_async_operation(async_op).await),
+            }
+        });
+        let duration_ms = start.elapsed().as_secs_f64() * 1000.0;
+        let timing_type = if result.as_ref().map_or(true, |r| r.get("error").is_some()) { "drift_error" } else { "drift" };
+        runtime.request_timings
+            .entry(req_id)
+            .or_default()
+            .push((timing_type.to_string(), duration_ms));
+
+        // Answer 504 now, drop the request the way a timed-out replayed drift
+        // does, and unwind the action the same way a suspend does
+        let Some(result) = result else {
+            crate::runtime::release_request(runtime, req_id, drift_timeout_response());
+            crate::extensions::forget_request_state_in(scope, req_id);
+            throw(scope, "__SUSPEND__");
+            return;
+        };
+
+        let json_str = serde_json::to_string(&result).unwrap_or_else(|_| "null".to_string());
+        let v8_json = v8_str(scope, &json_str);
+        let val = v8::json::parse(scope, v8_json).unwrap_or_else(|| v8::null(scope).into());
+        attach_binary_data(scope, &result, val);
+        retval.set(val);
+        return;
     }
 
     // --- REPLAY CHECK ---
-    if let Some(res) = runtime.completed_drifts.get(&drift_id) {
+    // The n-th drift() of a replayed request returns that request's n-th result
+    let seq = runtime.drift_seq;
+    runtime.drift_seq += 1;
+    let replayed = runtime
+        .request_drifts
+        .get(&req_id)
+        .and_then(|ids| ids.get(seq))
+        .copied()
+        .filter(|id| runtime.completed_drifts.contains_key(id));
+    if let Some(id) = replayed {
+         runtime.replay_pending = runtime.replay_pending.saturating_sub(1);
+         let res = &runtime.completed_drifts[&id];
          let json_str = serde_json::to_string(res).unwrap_or_else(|_| "null".to_string());
          let v8_str = v8::String::new(scope, &json_str).unwrap();
          let mut try_catch = v8::TryCatch::new(scope);
          if let Some(val) = v8::json::parse(&mut try_catch, v8_str) {
+             attach_binary_data(&mut try_catch, res, val);
              retval.set(val);
          } else {
              retval.set(v8::null(&mut try_catch).into());
@@ -488,6 +815,14 @@ pub fn native_drift_call(scope: &mut v8::HandleScope, mut args: v8::FunctionCall
          return;
     }
 
+    runtime.drift_counter = runtime.drift_counter.wrapping_add(1);
+    let drift_id = runtime.drift_counter;
+
+    if req_id != 0 {
+        runtime.drift_to_request.insert(drift_id, req_id);
+        runtime.request_drifts.entry(req_id).or_default().push(drift_id);
+    }
+
     let (tx, rx) = tokio::sync::oneshot::channel::<crate::extensions::WorkerAsyncResult>();
     
     let req = crate::extensions::AsyncOpRequest {
@@ -495,6 +830,7 @@ pub fn native_drift_call(scope: &mut v8::HandleScope, mut args: v8::FunctionCall
         drift_id,
         request_id: req_id,
         op_type: op_type.clone(),
+        timeout,
         respond_tx: tx,
     };
     
@@ -518,12 +854,36 @@ pub fn native_drift_call(scope: &mut v8::HandleScope, mut args: v8::FunctionCall
     throw(scope, "__SUSPEND__");
 }
 
+/// An action's return value; one too deep or circular to serialize becomes
+/// an error (500) rather than a partial body.
+fn result_to_json(scope: &mut v8::HandleScope, value: v8::Local<v8::Value>) -> Value {
+    crate::extensions::try_v8_to_json(scope, value).unwrap_or_else(|e| serde_json::json!({ "error": e }))
+}
+
+/// A plain object or array result: its JSON text is passed on as `_rawJson`
+/// and becomes the body unchanged, skipping the `Value` round trip. A
+/// top-level `error` key keeps the `Value` path, where it marks a failure.
+fn result_to_body(scope: &mut v8::HandleScope, obj: v8::Local<v8::Object>) -> Value {
+    let error_key = v8_str(scope, "error");
+    if !obj.has_own_property(scope, error_key.into()).unwrap_or(false) {
+        match crate::extensions::v8_to_json_text(scope, obj.into()) {
+            Some(Ok(text)) => return serde_json::json!({ "_rawJson": text }),
+            Some(Err(e)) => return serde_json::json!({ "error": e }),
+            None => {}
+        }
+    }
+    result_to_json(scope, obj.into())
+}
+
 pub fn native_finish_request(scope: &mut v8::HandleScope, mut args: v8::FunctionCallbackArguments, _retval: v8::ReturnValue) {
     let request_id = args.get(0).uint32_value(scope).unwrap_or(0);
     let result_val = args.get(1);
 
     // --- OPTIMIZATION: Direct field extraction for _isResponse objects ---
-    let json = if result_val.is_object() {
+    let json = if result_val.is_undefined() {
+        // Kept distinct from an explicit `null` so the server can answer 204
+        serde_json::json!({ "_isUndefined": true })
+    } else if result_val.is_object() {
         let obj = result_val.to_object(scope).unwrap();
         let is_resp_key = v8_str(scope, "_isResponse");
         let is_response = obj
@@ -560,41 +920,57 @@ pub fn native_finish_request(scope: &mut v8::HandleScope, mut args: v8::Function
                 }
             }
 
+            // file / download (t.response.file) — the server streams the file itself
+            for field in ["file", "download"] {
+                let key = v8_str(scope, field);
+                if let Some(v) = obj.get(scope, key.into()).filter(|v| v.is_string()) {
+                    map.insert(field.into(), Value::String(v8_to_string(scope, v)));
+                }
+            }
+
             // headers (flat object with ~2-3 keys typically)
-            let headers_key = v8_str(scope, "headers");
-            if let Some(h) = obj.get(scope, headers_key.into()) {
-                if h.is_object() {
-                    let h_obj = h.to_object(scope).unwrap();
-                    if let Some(keys) =
-                        h_obj.get_own_property_names(scope, Default::default())
-                    {
-                        let mut h_map = serde_json::Map::with_capacity(keys.length() as usize);
-                        for i in 0..keys.length() {
-                            if let Some(key) = keys.get_index(scope, i) {
-                                if let Some(val) = h_obj.get(scope, key) {
-                                    let k_str =
-                                        key.to_string(scope).unwrap().to_rust_string_lossy(scope);
-                                    let v_str =
-                                        val.to_string(scope).unwrap().to_rust_string_lossy(scope);
-                                    h_map.insert(k_str, Value::String(v_str));
-                                }
-                            }
-                        }
-                        map.insert("headers".into(), Value::Object(h_map));
+            if let Some(h_map) = extract_string_map(scope, obj, "headers") {
+                map.insert("headers".into(), Value::Object(h_map));
+            }
+
+            // trailers (t.response.trailer) — same flat shape as headers
+            if let Some(t_map) = extract_string_map(scope, obj, "trailers") {
+                map.insert("trailers".into(), Value::Object(t_map));
+            }
+
+            // cookies (t.cookies.set / clear) — one Set-Cookie line each, so not a header map
+            let cookies_key = v8_str(scope, "cookies");
+            if let Some(c) = obj.get(scope, cookies_key.into()).filter(|v| v.is_array()) {
+                let arr = v8::Local::<v8::Array>::try_from(c).unwrap();
+                let mut lines = Vec::with_capacity(arr.length() as usize);
+                for i in 0..arr.length() {
+                    if let Some(line) = arr.get_index(scope, i) {
+                        lines.push(Value::String(v8_to_string(scope, line)));
                     }
                 }
+                map.insert("cookies".into(), Value::Array(lines));
             }
             serde_json::Value::Object(map)
         } else {
-            crate::extensions::v8_to_json(scope, result_val)
+            result_to_body(scope, obj)
         }
     } else {
-        crate::extensions::v8_to_json(scope, result_val)
+        result_to_json(scope, result_val)
     };
 
     let runtime_ptr = unsafe { args.get_isolate() }.get_data(0) as *mut TitanRuntime;
     let runtime = unsafe { &mut *runtime_ptr };
-    
+
+    // A streamed response already has its head; its trailers go after the
+    // body, which ends when the sender is dropped
+    if let Some((body_tx, trailers)) = runtime.response_streams.remove(&request_id) {
+        if let Some(t_map) = json.get("trailers").and_then(Value::as_object) {
+            *trailers.lock().unwrap() = t_map.iter().filter_map(|(k, v)| v.as_str().map(|v| (k.clone(), v.to_string()))).collect();
+        }
+        drop(body_tx);
+        runtime.request_timings.remove(&request_id);
+    }
+
     if let Some(tx) = runtime.pending_requests.remove(&request_id) {
         let timings = runtime.request_timings.remove(&request_id).unwrap_or_default();
         let _ = tx.send(crate::runtime::WorkerResult {
@@ -604,6 +980,156 @@ pub fn native_finish_request(scope: &mut v8::HandleScope, mut args: v8::Function
     }
 }
 
+/// Chunks buffered between the worker and the connection before `_stream_write` blocks.
+const STREAM_BUFFER: usize = 16;
+
+/// How long `_stream_write` waits for a full buffer to drain before giving up.
+const STREAM_WRITE_TIMEOUT: Duration = Duration::from_secs(30);
+
+static NEXT_STREAM_ID: AtomicU64 = AtomicU64::new(1);
+
+/// `t._stream_open(requestId, { status, headers })`: answer the request with a
+/// response head now and keep its body open for `t._stream_write`. A replay
+/// finds the stream already open and leaves it alone. False when the request
+/// has no response left to stream into.
+pub fn native_stream_open(scope: &mut v8::HandleScope, args: v8::FunctionCallbackArguments, mut retval: v8::ReturnValue) {
+    let request_id = args.get(0).uint32_value(scope).unwrap_or(0);
+    let head = args.get(1);
+    let (status, headers) = if head.is_object() {
+        let head = head.to_object(scope).unwrap();
+        let status_key = v8_str(scope, "status");
+        let status = head.get(scope, status_key.into()).and_then(|v| v.uint32_value(scope)).unwrap_or(200);
+        (status, extract_string_map(scope, head, "headers").unwrap_or_default())
+    } else {
+        (200, serde_json::Map::new())
+    };
+
+    let runtime_ptr = scope.get_data(0) as *mut TitanRuntime;
+    let runtime = unsafe { &mut *runtime_ptr };
+    if runtime.response_streams.contains_key(&request_id) {
+        retval.set_bool(true);
+        return;
+    }
+    let Some(tx) = runtime.pending_requests.remove(&request_id) else {
+        retval.set_bool(false);
+        return;
+    };
+
+    let id = NEXT_STREAM_ID.fetch_add(1, Ordering::Relaxed);
+    let (body_tx, body_rx) = tokio::sync::mpsc::channel(STREAM_BUFFER);
+    let trailers = crate::extensions::StreamTrailers::default();
+    crate::extensions::response_streams().insert(id, crate::extensions::ResponseStream { rx: body_rx, trailers: trailers.clone() });
+    runtime.response_streams.insert(request_id, (body_tx, trailers));
+
+    let timings = runtime.request_timings.get(&request_id).cloned().unwrap_or_default();
+    let head = crate::runtime::WorkerResult {
+        json: serde_json::json!({ "_isResponse": true, "status": status, "headers": headers, "_stream": id }),
+        timings,
+    };
+    if tx.send(head).is_err() {
+        // Client already gone; later writes find a closed channel and are dropped
+        crate::extensions::response_streams().remove(&id);
+    }
+    retval.set_bool(true);
+}
+
+/// `t._stream_write(requestId, chunk)`: append a string or byte chunk to an
+/// open response body.
+/// Waits while the client is slower than the action (backpressure), for at
+/// most [`STREAM_WRITE_TIMEOUT`]; past that the body ends short and the write
+/// throws, so a client that stops reading cannot pin the worker. Output a
+/// replay re-produces was already sent and is skipped.
+pub fn native_stream_write(scope: &mut v8::HandleScope, args: v8::FunctionCallbackArguments, _retval: v8::ReturnValue) {
+    if replaying(scope) {
+        return;
+    }
+    let request_id = args.get(0).uint32_value(scope).unwrap_or(0);
+    let chunk = v8_to_bytes(scope, args.get(1)).unwrap_or_else(|| v8_to_string(scope, args.get(1)).into_bytes());
+
+    let runtime_ptr = scope.get_data(0) as *mut TitanRuntime;
+    let runtime = unsafe { &mut *runtime_ptr };
+    let Some((body_tx, _)) = runtime.response_streams.get(&request_id) else { return };
+
+    let deadline = std::time::Instant::now() + STREAM_WRITE_TIMEOUT;
+    let mut pending = bytes::Bytes::from(chunk);
+    let mut backoff = Duration::from_micros(100);
+    loop {
+        match body_tx.try_send(pending) {
+            // Closed only once the connection is gone; the action still runs to completion
+            Ok(()) | Err(tokio::sync::mpsc::error::TrySendError::Closed(_)) => return,
+            Err(tokio::sync::mpsc::error::TrySendError::Full(back)) => pending = back,
+        }
+        let now = std::time::Instant::now();
+        if now >= deadline {
+            break;
+        }
+        std::thread::sleep(backoff.min(deadline - now));
+        backoff = (backoff * 2).min(Duration::from_millis(50));
+    }
+
+    // Dropping the sender ends the body short and frees the connection
+    runtime.response_streams.remove(&request_id);
+    throw(scope, &format!("t.response stream: client stopped reading for {}s", STREAM_WRITE_TIMEOUT.as_secs()));
+}
+
+/// `t._early_hints(requestId, links)`: send a `103 Early Hints` with these
+/// `Link` values now, while the action keeps running. Hints a replay
+/// re-produces were already sent and are skipped.
+pub fn native_early_hints(scope: &mut v8::HandleScope, args: v8::FunctionCallbackArguments, _retval: v8::ReturnValue) {
+    if replaying(scope) {
+        return;
+    }
+    let request_id = args.get(0).uint32_value(scope).unwrap_or(0);
+    let Ok(list) = v8::Local::<v8::Array>::try_from(args.get(1)) else { return };
+    let mut links = Vec::with_capacity(list.length() as usize);
+    for i in 0..list.length() {
+        if let Some(link) = list.get_index(scope, i) {
+            links.push(v8_to_string(scope, link));
+        }
+    }
+
+    let runtime_ptr = scope.get_data(0) as *mut TitanRuntime;
+    let runtime = unsafe { &mut *runtime_ptr };
+    if let Some(sink) = runtime.early_hints.get(&request_id) {
+        sink(&links);
+    }
+}
+
+/// `t._debug_panic(message)`: make this worker panic once the action
+/// returns. Bound only when `TITAN_DEBUG_PANIC` is set, for the `onPanic` tests.
+pub fn native_debug_panic(scope: &mut v8::HandleScope, args: v8::FunctionCallbackArguments, _retval: v8::ReturnValue) {
+    let message = v8_to_string(scope, args.get(0));
+    let runtime_ptr = scope.get_data(0) as *mut TitanRuntime;
+    let runtime = unsafe { &mut *runtime_ptr };
+    runtime.requested_panic = Some(message);
+}
+
+/// Read a flat `{ name: value }` object property as a map of strings.
+fn extract_string_map(
+    scope: &mut v8::HandleScope,
+    obj: v8::Local<v8::Object>,
+    field: &str,
+) -> Option<serde_json::Map<String, Value>> {
+    let field_key = v8_str(scope, field);
+    let h = obj.get(scope, field_key.into())?;
+    if !h.is_object() {
+        return None;
+    }
+    let h_obj = h.to_object(scope).unwrap();
+    let keys = h_obj.get_own_property_names(scope, Default::default())?;
+    let mut h_map = serde_json::Map::with_capacity(keys.length() as usize);
+    for i in 0..keys.length() {
+        if let Some(key) = keys.get_index(scope, i) {
+            if let Some(val) = h_obj.get(scope, key) {
+                let k_str = key.to_string(scope).unwrap().to_rust_string_lossy(scope);
+                let v_str = val.to_string(scope).unwrap().to_rust_string_lossy(scope);
+                h_map.insert(k_str, Value::String(v_str));
+            }
+        }
+    }
+    Some(h_map)
+}
+
 pub fn run_async_operation(
     op: TitanAsyncOp,
 ) -> std::pin::Pin<Box<dyn std::future::Future<Output = serde_json::Value> + Send>> {
@@ -618,6 +1144,7 @@ pub fn run_async_operation(
                 method,
                 body,
                 headers,
+                max_body_bytes,
             } => {
                 let client = get_http_client();
 
@@ -635,10 +1162,35 @@ pub fn run_async_operation(
                 }
 
                 match req.send().await {
-                    Ok(resp) => {
+                    Ok(mut resp) => {
                         let status = resp.status().as_u16();
                         let api_headers = resp.headers().clone();
-                        let text = resp.text().await.unwrap_or_default();
+
+                        let text = match max_body_bytes {
+                            None => resp.text().await.unwrap_or_default(),
+                            Some(limit) => {
+                                let too_large = serde_json::json!({ "ok": false, "error": "body too large", "status": status });
+
+                                // Reject early on an advertised length, then enforce while reading
+                                if resp.content_length().is_some_and(|len| len > limit) {
+                                    return too_large;
+                                }
+                                let mut buf: Vec<u8> = Vec::new();
+                                loop {
+                                    match resp.chunk().await {
+                                        Ok(Some(chunk)) => {
+                                            if (buf.len() + chunk.len()) as u64 > limit {
+                                                return too_large;
+                                            }
+                                            buf.extend_from_slice(&chunk);
+                                        }
+                                        Ok(None) => break,
+                                        Err(e) => return serde_json::json!({ "error": e.to_string() }),
+                                    }
+                                }
+                                String::from_utf8_lossy(&buf).into_owned()
+                            }
+                        };
 
                         let mut h_map = serde_json::Map::new();
                         for (k, v) in api_headers.iter() {
@@ -665,24 +1217,27 @@ pub fn run_async_operation(
             // DB QUERY
             // =========================
             TitanAsyncOp::DbQuery { 
-                conn: _, 
+                conn, 
                 query, 
                 params,
                 pool_timeout,
-                query_timeout
+                query_timeout,
+                statement_timeout
             } => {
-                let pool = match DB_POOL.get() {
-                    Some(p) => p,
+                // Pool clones are cheap handles onto the same shared pool
+                let pool = match db_pools().get(&conn) {
+                    Some(p) => p.clone(),
                     None => {
                         return serde_json::json!({
-                            "error": "DB pool not initialized"
+                            "error": "DB pool not initialized (call t.db.connect(url) first)"
                         });
                     }
                 };
                 let p_timeout = pool_timeout.unwrap_or(5000);
-                let q_timeout = query_timeout.unwrap_or(10000);
+                // Client-side deadline; with timeoutMs the server cancels first and this is a backstop
+                let q_timeout = query_timeout.unwrap_or_else(|| statement_timeout.map_or(10000, |ms| ms + 1000));
 
-                if std::env::var("TITAN_DEV").unwrap_or_default() == "1" {
+                if crate::utils::dev_mode() {
                     println!("{} {} Operation started (Pool: {}ms, Query: {}ms)", crate::utils::blue("[Titan]"), crate::utils::yellow("DB:"), p_timeout, q_timeout);
                 }
 
@@ -705,7 +1260,26 @@ pub fn run_async_operation(
                                 .map(|p| p as &(dyn tokio_postgres::types::ToSql + Sync))
                                 .collect();
 
-                        match tokio::time::timeout(std::time::Duration::from_millis(q_timeout), client.query(&stmt, &param_refs)).await {
+                        // statement_timeout makes Postgres cancel the query itself, so the
+                        // backend stops working instead of us just abandoning the wait.
+                        if let Some(ms) = statement_timeout {
+                            if let Err(e) = client.batch_execute(&format!("SET statement_timeout = {}", ms)).await {
+                                return serde_json::json!({ "error": e.to_string() });
+                            }
+                        }
+
+                        let outcome = tokio::time::timeout(std::time::Duration::from_millis(q_timeout), client.query(&stmt, &param_refs)).await;
+
+                        if statement_timeout.is_some() {
+                            if outcome.is_err() {
+                                // Still busy with the query: detach it so the session setting never returns to the pool
+                                let _ = deadpool_postgres::Object::take(client);
+                            } else {
+                                let _ = client.batch_execute("RESET statement_timeout").await;
+                            }
+                        }
+
+                        match outcome {
                             Ok(Ok(rows)) => {
                                 let mut result = Vec::new();
 
@@ -746,6 +1320,15 @@ pub fn run_async_operation(
 
                                 serde_json::Value::Array(result)
                             }
+                            Ok(Err(e)) if statement_timeout.is_some() && e.code() == Some(&tokio_postgres::error::SqlState::QUERY_CANCELED) => {
+                                let ms = statement_timeout.unwrap_or_default();
+                                crate::utils::log(
+                                    crate::utils::LogLevel::Warn,
+                                    "db_timeout",
+                                    serde_json::json!({ "timeoutMs": ms, "message": format!("query cancelled by statement_timeout after {}ms", ms) }),
+                                );
+                                serde_json::json!({ "error": format!("Query timeout after {} milliseconds", ms), "timeout": true })
+                            }
                             Ok(Err(e)) => {
                                 serde_json::json!({ "error": e.to_string() })
                             }
@@ -768,33 +1351,17 @@ pub fn run_async_operation(
             // =========================
             // FS READ
             // =========================
-            TitanAsyncOp::FsRead { path } => {
-
-                let root = crate::extensions::PROJECT_ROOT
-                    .get()
-                    .cloned()
-                    .unwrap_or(std::path::PathBuf::from("."));
-
-                let target = root.join(&path);
-
-                let safe = target
-                    .canonicalize()
-                    .map(|p| {
-                        p.starts_with(
-                            crate::extensions::PROJECT_ROOT.get()
-                                .and_then(|r| r.canonicalize().ok())
-                                .unwrap_or(root.clone())
-                        )
-                    })
-                    .unwrap_or(false);
-
-                if safe {
-                    match tokio::fs::read_to_string(target).await {
+            TitanAsyncOp::FsRead { path, binary } => {
+                match crate::extensions::builtins::fs::resolve_in_project(&path) {
+                    Some(target) if binary => match tokio::fs::read(target).await {
+                        Ok(bytes) => serde_json::json!({ "data": { BYTES_KEY: STANDARD.encode(bytes) } }),
+                        Err(e) => serde_json::json!({ "error": e.to_string() }),
+                    },
+                    Some(target) => match tokio::fs::read_to_string(target).await {
                         Ok(c) => serde_json::json!({ "data": c }),
                         Err(e) => serde_json::json!({ "error": e.to_string() }),
-                    }
-                } else {
-                    serde_json::json!({ "error": "Access denied" })
+                    },
+                    None => serde_json::json!({ "error": "Access denied" }),
                 }
             }
 
@@ -803,14 +1370,67 @@ pub fn run_async_operation(
             }
 
             // =========================
-            // BATCH
+            // SHARE WAIT
             // =========================
-            TitanAsyncOp::Batch(ops) => {
+            TitanAsyncOp::ShareWait { key, timeout_ms } => {
+                use tokio::sync::broadcast::error::RecvError;
+
+                let store = crate::extensions::ShareContextStore::get();
+                let event = format!("{}{}", super::share_context::SHARE_EVENT_PREFIX, key);
+                let mut rx = store.broadcast_tx.subscribe();
+
+                let wait = async {
+                    loop {
+                        match rx.recv().await {
+                            Ok((ev, value)) if ev == event => {
+                                return serde_json::json!({ "key": key, "value": value });
+                            }
+                            Ok(_) => continue,
+                            // Our write may be among the dropped ones; report the current value
+                            Err(RecvError::Lagged(_)) => {
+                                let value = store.kv.get(&key).map(|v| v.clone()).unwrap_or(Value::Null);
+                                return serde_json::json!({ "key": key, "value": value });
+                            }
+                            Err(RecvError::Closed) => {
+                                return serde_json::json!({ "error": "share channel closed" });
+                            }
+                        }
+                    }
+                };
 
-                let mut res = Vec::new();
+                match timeout_ms {
+                    Some(ms) => match tokio::time::timeout(Duration::from_millis(ms), wait).await {
+                        Ok(res) => res,
+                        Err(_) => serde_json::json!({ "key": key, "timeout": true }),
+                    },
+                    None => wait.await,
+                }
+            }
 
-                for op in ops {
-                    res.push(run_async_operation(op).await);
+            // =========================
+            // BATCH
+            // =========================
+            TitanAsyncOp::Batch { ops, fail_fast } => {
+
+                use futures_util::StreamExt;
+
+                let handles: Vec<_> = ops.into_iter().map(|op| tokio::spawn(run_async_operation(op))).collect();
+                let aborts: Vec<_> = handles.iter().map(|h| h.abort_handle()).collect();
+                let mut pending: futures_util::stream::FuturesUnordered<_> =
+                    handles.into_iter().enumerate().map(|(i, h)| async move { (i, h.await) }).collect();
+
+                // Children settle in completion order; the first failure ends a
+                // fail-fast batch and cancels the ones still running
+                let mut res = vec![Value::Null; aborts.len()];
+                while let Some((i, r)) = pending.next().await {
+                    let val = r.unwrap_or_else(|e| serde_json::json!({ "error": e.to_string() }));
+                    if fail_fast {
+                        if let Some(err) = val.get("error") {
+                            aborts.iter().for_each(|a| a.abort());
+                            return serde_json::json!({ "error": err, "index": i });
+                        }
+                    }
+                    res[i] = val;
                 }
 
                 serde_json::Value::Array(res)
@@ -234,7 +234,7 @@ impl TaskRegistry {
 /// All [TitanTask] logs are suppressed in production.
 #[inline]
 fn is_dev() -> bool {
-    std::env::var("TITAN_DEV").unwrap_or_default() == "1"
+    crate::utils::dev_mode()
 }
 
 /// Execute a single TaskJob by dispatching through the V8 RuntimeManager.
@@ -261,17 +261,17 @@ async fn execute_job(job: &TaskJob) -> Result<Value, String> {
         std::time::Duration::from_millis(timeout),
         runtime.execute(
             job.action_name.clone(),
-            "TASK".to_string(),          // method = "TASK" (distinguishable from HTTP)
-            format!("/__task/{}", job.key), // path for logging
-            body_bytes,
-            smallvec![                   // headers: mark as internal task
-                ("x-titan-task".to_string(), "1".to_string()),
-                ("content-type".to_string(), "application/json".to_string()),
-            ],
-            smallvec![],                 // params
-            smallvec![],                 // query
+            crate::runtime::RequestContext {
+                method: "TASK".to_string(),           // distinguishable from HTTP
+                path: format!("/__task/{}", job.key), // for logging
+                body: body_bytes,
+                headers: smallvec![                   // mark as internal task
+                    ("x-titan-task".to_string(), "1".to_string()),
+                    ("content-type".to_string(), "application/json".to_string()),
+                ],
+                ..Default::default()
+            },
         ),
-    )
     .await;
 
     match result {
@@ -279,8 +279,10 @@ async fn execute_job(job: &TaskJob) -> Result<Value, String> {
             // Check for action-level errors
             if let Some(err) = json.get("error") {
                 Err(err.as_str().unwrap_or("Unknown action error").to_string())
+            } else if json.get("_isUndefined").is_some() {
+                Ok(serde_json::Value::Null)
             } else {
-                Ok(json)
+                Ok(crate::extensions::expand_raw_json(json))
             }
         }
         Ok(Err(e)) => Err(e),
@@ -1,7 +1,12 @@
 use v8;
 use crate::extensions::{v8_to_string, WS_CHANNELS};
+use super::system::replaying;
 
 pub fn native_ws_send(scope: &mut v8::HandleScope, args: v8::FunctionCallbackArguments, mut _retval: v8::ReturnValue) {
+    // Sent before the drift a replay is catching up to
+    if replaying(scope) {
+        return;
+    }
     let id = v8_to_string(scope, args.get(0));
     let msg = v8_to_string(scope, args.get(1));
     
@@ -13,6 +18,9 @@ pub fn native_ws_send(scope: &mut v8::HandleScope, args: v8::FunctionCallbackArg
 }
 
 pub fn native_ws_broadcast(scope: &mut v8::HandleScope, args: v8::FunctionCallbackArguments, mut _retval: v8::ReturnValue) {
+    if replaying(scope) {
+        return;
+    }
     let msg = v8_to_string(scope, args.get(0));
     
     if let Some(channels) = WS_CHANNELS.get() {
@@ -18,6 +18,20 @@ pub struct Registry {
     pub extensions: HashMap<String, ExtensionDef>,
     pub allow_wasm: bool,
     pub allow_native: HashSet<String>,
+    /// Middleware hooks in registration (scan) order.
+    pub hooks: Vec<ExtensionHooks>,
+}
+
+/// `titan.json` `hooks: { before, after }` — names of functions on `t.<extension>`
+/// run around every action.
+#[derive(Clone, serde::Deserialize, serde::Serialize)]
+pub struct ExtensionHooks {
+    #[serde(default)]
+    pub extension: String,
+    #[serde(default)]
+    pub before: Option<String>,
+    #[serde(default)]
+    pub after: Option<String>,
 }
 
 #[derive(Clone)]
@@ -27,6 +41,8 @@ pub struct ExtensionDef {
     pub entry_js: String,
     pub wasm_path: Option<PathBuf>,
     pub native_path: Option<PathBuf>,
+    /// `native.free`: symbol the native host calls to release returned strings
+    pub native_free: Option<String>,
 }
 
 #[derive(serde::Deserialize)]
@@ -40,6 +56,8 @@ struct TitanJson {
     wasm: serde_json::Value,
     #[serde(default)]
     native: serde_json::Value,
+    #[serde(default)]
+    hooks: Option<ExtensionHooks>,
 }
 
 fn default_type() -> String { "js".to_string() }
@@ -60,6 +78,7 @@ struct TanfigJson {
 
 pub fn load_project_extensions(mut root: PathBuf) {
     let mut extensions = HashMap::new();
+    let mut hooks = Vec::new();
     
     // Heuristic: if we are in 'dist', the actual project root is parent
     if root.ends_with("dist") {
@@ -135,6 +154,8 @@ pub fn load_project_extensions(mut root: PathBuf) {
                     path
                 } else { None };
 
+                let native_free = config.native.get("free").and_then(|v| v.as_str()).map(String::from);
+
                 let final_type = if is_native {
                     "native"
                 } else if is_wasm {
@@ -149,19 +170,36 @@ pub fn load_project_extensions(mut root: PathBuf) {
                     entry_js,
                     wasm_path,
                     native_path,
+                    native_free,
                 });
 
+                if let Some(mut h) = config.hooks {
+                    h.extension = config.name.clone();
+                    hooks.push(h);
+                }
+
                 println!("{} {} {} [{}]", blue("[Gravity]"), green("Loaded:"), config.name, final_type.to_uppercase());
             }
         }
     }
 
-    *REGISTRY.lock().unwrap() = Some(Registry { extensions, allow_wasm, allow_native });
+    *REGISTRY.lock().unwrap() = Some(Registry { extensions, allow_wasm, allow_native, hooks });
+}
+
+/// Names of the extensions registered by `load_project_extensions`, sorted.
+pub fn loaded_extension_names() -> Vec<String> {
+    let mut names: Vec<String> = REGISTRY
+        .lock()
+        .ok()
+        .and_then(|guard| guard.as_ref().map(|r| r.extensions.keys().cloned().collect()))
+        .unwrap_or_default();
+    names.sort();
+    names
 }
 
 pub fn inject_external_extensions(scope: &mut v8::HandleScope, _global: v8::Local<v8::Object>, t_obj: v8::Local<v8::Object>) {
-    let registry = if let Ok(guard) = REGISTRY.lock() {
-        guard.as_ref().map(|r| r.extensions.clone()).unwrap_or_default()
+    let (registry, hooks) = if let Ok(guard) = REGISTRY.lock() {
+        guard.as_ref().map(|r| (r.extensions.clone(), r.hooks.clone())).unwrap_or_default()
     } else { return; };
 
     // Inject __native helper for t.__native.call/call_meta
@@ -211,6 +249,30 @@ pub fn inject_external_extensions(scope: &mut v8::HandleScope, _global: v8::Loca
              script.run(tc);
         }
     }
+
+    // Resolve hook names against the now-populated t.<extension> objects.
+    // defineAction's wrapper runs __titan_hooks around every action.
+    let hooks_json = serde_json::to_string(&hooks).unwrap_or_else(|_| "[]".to_string());
+    let hooks_script = format!(
+        "globalThis.__titan_hooks = {}.map(h => {{
+            const ext = t[h.extension] || {{}};
+            const pick = (name) => {{
+                if (!name) return null;
+                if (typeof ext[name] !== 'function') {{
+                    t.log('[Gravity] Extension ' + h.extension + ' declares hook ' + name + ' but does not export it');
+                    return null;
+                }}
+                return ext[name].bind(ext);
+            }};
+            return {{ extension: h.extension, before: pick(h.before), after: pick(h.after) }};
+        }}).filter(h => h.before || h.after);",
+        hooks_json
+    );
+    let hooks_str = v8_str(scope, &hooks_script);
+    let tc = &mut v8::TryCatch::new(scope);
+    if let Some(script) = v8::Script::compile(tc, hooks_str, None) {
+        script.run(tc);
+    }
 }
 
 /// DEFAULT: Synchronous Native Call (No Replay/Suspension)
@@ -11,7 +11,7 @@ pub mod builtins;
 pub mod external;
 pub mod native_host_bridge;
 
-use crate::utils::{blue, gray, green, red};
+use crate::utils::{blue, gray, green, red, LogLevel};
 use bytes::Bytes;
 use crossbeam::channel::Sender;
 use dashmap::DashMap;
@@ -20,6 +20,7 @@ use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::path::PathBuf;
 use std::sync::Once;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex, OnceLock};
 use tokio::sync::broadcast;
 use v8;
@@ -30,10 +31,67 @@ pub static SHARE_CONTEXT: OnceLock<ShareContextStore> = OnceLock::new();
 pub static WS_CHANNELS: OnceLock<Arc<DashMap<String, tokio::sync::mpsc::UnboundedSender<crate::WsMessage>>>> = OnceLock::new();
 pub static PROJECT_ROOT: OnceLock<PathBuf> = OnceLock::new();
 pub static TASK_RUNTIME: OnceLock<Arc<crate::runtime::RuntimeManager>> = OnceLock::new();
+/// Bodies of streamed responses (`t.response.jsonArray`), keyed by the
+/// `_stream` id of the response head sent ahead of them.
+pub static RESPONSE_STREAMS: OnceLock<DashMap<u64, ResponseStream>> = OnceLock::new();
+
+/// Trailers of a streamed response (`t.response.trailer`). Filled in when the
+/// action finishes, before the body's channel closes.
+pub type StreamTrailers = Arc<Mutex<Vec<(String, String)>>>;
+
+/// The receiving half of a streamed response body.
+pub struct ResponseStream {
+    pub rx: tokio::sync::mpsc::Receiver<Bytes>,
+    /// Read once `rx` has ended
+    pub trailers: StreamTrailers,
+}
+
+pub fn response_streams() -> &'static DashMap<u64, ResponseStream> {
+    RESPONSE_STREAMS.get_or_init(DashMap::new)
+}
+
+/// Claim the body of a streamed response; the server calls this once per `_stream` id.
+pub fn take_response_stream(id: u64) -> Option<ResponseStream> {
+    response_streams().remove(&id).map(|(_, stream)| stream)
+}
+
+/// A response held by the server's per-route cache (`__config.cache`). Keyed
+/// by path and query string so `t.cache.invalidate(prefix)` can drop entries
+/// from inside an action.
+pub struct CachedResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Bytes,
+    pub expires: std::time::Instant,
+}
+
+pub static RESPONSE_CACHE: OnceLock<DashMap<String, Arc<CachedResponse>>> = OnceLock::new();
+
+pub fn response_cache() -> &'static DashMap<String, Arc<CachedResponse>> {
+    RESPONSE_CACHE.get_or_init(DashMap::new)
+}
+
+/// Bundle file behind each action, registered by the server at startup so an
+/// isolate can recompile one that failed to load (`retry_failed_load`).
+pub static ACTION_SOURCES: OnceLock<DashMap<String, PathBuf>> = OnceLock::new();
+
+pub fn action_sources() -> &'static DashMap<String, PathBuf> {
+    ACTION_SOURCES.get_or_init(DashMap::new)
+}
+
+/// `__config.compileRetryMs`: least time between recompiles of a failed
+/// bundle on one isolate; 0 turns retries off.
+static COMPILE_RETRY_MS: AtomicU64 = AtomicU64::new(1000);
+
+pub fn set_compile_retry_interval(ms: u64) {
+    COMPILE_RETRY_MS.store(ms, Ordering::Relaxed);
+}
 
 pub struct ShareContextStore {
     pub kv: DashMap<String, serde_json::Value>,
     pub broadcast_tx: broadcast::Sender<(String, serde_json::Value)>,
+    /// `t.cache` entries with their expiry, shared by every isolate
+    pub cache: DashMap<String, (serde_json::Value, std::time::Instant)>,
 }
 
 impl ShareContextStore {
@@ -43,6 +101,7 @@ impl ShareContextStore {
             Self {
                 kv: DashMap::new(),
                 broadcast_tx: tx,
+                cache: DashMap::new(),
             }
         })
     }
@@ -61,6 +120,8 @@ pub enum TitanAsyncOp {
         method: String,
         body: Option<String>,
         headers: Vec<(String, String)>,
+        /// Reject responses larger than this instead of buffering them
+        max_body_bytes: Option<u64>,
     },
     DbQuery {
         conn: String,
@@ -68,22 +129,39 @@ pub enum TitanAsyncOp {
         params: Vec<serde_json::Value>,
         pool_timeout: Option<u64>,
         query_timeout: Option<u64>,
+        /// Server-side `statement_timeout` (ms) for this query only
+        statement_timeout: Option<u64>,
     },
     FsRead {
         path: String,
+        /// Return raw bytes (`Uint8Array`) instead of a UTF-8 string
+        binary: bool,
     },
     NativeCall {
         extension: String,
         function: String,
         params: Vec<serde_json::Value>,
     },
-    Batch(Vec<TitanAsyncOp>),
+    /// `t.share.subscribe`: resolves on the next `t.share` write to `key`,
+    /// or with `{ timeout: true }` after `timeout_ms`.
+    ShareWait {
+        key: String,
+        timeout_ms: Option<u64>,
+    },
+    /// Children run concurrently; results keep submission order.
+    /// With `fail_fast`, the first child error fails the whole batch.
+    Batch {
+        ops: Vec<TitanAsyncOp>,
+        fail_fast: bool,
+    },
 }
 
 pub struct WorkerAsyncResult {
     pub drift_id: u32,
     pub result: serde_json::Value,
     pub duration_ms: f64,
+    /// The op lost the race against its drift timeout; `result` is `{ error: "drift timeout" }`
+    pub timed_out: bool,
 }
 
 pub struct AsyncOpRequest {
@@ -91,6 +169,8 @@ pub struct AsyncOpRequest {
     pub drift_id: u32,
     pub request_id: u32,
     pub op_type: String,
+    /// `drift(op, { timeout })`, or the `__config.driftTimeoutMs` default
+    pub timeout: Option<std::time::Duration>,
     pub respond_tx: tokio::sync::oneshot::Sender<WorkerAsyncResult>,
 }
 
@@ -125,11 +205,21 @@ pub struct TitanRuntime {
     // Action metadata: tracks which req fields each action uses
     pub action_field_usage: HashMap<String, Option<HashSet<String>>>,
 
+    // Actions marked `// @titan-no-replay`: drift blocks this worker until the
+    // op completes instead of suspending and replaying the action.
+    pub no_replay_actions: HashSet<String>,
+
+    // Why a bundle that exists failed to load, returned instead of "not found"
+    pub load_errors: HashMap<String, String>,
+    // When each failed bundle was last recompiled, and how many times
+    pub load_retries: HashMap<String, (std::time::Instant, u32)>,
+
     // Async State
     pub async_rx: crossbeam::channel::Receiver<WorkerAsyncResult>,
     pub async_tx: crossbeam::channel::Sender<WorkerAsyncResult>,
     pub pending_drifts: HashMap<u32, v8::Global<v8::PromiseResolver>>,
     pub pending_requests: HashMap<u32, tokio::sync::oneshot::Sender<crate::runtime::WorkerResult>>,
+    /// Last drift id handed out; ids are unique per worker and never reused
     pub drift_counter: u32,
     pub request_counter: u32,
 
@@ -139,18 +229,109 @@ pub struct TitanRuntime {
     pub drift_to_request: HashMap<u32, u32>,
     pub completed_drifts: HashMap<u32, serde_json::Value>,
     pub active_requests: HashMap<u32, RequestData>,
-    pub request_start_counters: HashMap<u32, u32>,
+    /// Drift ids each suspended request has issued, in call order. A replay
+    /// walks this list so the n-th `drift()` call gets the n-th result.
+    pub request_drifts: HashMap<u32, Vec<u32>>,
+    /// Position in the executing request's drift sequence
+    pub drift_seq: usize,
+    /// Completed drifts the current execution still has to replay; while
+    /// non-zero the action is re-running code that already ran once, so
+    /// side effects (`t.log`, WebSocket sends) are suppressed.
+    pub replay_pending: usize,
+    /// Open streamed response bodies by request. Their head has already been
+    /// sent, so a request stays active until the action finishes writing.
+    pub response_streams: HashMap<u32, (tokio::sync::mpsc::Sender<Bytes>, StreamTrailers)>,
+    /// Where `t.response.earlyHints()` sends its 103, by request
+    pub early_hints: HashMap<u32, EarlyHintsSink>,
+    /// Spilled body of the executing request, read by `req.bodyFile.read()`
+    pub body_file: Option<Arc<SpilledBody>>,
+    /// Set by `t._debug_panic()`; the worker panics with it once the action returns
+    pub requested_panic: Option<String>,
+
+    // Global property names present after init + action loading.
+    // Anything else is request-scoped and removed before the next execution.
+    pub baseline_globals: HashSet<String>,
 }
 
 #[derive(Clone)]
 pub struct RequestData {
     pub action_name: String,
     pub body: Option<Bytes>,
+    pub form: Option<Arc<FormData>>,
+    pub body_file: Option<Arc<SpilledBody>>,
+    pub middleware: Option<Arc<[String]>>,
+    pub client: Option<ClientInfo>,
     pub method: String,
     pub path: String,
     pub headers: Vec<(String, String)>,
     pub params: Vec<(String, String)>,
-    pub query: Vec<(String, String)>,
+    /// Strings, arrays for repeated keys, or values typed by the route's `querySchema`
+    pub query: Vec<(String, serde_json::Value)>,
+}
+
+/// Writes a `103 Early Hints` with these `Link` values to the request's
+/// connection, ahead of the final response. Supplied by the server per request;
+/// it ignores calls once the final response has started.
+pub type EarlyHintsSink = Arc<dyn Fn(&[String]) + Send + Sync>;
+
+/// Who sent a request, as resolved by the server; exposed as `req.ip` and `req.protocol`.
+#[derive(Clone, Copy, Debug)]
+pub struct ClientInfo {
+    pub ip: Option<std::net::IpAddr>,
+    pub https: bool,
+}
+
+/// Parsed `multipart/form-data` body, exposed to actions as `req.formData`.
+#[derive(Default)]
+pub struct FormData {
+    pub fields: Vec<(String, String)>,
+    pub files: Vec<FormFile>,
+}
+
+pub struct FormFile {
+    pub name: String,
+    pub filename: String,
+    pub content_type: String,
+    pub bytes: Bytes,
+}
+
+/// A request body larger than `__config.bodySpillBytes`: the first bytes stay
+/// in memory and the rest is in a temp file, removed once the last handle
+/// drops. Exposed to actions as `req.bodyFile`.
+pub struct SpilledBody {
+    pub head: Bytes,
+    pub path: PathBuf,
+    pub len: u64,
+}
+
+impl SpilledBody {
+    /// Up to `length` bytes from `offset`, across memory and the file.
+    pub fn read(&self, offset: u64, length: usize) -> std::io::Result<Vec<u8>> {
+        use std::io::{Read, Seek, SeekFrom};
+
+        let end = self.len.min(offset.saturating_add(length as u64));
+        if offset >= end {
+            return Ok(Vec::new());
+        }
+        let mut out = Vec::with_capacity((end - offset) as usize);
+        let head_len = self.head.len() as u64;
+        if offset < head_len {
+            out.extend_from_slice(&self.head[offset as usize..end.min(head_len) as usize]);
+        }
+        if end > head_len {
+            let from = offset.max(head_len) - head_len;
+            let mut file = fs::File::open(&self.path)?;
+            file.seek(SeekFrom::Start(from))?;
+            file.take(end - head_len - from).read_to_end(&mut out)?;
+        }
+        Ok(out)
+    }
+}
+
+impl Drop for SpilledBody {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
 }
 
 unsafe impl Send for TitanRuntime {}
@@ -165,12 +346,88 @@ impl TitanRuntime {
     /// Load a script into the runtime as a named action.
     /// The script should evaluate to a function or be wrapped in one.
     pub fn load_action(&mut self, name: String, code: String) {
+        match self.compile_action(&name, &code) {
+            Ok(func) => {
+                if has_no_replay_directive(&code) {
+                    self.no_replay_actions.insert(name.clone());
+                }
+                self.load_errors.remove(&name);
+                self.load_retries.remove(&name);
+                self.actions.insert(name, func);
+            }
+            Err(msg) => {
+                if self.id == 0 {
+                    println!("[Gravity] {}", msg);
+                }
+                self.load_errors.insert(name, msg);
+            }
+        }
+    }
+
+    /// Replace an already loaded action with a new build of its bundle.
+    /// On error the previous version stays live.
+    pub fn reload_action(&mut self, name: &str, code: &str) -> Result<(), String> {
+        let func = self.compile_action(name, code)?;
+        if has_no_replay_directive(code) {
+            self.no_replay_actions.insert(name.to_string());
+        } else {
+            self.no_replay_actions.remove(name);
+        }
+        self.load_errors.remove(name);
+        self.load_retries.remove(name);
+        self.actions.insert(name.to_string(), func);
+        Ok(())
+    }
+
+    /// Drop the JS-side state a request's drift replays share (`t.cache.response`
+    /// pins, multipart boundaries). The action's own completion does this; a
+    /// request answered without it (timeout, panic) needs it done here.
+    pub fn forget_request_state(&mut self, request_id: u32) {
+        let context = self.context.clone();
+        let handle_scope = &mut v8::HandleScope::new(&mut self.isolate);
+        let context = v8::Local::new(handle_scope, context);
+        let scope = &mut v8::ContextScope::new(handle_scope, context);
+        forget_request_state_in(scope, request_id);
+    }
+
+    /// Recompile a bundle that failed to load, from its file as it is now, so
+    /// a fixed source or a transient failure recovers without a restart.
+    /// Called per request; runs at most once per `__config.compileRetryMs`.
+    pub fn retry_failed_load(&mut self, name: &str) {
+        let interval = COMPILE_RETRY_MS.load(Ordering::Relaxed);
+        if interval == 0 || !self.load_errors.contains_key(name) {
+            return;
+        }
+        let Some(path) = action_sources().get(name).map(|p| p.value().clone()) else {
+            return;
+        };
+        let attempt = match self.load_retries.get(name) {
+            Some((at, _)) if at.elapsed() < std::time::Duration::from_millis(interval) => return,
+            Some((_, n)) => n + 1,
+            None => 1,
+        };
+        self.load_retries.insert(name.to_string(), (std::time::Instant::now(), attempt));
+
+        let result = std::fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read action '{}' from {}: {}", name, path.display(), e))
+            .and_then(|code| self.reload_action(name, &code));
+        match result {
+            Ok(()) => log_compile_retry(self.id, name, attempt, LogLevel::Info, "compiled, action restored"),
+            Err(msg) => {
+                log_compile_retry(self.id, name, attempt, LogLevel::Warn, &msg);
+                self.load_errors.insert(name.to_string(), msg);
+            }
+        }
+    }
+
+    /// Evaluate an action bundle into its (wrapped) action function.
+    fn compile_action(&mut self, name: &str, code: &str) -> Result<v8::Global<v8::Function>, String> {
         let handle_scope = &mut v8::HandleScope::new(&mut self.isolate);
         let context = v8::Local::new(handle_scope, &self.context);
         let scope = &mut v8::ContextScope::new(handle_scope, context);
 
         // Advanced transform: Handle 'export default', 'module.exports', or a function named 'name'
-        let mut processed_code = code.clone();
+        let mut processed_code = code.to_string();
         let mut has_export = false;
         
         if processed_code.contains("export default") {
@@ -205,6 +462,10 @@ impl TitanRuntime {
                 if (typeof raw === 'function') {{
                     return defineAction(raw);
                 }}
+                // WebSocket actions exporting onOpen / onMessage / onClose
+                if (raw && ['onOpen', 'onMessage', 'onClose'].some(k => typeof raw[k] === 'function')) {{
+                    return defineAction(globalThis.__titan_ws_handlers(raw));
+                }}
                 return raw;
             }})()
             "#,
@@ -216,19 +477,136 @@ impl TitanRuntime {
         let source_str = v8_str(scope, &wrapper);
         let try_catch = &mut v8::TryCatch::new(scope);
 
-        if let Some(script) = v8::Script::compile(try_catch, source_str, None) {
+        let result = if let Some(script) = v8::Script::compile(try_catch, source_str, None) {
             if let Some(val) = script.run(try_catch) {
-                if val.is_function() {
-                    let func = v8::Local::<v8::Function>::try_from(val).unwrap();
-                    self.actions.insert(name, v8::Global::new(try_catch, func));
+                match v8::Local::<v8::Function>::try_from(val) {
+                    Ok(func) => Ok(v8::Global::new(try_catch, func)),
+                    Err(_) => Err(missing_export_message(try_catch, name, val)),
                 }
-            } else if self.id == 0 {
+            } else {
                 let msg = try_catch.message().map(|m| m.get(try_catch).to_rust_string_lossy(try_catch)).unwrap_or("Unknown".to_string());
-                println!("[Gravity] Failed to run action '{}': {}", name, msg);
+                Err(format!("Failed to run action '{}': {}", name, msg))
             }
-        } else if self.id == 0 {
+        } else {
             let msg = try_catch.message().map(|m| m.get(try_catch).to_rust_string_lossy(try_catch)).unwrap_or("Unknown".to_string());
-            println!("[Gravity] Failed to compile action '{}': {}", name, msg);
+            Err(format!("Failed to compile action '{}': {}", name, msg))
+        };
+
+        // Globals created while loading a bundle are module-level state, not request leaks
+        let global = context.global(try_catch);
+        self.baseline_globals.extend(global_property_names(try_catch, global));
+        result
+    }
+}
+
+/// Whether `code` carries `// @titan-no-replay` among the blank lines and
+/// line comments before its first statement; the text anywhere else (a string,
+/// a later comment) doesn't count.
+fn has_no_replay_directive(code: &str) -> bool {
+    for line in code.lines().map(str::trim) {
+        if line.is_empty() {
+            continue;
+        }
+        let Some(comment) = line.strip_prefix("//") else {
+            return false;
+        };
+        if let Some(rest) = comment.trim_start().strip_prefix("@titan-no-replay") {
+            if !rest.starts_with(|c: char| c.is_alphanumeric() || c == '_') {
+                return true;
+            }
+        }
+    }
+    false
+}
+
+/// [`TitanRuntime::forget_request_state`] from inside a native call, through
+/// the scope already open there.
+pub(crate) fn forget_request_state_in(scope: &mut v8::HandleScope, request_id: u32) {
+    let global = scope.get_current_context().global(scope);
+    let key = v8_str(scope, "__titan_forget_request");
+    let Some(forget) = global.get(scope, key.into()).and_then(|f| v8::Local::<v8::Function>::try_from(f).ok()) else {
+        return;
+    };
+    let id = v8::Integer::new_from_unsigned(scope, request_id);
+    let try_catch = &mut v8::TryCatch::new(scope);
+    let _ = forget.call(try_catch, global.into(), &[id.into()]);
+}
+
+/// Explain a bundle that ran but produced no function for `name`, listing the
+/// functions it does export so a name mismatch (`handler` vs `home`) is obvious.
+fn missing_export_message(scope: &mut v8::HandleScope, name: &str, exported: v8::Local<v8::Value>) -> String {
+    let mut functions = Vec::new();
+    if let Some(obj) = exported.to_object(scope).filter(|_| exported.is_object()) {
+        if let Some(props) = obj.get_own_property_names(scope, v8::GetPropertyNamesArgs::default()) {
+            for i in 0..props.length() {
+                let Some(key) = props.get_index(scope, i) else { continue };
+                if obj.get(scope, key).is_some_and(|v| v.is_function()) {
+                    functions.push(key.to_rust_string_lossy(scope));
+                }
+            }
+        }
+    }
+    if functions.is_empty() {
+        format!(
+            "Action '{}' does not export a function (expected a default export, module.exports or a function named '{}')",
+            name, name
+        )
+    } else {
+        format!("Action '{}' does not export a function named '{}'; the bundle exports: {}", name, name, functions.join(", "))
+    }
+}
+
+/// Every own string key of the global object, like `Object.getOwnPropertyNames`:
+/// non-enumerable ones too, so `Object.defineProperty(globalThis, ...)` can't
+/// hide a leaked global from the reset.
+fn own_global_keys<'s>(
+    scope: &mut v8::HandleScope<'s>,
+    global: v8::Local<v8::Object>,
+) -> Option<v8::Local<'s, v8::Array>> {
+    let args = v8::GetPropertyNamesArgs {
+        property_filter: v8::PropertyFilter::SKIP_SYMBOLS,
+        key_conversion: v8::KeyConversionMode::ConvertToString,
+        ..Default::default()
+    };
+    global.get_own_property_names(scope, args)
+}
+
+/// [`own_global_keys`] as strings, for the baseline snapshot.
+fn global_property_names(
+    scope: &mut v8::HandleScope,
+    global: v8::Local<v8::Object>,
+) -> Vec<String> {
+    let mut names = Vec::new();
+    if let Some(props) = own_global_keys(scope, global) {
+        for i in 0..props.length() {
+            if let Some(key) = props.get_index(scope, i) {
+                names.push(key.to_rust_string_lossy(scope));
+            }
+        }
+    }
+    names
+}
+
+/// Remove globals added since the baseline snapshot (e.g. `globalThis.leak = 1`
+/// from a previous request on this isolate).
+///
+/// Cost is one enumeration of the global object per execution (~1-3µs with a
+/// typical global count) — far cheaper than a fresh Context per request, which
+/// would also re-run the core JS bootstrap and re-wrap every action. Only
+/// *added* globals are removed; reassigning an existing global is not reverted.
+fn reset_request_globals(
+    scope: &mut v8::HandleScope,
+    global: v8::Local<v8::Object>,
+    baseline: &HashSet<String>,
+) {
+    let Some(props) = own_global_keys(scope, global) else {
+        return;
+    };
+    for i in 0..props.length() {
+        if let Some(key) = props.get_index(scope, i) {
+            if !baseline.contains(&key.to_rust_string_lossy(scope)) {
+                global.delete(scope, key);
+            }
         }
     }
 }
@@ -260,7 +638,7 @@ pub fn init_runtime_worker(
     let params = v8::CreateParams::default();
     let mut isolate = v8::Isolate::new(params);
 
-    let (global_context, actions_map, interned) = {
+    let (global_context, actions_map, interned, baseline_globals) = {
         let handle_scope = &mut v8::HandleScope::new(&mut isolate);
         let context = v8::Context::new(handle_scope, v8::ContextOptions::default());
         let scope = &mut v8::ContextScope::new(handle_scope, context);
@@ -297,9 +675,11 @@ pub fn init_runtime_worker(
             titan_action: v8::Global::new(scope, s_titan_action),
         };
 
+        let baseline: HashSet<String> = global_property_names(scope, global).into_iter().collect();
+
         // Load Actions
         let map = HashMap::new();
-        (v8::Global::new(scope, context), map, interned)
+        (v8::Global::new(scope, context), map, interned, baseline)
     };
 
     let (async_tx, async_rx) = crossbeam::channel::unbounded();
@@ -312,6 +692,9 @@ pub fn init_runtime_worker(
         worker_tx,
         interned_keys: Some(interned),
         action_field_usage: HashMap::new(),
+        no_replay_actions: HashSet::new(),
+        load_errors: HashMap::new(),
+        load_retries: HashMap::new(),
         async_rx,
         async_tx,
         pending_drifts: HashMap::new(),
@@ -324,7 +707,14 @@ pub fn init_runtime_worker(
         drift_to_request: HashMap::new(),
         completed_drifts: HashMap::new(),
         active_requests: HashMap::new(),
-        request_start_counters: HashMap::new(),
+        request_drifts: HashMap::new(),
+        drift_seq: 0,
+        replay_pending: 0,
+        response_streams: HashMap::new(),
+        early_hints: HashMap::new(),
+        body_file: None,
+        requested_panic: None,
+        baseline_globals,
     }
 }
 
@@ -346,47 +736,221 @@ pub fn inject_extensions(scope: &mut v8::HandleScope, global: v8::Local<v8::Obje
     global.set(scope, t_key.into(), t_obj.into());
 }
 
+fn log_compile_retry(isolate: usize, action: &str, attempt: u32, level: LogLevel, message: &str) {
+    crate::utils::log_with(
+        level,
+        "compile_retry",
+        serde_json::json!({ "isolate": isolate, "action": action, "attempt": attempt, "message": message }),
+        || format!("[Isolate {}] Retry {} of action '{}': {}", isolate, attempt, action, message),
+    );
+}
+
 // V8 ↔ JSON CONVERSION (Optimized)
 
+/// `__config.maxResultDepth`: how deeply a value may nest before serialization
+/// gives up, so a runaway structure fails the request instead of overflowing
+/// the worker's stack.
+static MAX_JSON_DEPTH: AtomicUsize = AtomicUsize::new(256);
+
+/// serde_json refuses to parse past this depth, so deeper values always take
+/// the recursive path.
+const SERDE_DEPTH_LIMIT: usize = 128;
+
+pub fn set_max_json_depth(depth: usize) {
+    MAX_JSON_DEPTH.store(depth.max(1), Ordering::Relaxed);
+}
+
 /// Convert a V8 value to serde_json::Value.
-/// Uses JSON.stringify for objects (V8-native, faster than recursive extraction).
+/// Uses JSON.stringify for objects (V8-native, faster than recursive extraction);
+/// `Map` and `Set` serialize as titan_core.js's `__titan_json_replacer` shapes them.
+/// `Date` becomes an ISO-8601 string, as `JSON.stringify` would emit.
+/// Integral numbers stay integers and a `BigInt` keeps every digit (see
+/// `number_to_json` / `bigint_to_json`).
+/// A value that is too deep or circular becomes `null`; use
+/// [`try_v8_to_json`] where that has to be reported.
 #[inline]
 pub fn v8_to_json<'s>(
     scope: &mut v8::HandleScope<'s>,
     value: v8::Local<v8::Value>,
 ) -> serde_json::Value {
+    try_v8_to_json(scope, value).unwrap_or(serde_json::Value::Null)
+}
+
+/// [`v8_to_json`], failing on values nested deeper than `__config.maxResultDepth`
+/// or containing a circular reference.
+pub fn try_v8_to_json<'s>(
+    scope: &mut v8::HandleScope<'s>,
+    value: v8::Local<v8::Value>,
+) -> Result<serde_json::Value, String> {
     if value.is_null_or_undefined() {
-        return serde_json::Value::Null;
+        return Ok(serde_json::Value::Null);
     }
 
     if value.is_boolean() {
-        return serde_json::Value::Bool(value.boolean_value(scope));
+        return Ok(serde_json::Value::Bool(value.boolean_value(scope)));
     }
 
     if value.is_number() {
-        let n = value.number_value(scope).unwrap_or(0.0);
-        return serde_json::Value::Number(
-            serde_json::Number::from_f64(n).unwrap_or_else(|| serde_json::Number::from(0)),
-        );
+        return Ok(number_to_json(value.number_value(scope).unwrap_or(0.0)));
+    }
+    if value.is_big_int() {
+        return Ok(bigint_to_json(scope, value));
     }
 
     if value.is_string() {
         let s = value.to_string(scope).unwrap().to_rust_string_lossy(scope);
-        return serde_json::Value::String(s);
+        return Ok(serde_json::Value::String(s));
+    }
+
+    if value.is_date() {
+        return Ok(date_to_json(value));
     }
 
-    // For arrays and objects: use V8's native JSON.stringify
+    let max_depth = MAX_JSON_DEPTH.load(Ordering::Relaxed);
+
+    // For arrays and objects: use V8's native JSON.stringify. It throws on
+    // cycles and on stack exhaustion, which the recursive path then reports.
     if value.is_object() || value.is_array() {
-        if let Some(json_str) = v8::json::stringify(scope, value) {
-            let rust_str = json_str.to_rust_string_lossy(scope);
-            if let Ok(parsed) = serde_json::from_str(&rust_str) {
-                return parsed;
+        if let Some(rust_str) = stringify_result(scope, value) {
+            if let Ok(parsed) = serde_json::from_str::<serde_json::Value>(&rust_str) {
+                if max_depth >= SERDE_DEPTH_LIMIT || json_depth(&parsed) <= max_depth {
+                    return Ok(parsed);
+                }
+                return Err(depth_error(max_depth));
             }
         }
-        return v8_to_json_recursive(scope, value);
+        let scope = &mut v8::HandleScope::new(scope);
+        let value = v8::Local::new(scope, value);
+        return v8_to_json_recursive(scope, value, &mut Vec::new(), max_depth);
     }
 
-    serde_json::Value::Null
+    Ok(serde_json::Value::Null)
+}
+
+/// The `JSON.stringify` text of an object or array result bound straight for
+/// the HTTP body, checked against `__config.maxResultDepth` on the text itself
+/// so no `serde_json::Value` tree is built. `None` when stringify fails (cycles,
+/// stack exhaustion) or yields no object/array; [`try_v8_to_json`] reports why.
+pub fn v8_to_json_text<'s>(
+    scope: &mut v8::HandleScope<'s>,
+    value: v8::Local<v8::Value>,
+) -> Option<Result<String, String>> {
+    let text = stringify_result(scope, value)?;
+    if !text.starts_with(['{', '[']) {
+        return None;
+    }
+    let max_depth = MAX_JSON_DEPTH.load(Ordering::Relaxed);
+    if text_depth(&text) > max_depth {
+        return Some(Err(depth_error(max_depth)));
+    }
+    Some(Ok(text))
+}
+
+/// `JSON.stringify(value)`. Text with an empty object, which a `Map` or `Set`
+/// may have become, is redone with titan_core.js's `__titan_json_replacer`;
+/// anything else keeps V8's replacer-free fast path. `None` when it throws.
+fn stringify_result<'s>(scope: &mut v8::HandleScope<'s>, value: v8::Local<v8::Value>) -> Option<String> {
+    let tc = &mut v8::TryCatch::new(scope);
+    let text = v8::json::stringify(tc, value)?.to_rust_string_lossy(tc);
+    if !text.contains("{}") {
+        return Some(text);
+    }
+    let global = tc.get_current_context().global(tc);
+    let json_key = v8_str(tc, "JSON");
+    let stringify_key = v8_str(tc, "stringify");
+    let replacer_key = v8_str(tc, "__titan_json_replacer");
+    let stringify = global
+        .get(tc, json_key.into())
+        .and_then(|json| json.to_object(tc))
+        .and_then(|json| json.get(tc, stringify_key.into()))
+        .and_then(|f| v8::Local::<v8::Function>::try_from(f).ok());
+    let replacer = global.get(tc, replacer_key.into()).filter(|r| r.is_function());
+    let (Some(stringify), Some(replacer)) = (stringify, replacer) else {
+        return Some(text);
+    };
+    let undefined = v8::undefined(tc).into();
+    let redone = stringify.call(tc, undefined, &[value, replacer])?;
+    redone.is_string().then(|| redone.to_rust_string_lossy(tc))
+}
+
+/// A result carrying `_rawJson` text (see [`v8_to_json_text`]) as the value it
+/// encodes, for callers that inspect results rather than send them.
+pub fn expand_raw_json(result: serde_json::Value) -> serde_json::Value {
+    match result.get("_rawJson").and_then(|v| v.as_str()) {
+        Some(text) => serde_json::from_str(text).unwrap_or(serde_json::Value::Null),
+        None => result,
+    }
+}
+
+/// Nesting depth of JSON text, ignoring brackets inside strings.
+fn text_depth(text: &str) -> usize {
+    let (mut depth, mut max) = (0usize, 0usize);
+    let (mut in_string, mut escaped) = (false, false);
+    for b in text.bytes() {
+        if in_string {
+            match b {
+                _ if escaped => escaped = false,
+                b'\\' => escaped = true,
+                b'"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match b {
+            b'"' => in_string = true,
+            b'{' | b'[' => {
+                depth += 1;
+                max = max.max(depth);
+            }
+            b'}' | b']' => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+    }
+    max
+}
+
+/// Integral values within i64 range become integers (`42`, not `42.0`), as
+/// in fast_path's `number_to_json`. A JS number past 2^53 has already lost
+/// precision before it gets here; return such IDs as `BigInt` or strings.
+/// NaN and ±Infinity become `0`.
+fn number_to_json(n: f64) -> serde_json::Value {
+    if n.fract() == 0.0 && n >= i64::MIN as f64 && n < i64::MAX as f64 {
+        return serde_json::Value::Number((n as i64).into());
+    }
+    serde_json::Value::Number(serde_json::Number::from_f64(n).unwrap_or_else(|| serde_json::Number::from(0)))
+}
+
+/// A `BigInt` as an exact integer when it fits i64 or u64, otherwise its
+/// decimal string.
+fn bigint_to_json(scope: &mut v8::HandleScope, value: v8::Local<v8::Value>) -> serde_json::Value {
+    let Ok(big) = v8::Local::<v8::BigInt>::try_from(value) else {
+        return serde_json::Value::Null;
+    };
+    match (big.i64_value(), big.u64_value()) {
+        ((n, true), _) => serde_json::Value::Number(n.into()),
+        (_, (n, true)) => serde_json::Value::Number(n.into()),
+        _ => serde_json::Value::String(value.to_rust_string_lossy(scope)),
+    }
+}
+
+fn depth_error(max_depth: usize) -> String {
+    format!("Result nests deeper than {} levels (__config.maxResultDepth)", max_depth)
+}
+
+/// Nesting depth of a parsed value, without recursing.
+fn json_depth(value: &serde_json::Value) -> usize {
+    let mut max = 0;
+    let mut stack = vec![(value, 0usize)];
+    while let Some((v, depth)) = stack.pop() {
+        let children: Box<dyn Iterator<Item = &serde_json::Value>> = match v {
+            serde_json::Value::Array(a) => Box::new(a.iter()),
+            serde_json::Value::Object(o) => Box::new(o.values()),
+            _ => continue,
+        };
+        max = max.max(depth + 1);
+        stack.extend(children.map(|c| (c, depth + 1)));
+    }
+    max
 }
 
 /// Convert a serde_json::Value to v8::Local<v8::Value>.
@@ -420,25 +984,78 @@ pub fn json_to_v8<'s>(
 }
 
 /// Recursive fallback for v8_to_json (used when JSON.stringify fails).
+/// `ancestors` holds the containers on the current path, for cycle detection;
+/// its length is the current depth.
 fn v8_to_json_recursive<'s>(
     scope: &mut v8::HandleScope<'s>,
-    value: v8::Local<v8::Value>,
-) -> serde_json::Value {
+    value: v8::Local<'s, v8::Value>,
+    ancestors: &mut Vec<v8::Local<'s, v8::Value>>,
+    max_depth: usize,
+) -> Result<serde_json::Value, String> {
     if value.is_null_or_undefined() {
-        return serde_json::Value::Null;
+        return Ok(serde_json::Value::Null);
     }
     if value.is_boolean() {
-        return serde_json::Value::Bool(value.boolean_value(scope));
+        return Ok(serde_json::Value::Bool(value.boolean_value(scope)));
     }
     if value.is_number() {
-        let n = value.number_value(scope).unwrap_or(0.0);
-        return serde_json::Value::Number(
-            serde_json::Number::from_f64(n).unwrap_or_else(|| serde_json::Number::from(0)),
-        );
+        return Ok(number_to_json(value.number_value(scope).unwrap_or(0.0)));
+    }
+    if value.is_big_int() {
+        return Ok(bigint_to_json(scope, value));
     }
     if value.is_string() {
         let s = value.to_string(scope).unwrap().to_rust_string_lossy(scope);
-        return serde_json::Value::String(s);
+        return Ok(serde_json::Value::String(s));
+    }
+
+    if value.is_date() {
+        return Ok(date_to_json(value));
+    }
+
+    if !value.is_object() {
+        return Ok(serde_json::Value::Null);
+    }
+    if ancestors.iter().any(|a| a.strict_equals(value)) {
+        return Err("Result contains a circular reference".to_string());
+    }
+    if ancestors.len() >= max_depth {
+        return Err(depth_error(max_depth));
+    }
+
+    ancestors.push(value);
+    let out = v8_container_to_json(scope, value, ancestors, max_depth);
+    ancestors.pop();
+    out
+}
+
+fn v8_container_to_json<'s>(
+    scope: &mut v8::HandleScope<'s>,
+    value: v8::Local<'s, v8::Value>,
+    ancestors: &mut Vec<v8::Local<'s, v8::Value>>,
+    max_depth: usize,
+) -> Result<serde_json::Value, String> {
+    // Same shapes as `__titan_json_replacer` in titan_core.js
+    if value.is_set() {
+        let set = v8::Local::<v8::Set>::try_from(value).unwrap();
+        let items = set.as_array(scope);
+        let mut list = Vec::with_capacity(items.length() as usize);
+        for i in 0..items.length() {
+            let item = items.get_index(scope, i).unwrap_or_else(|| v8::null(scope).into());
+            list.push(v8_to_json_recursive(scope, item, ancestors, max_depth)?);
+        }
+        return Ok(serde_json::Value::Array(list));
+    }
+    if value.is_map() {
+        let entries = v8::Local::<v8::Map>::try_from(value).unwrap().as_array(scope);
+        let mut map = serde_json::Map::new();
+        for i in (0..entries.length()).step_by(2) {
+            let key = entries.get_index(scope, i).unwrap_or_else(|| v8::null(scope).into());
+            let key = key.to_string(scope).map(|k| k.to_rust_string_lossy(scope)).unwrap_or_default();
+            let val = entries.get_index(scope, i + 1).unwrap_or_else(|| v8::null(scope).into());
+            map.insert(key, v8_to_json_recursive(scope, val, ancestors, max_depth)?);
+        }
+        return Ok(serde_json::Value::Object(map));
     }
 
     if value.is_array() {
@@ -448,34 +1065,42 @@ fn v8_to_json_recursive<'s>(
             let element = arr
                 .get_index(scope, i)
                 .unwrap_or_else(|| v8::null(scope).into());
-            list.push(v8_to_json_recursive(scope, element));
+            list.push(v8_to_json_recursive(scope, element, ancestors, max_depth)?);
         }
-        return serde_json::Value::Array(list);
+        return Ok(serde_json::Value::Array(list));
     }
 
-    if value.is_object() {
-        let obj = value.to_object(scope).unwrap();
-        let props = obj
-            .get_own_property_names(scope, v8::GetPropertyNamesArgs::default())
-            .unwrap();
-        let mut map = serde_json::Map::new();
-        for i in 0..props.length() {
-            let key_val = props
-                .get_index(scope, i)
-                .unwrap_or_else(|| v8::null(scope).into());
-            let key = key_val
-                .to_string(scope)
-                .unwrap()
-                .to_rust_string_lossy(scope);
-            let val = obj
-                .get(scope, key_val.into())
-                .unwrap_or_else(|| v8::null(scope).into());
-            map.insert(key, v8_to_json_recursive(scope, val));
-        }
-        return serde_json::Value::Object(map);
+    let obj = value.to_object(scope).unwrap();
+    let props = obj
+        .get_own_property_names(scope, v8::GetPropertyNamesArgs::default())
+        .unwrap();
+    let mut map = serde_json::Map::new();
+    for i in 0..props.length() {
+        let key_val = props
+            .get_index(scope, i)
+            .unwrap_or_else(|| v8::null(scope).into());
+        let key = key_val
+            .to_string(scope)
+            .unwrap()
+            .to_rust_string_lossy(scope);
+        let val = obj
+            .get(scope, key_val.into())
+            .unwrap_or_else(|| v8::null(scope).into());
+        map.insert(key, v8_to_json_recursive(scope, val, ancestors, max_depth)?);
     }
+    Ok(serde_json::Value::Object(map))
+}
 
-    serde_json::Value::Null
+/// `Date.prototype.toJSON`: `toISOString()` in UTC, or `null` for an invalid date.
+fn date_to_json(value: v8::Local<v8::Value>) -> serde_json::Value {
+    let ms = v8::Local::<v8::Date>::try_from(value).unwrap().value_of();
+    if !ms.is_finite() {
+        return serde_json::Value::Null;
+    }
+    match chrono::DateTime::from_timestamp_millis(ms as i64) {
+        Some(dt) => serde_json::Value::String(dt.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string()),
+        None => serde_json::Value::Null,
+    }
 }
 
 // ACTION EXECUTION (Optimized with Pre-Internalized Keys)
@@ -493,18 +1118,30 @@ pub fn execute_action_optimized(
     request_id: u32,
     action_name: &str,
     req_body: Option<bytes::Bytes>,
+    form: Option<&FormData>,
+    body_file: Option<&Arc<SpilledBody>>,
+    middleware: Option<&[String]>,
+    client: Option<&ClientInfo>,
     req_method: &str,
     req_path: &str,
     headers: &[(String, String)],
     params: &[(String, String)],
-    query: &[(String, String)],
+    query: &[(String, serde_json::Value)],
 ) {
     // =========================================================================
     // STEP 1: Extract all data from runtime BEFORE borrowing isolate.
     // v8::Global::clone() is O(1) refcount bump — no V8 heap allocation.
     // =========================================================================
+    if !runtime.load_errors.is_empty() {
+        runtime.retry_failed_load(action_name);
+        for name in middleware.unwrap_or_default() {
+            runtime.retry_failed_load(name);
+        }
+    }
+
     let context_global = runtime.context.clone();
     let actions_map = runtime.actions.clone();
+    runtime.body_file = body_file.cloned();
 
     let ik = runtime.interned_keys.as_ref().unwrap();
     let gk_method = ik.method.clone();
@@ -522,6 +1159,32 @@ pub fn execute_action_optimized(
     let context = v8::Local::new(handle_scope, context_global);
     let scope = &mut v8::ContextScope::new(handle_scope, context);
 
+    // Decode the body by Content-Type up front so a malformed JSON body
+    // is rejected before the action runs
+    let parsed_body = match req_body.as_deref().map(|bytes| parse_body(bytes, headers)) {
+        Some(Err(message)) => {
+            if let Some(tx) = runtime.pending_requests.remove(&request_id) {
+                let error = serde_json::json!({ "error": { "code": "invalid_json", "message": message } });
+                let _ = tx.send(crate::runtime::WorkerResult {
+                    json: serde_json::json!({
+                        "_isResponse": true,
+                        "status": 400,
+                        "headers": { "Content-Type": "application/json" },
+                        "body": error.to_string(),
+                    }),
+                    timings: runtime.request_timings.remove(&request_id).unwrap_or_default(),
+                });
+            }
+            return;
+        }
+        Some(Ok(parsed)) => Some(parsed),
+        None => None,
+    };
+
+    // Isolates are reused: drop globals leaked by previous executions
+    let global = context.global(scope);
+    reset_request_globals(scope, global, &runtime.baseline_globals);
+
     // =========================================================================
     // STEP 2: Build request object with pre-internalized keys.
     // v8::Local::new(scope, &global) is a pointer deref — no allocation.
@@ -547,15 +1210,101 @@ pub fn execute_action_optimized(
 
     // body — attach raw bytes as ArrayBuffer under "rawBody" key
     let rb_key = v8::Local::new(scope, &gk_raw_body);
+    let body_key = v8_str(scope, "body");
     let body_val: v8::Local<v8::Value> = if let Some(bytes) = req_body {
+        let len = bytes.len();
         let backing = v8::ArrayBuffer::new_backing_store_from_vec(bytes.to_vec());
         let ab = v8::ArrayBuffer::with_backing_store(scope, &backing.make_shared());
+        // body — JSON/form/text decoded, anything else a view over rawBody
+        let parsed: v8::Local<v8::Value> = match &parsed_body {
+            Some(ParsedBody::Value(v)) => json_to_v8(scope, v),
+            _ => match v8::Uint8Array::new(scope, ab, 0, len) {
+                Some(view) => view.into(),
+                None => v8::null(scope).into(),
+            },
+        };
+        req_obj.set(scope, body_key.into(), parsed);
         ab.into()
     } else {
+        let empty = v8::Object::new(scope);
+        req_obj.set(scope, body_key.into(), empty.into());
         v8::null(scope).into()
     };
     req_obj.set(scope, rb_key.into(), body_val);
 
+    // bodyFile — { size, read(offset?, length?), text() } for a body spilled to disk;
+    // body stays {} and rawBody null so nothing pulls it back into memory
+    if let Some(file) = body_file {
+        let file_obj = v8::Object::new(scope);
+        let size_key = v8_str(scope, "size");
+        let size_val = v8::Number::new(scope, file.len as f64);
+        file_obj.set(scope, size_key.into(), size_val.into());
+        let read_key = v8_str(scope, "read");
+        let read_fn = v8::Function::new(scope, builtins::system::native_body_read).unwrap();
+        file_obj.set(scope, read_key.into(), read_fn.into());
+        let text_key = v8_str(scope, "text");
+        let text_fn = v8::Function::new(scope, builtins::system::native_body_text).unwrap();
+        file_obj.set(scope, text_key.into(), text_fn.into());
+        let bf_key = v8_str(scope, "bodyFile");
+        req_obj.set(scope, bf_key.into(), file_obj.into());
+    }
+
+    // formData — { fields: {...}, files: [{ name, filename, contentType, bytes }] }
+    if let Some(form) = form {
+        let form_obj = v8::Object::new(scope);
+        let fields_obj = v8::Object::new(scope);
+        for (k, v) in &form.fields {
+            let k_v8 = v8_str(scope, k);
+            let v_v8 = v8_str(scope, v);
+            fields_obj.set(scope, k_v8.into(), v_v8.into());
+        }
+        let fields_key = v8_str(scope, "fields");
+        form_obj.set(scope, fields_key.into(), fields_obj.into());
+
+        let files_arr = v8::Array::new(scope, form.files.len() as i32);
+        for (i, file) in form.files.iter().enumerate() {
+            let file_obj = v8::Object::new(scope);
+            for (key, val) in [("name", &file.name), ("filename", &file.filename), ("contentType", &file.content_type)] {
+                let k_v8 = v8_str(scope, key);
+                let v_v8 = v8_str(scope, val);
+                file_obj.set(scope, k_v8.into(), v_v8.into());
+            }
+            let len = file.bytes.len();
+            let backing = v8::ArrayBuffer::new_backing_store_from_vec(file.bytes.to_vec());
+            let ab = v8::ArrayBuffer::with_backing_store(scope, &backing.make_shared());
+            let bytes_key = v8_str(scope, "bytes");
+            if let Some(u8arr) = v8::Uint8Array::new(scope, ab, 0, len) {
+                file_obj.set(scope, bytes_key.into(), u8arr.into());
+            }
+            files_arr.set_index(scope, i as u32, file_obj.into());
+        }
+        let files_key = v8_str(scope, "files");
+        form_obj.set(scope, files_key.into(), files_arr.into());
+
+        let form_key = v8_str(scope, "formData");
+        req_obj.set(scope, form_key.into(), form_obj.into());
+    }
+
+    // ip / protocol — null / "http" for internal calls (tasks, WebSocket events)
+    let ip_key = v8_str(scope, "ip");
+    let ip_val: v8::Local<v8::Value> = match client.and_then(|c| c.ip) {
+        Some(ip) => v8_str(scope, &ip.to_string()).into(),
+        None => v8::null(scope).into(),
+    };
+    req_obj.set(scope, ip_key.into(), ip_val);
+    let proto_key = v8_str(scope, "protocol");
+    let proto_val = v8_str(scope, if client.is_some_and(|c| c.https) { "https" } else { "http" });
+    req_obj.set(scope, proto_key.into(), proto_val.into());
+
+    // requestId — the server's `x-request-id`; null for tasks and WebSocket events
+    let request_tag = headers.iter().find(|(k, _)| k == "x-request-id").map(|(_, v)| v.as_str());
+    let rid_key = v8_str(scope, "requestId");
+    let rid_val: v8::Local<v8::Value> = match request_tag {
+        Some(id) => v8_str(scope, id).into(),
+        None => v8::null(scope).into(),
+    };
+    req_obj.set(scope, rid_key.into(), rid_val);
+
     // headers
     let h_key = v8::Local::new(scope, &gk_headers);
     let h_obj = v8::Object::new(scope);
@@ -581,13 +1330,35 @@ pub fn execute_action_optimized(
     let q_obj = v8::Object::new(scope);
     for (k, v) in query {
         let k_v8 = v8_str(scope, k);
-        let v_v8 = v8_str(scope, v);
-        q_obj.set(scope, k_v8.into(), v_v8.into());
+        let v_v8 = match v {
+            serde_json::Value::String(v) => v8_str(scope, v).into(),
+            _ => json_to_v8(scope, v),
+        };
+        q_obj.set(scope, k_v8.into(), v_v8);
     }
     req_obj.set(scope, q_key.into(), q_obj.into());
 
+    // Route middleware: the wrapped action functions, run by defineAction before the action
+    if let Some(names) = middleware {
+        let chain = v8::Array::new(scope, names.len() as i32);
+        for (i, name) in names.iter().enumerate() {
+            let Some(mw_global) = actions_map.get(name) else {
+                if let Some(tx) = runtime.pending_requests.remove(&request_id) {
+                    let _ = tx.send(crate::runtime::WorkerResult {
+                        json: serde_json::json!({"error": format!("Middleware '{}' not found", name)}),
+                        timings: runtime.request_timings.remove(&request_id).unwrap_or_default(),
+                    });
+                }
+                return;
+            };
+            let mw_fn = v8::Local::new(scope, mw_global);
+            chain.set_index(scope, i as u32, mw_fn.into());
+        }
+        let chain_key = v8_str(scope, "__titan_middleware");
+        req_obj.define_own_property(scope, chain_key.into(), chain.into(), v8::PropertyAttribute::DONT_ENUM);
+    }
+
     // Set __titan_req on global
-    let global = context.global(scope);
     let req_tr_key = v8::Local::new(scope, &gk_titan_req);
     global.set(scope, req_tr_key.into(), req_obj.into());
 
@@ -617,23 +1388,96 @@ pub fn execute_action_optimized(
             return;
         }
 
-        println!("[Isolate {}] Action Error: {}", runtime.id, msg);
+        crate::utils::log_with(
+            LogLevel::Error,
+            "isolate",
+            serde_json::json!({ "isolate": runtime.id, "action": action_name, "requestId": request_tag, "error": msg }),
+            || match request_tag {
+                Some(id) => format!("[Isolate {}] Action Error [{}]: {}", runtime.id, id, msg),
+                None => format!("[Isolate {}] Action Error: {}", runtime.id, msg),
+            },
+        );
         if let Some(tx) = runtime.pending_requests.remove(&request_id) {
             let _ = tx.send(crate::runtime::WorkerResult {
                 json: serde_json::json!({"error": msg}),
-                timings: vec![],
+                timings: runtime.request_timings.remove(&request_id).unwrap_or_default(),
             });
         }
     } else {
+        // No bundle was loaded under this name: missing from dist/actions, or it failed to load
+        let error = runtime.load_errors.get(action_name).map_or("Action bundle not found", String::as_str);
         if let Some(tx) = runtime.pending_requests.remove(&request_id) {
             let _ = tx.send(crate::runtime::WorkerResult {
-                json: serde_json::json!({"error": format!("Action '{}' not found", action_name)}),
-                timings: vec![],
+                json: serde_json::json!({"error": error, "action": action_name}),
+                timings: runtime.request_timings.remove(&request_id).unwrap_or_default(),
             });
         }
     }
 }
 
+/// `req.body` as decoded from the raw bytes.
+enum ParsedBody {
+    Value(serde_json::Value),
+    Bytes,
+}
+
+/// Decode a request body by its Content-Type:
+/// JSON → object, urlencoded → object, text → string, anything else → bytes.
+/// Bodies without a Content-Type (WebSocket messages) stay strings.
+/// `Err` only for malformed JSON.
+fn parse_body(bytes: &[u8], headers: &[(String, String)]) -> Result<ParsedBody, String> {
+    let content_type = headers
+        .iter()
+        .find(|(k, _)| k.eq_ignore_ascii_case("content-type"))
+        .map(|(_, v)| v.split(';').next().unwrap_or("").trim().to_ascii_lowercase())
+        .unwrap_or_default();
+
+    if content_type == "application/json" || content_type.ends_with("+json") {
+        if bytes.iter().all(u8::is_ascii_whitespace) {
+            return Ok(ParsedBody::Value(Value::Object(Default::default())));
+        }
+        return parse_json(bytes)
+            .map(ParsedBody::Value)
+            .map_err(|e| format!("Malformed JSON body: {}", e));
+    }
+    if content_type == "application/x-www-form-urlencoded" {
+        let map = form_urlencoded::parse(bytes)
+            .map(|(k, v)| (k.into_owned(), Value::String(v.into_owned())))
+            .collect::<serde_json::Map<_, _>>();
+        return Ok(ParsedBody::Value(Value::Object(map)));
+    }
+    if content_type.is_empty() || content_type.starts_with("text/") {
+        return Ok(ParsedBody::Value(Value::String(String::from_utf8_lossy(bytes).into_owned())));
+    }
+    Ok(ParsedBody::Bytes)
+}
+
+/// Below this size serde_json wins: simd-json first copies the input into a
+/// mutable buffer and sets up its SIMD stages, which only pays off on
+/// larger documents. `cargo bench --features simd-json --bench parse_json`
+/// compares the two across body sizes.
+#[cfg(feature = "simd-json")]
+const SIMD_JSON_MIN_BYTES: usize = 8 * 1024;
+
+/// Parse a JSON request body, with simd-json for large bodies when the
+/// `simd-json` feature is enabled. Both produce the same `Value`; input
+/// simd-json rejects goes through serde_json, so errors read the same too.
+fn parse_json(bytes: &[u8]) -> Result<Value, String> {
+    #[cfg(feature = "simd-json")]
+    if bytes.len() >= SIMD_JSON_MIN_BYTES {
+        if let Ok(value) = parse_json_simd(bytes) {
+            return Ok(value);
+        }
+    }
+    serde_json::from_slice(bytes).map_err(|e| e.to_string())
+}
+
+#[cfg(feature = "simd-json")]
+fn parse_json_simd(bytes: &[u8]) -> Result<Value, String> {
+    let mut owned = bytes.to_vec();
+    simd_json::serde::from_slice::<Value>(&mut owned).map_err(|e| e.to_string())
+}
+
 // V8 HELPERS
 
 #[inline(always)]
@@ -646,9 +1490,181 @@ pub fn v8_to_string(scope: &mut v8::HandleScope, value: v8::Local<v8::Value>) ->
     value.to_string(scope).unwrap().to_rust_string_lossy(scope)
 }
 
+/// Strings as UTF-8, `ArrayBuffer` (e.g. `req.rawBody`) and typed arrays as their bytes.
+pub fn v8_to_bytes(scope: &mut v8::HandleScope, value: v8::Local<v8::Value>) -> Option<Vec<u8>> {
+    if value.is_string() {
+        Some(v8_to_string(scope, value).into_bytes())
+    } else if let Ok(view) = v8::Local::<v8::ArrayBufferView>::try_from(value) {
+        let mut buf = vec![0u8; view.byte_length()];
+        view.copy_contents(&mut buf);
+        Some(buf)
+    } else if let Ok(ab) = v8::Local::<v8::ArrayBuffer>::try_from(value) {
+        let store = v8::ArrayBuffer::get_backing_store(&ab);
+        Some(store.iter().map(|b| b.get()).collect())
+    } else {
+        None
+    }
+}
+
+/// A new `Uint8Array` owning `bytes`.
+pub fn bytes_to_v8<'s>(scope: &mut v8::HandleScope<'s>, bytes: Vec<u8>) -> v8::Local<'s, v8::Value> {
+    let len = bytes.len();
+    let backing = v8::ArrayBuffer::new_backing_store_from_vec(bytes).make_shared();
+    let ab = v8::ArrayBuffer::with_backing_store(scope, &backing);
+    match v8::Uint8Array::new(scope, ab, 0, len) {
+        Some(view) => view.into(),
+        None => v8::null(scope).into(),
+    }
+}
+
 #[inline]
 pub fn throw(scope: &mut v8::HandleScope, msg: &str) {
     let message = v8_str(scope, msg);
     let exception = v8::Exception::error(scope, message);
     scope.throw_exception(exception);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Run `f` in a fresh context on a throwaway isolate.
+    fn with_scope(f: impl FnOnce(&mut v8::HandleScope)) {
+        init_v8();
+        let isolate = &mut v8::Isolate::new(v8::CreateParams::default());
+        let handle_scope = &mut v8::HandleScope::new(isolate);
+        let context = v8::Context::new(handle_scope, v8::ContextOptions::default());
+        let scope = &mut v8::ContextScope::new(handle_scope, context);
+        f(scope);
+    }
+
+    fn eval<'s>(scope: &mut v8::HandleScope<'s>, code: &str) -> v8::Local<'s, v8::Value> {
+        let source = v8_str(scope, code);
+        v8::Script::compile(scope, source, None).unwrap().run(scope).unwrap()
+    }
+
+    #[test]
+    fn no_replay_directive_must_lead_the_file() {
+        assert!(has_no_replay_directive("// @titan-no-replay\nexport function a() {}"));
+        assert!(has_no_replay_directive("\n// Checkout\n//   @titan-no-replay\nvar Titan = t;"));
+        assert!(!has_no_replay_directive("var Titan = t;\n// @titan-no-replay"));
+        assert!(!has_no_replay_directive("const note = \"@titan-no-replay\";"));
+        assert!(!has_no_replay_directive("// @titan-no-replayed"));
+    }
+
+    #[test]
+    fn text_depth_counts_nesting() {
+        assert_eq!(text_depth("42"), 0);
+        assert_eq!(text_depth("{}"), 1);
+        assert_eq!(text_depth(r#"{"a":[1,{"b":[]}],"c":{}}"#), 4);
+        assert_eq!(text_depth("[[],[[]],[]]"), 3);
+    }
+
+    #[test]
+    fn text_depth_ignores_brackets_in_strings() {
+        assert_eq!(text_depth(r#"{"a":"[[[{{{"}"#), 1);
+        assert_eq!(text_depth(r#"["\"[[", "\\", [1]]"#), 2);
+    }
+
+    #[test]
+    fn number_to_json_keeps_integers_integral() {
+        assert_eq!(number_to_json(42.0).to_string(), "42");
+        assert_eq!(number_to_json(-7.0).to_string(), "-7");
+        assert_eq!(number_to_json(1.5).to_string(), "1.5");
+        assert_eq!(number_to_json(f64::NAN).to_string(), "0");
+    }
+
+    #[test]
+    fn number_to_json_leaves_two_to_the_63_a_float() {
+        let two_63 = 9_223_372_036_854_775_808.0_f64;
+        assert!(number_to_json(two_63).as_i64().is_none());
+        assert_eq!(number_to_json(two_63).as_f64(), Some(two_63));
+        assert_eq!(number_to_json(-two_63).as_i64(), Some(i64::MIN));
+        // The largest double below 2^63 still fits
+        let below = 9_223_372_036_854_774_784.0_f64;
+        assert_eq!(number_to_json(below).as_i64(), Some(below as i64));
+    }
+
+    #[test]
+    fn bigint_to_json_is_exact_or_a_string() {
+        with_scope(|scope| {
+            let small = eval(scope, "12345678901234567n");
+            assert_eq!(bigint_to_json(scope, small).as_i64(), Some(12_345_678_901_234_567));
+            let unsigned = eval(scope, "18446744073709551615n");
+            assert_eq!(bigint_to_json(scope, unsigned).as_u64(), Some(u64::MAX));
+            let huge = eval(scope, "123456789012345678901234567890n");
+            assert_eq!(bigint_to_json(scope, huge), serde_json::json!("123456789012345678901234567890"));
+        });
+    }
+}
+
+#[cfg(all(test, feature = "simd-json"))]
+mod simd_json_tests {
+    use super::*;
+
+    const CORPUS: &[&str] = &[
+        r#"{}"#,
+        r#"[]"#,
+        r#"null"#,
+        r#"{"a":1,"b":[true,false,null],"c":{"d":"e"}}"#,
+        r#"[0,-1,42,9007199254740993,9223372036854775807,-9223372036854775808,18446744073709551615]"#,
+        r#"[18446744073709551616,123456789012345678901234567890]"#,
+        r#"[1.5,0.1,-2.25,3.141592653589793,1e-7,6.02214076e23,1.7976931348623157e308,2.2250738585072014e-308]"#,
+        r#"[1E3,2e+2,5e-1,123456789.123456789]"#,
+        r#"{"text":"caf\u00e9 \ud83d\ude00 \"quoted\" \\ \n\t","raw":"naïve 日本語"}"#,
+        "  \n\t{ \"spaced\" :  [ 1 , 2 ] }\r\n",
+        r#"{"nested":[[[[[[[[[[{"deep":true}]]]]]]]]]]}"#,
+    ];
+
+    /// An array of records, at least `min_bytes` long.
+    fn large_document(min_bytes: usize) -> String {
+        let mut doc = String::from("[");
+        let mut i = 0u64;
+        while doc.len() < min_bytes {
+            if i > 0 {
+                doc.push(',');
+            }
+            doc.push_str(&format!(
+                r#"{{"id":{i},"big":{},"score":{},"name":"user-{i}","tags":["a","b"],"active":{}}}"#,
+                u64::MAX - i,
+                i as f64 * 1.37 + 0.001,
+                i % 2 == 0
+            ));
+            i += 1;
+        }
+        doc.push(']');
+        doc
+    }
+
+    /// CORPUS entries simd-json rejects (integers past u64); parse_json hands
+    /// these to serde_json, which reads them as floats.
+    const SIMD_FALLBACKS: &[&str] = &[r#"[18446744073709551616,123456789012345678901234567890]"#];
+
+    #[test]
+    fn simd_json_matches_serde_json() {
+        for doc in CORPUS {
+            let serde = serde_json::from_str::<Value>(doc).unwrap();
+            if SIMD_FALLBACKS.contains(doc) {
+                assert!(parse_json_simd(doc.as_bytes()).is_err(), "{doc}");
+            } else {
+                assert_eq!(parse_json_simd(doc.as_bytes()).unwrap(), serde, "{doc}");
+            }
+            assert_eq!(parse_json(doc.as_bytes()).unwrap(), serde, "{doc}");
+        }
+    }
+
+    #[test]
+    fn parse_json_matches_serde_json_on_large_bodies() {
+        let doc = large_document(4 * SIMD_JSON_MIN_BYTES);
+        let serde = serde_json::from_str::<Value>(&doc).unwrap();
+        assert_eq!(parse_json_simd(doc.as_bytes()).unwrap(), serde);
+        assert_eq!(parse_json(doc.as_bytes()).unwrap(), serde);
+    }
+
+    #[test]
+    fn parse_json_reports_serde_json_errors() {
+        let mut doc = large_document(SIMD_JSON_MIN_BYTES);
+        doc.push_str(",}");
+        assert_eq!(parse_json(doc.as_bytes()).unwrap_err(), serde_json::from_str::<Value>(&doc).unwrap_err().to_string());
+    }
+}
@@ -10,12 +10,13 @@ static HOSTS: Mutex<Option<HashMap<String, Arc<Mutex<NativeHostState>>>>> = Mute
 struct NativeHostState {
     name: String,
     path: PathBuf,
+    free: Option<String>,
     child: Child,
     stdin: ChildStdin,
     reader: BufReader<ChildStdout>,
 }
 
-fn spawn_child(path: &PathBuf) -> Option<(Child, ChildStdin, BufReader<ChildStdout>)> {
+fn spawn_child(path: &PathBuf, free: Option<&str>) -> Option<(Child, ChildStdin, BufReader<ChildStdout>)> {
     let canonical = std::fs::canonicalize(path).unwrap_or(path.clone());
     let dll_dir   = canonical.parent().unwrap_or(&canonical).to_path_buf();
 
@@ -46,6 +47,9 @@ fn spawn_child(path: &PathBuf) -> Option<(Child, ChildStdin, BufReader<ChildStdo
        .stdin(Stdio::piped())
        .stdout(Stdio::piped())
        .stderr(Stdio::inherit());
+    if let Some(free) = free {
+        cmd.arg(free);
+    }
 
     // Let the DLL find its own dependencies
     if let Some(p) = std::env::var_os("PATH") {
@@ -63,9 +67,9 @@ fn spawn_child(path: &PathBuf) -> Option<(Child, ChildStdin, BufReader<ChildStdo
 }
 
 impl NativeHostState {
-    fn new(name: String, path: PathBuf) -> Option<Self> {
-        let (child, stdin, reader) = spawn_child(&path)?;
-        Some(Self { name, path, child, stdin, reader })
+    fn new(name: String, path: PathBuf, free: Option<String>) -> Option<Self> {
+        let (child, stdin, reader) = spawn_child(&path, free.as_deref())?;
+        Some(Self { name, path, free, child, stdin, reader })
     }
 
     /// Send one JSON request, read one JSON response line.
@@ -87,7 +91,7 @@ impl NativeHostState {
     fn respawn(&mut self) -> bool {
         let _ = self.child.kill();
         let _ = self.child.wait();
-        match spawn_child(&self.path) {
+        match spawn_child(&self.path, self.free.as_deref()) {
             Some((child, stdin, reader)) => {
                 self.child  = child;
                 self.stdin  = stdin;
@@ -104,8 +108,8 @@ pub async fn handle_native_call(extension: String, function: String, params: Vec
 }
 
 pub fn handle_native_call_sync(extension: String, function: String, params: Vec<Value>) -> Value {
-    // Resolve DLL path from registry
-    let native_path = {
+    // Resolve DLL path (and its free symbol) from registry
+    let (native_path, native_free) = {
         let guard = super::external::REGISTRY.lock().unwrap();
         let reg   = match guard.as_ref() {
             Some(r) => r,
@@ -116,7 +120,7 @@ pub fn handle_native_call_sync(extension: String, function: String, params: Vec<
             None    => return json!({ "error": format!("Extension '{}' not found", extension) }),
         };
         match &def.native_path {
-            Some(p) => p.clone(),
+            Some(p) => (p.clone(), def.native_free.clone()),
             None    => return json!({ "error": format!("'{}' is not a native extension", extension) }),
         }
     };
@@ -127,7 +131,7 @@ pub fn handle_native_call_sync(extension: String, function: String, params: Vec<
     let state_arc = hosts
         .entry(extension.clone())
         .or_insert_with(|| {
-            let state = NativeHostState::new(extension.clone(), native_path.clone())
+            let state = NativeHostState::new(extension.clone(), native_path.clone(), native_free.clone())
                 .expect("Failed to spawn NativeHost");
             Arc::new(Mutex::new(state))
         })
@@ -15,30 +15,36 @@ if (!globalThis.__TITAN_CORE_LOADED__) {
         const wrapped = function (req) {
             const requestId = req.__titan_request_id;
 
-            if (req.rawBody && req.rawBody.byteLength !== undefined) {
-                try {
-                    const decoder = new TextDecoder();
-                    const text = decoder.decode(req.rawBody);
-
-                    const contentType =
-                        (req.headers && req.headers["content-type"]) ||
-                        (req.headers && req.headers["Content-Type"]) ||
-                        "";
-
-                    if (contentType.includes("application/json")) {
-                        req.body = text ? JSON.parse(text) : {};
-                    } else if (contentType.includes("application/x-www-form-urlencoded")) {
-                        req.body = Object.fromEntries(new URLSearchParams(text));
-                    } else {
-                        req.body = text;
-                    }
-                } catch (e) {
-                    req.body = {};
-                }
-            } else {
-                req.body = {};
+            // Trailers are request-scoped; replays re-record them deterministically
+            globalThis.__titan_trailers = null;
+            globalThis.__titan_early_hints = null;
+            globalThis.__titan_set_cookies = null;
+            // Current request, for helpers like t.query.expand() that read it implicitly
+            globalThis.__titan_req = req;
+            // Fresh per request so request-scoped services (tagged logger) never leak across
+            t.services = globalThis.__titan_make_services(req);
+
+            // HTTP header names arrive lowercased; req.header() also covers
+            // hand-built requests (tasks, tests) that use other casings.
+            if (typeof req.header !== "function") {
+                Object.defineProperty(req, "header", {
+                    enumerable: false,
+                    value: (name) => {
+                        const headers = req.headers || {};
+                        const wanted = String(name).toLowerCase();
+                        if (headers[wanted] !== undefined) return headers[wanted];
+                        const key = Object.keys(headers).find((k) => k.toLowerCase() === wanted);
+                        return key === undefined ? undefined : headers[key];
+                    },
+                });
             }
 
+            // req.body is decoded by Content-Type in the runtime; rawBody keeps the bytes
+            if (req.body === undefined) req.body = {};
+
+            // Shared between route middleware and the action
+            if (req.context === undefined) req.context = {};
+
             // WebSocket normalization
             if (req.headers && req.headers.socketId) {
                 req.socketId = req.headers.socketId;
@@ -47,36 +53,152 @@ if (!globalThis.__TITAN_CORE_LOADED__) {
 
             // ===============================
 
+            // Drift replays re-run the action, so per-request state that must
+            // survive them (t.cache.response misses, multipart boundaries) is
+            // dropped only here
+            const complete = (payload) => {
+                globalThis.__titan_forget_request(requestId);
+                t._finish_request(requestId, payload);
+            };
+
             const isSuspend = (err) => {
                 const msg = err && (err.message || String(err));
                 return msg && (msg.includes("__SUSPEND__") || msg.includes("SUSPEND"));
             };
 
-            try {
-                const result = fn(req);
+            // Extension middleware (titan.json `hooks`), in registration order.
+            // Tasks are internal jobs, not requests, so they skip hooks.
+            // A before-hook that answers skips the action; only the after-hooks of
+            // the hooks that ran before it see that answer.
+            const hooks = req.method === "TASK" ? [] : (globalThis.__titan_hooks || []);
+            const runAfter = (data, upto = hooks.length) => {
+                for (const h of hooks.slice(0, upto)) {
+                    if (!h.after) continue;
+                    const out = h.after(req, data, req.context);
+                    if (out !== undefined) data = out;
+                }
+                return data;
+            };
 
-                if (result && typeof result.then === 'function') {
-                    result.then(
-                        (data) => t._finish_request(requestId, data),
-                        (err) => {
-                            if (isSuspend(err)) return;
-                            t._finish_request(requestId, { error: err.message || String(err) });
+            // t.fail() → { error: { code, message, details } } with its mapped status
+            const failResponse = (err) => {
+                const error = { code: err.code, message: err.message };
+                if (err.details !== undefined) error.details = err.details;
+                // The expected schema helps while integrating; production stays terse
+                if (err.schema !== undefined && t.env.TITAN_DEV === "1") error.schema = err.schema;
+                return t.response.json({ error }, err.status);
+            };
+
+            // Attach any t.response.trailer() / earlyHints() values to the outgoing response
+            const finish = (data) => {
+                const trailers = globalThis.__titan_trailers;
+                if (trailers) {
+                    const resp = (data && data._isResponse) ? data : t.response.json(data);
+                    resp.trailers = { ...(resp.trailers || {}), ...trailers };
+                    data = resp;
+                }
+                const cookies = globalThis.__titan_set_cookies;
+                if (cookies) {
+                    const resp = (data && data._isResponse) ? data : t.response.json(data);
+                    resp.cookies = [...(resp.cookies || []), ...cookies];
+                    data = resp;
+                }
+                const hints = globalThis.__titan_early_hints;
+                if (hints) {
+                    const resp = (data && data._isResponse) ? data : t.response.json(data);
+                    const headers = resp.headers || {};
+                    const existing = headers.Link || headers.link;
+                    delete headers.link;
+                    headers.Link = existing ? existing + ", " + hints.join(", ") : hints.join(", ");
+                    resp.headers = headers;
+                    data = resp;
+                }
+                complete(data);
+            };
+
+            const fail = (err) => {
+                if (isSuspend(err)) return;
+                if (err && err.__titanFail) return finish(failResponse(err));
+                complete({ error: err.message || String(err) });
+            };
+
+            // Async middleware and actions settle later; errors thrown while
+            // finishing are the request's failure either way
+            const settle = (promise, onValue) => {
+                promise.then(
+                    (value) => {
+                        try {
+                            onValue(value);
+                        } catch (err) {
+                            fail(err);
                         }
-                    );
+                    },
+                    fail
+                );
+            };
+
+            // Route middleware (routes.json `middleware`) from index `i`, then the
+            // action: mw(req, req.context) runs in order, and a returned promise
+            // is awaited before the next one. Resolving to undefined continues the
+            // chain; anything else is sent as the response and the action is skipped.
+            const runFrom = (i) => {
+                const chain = req.__titan_middleware || [];
+                for (; i < chain.length; i++) {
+                    const out = (chain[i].__titanRaw || chain[i])(req, req.context);
+                    if (out && typeof out.then === 'function') {
+                        const next = i + 1;
+                        return settle(out, (value) => (value !== undefined ? finish(runAfter(value)) : runFrom(next)));
+                    }
+                    if (out !== undefined) return finish(runAfter(out));
+                }
+
+                const result = fn(req, req.context);
+
+                if (result && typeof result.then === 'function') {
+                    settle(result, (data) => finish(runAfter(data)));
                 } else {
-                    t._finish_request(requestId, result);
+                    finish(runAfter(result));
                 }
+            };
+
+            try {
+                // A before-hook returning anything but undefined answers the request itself
+                for (let i = 0; i < hooks.length; i++) {
+                    if (!hooks[i].before) continue;
+                    const early = hooks[i].before(req, req.context);
+                    if (early !== undefined) return finish(runAfter(early, i));
+                }
+
+                runFrom(0);
             } catch (err) {
-                if (isSuspend(err)) return;
-                t._finish_request(requestId, { error: err.message || String(err) });
+                fail(err);
             }
         };
 
         wrapped.__titanWrapped = true;
+        // Middleware calls the plain function so it doesn't finish the request itself
+        wrapped.__titanRaw = fn;
         return wrapped;
     };
     globalThis.defineTask = globalThis.defineAction;
 
+    // A WebSocket action may export onOpen / onMessage / onClose instead of one
+    // function switching on req.event. Every event runs as its own request on
+    // whichever isolate is free, so nothing survives between events in JS
+    // variables; keep per-connection state in t.shareContext keyed by socketId.
+    globalThis.__titan_ws_handlers = (handlers) => (req, ctx) => {
+        const handler = { open: handlers.onOpen, message: handlers.onMessage, close: handlers.onClose }[req.event];
+        return typeof handler === "function" ? handler(req, ctx) : undefined;
+    };
+
+
+    // Per-request state kept across drift replays. Called when the action
+    // completes, and by the runtime for requests answered without it
+    // (drift timeout, panic).
+    globalThis.__titan_forget_request = (requestId) => {
+        if (globalThis.__titan_cache_pins) delete globalThis.__titan_cache_pins[requestId];
+        if (globalThis.__titan_multipart_boundaries) delete globalThis.__titan_multipart_boundaries[requestId];
+    };
 
     // TextDecoder Polyfill
     globalThis.TextDecoder = class TextDecoder {
@@ -108,6 +230,26 @@ if (!globalThis.__TITAN_CORE_LOADED__) {
         });
     }
 
+    // Map / Set serialization. JSON.stringify would otherwise turn both into
+    // `{}`. A Set becomes an array of its values, a Map an object keyed by
+    // String(key), or `[key, value]` pairs when t.response.json(data,
+    // { maps: "pairs" }) asks for them. Passed to JSON.stringify as a replacer
+    // so the built-in prototypes stay untouched; the server serializes action
+    // results with `__titan_json_replacer`.
+    const _collectionReplacer = (pairs) => function (key, value) {
+        if (value instanceof Set) return Array.from(value);
+        if (value instanceof Map) {
+            if (pairs) return Array.from(value);
+            const out = {};
+            for (const [k, v] of value) out[String(k)] = v;
+            return out;
+        }
+        return value;
+    };
+    const _mapsAsObjects = _collectionReplacer(false);
+    const _mapsAsPairs = _collectionReplacer(true);
+    globalThis.__titan_json_replacer = _mapsAsObjects;
+
     // Response API (Dual-Signature)
     // Supports TWO calling conventions for compatibility with fast-path parser:
     //
@@ -120,6 +262,7 @@ if (!globalThis.__TITAN_CORE_LOADED__) {
     // The fast-path scanner parses the source code and expects the options-object
     // form. Using the positional form works at runtime but won't be detected by
     // fast-path. The options-object form works in BOTH paths.
+    // t.response.json also takes `maps: "object" | "pairs"` in the options object.
     //
     // Internal helper to normalize the second argument:
     function _parseResponseOpts(secondArg, thirdArg) {
@@ -134,6 +277,12 @@ if (!globalThis.__TITAN_CORE_LOADED__) {
             if (thirdArg && typeof thirdArg === 'object') {
                 extraHeaders = { ...extraHeaders, ...thirdArg };
             }
+            // retryAfter: seconds or an HTTP date (503 / 429 pages)
+            const retryAfter = secondArg.retryAfter;
+            if (retryAfter !== undefined && retryAfter !== null && retryAfter !== ""
+                && !Object.keys(extraHeaders).some((k) => k.toLowerCase() === "retry-after")) {
+                extraHeaders = { ...extraHeaders, "Retry-After": String(retryAfter) };
+            }
         } else {
             // Positional form: (status, extraHeaders)
             status = secondArg || 200;
@@ -143,14 +292,145 @@ if (!globalThis.__TITAN_CORE_LOADED__) {
         return { status, extraHeaders };
     }
 
+    // Streamed bodies (jsonArray, sse, multipart): writes `head`, each
+    // serialized item and `tail` to the response as they're produced. Items
+    // that are drift handles are resolved in turn; pending output is written
+    // before each drift, and the replay after it re-produces that output,
+    // which the runtime drops. `serialize` returns a string or a list of
+    // strings and byte arrays; bytes are written as they are, never re-encoded.
+    // `tail` may be a function of the item count.
+    // Returns null when the request can't stream (tasks, WebSocket events) so
+    // the caller can buffer instead; `items` is left unconsumed in that case.
+    const _isOp = (v) => !!v && typeof v === 'object' && v.__titanAsync === true;
+
+    function _streamItems(items, { status, headers, head, tail, serialize, batchBytes }) {
+        const req = globalThis.__titan_req;
+        // The head goes out now, so it announces the trailers set so far;
+        // their values can still change until the action returns
+        const trailerNames = Object.keys(globalThis.__titan_trailers || {});
+        if (trailerNames.length && !Object.keys(headers || {}).some(k => k.toLowerCase() === "trailer")) {
+            headers = { ...headers, Trailer: trailerNames.join(", ") };
+        }
+        const opened = !!req && req.method !== "TASK" && req.method !== "WS"
+            && t._stream_open(req.__titan_request_id, { status, headers });
+        if (!opened) return null;
+
+        let chunk = head;
+        let index = 0;
+        const flush = () => {
+            if (chunk) t._stream_write(req.__titan_request_id, chunk);
+            chunk = "";
+        };
+        for (let item of items) {
+            if (_isOp(item)) {
+                flush();
+                item = drift(item);
+            }
+            const out = serialize(item, index++);
+            for (const piece of Array.isArray(out) ? out : [out]) {
+                if (typeof piece === "string") {
+                    chunk += piece;
+                    continue;
+                }
+                flush();
+                t._stream_write(req.__titan_request_id, piece);
+            }
+            if (chunk.length >= batchBytes) flush();
+        }
+        chunk += typeof tail === "function" ? tail(index) : tail;
+        flush();
+
+        // Finishing the request closes the body; the head was already sent
+        return { _isResponse: true, status, headers, body: "" };
+    }
+
+    // One `text/event-stream` frame. Multi-line data becomes several `data:`
+    // lines; newlines in id/event would end the field early and are dropped.
+    function _sseEvent(ev) {
+        if (ev === null || typeof ev !== "object") ev = { data: ev };
+        const field = (v) => String(v).replace(/[\r\n]/g, "");
+        let out = "";
+        if (ev.id !== undefined) out += `id: ${field(ev.id)}\n`;
+        if (ev.event !== undefined) out += `event: ${field(ev.event)}\n`;
+        if (ev.retry !== undefined) out += `retry: ${Math.floor(Number(ev.retry))}\n`;
+        const data = typeof ev.data === "string" ? ev.data : (JSON.stringify(ev.data) ?? "");
+        for (const line of data.split(/\r\n|\r|\n/)) out += `data: ${line}\n`;
+        return out + "\n";
+    }
+
+    // multipart/mixed framing. Part bodies go out untouched between
+    // `--boundary` lines, so the boundary is random per response and a body
+    // that contains it is refused rather than sent mis-framed. A drift replay
+    // must reuse the boundary already written, so it's kept per request until
+    // the response completes.
+    globalThis.__titan_multipart_boundaries = {};
+
+    function _multipartBoundary(req) {
+        const id = req ? req.__titan_request_id : undefined;
+        const kept = id === undefined ? undefined : globalThis.__titan_multipart_boundaries[id];
+        if (kept) return kept;
+        const boundary = "titan-" + t.uuid.v4().replace(/-/g, "");
+        if (id !== undefined) globalThis.__titan_multipart_boundaries[id] = boundary;
+        return boundary;
+    }
+
+    const _isBytes = (v) => v instanceof ArrayBuffer || ArrayBuffer.isView(v);
+
+    function _bytesInclude(bytes, ascii) {
+        const first = ascii.charCodeAt(0);
+        const last = bytes.length - ascii.length;
+        for (let i = bytes.indexOf(first); i !== -1 && i <= last; i = bytes.indexOf(first, i + 1)) {
+            let j = 1;
+            while (j < ascii.length && bytes[i + j] === ascii.charCodeAt(j)) j++;
+            if (j === ascii.length) return true;
+        }
+        return false;
+    }
+
+    // One part as [head, body]: `{ headers?, body }`, or a bare body. Strings
+    // default to text/plain, bytes to application/octet-stream and anything
+    // else is sent as JSON. Newlines in header names/values are dropped.
+    function _multipartPart(part, index, boundary) {
+        if (part === null || typeof part !== "object" || _isBytes(part)) part = { body: part };
+        let body = part.body;
+        let type;
+        if (typeof body === "string") {
+            type = "text/plain; charset=utf-8";
+        } else if (_isBytes(body)) {
+            type = "application/octet-stream";
+            body = body instanceof Uint8Array ? body
+                : ArrayBuffer.isView(body) ? new Uint8Array(body.buffer, body.byteOffset, body.byteLength)
+                : new Uint8Array(body);
+        } else {
+            type = "application/json";
+            body = JSON.stringify(body) ?? "null";
+        }
+
+        const delimiter = "--" + boundary;
+        if (typeof body === "string" ? body.includes(delimiter) : _bytesInclude(body, delimiter)) {
+            throw new Error(`t.response.multipart(): part ${index} contains the boundary`);
+        }
+
+        const field = (v) => String(v).replace(/[\r\n]/g, "");
+        const headers = part.headers || {};
+        let head = (index ? "\r\n" : "") + delimiter + "\r\n";
+        if (!Object.keys(headers).some((k) => k.toLowerCase() === "content-type")) {
+            head += `Content-Type: ${type}\r\n`;
+        }
+        for (const [name, value] of Object.entries(headers)) head += `${field(name)}: ${field(value)}\r\n`;
+        return [head + "\r\n", body];
+    }
+
     const titanResponse = {
         json(data, second, third) {
             const { status, extraHeaders } = _parseResponseOpts(second, third);
+            const pairs = !!(second && typeof second === 'object' && second.maps === "pairs");
+            const body = JSON.stringify(data, pairs ? _mapsAsPairs : _mapsAsObjects);
             return {
                 _isResponse: true,
                 status,
                 headers: { "Content-Type": "application/json", ...extraHeaders },
-                body: JSON.stringify(data)
+                body
             };
         },
         text(data, second, third) {
@@ -182,11 +462,375 @@ if (!globalThis.__TITAN_CORE_LOADED__) {
                 headers: { "Location": url, ...extraHeaders },
                 redirect: url
             };
+        },
+        // A file under the project root, streamed from disk by the server (same
+        // boundary as t.read; missing or outside the root answers 404).
+        // `download: true` sends it as an attachment named after the file, a
+        // string picks the name. Content-Type follows the extension unless
+        // `contentType` is given.
+        file(path, options) {
+            options = options || {};
+            const { status, extraHeaders } = _parseResponseOpts(options);
+            const headers = { ...extraHeaders };
+            if (options.contentType) headers["Content-Type"] = String(options.contentType);
+            const res = { _isResponse: true, status, headers, file: String(path) };
+            if (options.download === true) res.download = String(path).split(/[\\/]/).pop();
+            else if (options.download) res.download = String(options.download);
+            return res;
+        },
+        // Streams `[`, the serialized elements and `]` as they're produced instead
+        // of buffering the array. `items` is any iterable (or a drift handle that
+        // resolves to one). Tasks and WebSocket events have no body to stream
+        // into and get the buffered equivalent.
+        jsonArray(items, second, third) {
+            const { status, extraHeaders } = _parseResponseOpts(second, third);
+            const replacer = second && typeof second === 'object' && second.maps === "pairs" ? _mapsAsPairs : _mapsAsObjects;
+            const headers = { "Content-Type": "application/json", ...extraHeaders };
+            if (_isOp(items)) items = drift(items);
+
+            const serialize = (item, index) => {
+                const json = JSON.stringify(item, replacer);
+                // Same as JSON.stringify on an array: unserializable elements become null
+                return (index ? "," : "") + (json === undefined ? "null" : json);
+            };
+            const streamed = _streamItems(items, { status, headers, head: "[", tail: "]", serialize, batchBytes: 16384 });
+            if (streamed) return streamed;
+
+            const all = [];
+            for (const item of items) all.push(_isOp(item) ? drift(item) : item);
+            return titanResponse.json(all, { status, headers: extraHeaders, maps: second && second.maps });
+        },
+        // Server-Sent Events: `events` is an iterable (or drift handle) of
+        // `{ event?, data, id?, retry? }` objects (anything else is sent as
+        // `data`), each written to a `text/event-stream` body as soon as it's
+        // produced. A generator yielding drift handles can push updates as its
+        // ops complete; the connection closes when the iterable ends.
+        sse(events, second, third) {
+            const { status, extraHeaders } = _parseResponseOpts(second, third);
+            const headers = { "Content-Type": "text/event-stream", "Cache-Control": "no-cache", ...extraHeaders };
+            if (_isOp(events)) events = drift(events);
+
+            const streamed = _streamItems(events, { status, headers, head: "", tail: "", serialize: _sseEvent, batchBytes: 0 });
+            if (streamed) return streamed;
+
+            let body = "";
+            for (const ev of events) body += _sseEvent(_isOp(ev) ? drift(ev) : ev);
+            return { _isResponse: true, status, headers, body };
+        },
+        // `multipart/mixed` body from `parts`, an iterable (or drift handle) of
+        // `{ headers?, body }` objects, each written as soon as it's produced.
+        // Bodies may be strings, byte arrays or JSON values. `subtype` picks
+        // another multipart type, e.g. "related" or "x-mixed-replace".
+        // Tasks and WebSocket events get the buffered body, with byte parts
+        // decoded as UTF-8.
+        multipart(parts, second, third) {
+            const { status, extraHeaders } = _parseResponseOpts(second, third);
+            const subtype = (second && typeof second === 'object' && second.subtype) || "mixed";
+            const req = globalThis.__titan_req;
+            const boundary = _multipartBoundary(req);
+            const headers = { "Content-Type": `multipart/${subtype}; boundary=${boundary}`, ...extraHeaders };
+            if (_isOp(parts)) parts = drift(parts);
+
+            const serialize = (part, index) => _multipartPart(part, index, boundary);
+            const tail = (count) => (count ? "\r\n" : "") + `--${boundary}--\r\n`;
+            const streamed = _streamItems(parts, { status, headers, head: "", tail, serialize, batchBytes: 16384 });
+            if (streamed) return streamed;
+
+            let body = "";
+            let index = 0;
+            for (const part of parts) {
+                const [head, partBody] = serialize(_isOp(part) ? drift(part) : part, index++);
+                body += head + (typeof partBody === "string" ? partBody : t.decodeUtf8(partBody));
+            }
+            return { _isResponse: true, status, headers, body: body + tail(index) };
+        },
+        // Emitted as an HTTP trailer after the body (chunked transfer encoding)
+        trailer(name, value) {
+            if (!globalThis.__titan_trailers) globalThis.__titan_trailers = {};
+            globalThis.__titan_trailers[String(name)] = String(value);
+        },
+        // Sends a `103 Early Hints` right away (HTTP/1.1 clients), so the
+        // browser can start preloading while the action still runs; the same
+        // links are repeated as `Link` headers on the final response.
+        earlyHints({ links = [] } = {}) {
+            if (!globalThis.__titan_early_hints) globalThis.__titan_early_hints = [];
+            const values = [];
+            for (const link of links) {
+                if (typeof link === "string") {
+                    values.push(link);
+                    continue;
+                }
+                let value = `<${link.href}>; rel=${link.rel || "preload"}`;
+                if (link.as) value += `; as=${link.as}`;
+                if (link.type) value += `; type="${link.type}"`;
+                if (link.crossorigin) value += link.crossorigin === true ? "; crossorigin" : `; crossorigin=${link.crossorigin}`;
+                values.push(value);
+            }
+            globalThis.__titan_early_hints.push(...values);
+            const req = globalThis.__titan_req;
+            if (values.length && req && t._early_hints) t._early_hints(req.__titan_request_id, values);
         }
     };
 
     t.response = titanResponse;
+
+    // Cookies. Every set/clear in a request adds one `Set-Cookie` line to the
+    // response; lines are kept in call order, so setting a name twice lets the
+    // later one win in the browser. The leading request argument of the older
+    // `t.cookies.set(req, name, value)` form is accepted and ignored.
+    const SAME_SITE = { strict: "Strict", lax: "Lax", none: "None" };
+
+    // RFC 6265 cookie-name (an RFC 7230 token); attribute values may not
+    // carry `;` or control characters, which would inject attributes or
+    // break the header line.
+    const COOKIE_NAME = /^[!#$%&'*+\-.^_`|~0-9A-Za-z]+$/;
+    const COOKIE_UNSAFE = /[;\x00-\x1f\x7f]/;
+
+    function _serializeCookie(name, value, options = {}) {
+        if (typeof name !== "string" || !COOKIE_NAME.test(name)) {
+            throw new Error(`t.cookies: invalid cookie name ${JSON.stringify(name)}`);
+        }
+        for (const attr of ["domain", "path"]) {
+            if (options[attr] && COOKIE_UNSAFE.test(String(options[attr]))) {
+                throw new Error(`t.cookies: invalid ${attr} ${JSON.stringify(String(options[attr]))}`);
+            }
+        }
+        let line = `${name}=${encodeURIComponent(String(value))}`;
+        if (options.maxAge !== undefined) line += `; Max-Age=${Math.floor(Number(options.maxAge))}`;
+        if (options.expires !== undefined) line += `; Expires=${new Date(options.expires).toUTCString()}`;
+        if (options.domain) line += `; Domain=${options.domain}`;
+        line += `; Path=${options.path || "/"}`;
+        // Browsers reject Partitioned (CHIPS) and SameSite=None cookies without Secure
+        const sameSite = options.sameSite && SAME_SITE[String(options.sameSite).toLowerCase()];
+        if (options.secure || options.partitioned || sameSite === "None") line += "; Secure";
+        if (options.httpOnly) line += "; HttpOnly";
+        if (sameSite) line += `; SameSite=${sameSite}`;
+        if (options.partitioned) line += "; Partitioned";
+        return line;
+    }
+
+    function _pushCookie(line) {
+        if (!globalThis.__titan_set_cookies) globalThis.__titan_set_cookies = [];
+        globalThis.__titan_set_cookies.push(line);
+    }
+
+    const _dropTarget = (args) => (args.length && typeof args[0] === "object" && args[0] !== null) ? args.slice(1) : args;
+
+    t.cookies = {
+        get(req, name) {
+            const header = (req && req.headers && req.headers.cookie) || "";
+            for (const part of header.split(";")) {
+                const eq = part.indexOf("=");
+                if (eq === -1 || part.slice(0, eq).trim() !== name) continue;
+                const raw = part.slice(eq + 1).trim();
+                try {
+                    return decodeURIComponent(raw);
+                } catch {
+                    return raw;
+                }
+            }
+            return null;
+        },
+        // set(name, value, { maxAge, expires, domain, path, secure, httpOnly, sameSite, partitioned })
+        set(...args) {
+            const [name, value, options] = _dropTarget(args);
+            _pushCookie(_serializeCookie(name, value, options));
+        },
+        // Expire a cookie now. Pass the same domain/path it was set with, or the
+        // browser treats this as a different cookie and keeps the original.
+        clear(...args) {
+            const [name, options = {}] = _dropTarget(args);
+            _pushCookie(_serializeCookie(name, "", { ...options, maxAge: 0, expires: 0 }));
+        },
+    };
+    t.cookies.delete = t.cookies.clear;
+
+    // Structured client errors with stable codes:
+    //   t.fail("USER_NOT_FOUND", "No such user", { status: 404, details: { id } })
+    // Throws; defineAction turns it into { error: { code, message, details } }.
+    t.fail = function (code, message, options = {}) {
+        const err = new Error(message || String(code));
+        err.__titanFail = true;
+        err.code = String(code);
+        err.status = options.status || 400;
+        err.details = options.details;
+        err.schema = options.schema;
+        throw err;
+    };
+
+    // t.fail keyed by status: t.error(404, "No such user", { id }) answers 404
+    // with { error: { code: "HTTP_404", message, details } }.
+    t.error = function (status, message, details) {
+        const code = Number(status);
+        if (!Number.isInteger(code) || code < 400 || code > 599) {
+            throw new TypeError(`t.error(): status must be an HTTP error status (400-599), got ${status}`);
+        }
+        t.fail(`HTTP_${code}`, message, { status: code, details });
+    };
+
+    // Request validation against a JSON Schema subset: type (incl. "integer"),
+    // enum, required, properties, additionalProperties: false, items,
+    // minLength/maxLength/pattern and minimum/maximum.
+    //   const body = t.validate(req.body, { type: "object", required: ["email"], ... });
+    // Returns the value when valid, otherwise fails with 400 VALIDATION_FAILED.
+    // In dev (TITAN_DEV=1) the error lists each { path, expected, received }
+    // and carries the schema; in production only the generic message is sent.
+    const _typeOf = (v) => {
+        if (v === null) return "null";
+        if (Array.isArray(v)) return "array";
+        if (typeof v === "number" && Number.isInteger(v)) return "integer";
+        return typeof v;
+    };
+    const _childPath = (path, key) => typeof key === "number" ? `${path}[${key}]` : (path ? `${path}.${key}` : key);
+
+    function _validate(value, schema, path, errors) {
+        if (!schema || typeof schema !== "object") return;
+        const at = path || "(root)";
+        const actual = _typeOf(value);
+
+        if (schema.type !== undefined) {
+            const types = Array.isArray(schema.type) ? schema.type : [schema.type];
+            const ok = types.some((ty) => ty === actual || (ty === "number" && actual === "integer"));
+            if (!ok) return void errors.push({ path: at, expected: types.join(" | "), received: actual });
+        }
+        if (Array.isArray(schema.enum) && !schema.enum.includes(value)) {
+            return void errors.push({ path: at, expected: `one of ${JSON.stringify(schema.enum)}`, received: value });
+        }
+
+        if (typeof value === "string") {
+            if (schema.minLength !== undefined && value.length < schema.minLength) errors.push({ path: at, expected: `length >= ${schema.minLength}`, received: value.length });
+            if (schema.maxLength !== undefined && value.length > schema.maxLength) errors.push({ path: at, expected: `length <= ${schema.maxLength}`, received: value.length });
+            if (schema.pattern !== undefined && !new RegExp(schema.pattern).test(value)) errors.push({ path: at, expected: `match /${schema.pattern}/`, received: value });
+        } else if (typeof value === "number") {
+            if (schema.minimum !== undefined && value < schema.minimum) errors.push({ path: at, expected: `>= ${schema.minimum}`, received: value });
+            if (schema.maximum !== undefined && value > schema.maximum) errors.push({ path: at, expected: `<= ${schema.maximum}`, received: value });
+        } else if (actual === "array") {
+            if (schema.items) value.forEach((item, i) => _validate(item, schema.items, _childPath(path, i), errors));
+        } else if (actual === "object") {
+            const props = schema.properties || {};
+            for (const key of schema.required || []) {
+                if (value[key] === undefined) errors.push({ path: _childPath(path, key), expected: "required", received: "missing" });
+            }
+            for (const key of Object.keys(value)) {
+                if (props[key]) _validate(value[key], props[key], _childPath(path, key), errors);
+                else if (schema.additionalProperties === false) errors.push({ path: _childPath(path, key), expected: "no such property", received: _typeOf(value[key]) });
+            }
+        }
+    }
+
+    t.validate = function (value, schema) {
+        const errors = [];
+        _validate(value, schema, "", errors);
+        if (errors.length === 0) return value;
+        if (t.env.TITAN_DEV === "1") {
+            t.fail("VALIDATION_FAILED", `Invalid request: ${errors.length} error(s)`, { details: { errors }, schema });
+        }
+        t.fail("VALIDATION_FAILED", "Invalid request");
+    };
     
+    // Field expansion (Stripe-style): ?expand=author,comments.author
+    //   if (t.query.expands("author")) post.author = drift(loadAuthor(post.authorId));
+    //   t.query.include(post, { comments: () => drift(loadComments(post.id)) });
+    // "comments.author" also expands its parent "comments".
+    const _expandOf = (req) => {
+        const raw = ((req || globalThis.__titan_req || {}).query || {}).expand;
+        if (!raw) return [];
+        let decoded = String(raw).replace(/\+/g, " ");
+        try { decoded = decodeURIComponent(decoded); } catch (_) { }
+        const out = [];
+        for (const part of decoded.split(",")) {
+            const name = part.trim();
+            if (name && !out.includes(name)) out.push(name);
+        }
+        return out;
+    };
+    t.query = {
+        expand(req) {
+            return _expandOf(req);
+        },
+        expands(name, req) {
+            const wanted = String(name);
+            return _expandOf(req).some((e) => e === wanted || e.startsWith(wanted + "."));
+        },
+        include(target, loaders, req) {
+            for (const name of Object.keys(loaders || {})) {
+                if (t.query.expands(name, req)) target[name] = loaders[name]();
+            }
+            return target;
+        },
+    };
+
+    // Result cache keyed by the action itself:
+    //   t.cache.response(`user:${req.params.id}`, 60000, () => drift(loadUser(req.params.id)))
+    // Entries live in the shared store (all isolates) for `ttlMs`. A key that
+    // missed stays a miss for the rest of that request, so a producer that
+    // drifts is replayed with the same drift sequence even if another request
+    // fills the key meanwhile.
+    if (t.cache && !t.cache.__titanWrapped) {
+        const _native = t.cache; // exposes: _native_get, _native_set, _native_delete, _native_invalidate
+        globalThis.__titan_cache_pins = {};
+
+        t.cache = {
+            response(key, ttlMs, producer) {
+                const k = "response:" + String(key);
+                const req = globalThis.__titan_req;
+                const requestId = req ? req.__titan_request_id : undefined;
+                const pins = requestId === undefined ? null
+                    : (globalThis.__titan_cache_pins[requestId] ||= new Set());
+
+                if (!pins || !pins.has(k)) {
+                    const hit = _native._native_get(k);
+                    if (hit !== undefined) return hit.value;
+                    if (pins) pins.add(k);
+                }
+
+                const result = producer();
+                if (result !== undefined) _native._native_set(k, result, Number(ttlMs));
+                return result;
+            },
+            delete(key) {
+                _native._native_delete("response:" + String(key));
+            },
+            // Drop route responses cached by `__config.cache` whose path (plus
+            // query string) starts with `prefix`, e.g. after a write:
+            //   t.cache.invalidate("/products");
+            invalidate(prefix) {
+                if (typeof prefix !== "string" || !prefix) {
+                    throw new Error("t.cache.invalidate(): path prefix required");
+                }
+                return _native._native_invalidate(prefix);
+            },
+        };
+
+        t.cache.__titanWrapped = true;
+    }
+
+    // Shared state across isolates; set/delete publish the change.
+    //   const { value } = t.share.subscribe("flags", (flags) => apply(flags), { timeoutMs: 30000 });
+    // subscribe drifts until the next write to `key`, so it works as a long poll:
+    // the callback runs once with the new value (null when deleted) and the
+    // update `{ key, value }` is returned, or `{ key, timeout: true }` if
+    // `timeoutMs` passes first. A replayed action gets the recorded update.
+    if (t.share && !t.share.__titanWrapped) {
+        t.share.subscribe = function (key, cb, options = {}) {
+            if (typeof cb === "object" && cb !== null) {
+                options = cb;
+                cb = undefined;
+            }
+            const update = drift(createAsyncOp({
+                __titanAsync: true,
+                type: "share_wait",
+                data: { key: String(key), timeoutMs: options.timeoutMs },
+            }));
+            if (update && update.error) {
+                throw new Error(`share.subscribe(): ${update.error}`);
+            }
+            if (typeof cb === "function" && !update.timeout) cb(update.value, update.key);
+            return update;
+        };
+        t.share.__titanWrapped = true;
+    }
+
     // Type Casting API
     t.types = {
         STRING: (val) => ({ _titanType: "string", value: String(val) }),
@@ -206,8 +850,20 @@ if (!globalThis.__TITAN_CORE_LOADED__) {
     };
 
     // Drift Support (Flexible: allows sync & async)
-    globalThis.drift = function (value) {
-        return t._drift_call(value);
+    // drift(op, { timeout? }) — past `timeout` ms the op is cancelled and the request answers 504.
+    globalThis.drift = function (value, options) {
+        return t._drift_call(value, options);
+    };
+
+    // drift.all([op1, op2, ...], { failFast?, timeout? }) — one drift, children run concurrently.
+    // Resolves to results in submission order; a failing child yields { error } in its slot
+    // unless failFast is set, in which case the first failure resolves the whole batch to
+    // { error, index } right away and the children still running are cancelled.
+    globalThis.drift.all = function (ops, options = {}) {
+        if (!Array.isArray(ops)) {
+            throw new Error("drift.all(): expected an array of async operations");
+        }
+        return t._drift_call(ops, options);
     };
 
     // Safe Wrappers
@@ -264,9 +920,118 @@ if (!globalThis.__TITAN_CORE_LOADED__) {
             return conn;
         };
 
+        // Keyset pagination:
+        //   const page = t.db.paginate(conn, { query: "SELECT id, name FROM users", sortColumn: "id", limit: 50, cursor: req.query.cursor });
+        //   return { users: page.rows, next: page.nextCursor };
+        // The query is wrapped as a subquery filtered on `sortColumn > <last key>`,
+        // so it must select sortColumn and the column should be unique. Cursors are
+        // HS256 tokens over the last key, signed with `secret` or TITAN_CURSOR_SECRET;
+        // a tampered, expired or foreign cursor fails the request with INVALID_CURSOR.
+        t.db.paginate = function (conn, options = {}) {
+            const { query, params = [], sortColumn, cursor, ttl = "1d" } = options;
+            const desc = String(options.direction || "asc").toLowerCase() === "desc";
+            const limit = Math.floor(Number(options.limit ?? 20));
+            const secret = options.secret || t.env.TITAN_CURSOR_SECRET;
+
+            if (!conn || typeof conn.query !== "function") {
+                throw new Error("db.paginate(): connection from t.db.connect() required");
+            }
+            if (typeof query !== "string" || !query.trim()) {
+                throw new Error("db.paginate(): query SQL string required");
+            }
+            if (!Array.isArray(params)) {
+                throw new Error("db.paginate(): params must be array");
+            }
+            if (typeof sortColumn !== "string" || !/^[A-Za-z_][A-Za-z0-9_]*$/.test(sortColumn)) {
+                throw new Error("db.paginate(): sortColumn must be a plain column name");
+            }
+            if (!(limit > 0)) {
+                throw new Error("db.paginate(): limit must be a positive number");
+            }
+            if (!secret) {
+                throw new Error("db.paginate(): pass options.secret or set TITAN_CURSOR_SECRET");
+            }
+
+            const scope = sortColumn + (desc ? ":desc" : ":asc");
+            const values = [...params];
+            let sql = `SELECT * FROM (${query.trim().replace(/;\s*$/, "")}) AS _titan_page`;
+            if (cursor) {
+                let claims;
+                try {
+                    claims = t.jwt.verify(String(cursor), secret);
+                } catch (_) {
+                    claims = null;
+                }
+                if (!claims || claims.c !== scope || !(claims.exp * 1000 > Date.now())) {
+                    t.fail("INVALID_CURSOR", "Invalid or expired cursor");
+                }
+                values.push(claims.k);
+                sql += ` WHERE "${sortColumn}" ${desc ? "<" : ">"} $${values.length}`;
+            }
+            sql += ` ORDER BY "${sortColumn}" ${desc ? "DESC" : "ASC"} LIMIT ${limit + 1}`;
+
+            const rows = drift(conn.query(sql, values, options.queryOptions));
+            if (!Array.isArray(rows)) {
+                throw new Error(`db.paginate(): ${(rows && rows.error) || "query failed"}`);
+            }
+
+            let nextCursor = null;
+            if (rows.length > limit) {
+                rows.length = limit;
+                nextCursor = t.jwt.sign({ k: rows[limit - 1][sortColumn], c: scope }, secret, { expiresIn: ttl });
+            }
+            return { rows, nextCursor };
+        };
+
         t.db.__titanWrapped = true;
     }
 
+    // =========================================================================
+    // t.services — shared resources surfaced per request
+    // =========================================================================
+    //
+    //   const { db, log, cache } = t.services;
+    //   t.service("mailer", (req) => createMailer(t.env.SMTP_URL));
+    //
+    // Each factory runs lazily, at most once per request, when its service is
+    // first read. Built in: db (t.db.connect(t.env.DATABASE_URL)), cache
+    // (t.cache) and log (t.log tagged with the request).
+    const serviceFactories = {
+        db: () => {
+            if (!t.env.DATABASE_URL) {
+                throw new Error("t.services.db: DATABASE_URL is not set");
+            }
+            return t.db.connect(t.env.DATABASE_URL);
+        },
+        cache: () => t.cache,
+        log: (req) => {
+            const tag = `[${req.method} ${req.path} #${req.__titan_request_id}]`;
+            return (...args) => t.log(tag, ...args);
+        },
+    };
+
+    t.service = function (name, factory) {
+        if (typeof factory !== "function") {
+            throw new Error(`t.service("${name}"): factory must be a function`);
+        }
+        serviceFactories[name] = factory;
+    };
+
+    globalThis.__titan_make_services = (req) => {
+        const services = {};
+        const built = {};
+        for (const name of Object.keys(serviceFactories)) {
+            Object.defineProperty(services, name, {
+                enumerable: true,
+                get() {
+                    if (!(name in built)) built[name] = serviceFactories[name](req);
+                    return built[name];
+                },
+            });
+        }
+        return services;
+    };
+
     // =========================================================================
     // t.task — Managed background job scheduler
     // =========================================================================
@@ -2,9 +2,10 @@ pub mod extensions;
 pub mod runtime;
 pub mod utils;
 pub mod native_host;
+pub mod panic;
 
-pub use runtime::{RuntimeManager, WorkerCommand, RequestTask, WorkerResult};
-pub use extensions::{TitanRuntime, WorkerAsyncResult, RequestData};
+pub use runtime::{RuntimeManager, WorkerCommand, RequestTask, RequestContext, WorkerResult, ExecuteError};
+pub use extensions::{TitanRuntime, WorkerAsyncResult, RequestData, FormData, FormFile, ClientInfo, EarlyHintsSink};
 pub use native_host::run_native_host;
 
 #[derive(Clone, Debug)]
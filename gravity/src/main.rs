@@ -1,8 +1,7 @@
 use clap::{Parser, Subcommand};
 use std::path::PathBuf;
 use std::fs;
-use gravity::{RuntimeManager, RequestTask};
-use smallvec::smallvec;
+use gravity::{RuntimeManager, RequestContext, RequestTask};
 use serde_json::json;
 mod utils;
 use crate::utils::{blue, green, yellow, red, bold, gray};
@@ -38,6 +37,8 @@ enum Commands {
     NativeHost {
         /// Path to the native DLL/SO file
         path: String,
+        /// Symbol that frees strings returned by the library (`titan.json` `native.free`)
+        free: Option<String>,
     },
 }
 
@@ -53,8 +54,8 @@ async fn main() -> anyhow::Result<()> {
             Commands::Install { package } | Commands::I { package } => {
                 handle_install(Some(package)).await?;
             }
-            Commands::NativeHost { path } => {
-                gravity::run_native_host(&path).await;
+            Commands::NativeHost { path, free } => {
+                gravity::run_native_host(&path, free.as_deref()).await;
                 return Ok(());
             }
         }
@@ -368,24 +369,21 @@ async fn handle_run(file: PathBuf) -> anyhow::Result<()> {
     }
 
     gravity::extensions::load_project_extensions(project_root.clone());
-    let runtime = RuntimeManager::new(project_root.clone(), threads, stack_size);
+    let runtime = RuntimeManager::new(project_root.clone(), threads, stack_size, false);
     runtime.load_action(action_name.clone(), code);
 
     if !is_direct {
         tokio::time::sleep(std::time::Duration::from_millis(150)).await;
         let exec_res = runtime.execute(
             action_name.clone(),
-            "GET".to_string(),
-            "/".to_string(),
-            None,
-            smallvec![],
-            smallvec![],
-            smallvec![]
+            RequestContext { method: "GET".to_string(), path: "/".to_string(), ..Default::default() },
         ).await;
 
         if let Ok((res, _)) = exec_res {
+            let res = gravity::extensions::expand_raw_json(res);
             let is_not_found = res.get("error").and_then(|e| e.as_str()).map(|s| s.contains("not found")).unwrap_or(false);
-            if !res.is_null() && res != json!({}) && !is_not_found {
+            let is_undefined = res.get("_isUndefined").is_some();
+            if !res.is_null() && res != json!({}) && !is_not_found && !is_undefined {
                 println!("\n{}", green("--- Action Result ---"));
                 println!("{}", serde_json::to_string_pretty(&res)?);
             }
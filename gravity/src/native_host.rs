@@ -2,8 +2,73 @@ use std::path::PathBuf;
 use std::io::{BufReader, BufRead, stdin, Write};
 use serde_json::{json, Value};
 use libloading::Library;
+use std::ffi::{CStr, CString};
+use std::os::raw::c_char;
+
+/// One argument of a direct C export call.
+#[derive(Clone, Copy)]
+enum NativeArg {
+    Num(f64),
+    Str(*const c_char),
+}
 
-pub async fn run_native_host(lib_path: &str) {
+/// Call a direct C export with the signature the arguments imply: each one is
+/// matched as `f64` or `*const c_char` in turn, and every combination becomes
+/// its own `extern "C" fn(..) -> *const c_char` transmute. Unsafe to expand.
+macro_rules! dispatch {
+    (@next $ptr:expr; [$(($ty:ty, $val:expr))*];) => {{
+        let f: extern "C" fn($($ty),*) -> *const c_char = std::mem::transmute($ptr);
+        f($($val),*)
+    }};
+    (@next $ptr:expr; [$($done:tt)*]; $arg:expr $(, $rest:expr)*) => {
+        match $arg {
+            NativeArg::Num(n) => dispatch!(@next $ptr; [$($done)* (f64, n)]; $($rest),*),
+            NativeArg::Str(s) => dispatch!(@next $ptr; [$($done)* (*const c_char, s)]; $($rest),*),
+        }
+    };
+    ($ptr:expr; $($arg:expr),*) => {
+        dispatch!(@next $ptr; []; $($arg),*)
+    };
+}
+
+/// `titan.json` `native.free`: releases a string the library returned.
+type FreeFn = extern "C" fn(*mut c_char);
+
+/// Hand a returned string back to the library once it has been copied. Without
+/// a `free` symbol it is leaked, as the host cannot know the library's allocator.
+fn release(c_res: *const c_char, free: Option<FreeFn>, warned: &mut bool) {
+    match free {
+        Some(free) => free(c_res as *mut c_char),
+        None if !*warned => {
+            *warned = true;
+            eprintln!("[NativeHost] Warning: no `free` symbol declared in titan.json; returned strings are leaked");
+        }
+        None => {}
+    }
+}
+
+/// Read the C string a direct export returned: JSON when it parses, else the raw text.
+unsafe fn read_native_result(c_res: *const c_char, free: Option<FreeFn>, warned: &mut bool) -> Value {
+    #[cfg(windows)]
+    let is_bad = {
+        unsafe extern "system" {
+            fn IsBadReadPtr(lp: *const std::os::raw::c_void, ucb: usize) -> i32;
+        }
+        unsafe { IsBadReadPtr(c_res as *const _, 1) != 0 }
+    };
+    #[cfg(not(windows))]
+    let is_bad = false;
+
+    if c_res.is_null() || is_bad {
+        json!({"error": "Native returned NULL or void pointer"})
+    } else {
+        let s = unsafe { CStr::from_ptr(c_res) }.to_string_lossy().into_owned();
+        release(c_res, free, warned);
+        serde_json::from_str(&s).unwrap_or(Value::String(s))
+    }
+}
+
+pub async fn run_native_host(lib_path: &str, free_symbol: Option<&str>) {
     let path = PathBuf::from(lib_path);
     if !path.exists() {
         eprintln!("[NativeHost] Error: library {:?} not found", path);
@@ -22,6 +87,15 @@ pub async fn run_native_host(lib_path: &str) {
         }
     };
 
+    let free: Option<FreeFn> = free_symbol.and_then(|name| match unsafe { lib.get::<FreeFn>(name.as_bytes()) } {
+        Ok(sym) => Some(*sym),
+        Err(_) => {
+            eprintln!("[NativeHost] Warning: free symbol '{}' not found in {:?}", name, canonical_path);
+            None
+        }
+    });
+    let mut leak_warned = false;
+
     let mut reader = BufReader::new(stdin());
 
     loop {
@@ -49,117 +123,43 @@ pub async fn run_native_host(lib_path: &str) {
                 if c_res.is_null() {
                     json!({"error": "titan_export returned NULL"})
                 } else {
-                    let s = std::ffi::CStr::from_ptr(c_res).to_string_lossy();
+                    let s = CStr::from_ptr(c_res).to_string_lossy().into_owned();
+                    release(c_res, free, &mut leak_warned);
                     serde_json::from_str(&s)
-                        .unwrap_or_else(|_| json!({"error": "DLL returned invalid JSON", "raw": s.to_string()}))
+                        .unwrap_or_else(|_| json!({"error": "DLL returned invalid JSON", "raw": s}))
                 }
             }
-            // Priority 2: Direct C export — multiple separate strings/f64s inferred at runtime
+            // Priority 2: Direct C export — numbers are passed as f64, everything else as a C string
             else if let Ok(sym) = lib.get::<*mut std::ffi::c_void>(function_name.as_bytes()) {
-                let mut c_args_ptrs = Vec::new();
-                let mut c_args_nums = Vec::new();
-                let mut arg_types = Vec::new(); // true = f64, false = string
-
-                let mut c_strings = Vec::new();
-
-                if let Some(arr) = params {
-                    for p in arr {
-                        if p.is_number() {
-                            arg_types.push(true);
-                            c_args_nums.push(p.as_f64().unwrap_or(0.0));
-                            // push dummy to keep indexes aligned
-                            c_strings.push(std::ffi::CString::new("").unwrap());
-                        } else {
-                            arg_types.push(false);
-                            c_args_nums.push(0.0);
-                            let s = match p {
-                                Value::String(vs) => vs.clone(),
-                                _ => p.to_string(),
-                            };
-                            c_strings.push(std::ffi::CString::new(s).unwrap_or_default());
-                        }
-                    }
-                }
-
-                for c_str in &c_strings {
-                    c_args_ptrs.push(c_str.as_ptr());
-                }
+                let c_strings: Vec<Option<CString>> = params
+                    .map(|arr| arr.iter().map(|p| match p {
+                        Value::Number(_) => None,
+                        Value::String(vs) => Some(CString::new(vs.as_str()).unwrap_or_default()),
+                        _ => Some(CString::new(p.to_string()).unwrap_or_default()),
+                    }).collect())
+                    .unwrap_or_default();
+                let args: Vec<NativeArg> = params
+                    .map(|arr| arr.iter().zip(&c_strings).map(|(p, c_str)| match c_str {
+                        Some(c_str) => NativeArg::Str(c_str.as_ptr()),
+                        None => NativeArg::Num(p.as_f64().unwrap_or(0.0)),
+                    }).collect())
+                    .unwrap_or_default();
 
                 let ptr = *sym;
-
-                // Combinatorial matching for up to 3 arguments
-                let arg_comb = |i: usize| -> bool { *arg_types.get(i).unwrap_or(&false) };
-
-                // Get argument as correct type (helper)
-                // We use raw macros/transmutes
-                let c_res: *const std::os::raw::c_char = match arg_types.len() {
-                    0 => {
-                        let f: extern "C" fn() -> *const std::os::raw::c_char = std::mem::transmute(ptr);
-                        f()
-                    }
-                    1 => {
-                        if arg_comb(0) {
-                            let f: extern "C" fn(f64) -> *const std::os::raw::c_char = std::mem::transmute(ptr);
-                            f(c_args_nums[0])
-                        } else {
-                            let f: extern "C" fn(*const std::os::raw::c_char) -> *const std::os::raw::c_char = std::mem::transmute(ptr);
-                            f(c_args_ptrs[0])
-                        }
-                    }
-                    2 => {
-                        match (arg_comb(0), arg_comb(1)) {
-                            (false, false) => {
-                                let f: extern "C" fn(*const std::os::raw::c_char, *const std::os::raw::c_char) -> *const std::os::raw::c_char = std::mem::transmute(ptr);
-                                f(c_args_ptrs[0], c_args_ptrs[1])
-                            }
-                            (false, true) => {
-                                let f: extern "C" fn(*const std::os::raw::c_char, f64) -> *const std::os::raw::c_char = std::mem::transmute(ptr);
-                                f(c_args_ptrs[0], c_args_nums[1])
-                            }
-                            (true, false) => {
-                                let f: extern "C" fn(f64, *const std::os::raw::c_char) -> *const std::os::raw::c_char = std::mem::transmute(ptr);
-                                f(c_args_nums[0], c_args_ptrs[1])
-                            }
-                            (true, true) => {
-                                let f: extern "C" fn(f64, f64) -> *const std::os::raw::c_char = std::mem::transmute(ptr);
-                                f(c_args_nums[0], c_args_nums[1])
-                            }
-                        }
-                    }
-                    3 => {
-                        match (arg_comb(0), arg_comb(1), arg_comb(2)) {
-                            (false, false, false) => {
-                                let f: extern "C" fn(*const std::os::raw::c_char, *const std::os::raw::c_char, *const std::os::raw::c_char) -> *const std::os::raw::c_char = std::mem::transmute(ptr);
-                                f(c_args_ptrs[0], c_args_ptrs[1], c_args_ptrs[2])
-                            }
-                            // Simplified for likely uses – 3 params are rare in core native extensions, fallback to all-strings if unsupported combo to prevent massive boilerplate
-                            _ => {
-                                let f: extern "C" fn(*const std::os::raw::c_char, *const std::os::raw::c_char, *const std::os::raw::c_char) -> *const std::os::raw::c_char = std::mem::transmute(ptr);
-                                f(c_args_ptrs[0], c_args_ptrs[1], c_args_ptrs[2])
-                            }
-                        }
-                    }
-                    _ => {
-                        let f: extern "C" fn(*const std::os::raw::c_char, *const std::os::raw::c_char, *const std::os::raw::c_char, *const std::os::raw::c_char) -> *const std::os::raw::c_char = std::mem::transmute(ptr);
-                        f(c_args_ptrs[0], c_args_ptrs[1], c_args_ptrs[2], c_args_ptrs[3])
-                    }
-                };
-
-                #[cfg(windows)]
-                let is_bad = {
-                    unsafe extern "system" {
-                        fn IsBadReadPtr(lp: *const std::os::raw::c_void, ucb: usize) -> i32;
-                    }
-                    IsBadReadPtr(c_res as *const _, 1) != 0
+                let a = &args;
+                let c_res: Option<*const c_char> = match args.len() {
+                    0 => Some(dispatch!(ptr;)),
+                    1 => Some(dispatch!(ptr; a[0])),
+                    2 => Some(dispatch!(ptr; a[0], a[1])),
+                    3 => Some(dispatch!(ptr; a[0], a[1], a[2])),
+                    4 => Some(dispatch!(ptr; a[0], a[1], a[2], a[3])),
+                    5 => Some(dispatch!(ptr; a[0], a[1], a[2], a[3], a[4])),
+                    6 => Some(dispatch!(ptr; a[0], a[1], a[2], a[3], a[4], a[5])),
+                    _ => None,
                 };
-                #[cfg(not(windows))]
-                let is_bad = false;
-
-                if c_res.is_null() || is_bad {
-                    json!({"error": "Native returned NULL or void pointer"})
-                } else {
-                    let s = std::ffi::CStr::from_ptr(c_res).to_string_lossy();
-                    serde_json::from_str(&s).unwrap_or_else(|_| Value::String(s.to_string()))
+                match c_res {
+                    Some(c_res) => read_native_result(c_res, free, &mut leak_warned),
+                    None => json!({"error": format!("Direct C exports take at most 6 arguments, got {}; use titan_export for more", args.len())}),
                 }
             } else {
                 json!({"error": format!("Function '{}' not found in DLL", function_name)})
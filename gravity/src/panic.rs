@@ -0,0 +1,96 @@
+//! Worker Panic Policy
+//!
+//! `__config.onPanic` decides what a panic in a worker thread does:
+//! - `recover` (default): the worker catches it, fails the request it was
+//!   running with an error and keeps serving.
+//! - `abort`: a crash report is written next to the project, logs are
+//!   flushed and the process aborts so the orchestrator can restart it.
+//!
+//! Both modes log every panic as a `panic` event and count it. The policy
+//! covers worker threads only; a panic anywhere else (tokio, reqwest) gets
+//! Rust's default hook and never aborts the process.
+
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::utils::{blue, log_with, red, LogLevel};
+
+static PANICS: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PanicPolicy {
+    Recover,
+    Abort,
+}
+
+impl PanicPolicy {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "recover" => Some(Self::Recover),
+            "abort" => Some(Self::Abort),
+            _ => None,
+        }
+    }
+}
+
+/// Name prefix of the worker threads the policy applies to.
+pub const WORKER_THREAD_PREFIX: &str = "titan-worker-";
+
+/// Worker panics observed since startup.
+pub fn panic_count() -> u64 {
+    PANICS.load(Ordering::Relaxed)
+}
+
+/// Install the worker panic hook. `crash_dir` receives `titan-crash-<ts>.log`
+/// in abort mode.
+pub fn install(policy: PanicPolicy, crash_dir: PathBuf) {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let thread = std::thread::current().name().unwrap_or("unnamed").to_string();
+        if !thread.starts_with(WORKER_THREAD_PREFIX) {
+            default_hook(info);
+            return;
+        }
+        let count = PANICS.fetch_add(1, Ordering::Relaxed) + 1;
+        let message = info
+            .payload()
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| info.payload().downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "Box<dyn Any>".to_string());
+        let location = info.location().map(|l| format!("{}:{}", l.file(), l.line())).unwrap_or_default();
+        let abort = policy == PanicPolicy::Abort;
+
+        log_with(
+            LogLevel::Error,
+            "panic",
+            serde_json::json!({
+                "thread": thread,
+                "message": message,
+                "location": location,
+                "count": count,
+                "action": if abort { "abort" } else { "recover" },
+            }),
+            || format!("{} {} thread '{}' panicked at {}: {}", blue("[Titan]"), red("PANIC:"), thread, location, message),
+        );
+
+        if abort {
+            let report = format!(
+                "thread: {}\nlocation: {}\nmessage: {}\n\n{}\n",
+                thread,
+                location,
+                message,
+                std::backtrace::Backtrace::force_capture()
+            );
+            let ts = chrono::Utc::now().format("%Y%m%dT%H%M%S");
+            let path = crash_dir.join(format!("titan-crash-{}.log", ts));
+            if std::fs::write(&path, report).is_ok() {
+                eprintln!("{} crash report written to {}", blue("[Titan]"), path.display());
+            }
+            let _ = std::io::stdout().flush();
+            let _ = std::io::stderr().flush();
+            std::process::abort();
+        }
+    }));
+}
@@ -3,18 +3,110 @@
 use bytes::Bytes;
 use crossbeam::channel::{bounded, Sender, TrySendError};
 use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::panic::{catch_unwind, AssertUnwindSafe};
 use std::thread;
+use std::time::{Duration, Instant};
 use tokio::sync::mpsc;
 use tokio::sync::oneshot;
 use smallvec::SmallVec;
 
-use crate::extensions::{self, AsyncOpRequest, TitanRuntime, WorkerAsyncResult};
+use crate::utils::LogLevel;
+use crate::extensions::{self, AsyncOpRequest, ClientInfo, EarlyHintsSink, FormData, SpilledBody, TitanRuntime, WorkerAsyncResult};
+
+/// `__config.maxQueueDepth`: requests each worker's channel holds before
+/// `try_execute` reports the pool as overloaded.
+static MAX_QUEUE_DEPTH: AtomicUsize = AtomicUsize::new(256);
+
+/// Call before the pool starts; channels are sized when workers spawn.
+pub fn set_max_queue_depth(depth: usize) {
+    MAX_QUEUE_DEPTH.store(depth.max(1), Ordering::Relaxed);
+}
+
+/// Why an action didn't produce a result.
+#[derive(Debug)]
+pub enum ExecuteError {
+    /// Every worker's queue was full; nothing was enqueued (`try_execute` only)
+    Overloaded,
+    /// The worker went away before answering
+    Failed(String),
+}
 
 pub struct RuntimeManager {
-    request_txs: Vec<Sender<WorkerCommand>>,
+    /// Workers taking new requests: the baseline pool first, elastic
+    /// workers appended and retired at the end.
+    request_txs: RwLock<Vec<PoolWorker>>,
+    /// Breaks ties between equally loaded workers
     round_robin_counter: AtomicUsize,
-    num_workers: usize,
-    _workers: Vec<thread::JoinHandle<()>>,
+    /// Size of the pool at startup; the autoscaler never retires below it
+    baseline: usize,
+    /// Requests handed to `execute` that have not been answered yet
+    in_flight: AtomicUsize,
+    /// Workers that have processed `WorkerCommand::Ready`
+    ready_workers: Arc<AtomicUsize>,
+    /// Workers currently running JS for a request or a drift resume
+    busy_workers: Arc<AtomicUsize>,
+    /// `request_txs.len()`, kept in step under its write lock so workers
+    /// (`t.system.stats()`) can read it without taking the lock themselves
+    pool_len: AtomicUsize,
+    started_at: Instant,
+    workers: Mutex<Vec<thread::JoinHandle<()>>>,
+    spawner: WorkerSpawner,
+    /// Every action loaded so far, replayed into elastic workers before they take traffic
+    loaded_actions: Mutex<Vec<(String, String)>>,
+}
+
+/// Everything needed to start one more worker thread.
+struct WorkerSpawner {
+    project_root: std::path::PathBuf,
+    stack_size: usize,
+    tokio_handle: tokio::runtime::Handle,
+    async_tx: mpsc::Sender<AsyncOpRequest>,
+    /// Core ids for pinning; None when disabled or the platform can't report them
+    core_ids: Option<Vec<core_affinity::CoreId>>,
+    ready_workers: Arc<AtomicUsize>,
+    busy_workers: Arc<AtomicUsize>,
+    next_id: AtomicUsize,
+}
+
+/// Decrements `in_flight` when `execute` returns or its future is dropped.
+struct InFlightGuard<'a>(&'a AtomicUsize);
+
+impl Drop for InFlightGuard<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// A worker's channel plus the requests dispatched to it and not yet
+/// answered, queued or running (suspended on a drift included).
+struct PoolWorker {
+    tx: Sender<WorkerCommand>,
+    load: Arc<AtomicUsize>,
+}
+
+impl PoolWorker {
+    fn new(tx: Sender<WorkerCommand>) -> Self {
+        Self { tx, load: Arc::new(AtomicUsize::new(0)) }
+    }
+}
+
+impl std::ops::Deref for PoolWorker {
+    type Target = Sender<WorkerCommand>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.tx
+    }
+}
+
+/// Counts a request against its worker's load until it is answered (or the
+/// caller stops waiting).
+struct LoadGuard(Arc<AtomicUsize>);
+
+impl Drop for LoadGuard {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
 }
 
 pub enum WorkerCommand {
@@ -27,18 +119,71 @@ pub enum WorkerCommand {
         name: String,
         code: String,
     },
+    /// Swap in a rebuilt action (dev hot reload). Deferred on a worker while
+    /// a request suspended on a drift still runs the old version.
+    Reload {
+        name: String,
+        source: String,
+    },
+    /// Queued after the startup `LoadAction`s; reaching it means the isolate
+    /// is initialized and has every action loaded. The sender, if any, is
+    /// notified at that point.
+    Ready(Option<oneshot::Sender<()>>),
+    /// Sent after everything else in the channel; the worker exits once it gets here
+    Shutdown,
+    /// Sent to an elastic worker no longer taking requests: it exits once its
+    /// channel is empty and no request is suspended on a drift
+    Retire,
+    /// Write a V8 heap snapshot (DevTools `.heapsnapshot` JSON) into the
+    /// channel. Handled between commands, so no JS is running meanwhile.
+    HeapSnapshot(mpsc::Sender<Bytes>),
 }
 
 #[allow(dead_code)]
 pub struct RequestTask {
     pub action_name: String,
     pub body: Option<Bytes>,
+    /// Parsed multipart body (`req.formData`); `body` is `None` when set
+    pub form: Option<Arc<FormData>>,
+    /// Body spilled to disk (`req.bodyFile`); `body` is `None` when set
+    pub body_file: Option<Arc<SpilledBody>>,
+    /// Route middleware run before the action, in order (routes.json `middleware`)
+    pub middleware: Option<Arc<[String]>>,
+    /// Resolved caller (`req.ip`, `req.protocol`); `None` for internal calls
+    pub client: Option<ClientInfo>,
+    /// Connection `t.response.earlyHints()` writes its 103 to; `None` for internal calls
+    pub early_hints: Option<EarlyHintsSink>,
     pub method: String,
     pub path: String,
     pub headers: SmallVec<[(String, String); 8]>,
     pub params: SmallVec<[(String, String); 4]>,
-    pub query: SmallVec<[(String, String); 4]>,
+    pub query: SmallVec<[(String, serde_json::Value); 4]>,
     pub response_tx: oneshot::Sender<WorkerResult>,
+    /// Set in `execute`; the worker reports the wait as the `queue` timing.
+    pub enqueued_at: Instant,
+}
+
+/// What an action gets to see of the request it runs for. Internal callers
+/// (tasks, WebSocket events, the CLI) fill in what they have and leave the
+/// rest to `Default`.
+#[derive(Default)]
+pub struct RequestContext {
+    pub method: String,
+    pub path: String,
+    pub body: Option<Bytes>,
+    /// Parsed multipart body (`req.formData`); `body` is `None` when set
+    pub form: Option<Arc<FormData>>,
+    /// Body spilled to disk (`req.bodyFile`); `body` is `None` when set
+    pub body_file: Option<Arc<SpilledBody>>,
+    /// Route middleware run before the action, in order (routes.json `middleware`)
+    pub middleware: Option<Arc<[String]>>,
+    /// Resolved caller (`req.ip`, `req.protocol`)
+    pub client: Option<ClientInfo>,
+    /// Connection `t.response.earlyHints()` writes its 103 to
+    pub early_hints: Option<EarlyHintsSink>,
+    pub headers: SmallVec<[(String, String); 8]>,
+    pub params: SmallVec<[(String, String); 4]>,
+    pub query: SmallVec<[(String, serde_json::Value); 4]>,
 }
 
 pub struct WorkerResult {
@@ -51,6 +196,7 @@ impl RuntimeManager {
         project_root: std::path::PathBuf,
         num_threads: usize,
         stack_size: usize,
+        cpu_affinity: bool,
     ) -> Self {
         let (async_tx, mut async_rx) = mpsc::channel::<AsyncOpRequest>(2048);
         let tokio_handle = tokio::runtime::Handle::current();
@@ -60,154 +206,565 @@ impl RuntimeManager {
             while let Some(req) = async_rx.recv().await {
                 let drift_id = req.drift_id;
                 let respond_tx = req.respond_tx;
+                let timeout = req.timeout;
                 tokio::spawn(async move {
                     let start = std::time::Instant::now();
-                    let result = extensions::builtins::system::run_async_operation(req.op).await;
+                    let op = extensions::builtins::system::run_async_operation(req.op);
+                    // Expiry drops the op's future, so a hung fetch or query stops being polled
+                    let (result, timed_out) = match timeout {
+                        Some(limit) => match tokio::time::timeout(limit, op).await {
+                            Ok(result) => (result, false),
+                            Err(_) => (serde_json::json!({ "error": "drift timeout" }), true),
+                        },
+                        None => (op.await, false),
+                    };
                     let duration_ms = start.elapsed().as_secs_f64() * 1000.0;
                     let _ = respond_tx.send(WorkerAsyncResult {
                         drift_id,
                         result,
                         duration_ms,
+                        timed_out,
                     });
                 });
             }
         });
 
-        // Create worker channels
-        let channel_capacity = 256;
-        let mut workers = Vec::with_capacity(num_threads);
-
-        let mut channels: Vec<(Sender<WorkerCommand>, crossbeam::channel::Receiver<WorkerCommand>)> =
-            Vec::with_capacity(num_threads);
+        let ready_workers = Arc::new(AtomicUsize::new(0));
+        let busy_workers = Arc::new(AtomicUsize::new(0));
+        let spawner = WorkerSpawner {
+            project_root,
+            stack_size,
+            tokio_handle,
+            async_tx,
+            core_ids: if cpu_affinity { pin_plan(num_threads) } else { None },
+            ready_workers: ready_workers.clone(),
+            busy_workers: busy_workers.clone(),
+            next_id: AtomicUsize::new(0),
+        };
 
+        // Spawn Worker Threads
+        let mut txs = Vec::with_capacity(num_threads);
+        let mut workers = Vec::with_capacity(num_threads);
         for _ in 0..num_threads {
-            let (tx, rx) = bounded(channel_capacity);
-            channels.push((tx, rx));
+            let (tx, handle) = spawner.spawn();
+            txs.push(PoolWorker::new(tx));
+            workers.push(handle);
         }
 
-        let mut final_txs: Vec<Sender<WorkerCommand>> = Vec::with_capacity(num_threads);
-        for (tx, _) in &channels {
-            final_txs.push(tx.clone());
+        Self {
+            request_txs: RwLock::new(txs),
+            round_robin_counter: AtomicUsize::new(0),
+            baseline: num_threads,
+            in_flight: AtomicUsize::new(0),
+            ready_workers,
+            busy_workers,
+            pool_len: AtomicUsize::new(num_threads),
+            started_at: Instant::now(),
+            workers: Mutex::new(workers),
+            spawner,
+            loaded_actions: Mutex::new(Vec::new()),
         }
+    }
 
-        // Spawn Worker Threads
-        for (i, (tx, rx)) in channels.into_iter().enumerate() {
-            let my_tx = tx.clone();
-            let root = project_root.clone();
-            let handle = tokio_handle.clone();
-            let async_tx = async_tx.clone();
-
-            let handle = thread::Builder::new()
-                .name(format!("titan-worker-{}", i))
-                .stack_size(stack_size)
-                .spawn(move || {
-                    let mut rt = extensions::init_runtime_worker(
-                        i,
-                        root,
-                        my_tx,
-                        handle,
-                        async_tx,
-                        stack_size,
-                    );
-                    rt.bind_to_isolate();
-
-                    loop {
-                        match rx.recv() {
-                            Ok(cmd) => match cmd {
-                                WorkerCommand::Request(task) => {
-                                    handle_new_request(task, &mut rt);
-                                }
-                                WorkerCommand::Resume { drift_id, result } => {
-                                    handle_resume(drift_id, result, &mut rt);
-                                }
-                                WorkerCommand::LoadAction { name, code } => {
-                                    rt.load_action(name, code);
-                                }
-                            },
-                            Err(_) => break,
-                        }
-                    }
-                })
-                .expect("Failed to spawn worker");
-
-            workers.push(handle);
+    /// Call once the startup actions have been queued with `load_action`;
+    /// `is_ready` flips when every worker has worked through them.
+    pub fn mark_loaded(&self) {
+        for tx in self.request_txs.read().unwrap().iter() {
+            let _ = tx.send(WorkerCommand::Ready(None));
         }
+    }
 
-        Self {
-            request_txs: final_txs,
-            round_robin_counter: AtomicUsize::new(0),
-            num_workers: num_threads,
-            _workers: workers,
+    /// Every isolate is initialized and has loaded the startup actions.
+    pub fn is_ready(&self) -> bool {
+        self.ready_workers.load(Ordering::Acquire) >= self.baseline
+    }
+
+    /// Requests currently executing or suspended on a drift.
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.load(Ordering::Relaxed)
+    }
+
+    /// Workers currently taking requests (baseline plus elastic).
+    pub fn pool_size(&self) -> usize {
+        self.pool_len.load(Ordering::Relaxed)
+    }
+
+    /// Time since the pool was created.
+    pub fn uptime(&self) -> Duration {
+        self.started_at.elapsed()
+    }
+
+    /// Workers executing JS right now; the rest of the pool is free.
+    pub fn busy_workers(&self) -> usize {
+        self.busy_workers.load(Ordering::Relaxed)
+    }
+
+    /// Ask every worker to exit after finishing what is already queued,
+    /// then wait for the threads. Call only once requests have drained.
+    pub fn shutdown(&self) {
+        for tx in self.request_txs.read().unwrap().iter() {
+            let _ = tx.send(WorkerCommand::Shutdown);
+        }
+        let workers = std::mem::take(&mut *self.workers.lock().unwrap());
+        for handle in workers {
+            let _ = handle.join();
         }
     }
 
     /// Load an action into all workers in the pool.
     pub fn load_action(&self, name: String, code: String) {
-        for tx in &self.request_txs {
+        let mut loaded = self.loaded_actions.lock().unwrap();
+        for tx in self.request_txs.read().unwrap().iter() {
             let _ = tx.send(WorkerCommand::LoadAction {
                 name: name.clone(),
                 code: code.clone(),
             });
         }
+        loaded.push((name, code));
+    }
+
+    /// Replace an action in every worker without restarting the pool. Recorded
+    /// after the original so a worker started later replays it last.
+    pub fn reload_action(&self, name: String, source: String) {
+        let mut loaded = self.loaded_actions.lock().unwrap();
+        for tx in self.request_txs.read().unwrap().iter() {
+            let _ = tx.send(WorkerCommand::Reload {
+                name: name.clone(),
+                source: source.clone(),
+            });
+        }
+        loaded.push((name, source));
+    }
+
+    /// Heap snapshot of the worker at position `isolate` in the pool, streamed
+    /// in chunks as V8 serializes it. `None` if there is no such worker. The
+    /// worker takes no other work until the snapshot is written or the
+    /// receiver is dropped.
+    pub fn heap_snapshot(&self, isolate: usize) -> Option<mpsc::Receiver<Bytes>> {
+        let tx = self.request_txs.read().unwrap().get(isolate)?.tx.clone();
+        let (chunk_tx, chunk_rx) = mpsc::channel(16);
+        tx.send(WorkerCommand::HeapSnapshot(chunk_tx)).ok()?;
+        Some(chunk_rx)
+    }
+
+    /// Execute an action on the least-loaded worker (round-robin among equals),
+    /// moving on to the next one whose queue has room; when every queue is full
+    /// it waits for room on the first choice (tasks, WebSocket events).
+    pub async fn execute(&self, action: String, ctx: RequestContext) -> Result<(serde_json::Value, Vec<(String, f64)>), String> {
+        self.dispatch(action, ctx, true).await.map_err(|e| match e {
+            ExecuteError::Failed(msg) => msg,
+            ExecuteError::Overloaded => "Worker queues are full".to_string(),
+        })
     }
 
-    /// Execute an action on a worker. Uses round-robin with work-stealing fallback.
-    pub async fn execute(
+    /// Like `execute`, but returns `ExecuteError::Overloaded` right away when
+    /// every worker's queue is full, so HTTP requests can be shed with a 503
+    /// instead of blocking the connection's task.
+    pub async fn try_execute(&self, action: String, ctx: RequestContext) -> Result<(serde_json::Value, Vec<(String, f64)>), ExecuteError> {
+        self.dispatch(action, ctx, false).await
+    }
+
+    async fn dispatch(
         &self,
         action: String,
-        method: String,
-        path: String,
-        body: Option<Bytes>,
-        headers: SmallVec<[(String, String); 8]>,
-        params: SmallVec<[(String, String); 4]>,
-        query: SmallVec<[(String, String); 4]>,
-    ) -> Result<(serde_json::Value, Vec<(String, f64)>), String> {
+        ctx: RequestContext,
+        wait_when_full: bool,
+    ) -> Result<(serde_json::Value, Vec<(String, f64)>), ExecuteError> {
+        self.in_flight.fetch_add(1, Ordering::Relaxed);
+        let _in_flight = InFlightGuard(&self.in_flight);
+
         let (tx, rx) = oneshot::channel();
         let task = RequestTask {
             action_name: action,
-            body,
-            method,
-            path,
-            headers,
-            params,
-            query,
+            body: ctx.body,
+            form: ctx.form,
+            body_file: ctx.body_file,
+            middleware: ctx.middleware,
+            client: ctx.client,
+            early_hints: ctx.early_hints,
+            method: ctx.method,
+            path: ctx.path,
+            headers: ctx.headers,
+            params: ctx.params,
+            query: ctx.query,
             response_tx: tx,
+            enqueued_at: Instant::now(),
         };
 
-        // Work-Stealing Distribution
-        let start_idx = self.round_robin_counter.fetch_add(1, Ordering::Relaxed) % self.num_workers;
-        let mut cmd = WorkerCommand::Request(task);
-
-        for attempt in 0..self.num_workers {
-            let idx = (start_idx + attempt) % self.num_workers;
-            match self.request_txs[idx].try_send(cmd) {
-                Ok(()) => {
-                    return match rx.await {
-                        Ok(res) => Ok((res.json, res.timings)),
-                        Err(_) => Err("Worker channel closed".to_string()),
-                    };
-                }
-                Err(TrySendError::Full(returned)) => {
-                    cmd = returned;
+        // Least-loaded first, so a worker stuck on slow requests stops
+        // attracting new ones; the rotating start breaks ties round-robin.
+        // The pool lock is never held across an await.
+        let (_load, fallback) = {
+            let txs = self.request_txs.read().unwrap();
+            let num_workers = txs.len();
+            let start_idx = self.round_robin_counter.fetch_add(1, Ordering::Relaxed) % num_workers;
+            let best = (0..num_workers)
+                .map(|i| (start_idx + i) % num_workers)
+                .min_by_key(|&idx| txs[idx].load.load(Ordering::Relaxed))
+                .unwrap_or(start_idx);
+            let mut cmd = Some(WorkerCommand::Request(task));
+            let mut target = best;
+
+            for attempt in 0..num_workers {
+                let idx = (best + attempt) % num_workers;
+                // Counted before the send so a concurrent pick already sees it
+                txs[idx].load.fetch_add(1, Ordering::Relaxed);
+                match txs[idx].try_send(cmd.take().unwrap()) {
+                    Ok(()) => {
+                        target = idx;
+                        break;
+                    }
+                    Err(TrySendError::Full(returned)) => {
+                        txs[idx].load.fetch_sub(1, Ordering::Relaxed);
+                        cmd = Some(returned);
+                    }
+                    Err(TrySendError::Disconnected(_)) => {
+                        txs[idx].load.fetch_sub(1, Ordering::Relaxed);
+                        return Err(ExecuteError::Failed("Worker disconnected".to_string()));
+                    }
                 }
-                Err(TrySendError::Disconnected(_)) => {
-                    return Err("Worker disconnected".to_string());
+            }
+            // All workers full — shed the request before it counts anywhere
+            if cmd.is_some() {
+                if !wait_when_full {
+                    return Err(ExecuteError::Overloaded);
                 }
+                txs[target].load.fetch_add(1, Ordering::Relaxed);
             }
-        }
+            let load = LoadGuard(txs[target].load.clone());
+            (load, cmd.map(|cmd| (txs[target].tx.clone(), cmd)))
+        };
 
-        // All workers full — blocking send to the original target as last resort
-        self.request_txs[start_idx]
-            .send(cmd)
-            .map_err(|e| e.to_string())?;
+        // Block on the first choice as last resort
+        if let Some((tx, cmd)) = fallback {
+            tx.send(cmd).map_err(|e| ExecuteError::Failed(e.to_string()))?;
+        }
 
         match rx.await {
             Ok(res) => Ok((res.json, res.timings)),
-            Err(_) => Err("Worker channel closed".to_string()),
+            Err(_) => Err(ExecuteError::Failed("Worker channel closed".to_string())),
         }
     }
 }
 
+impl RuntimeManager {
+    /// Elastic pool: grow by one worker at a time up to `max` while every
+    /// worker has requests queued for a sustained period, and retire elastic
+    /// workers (never the baseline) after `cooldown` without that pressure.
+    pub fn start_autoscaler(self: Arc<Self>, max: usize, cooldown: Duration) {
+        if max <= self.baseline {
+            return;
+        }
+        const TICK: Duration = Duration::from_millis(100);
+        const SUSTAINED: Duration = Duration::from_millis(500);
+
+        self.spawner.tokio_handle.clone().spawn(async move {
+            let mut saturated_for = Duration::ZERO;
+            let mut idle_for = Duration::ZERO;
+            loop {
+                tokio::time::sleep(TICK).await;
+                let (current, free) = {
+                    let txs = self.request_txs.read().unwrap();
+                    (txs.len(), txs.iter().filter(|tx| tx.is_empty()).count())
+                };
+
+                if free == 0 {
+                    saturated_for += TICK;
+                    idle_for = Duration::ZERO;
+                } else {
+                    saturated_for = Duration::ZERO;
+                    if self.in_flight() < current.saturating_sub(1) {
+                        idle_for += TICK;
+                    } else {
+                        idle_for = Duration::ZERO;
+                    }
+                }
+
+                if saturated_for >= SUSTAINED && current < max {
+                    saturated_for = Duration::ZERO;
+                    if self.grow().await {
+                        log_pool(LogLevel::Info, &format!("pool grew to {} workers (max {})", current + 1, max));
+                    }
+                } else if idle_for >= cooldown && current > self.baseline {
+                    idle_for = Duration::ZERO;
+                    self.retire_one();
+                    log_pool(LogLevel::Info, &format!("pool shrank to {} workers (baseline {})", current - 1, self.baseline));
+                }
+            }
+        });
+    }
+
+    /// Start a worker, load every action into it and only then let it take requests.
+    async fn grow(&self) -> bool {
+        let (tx, handle) = self.spawner.spawn();
+        self.workers.lock().unwrap().push(handle);
+
+        let (ready_tx, ready_rx) = oneshot::channel();
+        let replayed = {
+            let actions = self.loaded_actions.lock().unwrap();
+            for (name, code) in actions.iter() {
+                let _ = tx.send(WorkerCommand::LoadAction { name: name.clone(), code: code.clone() });
+            }
+            let _ = tx.send(WorkerCommand::Ready(Some(ready_tx)));
+            actions.len()
+        };
+        if ready_rx.await.is_err() {
+            return false;
+        }
+
+        // Catch actions loaded while the isolate was starting, then publish it
+        // under the same lock `load_action` takes so none slip in between
+        let actions = self.loaded_actions.lock().unwrap();
+        for (name, code) in actions.iter().skip(replayed) {
+            let _ = tx.send(WorkerCommand::LoadAction { name: name.clone(), code: code.clone() });
+        }
+        let mut txs = self.request_txs.write().unwrap();
+        txs.push(PoolWorker::new(tx));
+        self.pool_len.store(txs.len(), Ordering::Relaxed);
+        true
+    }
+
+    /// Stop routing to the newest elastic worker; it exits once drained.
+    fn retire_one(&self) {
+        let tx = {
+            let mut txs = self.request_txs.write().unwrap();
+            if txs.len() <= self.baseline {
+                return;
+            }
+            let tx = txs.pop();
+            self.pool_len.store(txs.len(), Ordering::Relaxed);
+            tx
+        };
+        if let Some(tx) = tx {
+            let _ = tx.send(WorkerCommand::Retire);
+        }
+    }
+}
+
+impl WorkerSpawner {
+    fn spawn(&self) -> (Sender<WorkerCommand>, thread::JoinHandle<()>) {
+        let i = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let (tx, rx) = bounded(MAX_QUEUE_DEPTH.load(Ordering::Relaxed));
+
+        let core = self.core_ids.as_ref().map(|ids| ids[i % ids.len()]);
+        let my_tx = tx.clone();
+        let root = self.project_root.clone();
+        let handle = self.tokio_handle.clone();
+        let async_tx = self.async_tx.clone();
+        let ready = self.ready_workers.clone();
+        let busy = self.busy_workers.clone();
+        let stack_size = self.stack_size;
+
+        let handle = thread::Builder::new()
+            .name(format!("{}{}", crate::panic::WORKER_THREAD_PREFIX, i))
+            .stack_size(stack_size)
+            .spawn(move || {
+                if let Some(core) = core {
+                    if !core_affinity::set_for_current(core) {
+                        log_affinity(LogLevel::Warn, &format!("titan-worker-{} could not be pinned to core {}", i, core.id));
+                    }
+                }
+
+                let new_runtime = move || extensions::init_runtime_worker(i, root.clone(), my_tx.clone(), handle.clone(), async_tx.clone(), stack_size);
+                let mut rt = new_runtime();
+                rt.bind_to_isolate();
+
+                // Panics only reach catch_unwind under `onPanic: "recover"`;
+                // in abort mode the hook ends the process first.
+                let mut retiring = false;
+                // Reloads waiting for suspended requests on the old version to finish
+                let mut deferred_reloads: Vec<(String, String)> = Vec::new();
+                // Every action as currently loaded, for a replacement isolate
+                let mut loaded: Vec<(String, String)> = Vec::new();
+                loop {
+                    let mut panicked = false;
+                    match rx.recv() {
+                        Ok(cmd) => match cmd {
+                            WorkerCommand::Request(task) => {
+                                busy.fetch_add(1, Ordering::Relaxed);
+                                panicked = catch_unwind(AssertUnwindSafe(|| {
+                                    handle_new_request(task, &mut rt);
+                                    raise_requested_panic(&mut rt);
+                                }))
+                                .is_err();
+                                busy.fetch_sub(1, Ordering::Relaxed);
+                            }
+                            WorkerCommand::Resume { drift_id, result } => {
+                                busy.fetch_add(1, Ordering::Relaxed);
+                                panicked = catch_unwind(AssertUnwindSafe(|| {
+                                    handle_resume(drift_id, result, &mut rt);
+                                    raise_requested_panic(&mut rt);
+                                }))
+                                .is_err();
+                                busy.fetch_sub(1, Ordering::Relaxed);
+                            }
+                            WorkerCommand::LoadAction { name, code } => {
+                                remember_action(&mut loaded, &name, &code);
+                                let _ = catch_unwind(AssertUnwindSafe(|| rt.load_action(name, code)));
+                            }
+                            WorkerCommand::Reload { name, source } => {
+                                deferred_reloads.retain(|(pending, _)| *pending != name);
+                                deferred_reloads.push((name, source));
+                            }
+                            WorkerCommand::Ready(notify) => {
+                                ready.fetch_add(1, Ordering::Release);
+                                if let Some(notify) = notify {
+                                    let _ = notify.send(());
+                                }
+                            }
+                            WorkerCommand::Shutdown => break,
+                            WorkerCommand::Retire => retiring = true,
+                            WorkerCommand::HeapSnapshot(chunks) => {
+                                // A dropped receiver (client gone) aborts serialization
+                                rt.isolate.take_heap_snapshot(|chunk| chunks.blocking_send(Bytes::copy_from_slice(chunk)).is_ok());
+                            }
+                        },
+                        Err(_) => break,
+                    }
+                    if panicked {
+                        // The unwind may have left V8 mid-operation, so nothing
+                        // more runs on this isolate: every request it holds
+                        // fails and a fresh one takes over with the same actions.
+                        // Ids continue where they were, so a late resume for a
+                        // drift of the old isolate matches nothing.
+                        fail_after_panic(&mut rt);
+                        let counters = (rt.request_counter, rt.drift_counter);
+                        // Isolates must be dropped before the next one is created
+                        drop(rt);
+                        rt = new_runtime();
+                        rt.bind_to_isolate();
+                        (rt.request_counter, rt.drift_counter) = counters;
+                        for (name, code) in &loaded {
+                            let _ = catch_unwind(AssertUnwindSafe(|| rt.load_action(name.clone(), code.clone())));
+                        }
+                    }
+                    if !deferred_reloads.is_empty() {
+                        deferred_reloads.retain(|(name, source)| {
+                            if action_in_use(&rt, name) {
+                                return true;
+                            }
+                            if apply_reload(&mut rt, name, source) {
+                                remember_action(&mut loaded, name, source);
+                            }
+                            false
+                        });
+                    }
+                    // Suspended requests still need their Resume, so wait them out
+                    if retiring && rx.is_empty() && rt.pending_requests.is_empty() && rt.response_streams.is_empty() {
+                        break;
+                    }
+                }
+            })
+            .expect("Failed to spawn worker");
+
+        (tx, handle)
+    }
+}
+
+/// Core ids for `__config.cpuAffinity`. Workers are assigned round-robin, so
+/// with more workers than cores some share a core.
+fn pin_plan(num_threads: usize) -> Option<Vec<core_affinity::CoreId>> {
+    match core_affinity::get_core_ids().filter(|ids| !ids.is_empty()) {
+        Some(ids) => {
+            log_affinity(LogLevel::Info, &format!("pinning {} workers across {} cores", num_threads, ids.len()));
+            Some(ids)
+        }
+        None => {
+            log_affinity(LogLevel::Warn, "cpuAffinity is not supported on this platform, workers are unpinned");
+            None
+        }
+    }
+}
+
+fn log_affinity(level: LogLevel, message: &str) {
+    log_runtime("affinity", level, message);
+}
+
+fn log_pool(level: LogLevel, message: &str) {
+    log_runtime("pool", level, message);
+}
+
+/// A suspended request replays its action (and middleware) from the top on
+/// resume, so swapping either mid-drift would change the code under it.
+fn action_in_use(rt: &TitanRuntime, name: &str) -> bool {
+    rt.active_requests.values().any(|req| {
+        req.action_name == name || req.middleware.as_deref().is_some_and(|mw| mw.iter().any(|m| m == name))
+    })
+}
+
+/// True when the new version is live.
+fn apply_reload(rt: &mut TitanRuntime, name: &str, source: &str) -> bool {
+    match catch_unwind(AssertUnwindSafe(|| rt.reload_action(name, source))) {
+        Ok(Ok(())) => {
+            if rt.id == 0 {
+                log_runtime("reload", LogLevel::Info, &format!("reloaded action '{}'", name));
+            }
+            true
+        }
+        Ok(Err(msg)) => {
+            if rt.id == 0 {
+                log_runtime("reload", LogLevel::Error, &format!("{}; keeping the previous version", msg));
+            }
+            false
+        }
+        Err(_) => false,
+    }
+}
+
+fn remember_action(loaded: &mut Vec<(String, String)>, name: &str, code: &str) {
+    loaded.retain(|(known, _)| known != name);
+    loaded.push((name.to_string(), code.to_string()));
+}
+
+fn log_runtime(event: &str, level: LogLevel, message: &str) {
+    crate::utils::log(level, event, serde_json::json!({ "message": message }));
+}
+
+/// Answer every request a worker holds after a panic (the one that panicked
+/// and any suspended on a drift) and drop their replay state. No JS runs: the
+/// isolate is about to be replaced.
+fn fail_after_panic(rt: &mut TitanRuntime) {
+    let held: Vec<u32> = rt.pending_requests.keys().chain(rt.response_streams.keys()).copied().collect();
+    for request_id in held {
+        release_request(rt, request_id, serde_json::json!({ "error": "Worker panicked while handling this request" }));
+    }
+}
+
+/// `t._debug_panic(message)`, bound only when `TITAN_DEBUG_PANIC` is set (the
+/// `onPanic` tests), asks for a worker panic. It is raised here, after the
+/// action returns, because a V8 callback cannot unwind.
+fn raise_requested_panic(rt: &mut TitanRuntime) {
+    if let Some(message) = rt.requested_panic.take() {
+        panic!("{}", message);
+    }
+}
+
+/// Answer a request with `json` without letting its action finish, and drop
+/// everything kept for it: replay data, drift ids, timings, an open streamed
+/// body (which just ends short) and the JS-side state its replays shared.
+/// Drifts still in flight lose their request, so their late resumes are ignored.
+fn abandon_request(rt: &mut TitanRuntime, request_id: u32, json: serde_json::Value) {
+    release_request(rt, request_id, json);
+    rt.forget_request_state(request_id);
+}
+
+/// [`abandon_request`] minus the JS-side state, for a native call that
+/// answers its own request mid-action; it forgets that state through its
+/// own scope (`extensions::forget_request_state_in`).
+pub(crate) fn release_request(rt: &mut TitanRuntime, request_id: u32, json: serde_json::Value) {
+    rt.active_requests.remove(&request_id);
+    rt.response_streams.remove(&request_id);
+    rt.early_hints.remove(&request_id);
+    forget_drifts(rt, request_id);
+    rt.drift_seq = 0;
+    rt.replay_pending = 0;
+    rt.body_file = None;
+    let timings = rt.request_timings.remove(&request_id).unwrap_or_default();
+    if let Some(tx) = rt.pending_requests.remove(&request_id) {
+        let _ = tx.send(WorkerResult { json, timings });
+    }
+}
+
 /// Handle a new incoming request.
 ///
 /// OPTIMIZATION: Deferred cloning.
@@ -216,11 +773,18 @@ fn handle_new_request(task: RequestTask, rt: &mut TitanRuntime) {
     rt.request_counter += 1;
     let request_id = rt.request_counter;
 
+    // Time spent waiting in this worker's channel, reported as the first timing
+    let queue_ms = task.enqueued_at.elapsed().as_secs_f64() * 1000.0;
+    rt.request_timings.insert(request_id, vec![("queue".to_string(), queue_ms)]);
+
     // Move response_tx into pending (partial move of task — other fields remain accessible)
     rt.pending_requests.insert(request_id, task.response_tx);
+    if let Some(sink) = task.early_hints {
+        rt.early_hints.insert(request_id, sink);
+    }
 
-    let drift_count = rt.drift_counter;
-    rt.request_start_counters.insert(request_id, drift_count);
+    rt.drift_seq = 0;
+    rt.replay_pending = 0;
 
     // Execute action — pass references, body is O(1) Bytes clone
     extensions::execute_action_optimized(
@@ -228,17 +792,24 @@ fn handle_new_request(task: RequestTask, rt: &mut TitanRuntime) {
         request_id,
         &task.action_name,
         task.body.clone(), // Bytes::clone() is O(1) refcount bump
+        task.form.as_deref(),
+        task.body_file.as_ref(),
+        task.middleware.as_deref(),
+        task.client.as_ref(),
         &task.method,
         &task.path,
         &task.headers,
         &task.params,
         &task.query,
     );
+    // Drifted requests keep their own handle in `active_requests`
+    rt.body_file = None;
 
     // Deferred cloning decision
-    if !rt.pending_requests.contains_key(&request_id) {
+    if !in_progress(rt, request_id) {
         // Completed synchronously — no data needed, minimal cleanup
-        rt.request_start_counters.remove(&request_id);
+        forget_drifts(rt, request_id);
+        rt.early_hints.remove(&request_id);
     } else {
         // Suspended via drift — MOVE (not clone) data for resume replay.
         rt.active_requests.insert(
@@ -246,6 +817,10 @@ fn handle_new_request(task: RequestTask, rt: &mut TitanRuntime) {
             extensions::RequestData {
                 action_name: task.action_name,
                 body: task.body,
+                form: task.form,
+                body_file: task.body_file,
+                middleware: task.middleware,
+                client: task.client,
                 method: task.method,
                 path: task.path,
                 headers: task.headers.into_vec(),
@@ -257,7 +832,11 @@ fn handle_new_request(task: RequestTask, rt: &mut TitanRuntime) {
 }
 
 fn handle_resume(drift_id: u32, result: WorkerAsyncResult, rt: &mut TitanRuntime) {
-    let req_id = rt.drift_to_request.get(&drift_id).copied().unwrap_or(0);
+    // The request was already answered (it failed, timed out or finished
+    // without waiting for this op), so there is nothing left to replay
+    let Some(req_id) = rt.drift_to_request.remove(&drift_id) else {
+        return;
+    };
 
     let timing_type = if result.result.get("error").is_some() {
         "drift_error"
@@ -269,27 +848,161 @@ fn handle_resume(drift_id: u32, result: WorkerAsyncResult, rt: &mut TitanRuntime
         .or_default()
         .push((timing_type.to_string(), result.duration_ms));
 
-    rt.completed_drifts.insert(drift_id, result.result);
+    // A timed-out op has no result to replay with; answer 504 instead of resuming
+    if result.timed_out {
+        abandon_request(rt, req_id, extensions::builtins::system::drift_timeout_response());
+        return;
+    }
 
     if let Some(req_data) = rt.active_requests.get(&req_id).cloned() {
-        let start_counter = rt.request_start_counters.get(&req_id).copied().unwrap_or(0);
-        rt.drift_counter = start_counter;
+        rt.completed_drifts.insert(drift_id, result.result);
+        rt.drift_seq = 0;
+        rt.replay_pending = rt.request_drifts.get(&req_id).map_or(0, Vec::len);
 
         extensions::execute_action_optimized(
             rt,
             req_id,
             &req_data.action_name,
             req_data.body,
+            req_data.form.as_deref(),
+            req_data.body_file.as_ref(),
+            req_data.middleware.as_deref(),
+            req_data.client.as_ref(),
             &req_data.method,
             &req_data.path,
             &req_data.headers,
             &req_data.params,
             &req_data.query,
         );
+        rt.body_file = None;
     }
 
-    if req_id != 0 && !rt.pending_requests.contains_key(&req_id) {
+    rt.replay_pending = 0;
+    if !in_progress(rt, req_id) {
         rt.active_requests.remove(&req_id);
-        rt.request_start_counters.remove(&req_id);
+        rt.early_hints.remove(&req_id);
+        forget_drifts(rt, req_id);
+    }
+}
+
+/// Still waiting on its action: unanswered, or streaming a body after its head.
+fn in_progress(rt: &TitanRuntime, request_id: u32) -> bool {
+    rt.pending_requests.contains_key(&request_id) || rt.response_streams.contains_key(&request_id)
+}
+
+/// Drop a finished request's replay results.
+fn forget_drifts(rt: &mut TitanRuntime, request_id: u32) {
+    for id in rt.request_drifts.remove(&request_id).unwrap_or_default() {
+        rt.completed_drifts.remove(&id);
+        rt.drift_to_request.remove(&id);
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// One worker isolate driven from the test thread. Drifts are parked in
+    /// `ops` instead of running, so a test decides when (and whether) they resume.
+    struct TestWorker {
+        rt: TitanRuntime,
+        ops: mpsc::Receiver<AsyncOpRequest>,
+        _tokio: tokio::runtime::Runtime,
+    }
+
+    impl TestWorker {
+        fn new(actions: &[(&str, &str)]) -> Self {
+            let tokio = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+            let (worker_tx, _) = bounded(16);
+            let (async_tx, ops) = mpsc::channel(16);
+            let mut rt = extensions::init_runtime_worker(0, std::env::temp_dir(), worker_tx, tokio.handle().clone(), async_tx, 8 * 1024 * 1024);
+            for (name, code) in actions {
+                rt.load_action(name.to_string(), code.to_string());
+            }
+            Self { rt, ops, _tokio: tokio }
+        }
+
+        /// Run `action` as `GET /<action>`; its answer is in the receiver once it has one.
+        fn request(&mut self, action: &str) -> oneshot::Receiver<WorkerResult> {
+            let (response_tx, response_rx) = oneshot::channel();
+            let task = RequestTask {
+                action_name: action.to_string(),
+                body: None,
+                form: None,
+                body_file: None,
+                middleware: None,
+                client: None,
+                early_hints: None,
+                method: "GET".to_string(),
+                path: format!("/{action}"),
+                headers: SmallVec::new(),
+                params: SmallVec::new(),
+                query: SmallVec::new(),
+                response_tx,
+                enqueued_at: Instant::now(),
+            };
+            handle_new_request(task, &mut self.rt);
+            response_rx
+        }
+
+        /// The drift the last request suspended on.
+        fn parked_drift(&mut self) -> u32 {
+            self.ops.try_recv().expect("the action should be suspended on a drift").drift_id
+        }
     }
-}
\ No newline at end of file
+
+    fn answer(response: &mut oneshot::Receiver<WorkerResult>) -> serde_json::Value {
+        extensions::expand_raw_json(response.try_recv().expect("the request should be answered").json)
+    }
+
+    #[test]
+    fn globals_added_by_a_request_are_gone_for_the_next() {
+        let mut worker = TestWorker::new(&[
+            ("setLeak", "export default function (req) { globalThis.leak = 1; return { leak: globalThis.leak }; }"),
+            ("readLeak", "export default function (req) { return { leak: typeof globalThis.leak }; }"),
+        ]);
+
+        assert_eq!(answer(&mut worker.request("setLeak")), serde_json::json!({ "leak": 1 }));
+        assert_eq!(answer(&mut worker.request("readLeak")), serde_json::json!({ "leak": "undefined" }));
+    }
+
+    #[test]
+    fn a_counter_starts_over_on_every_request() {
+        let mut worker = TestWorker::new(&[(
+            "count",
+            "export default function (req) { globalThis.counter = (globalThis.counter || 0) + 1; return { counter: globalThis.counter }; }",
+        )]);
+
+        for _ in 0..3 {
+            assert_eq!(answer(&mut worker.request("count")), serde_json::json!({ "counter": 1 }));
+        }
+    }
+
+    #[test]
+    fn a_late_resume_for_a_terminated_request_is_ignored() {
+        let mut worker = TestWorker::new(&[
+            ("slow", "export default function (req) { globalThis.seen = 1; const res = drift(t.fetch('http://127.0.0.1:9/')); return { res }; }"),
+            ("fast", "export default function (req) { return { seen: typeof globalThis.seen }; }"),
+        ]);
+
+        let mut slow = worker.request("slow");
+        let request_id = worker.rt.request_counter;
+        let drift_id = worker.parked_drift();
+        assert!(slow.try_recv().is_err(), "suspended on its drift");
+
+        // Terminated mid-drift, the way a worker panic ends a request
+        fail_after_panic(&mut worker.rt);
+        assert_eq!(answer(&mut slow)["error"], "Worker panicked while handling this request");
+
+        // The op finishes anyway and its resume arrives late
+        let late = WorkerAsyncResult { drift_id, result: serde_json::json!({ "status": 200, "body": "late" }), duration_ms: 1.0, timed_out: false };
+        handle_resume(drift_id, late, &mut worker.rt);
+
+        let rt = &worker.rt;
+        assert!(rt.pending_requests.is_empty() && rt.active_requests.is_empty());
+        assert!(rt.drift_to_request.is_empty() && rt.request_drifts.is_empty() && rt.completed_drifts.is_empty());
+        assert!(!rt.request_timings.contains_key(&request_id));
+        assert!(worker.ops.try_recv().is_err(), "the action must not be replayed");
+
+        assert_eq!(answer(&mut worker.request("fast")), serde_json::json!({ "seen": "undefined" }));
+    }
+}
@@ -1,5 +1,103 @@
 //! Terminal styling and utility functions.
 
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+
+static JSON_LOGS: AtomicBool = AtomicBool::new(false);
+static LOG_LEVEL: AtomicU8 = AtomicU8::new(LogLevel::Info as u8);
+
+/// Verbosity threshold (`TITAN_LOG_LEVEL`). Lower is quieter.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum LogLevel {
+    Error = 0,
+    Warn = 1,
+    Info = 2,
+    Debug = 3,
+}
+
+impl LogLevel {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "error" => Some(Self::Error),
+            "warn" | "warning" => Some(Self::Warn),
+            "info" => Some(Self::Info),
+            "debug" => Some(Self::Debug),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Error => "error",
+            Self::Warn => "warn",
+            Self::Info => "info",
+            Self::Debug => "debug",
+        }
+    }
+}
+
+pub fn set_log_level(level: LogLevel) {
+    LOG_LEVEL.store(level as u8, Ordering::Relaxed);
+}
+
+/// Cheap gate for log call sites: check before doing any formatting.
+#[inline(always)]
+pub fn log_enabled(level: LogLevel) -> bool {
+    level as u8 <= LOG_LEVEL.load(Ordering::Relaxed)
+}
+
+/// Switch runtime log output to single-line JSON (`__config.logFormat: "json"`).
+/// Color helpers are bypassed entirely in this mode.
+pub fn set_json_logs(enabled: bool) {
+    JSON_LOGS.store(enabled, Ordering::Relaxed);
+}
+
+pub fn json_logs() -> bool {
+    JSON_LOGS.load(Ordering::Relaxed)
+}
+
+/// Dev mode (`TITAN_DEV=1`, set by `titan dev`): the one switch for dev-only
+/// behavior in the engine and the runtime. `__config.dev` only turns on hot reload.
+pub fn dev_mode() -> bool {
+    static DEV: std::sync::OnceLock<bool> = std::sync::OnceLock::new();
+    *DEV.get_or_init(|| std::env::var("TITAN_DEV").is_ok_and(|v| v == "1"))
+}
+
+/// One log line in the configured format, dropped below `TITAN_LOG_LEVEL`.
+/// JSON mode (`__config.logFormat: "json"`) prints
+/// `{"level", "event", ...fields}`; text mode prints `fields.message` after
+/// the `[Titan]` tag, marked `WARNING:` / `ERROR:` by level.
+pub fn log(level: LogLevel, event: &str, fields: serde_json::Value) {
+    let message = fields.get("message").and_then(|m| m.as_str()).unwrap_or(event).to_string();
+    log_with(level, event, fields, || match level {
+        LogLevel::Error => format!("{} {} {}", blue("[Titan]"), red("ERROR:"), message),
+        LogLevel::Warn => format!("{} {} {}", blue("[Titan]"), yellow("WARNING:"), message),
+        LogLevel::Info | LogLevel::Debug => format!("{} {}", blue("[Titan]"), gray(&message)),
+    });
+}
+
+/// [`log`] with the text-mode line built by `text`, for output with a layout
+/// of its own (request lines, the startup summary, `t.log`). Every runtime
+/// log line goes through here, so the format switch lives in one place.
+/// Text-mode errors go to stderr.
+pub fn log_with(level: LogLevel, event: &str, fields: serde_json::Value, text: impl FnOnce() -> String) {
+    if !log_enabled(level) {
+        return;
+    }
+    if json_logs() {
+        let mut line = serde_json::Map::new();
+        line.insert("level".to_string(), serde_json::Value::String(level.as_str().to_string()));
+        line.insert("event".to_string(), serde_json::Value::String(event.to_string()));
+        if let serde_json::Value::Object(map) = fields {
+            line.extend(map);
+        }
+        println!("{}", serde_json::Value::Object(line));
+    } else if level == LogLevel::Error {
+        eprintln!("{}", text());
+    } else {
+        println!("{}", text());
+    }
+}
+
 pub fn blue(s: &str) -> String {
     format!("\x1b[38;5;39m{}\x1b[0m", s)
 }
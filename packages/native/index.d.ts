@@ -104,10 +104,13 @@ export const jwt: any;
 export const password: any;
 export const crypto: any;
 export const buffer: any;
+export const encode: any;
+export const uuid: any;
 export const ls: any;
 export const session: any;
 export const cookies: any;
 export const shareContext: ShareContext;
+export const share: any;
 export const os: any;
 export const net: any;
 export const proc: any;
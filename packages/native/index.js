@@ -3,6 +3,7 @@
 export const fetch = t.fetch;
 export const log = t.log;
 export const read = t.read;
+export const write = t.write;
 
 // Authentication & Security
 export const jwt = t.jwt;
@@ -21,6 +22,8 @@ export const path = t.path;
 // Crypto & Buffer
 export const crypto = t.crypto;
 export const buffer = t.buffer;
+export const encode = t.encode;
+export const uuid = t.uuid;
 
 // Storage & Sessions
 export const ls = t.ls;
@@ -28,11 +31,13 @@ export const localStorage = t.localStorage;
 export const session = t.session;
 export const cookies = t.cookies;
 export const shareContext = t.shareContext;
+export const share = t.share;
 
 // System
 export const os = t.os;
 export const net = t.net;
 export const proc = t.proc;
+export const system = t.system;
 
 // Utilities
 export const time = t.time;
@@ -33,12 +33,16 @@
  */
 export interface TitanRequest {
     /**
-     * The parsed request body.
+     * The request body, decoded by `Content-Type`:
      *
-     * - For `POST`, `PUT`, and `PATCH` requests, this contains the parsed JSON payload.
-     * - For `GET` and `DELETE` requests, this is typically `null`.
+     * - `application/json` (and `+json`) → parsed value. Malformed JSON is rejected
+     *   with `400` and `{ error: { code: "invalid_json" } }` before the action runs.
+     * - `application/x-www-form-urlencoded` → object of strings.
+     * - `text/*` or no `Content-Type` → string.
+     * - anything else → `Uint8Array` of the raw bytes.
+     * - no body → `{}`.
      *
-     * Titan automatically parses `application/json` bodies — no middleware needed.
+     * The undecoded bytes are always available as `req.rawBody`.
      *
      * @example
      * ```js
@@ -50,6 +54,11 @@ export interface TitanRequest {
      */
     body: any;
 
+    /**
+     * The request body exactly as received, or `null` when there is none.
+     */
+    rawBody: ArrayBuffer | null;
+
     /**
      * The HTTP method of the incoming request.
      *
@@ -78,6 +87,7 @@ export interface TitanRequest {
      * All HTTP request headers as a flat key-value map.
      *
      * Header names are **lowercased** (e.g., `"content-type"`, `"authorization"`).
+     * Index with lowercase names, or use `req.header()` for a case-insensitive lookup.
      * A header may be `undefined` if it was not sent by the client.
      *
      * @example
@@ -91,6 +101,72 @@ export interface TitanRequest {
      */
     headers: Record<string, string | undefined>;
 
+    /**
+     * Case-insensitive header lookup.
+     *
+     * `req.header("Content-Type")` and `req.header("content-type")` return the same value,
+     * regardless of the casing the client sent.
+     *
+     * @example
+     * ```js
+     * const type = req.header("Content-Type");
+     * ```
+     */
+    header(name: string): string | undefined;
+
+    /**
+     * A body larger than `__config.bodySpillBytes`, kept partly in a temp file.
+     * Only present for such requests, in which case `body` is `{}` and `rawBody`
+     * is `null`. Read it in slices to keep memory flat. The file is deleted
+     * after the request.
+     *
+     * @example
+     * ```js
+     * export function ingest(req) {
+     *   const chunk = 1 << 20;
+     *   for (let offset = 0; offset < req.bodyFile.size; offset += chunk) {
+     *     process(req.bodyFile.read(offset, chunk));
+     *   }
+     *   return { bytes: req.bodyFile.size };
+     * }
+     * ```
+     */
+    bodyFile?: {
+        /** Total body length in bytes. */
+        size: number;
+        /** Bytes from `offset` (default `0`), at most `length` (default: to the end). */
+        read(offset?: number, length?: number): Uint8Array;
+        /** Same range decoded as UTF-8. */
+        text(offset?: number, length?: number): string;
+    };
+
+    /**
+     * Parsed `multipart/form-data` body. Only present for multipart requests,
+     * in which case `rawBody` is `null`.
+     *
+     * Size limits come from `__config.multipart`; oversized uploads are
+     * rejected with `413` before the action runs.
+     *
+     * @example
+     * ```js
+     * export function upload(req) {
+     *   const [avatar] = req.formData.files;
+     *   t.write(`uploads/${avatar.filename}`, avatar.bytes);
+     *   return { user: req.formData.fields.user };
+     * }
+     * ```
+     */
+    formData?: {
+        fields: Record<string, string>;
+        files: {
+            /** Form field name */
+            name: string;
+            filename: string;
+            contentType: string;
+            bytes: Uint8Array;
+        }[];
+    };
+
     /**
      * Dynamic route parameters extracted from the URL path.
      *
@@ -116,8 +192,10 @@ export interface TitanRequest {
      * For a request to `/search?q=titan&page=2`, this would be:
      * `{ q: "titan", page: "2" }`.
      *
-     * Values are always strings. Returns an empty object `{}` when no
-     * query parameters are present.
+     * Values are strings; a repeated key (`?tag=a&tag=b`) becomes an array
+     * (`{ tag: ["a", "b"] }`). A route's `querySchema` turns listed keys into
+     * numbers, booleans or arrays instead. Returns an empty object `{}` when
+     * no query parameters are present.
      *
      * @example
      * ```js
@@ -129,7 +207,32 @@ export interface TitanRequest {
      * }
      * ```
      */
-    query: Record<string, string>;
+    query: Record<string, any>;
+
+    /**
+     * Client IP address: the TCP peer, or the first `X-Forwarded-For` entry when
+     * `__config.trustProxy` is enabled. `null` for tasks and WebSocket events.
+     */
+    ip: string | null;
+
+    /**
+     * `"https"` only when `__config.trustProxy` is enabled and `X-Forwarded-Proto`
+     * says so; Titan itself serves plain HTTP.
+     */
+    protocol: "http" | "https";
+
+    /**
+     * The request's `X-Request-Id`: the incoming header when it is 1-128 visible ASCII
+     * characters, otherwise a generated one. It is echoed on the response and tags the
+     * request's log lines, `t.log` output included. `null` for tasks and WebSocket events.
+     */
+    requestId: string | null;
+
+    /**
+     * Per-request object shared by the route's middleware chain and the action
+     * (also passed as the second argument). Starts empty.
+     */
+    context: Record<string, any>;
 
     /**
      * The unique ID of the WebSocket connection.
@@ -152,6 +255,11 @@ export interface TitanRequest {
  * It is **purely a development-time utility** — at runtime it simply
  * returns the same function unchanged (zero overhead).
  *
+ * Return values map to responses as follows:
+ * - `undefined` (no return) → `204 No Content` (configurable via `t.config({ undefinedStatus })`)
+ * - `null` → `200` with a `null` JSON body
+ * - `{}` → `200` with `{}`
+ *
  * @typeParam T - The return type of the action handler.
  * @param handler - The action function that receives a `TitanRequest` and returns `T`.
  * @returns The same handler function with proper type annotations.
@@ -220,6 +328,14 @@ export const log: typeof t.log;
  */
 export const read: typeof t.read;
 
+/**
+ * Synchronous file writer scoped to the project root.
+ *
+ * Re-exported from the `t` global for module-style imports.
+ * @see {@link TitanRuntimeUtils.write} for full documentation.
+ */
+export const write: typeof t.write;
+
 /**
  * JWT (JSON Web Token) signing and verification utilities.
  *
@@ -229,7 +345,7 @@ export const read: typeof t.read;
 export const jwt: typeof t.jwt;
 
 /**
- * Secure password hashing and verification (bcrypt-based).
+ * Secure password hashing and verification (bcrypt or argon2id).
  *
  * Re-exported from the `t` global for module-style imports.
  * @see {@link TitanRuntimeUtils.password} for full documentation.
@@ -327,6 +443,22 @@ export const crypto: typeof t.crypto;
  */
 export const buffer: typeof t.buffer;
 
+/**
+ * Native Base64 and hex encoding (standard and URL-safe Base64).
+ *
+ * Re-exported from the `t` global for module-style imports.
+ * @see {@link TitanCore.Encode} for full documentation.
+ */
+export const encode: typeof t.encode;
+
+/**
+ * UUID generation (random v4 and time-ordered v7).
+ *
+ * Re-exported from the `t` global for module-style imports.
+ * @see {@link TitanCore.Uuid} for full documentation.
+ */
+export const uuid: typeof t.uuid;
+
 /**
  * Persistent key-value local storage (shorthand alias).
  *
@@ -366,6 +498,7 @@ export const cookies: typeof t.cookies;
  * @see {@link TitanCore.ShareContext} for full documentation.
  */
 export const shareContext: typeof t.shareContext;
+export const share: typeof t.share;
 
 /**
  * Operating system information (platform, CPU count, memory).
@@ -374,6 +507,13 @@ export const shareContext: typeof t.shareContext;
  * @see {@link TitanCore.OS} for full documentation.
  */
 export const os: typeof t.os;
+/**
+ * Server load introspection (isolates, in-flight requests, uptime, memory).
+ *
+ * Re-exported from the `t` global for module-style imports.
+ * @see {@link TitanCore.System} for full documentation.
+ */
+export const system: typeof t.system;
 
 /**
  * Network utilities (DNS resolution, IP lookup, ping).
@@ -482,6 +622,12 @@ declare global {
      * - `drift` is the **only** way to await promises in Titan actions.
      * - The action function may be re-executed (replayed) — avoid side effects
      *   before the `drift` call that shouldn't be repeated.
+     * - Actions whose file opens with a `// @titan-no-replay` comment (above
+     *   the first statement) are never replayed: `drift` blocks the worker until the operation completes and
+     *   returns the result in place, so every side effect runs exactly once.
+     *   The cost is that the worker's isolate is pinned for the full request
+     *   duration and serves no other requests meanwhile — reserve it for
+     *   side-effect-heavy actions.
      * - Can be used with any Titan API that returns a `Promise` (e.g.,
      *   `t.fetch`, `t.db.connect`, `t.password.hash`, `t.fs.readFile`, etc.).
      *
@@ -510,6 +656,18 @@ declare global {
      *
      * @example
      * ```js
+     * // Independent calls in parallel (single drift, results in order)
+     * export function dashboard(req) {
+     *   const [user, order] = drift.all([
+     *     t.fetch("https://api.example.com/user/1"),
+     *     t.fetch("https://api.example.com/order/99"),
+     *   ]);
+     *   return { user: JSON.parse(user.body), order: JSON.parse(order.body) };
+     * }
+     * ```
+     *
+     * @example
+     * ```js
      * // Drift with database operations
      * export function getUsers(req) {
      *   const conn  = drift(t.db.connect(process.env.DATABASE_URL));
@@ -518,10 +676,36 @@ declare global {
      * }
      * ```
      *
+     * @example
+     * ```js
+     * // @titan-no-replay
+     * // Linear side effects: each insert runs exactly once
+     * export function checkout(req) {
+     *   const conn = drift(t.db.connect(process.env.DATABASE_URL));
+     *   drift(conn.query("INSERT INTO orders (id) VALUES ($1)", [req.body.id]));
+     *   drift(t.fetch("https://hooks.example.com/order", { method: "POST", body: req.body }));
+     *   return { ok: true };
+     * }
+     * ```
+     *
      * @see https://titanpl.vercel.app/docs/knowledge/04-drift — Drift documentation
      * @see https://titanpl.vercel.app/docs/knowledge/02-runtime-architecture — Gravity Runtime
      */
-    var drift: <T>(promise: Promise<T> | T) => T;
+    var drift: {
+        /**
+         * `timeout` (ms) overrides `__config.driftTimeoutMs`. On expiry the operation is
+         * cancelled and the request is answered with `504 { error: "drift timeout" }`.
+         */
+        <T>(promise: Promise<T> | T, options?: { timeout?: number }): T;
+        /**
+         * Submit several async operations as one drift. They run concurrently and
+         * resolve to an array of results in submission order. A failing operation
+         * yields `{ error }` in its slot unless `failFast` is set, in which case the
+         * first failure resolves the whole batch to `{ error, index }` without waiting
+         * for the rest, which are cancelled.
+         */
+        all<T extends any[] = any[]>(ops: any[], options?: { failFast?: boolean; timeout?: number }): T;
+    };
 
 
     // -----------------------------------------------------------------------
@@ -554,6 +738,16 @@ declare global {
      *
      * @see https://titanpl.vercel.app/docs/how-to-use/05-runtime-apis — Runtime APIs
      */
+    /** Per-query options for `conn.query()`. */
+    interface DbQueryOptions {
+        /** Server-side deadline in ms (Postgres `statement_timeout`); cancels the query on the server. */
+        timeoutMs?: number;
+        /** Client-side wait limit in ms. Defaults to `10000`. */
+        timeout?: number;
+        /** Pool checkout limit in ms. Defaults to `5000`. */
+        pool_timeout?: number;
+    }
+
     interface DbConnection {
         /**
          * Execute a SQL query against the connected database.
@@ -563,6 +757,11 @@ declare global {
          *
          * @param sql - The SQL query string. Use `$1`, `$2`, ... for parameter placeholders.
          * @param params - Optional array of values to bind to the query placeholders (in order).
+         * @param options.timeoutMs - Per-query deadline enforced by Postgres (`statement_timeout`).
+         *                            The server cancels the query when it elapses and the drift
+         *                            resolves to `{ error: "Query timeout after N milliseconds", timeout: true }`.
+         * @param options.timeout - Client-side wait limit in ms (default `10000`). Stops waiting
+         *                          but does not cancel the query on the server.
          * @returns A promise that resolves to an array of result rows (as plain objects).
          *
          * @example
@@ -584,9 +783,13 @@ declare global {
          *   "UPDATE users SET name = $1 WHERE id = $2",
          *   ["Bob", 42]
          * ));
+         *
+         * // User-facing lookup must answer within 500ms
+         * const hit = drift(conn.query("SELECT * FROM search($1)", [q], { timeoutMs: 500 }));
+         * if (hit.timeout) return t.response.json({ error: "Search timed out" }, 504);
          * ```
          */
-        query(sql: string, params?: any[]): Promise<any[]>;
+        query(sql: string, params?: any[], options?: DbQueryOptions): Promise<any[]>;
     }
 
 
@@ -614,6 +817,14 @@ declare global {
      *
      * @see https://titanpl.vercel.app/docs/how-to-use/05-runtime-apis — Complete Runtime APIs reference
      */
+    /** Algorithms supported by `t.jwt.sign` / `t.jwt.verify`. */
+    type JwtAlgorithm =
+        | "HS256" | "HS384" | "HS512"
+        | "RS256" | "RS384" | "RS512"
+        | "PS256" | "PS384" | "PS512"
+        | "ES256" | "ES384"
+        | "EdDSA";
+
     interface TitanRuntimeUtils {
 
         // -------------------------------------------------------------------
@@ -646,6 +857,20 @@ declare global {
          */
         log(...args: any[]): void;
 
+        /**
+         * `true` while a resumed action re-runs code that already ran before its
+         * last completed `drift()`. Titan replays actions from the top on resume;
+         * completed drifts return their recorded results and `t.log` / WebSocket
+         * sends are skipped, but other one-off side effects should be guarded.
+         *
+         * @example
+         * ```js
+         * if (!t.isReplaying()) requestCount++;
+         * const user = drift(t.fetch(url));
+         * ```
+         */
+        isReplaying(): boolean;
+
         /**
          * Synchronously reads the contents of a local file as a UTF-8 string.
          *
@@ -654,6 +879,8 @@ declare global {
          * For larger or async file operations, prefer `t.fs.readFile()` with `drift()`.
          *
          * @param path - Absolute or relative path to the file to read.
+         * @param options.encoding - Pass `"binary"` to get the raw bytes as a `Uint8Array`
+         *                           (images, SQLite files, ...). Defaults to UTF-8 text.
          * @returns The file contents as a UTF-8 string.
          * @throws If the file does not exist or cannot be read.
          *
@@ -663,11 +890,130 @@ declare global {
          *   const raw = t.read("./config.json");
          *   return JSON.parse(raw);
          * }
+         *
+         * // Binary files keep every byte
+         * const png = t.read("./static/logo.png", { encoding: "binary" });
          * ```
          *
          * @see https://titanpl.vercel.app/docs/how-to-use/05-runtime-apis — Runtime APIs
          */
-        read(path: string): string;
+        read(path: string, options?: { encoding?: "utf8" }): string;
+        read(path: string, options: { encoding: "binary" }): Uint8Array;
+
+        /**
+         * Abort the action with a structured, machine-readable error.
+         *
+         * Throws; the action responds with `{ error: { code, message, details } }`
+         * and the given status. `code` is a stable string clients can switch on.
+         *
+         * @param code - Stable error code (e.g. `"USER_NOT_FOUND"`).
+         * @param message - Human-readable message. Defaults to `code`.
+         * @param options.status - HTTP status. Defaults to `400`.
+         * @param options.details - Extra JSON data included as `error.details`.
+         * @param options.schema - Expected shape, included as `error.schema` in dev mode (`TITAN_DEV=1`) only.
+         *
+         * @example
+         * ```js
+         * export function getUser(req) {
+         *   const user = findUser(req.params.id);
+         *   if (!user) t.fail("USER_NOT_FOUND", "No such user", { status: 404, details: { id: req.params.id } });
+         *   return user;
+         * }
+         * // → 404 { "error": { "code": "USER_NOT_FOUND", "message": "No such user", "details": { "id": "7" } } }
+         * ```
+         */
+        fail(code: string, message?: string, options?: { status?: number; details?: any; schema?: any }): never;
+
+        /**
+         * Throws like `t.fail`, with the HTTP status as the code: the action responds
+         * `status` with `{ error: { code: "HTTP_<status>", message, details } }`.
+         *
+         * @param status - HTTP error status, `400`-`599`.
+         * @param message - Human-readable message. Defaults to the code.
+         * @param details - Extra JSON data included as `error.details`.
+         *
+         * @example
+         * ```js
+         * if (!body.email) t.error(422, "email is required", { field: "email" });
+         * // → 422 { "error": { "code": "HTTP_422", "message": "email is required", "details": { "field": "email" } } }
+         * ```
+         */
+        error(status: number, message?: string, details?: any): never;
+
+        /**
+         * Validate a value (usually `req.body`) against a JSON Schema subset and
+         * return it unchanged, or fail with `400 VALIDATION_FAILED`.
+         *
+         * Supported keywords: `type` (including `"integer"`), `enum`, `required`,
+         * `properties`, `additionalProperties: false`, `items`, `minLength`,
+         * `maxLength`, `pattern`, `minimum` and `maximum`.
+         *
+         * In dev mode (`TITAN_DEV=1`) the error lists every mismatch as
+         * `{ path, expected, received }` in `details.errors` and includes the
+         * schema. In production the response is only
+         * `{ error: { code: "VALIDATION_FAILED", message: "Invalid request" } }`.
+         *
+         * @example
+         * ```js
+         * export function createUser(req) {
+         *   const body = t.validate(req.body, {
+         *     type: "object",
+         *     required: ["email", "age"],
+         *     properties: { email: { type: "string", pattern: "@" }, age: { type: "integer", minimum: 18 } },
+         *   });
+         *   return { created: body.email };
+         * }
+         * // dev: 400 { "error": { "code": "VALIDATION_FAILED", "message": "Invalid request: 1 error(s)",
+         * //   "details": { "errors": [{ "path": "age", "expected": ">= 18", "received": 12 }] }, "schema": { ... } } }
+         * ```
+         */
+        validate<T = any>(value: any, schema: Record<string, any>): T;
+
+        /**
+         * Field expansion helpers for `?expand=author,comments` (Stripe-style).
+         * They read the current request unless one is passed explicitly.
+         *
+         * @example
+         * ```js
+         * // GET /posts/7?expand=author,comments.author
+         * export function getPost(req) {
+         *   const post = findPost(req.params.id);
+         *   t.query.expand();              // ["author", "comments.author"]
+         *   if (t.query.expands("author")) post.author = drift(loadUser(post.authorId));
+         *   return t.query.include(post, { comments: () => drift(loadComments(post.id)) });
+         * }
+         * ```
+         */
+        query: {
+            /** Requested expansions, URL-decoded, trimmed and de-duplicated in request order. */
+            expand(req?: TitanRequest): string[];
+            /** Whether `name` was requested, directly or through a dotted child (`"comments.author"`). */
+            expands(name: string, req?: TitanRequest): boolean;
+            /** Run the loader for every requested key and assign its result onto `target`. */
+            include<T extends object>(target: T, loaders: Record<string, () => any>, req?: TitanRequest): T;
+        };
+
+        /**
+         * Synchronously writes a file inside the project root, creating parent
+         * directories as needed.
+         *
+         * Absolute paths and paths that escape the project root (via `..` or a
+         * symlinked directory) are rejected with an error.
+         *
+         * @param path - Path relative to the project root.
+         * @param contents - Text (written as UTF-8) or raw bytes.
+         * @returns The number of bytes written.
+         * @throws If the path is outside the project root or the write fails.
+         *
+         * @example
+         * ```js
+         * export function report(req) {
+         *   const bytes = t.write("reports/daily.json", JSON.stringify({ ok: true }));
+         *   return { written: bytes };
+         * }
+         * ```
+         */
+        write(path: string, contents: string | Uint8Array): number;
 
         /**
          * Built-in Rust-powered HTTP client for making outbound requests.
@@ -683,6 +1029,12 @@ declare global {
          * @param options.headers - Key-value map of request headers.
          * @param options.body - Request body. Strings are sent as-is; objects are
          *                       automatically JSON-serialized with `Content-Type: application/json`.
+         * @param options.maxBodyBytes - Upper bound on the response body size. The body is read
+         *                       incrementally and the request is abandoned as soon as the limit
+         *                       (or an advertised `Content-Length`) is exceeded, resolving to
+         *                       `{ ok: false, error: "body too large" }`. Streaming response
+         *                       bodies into the action is not supported; this guard is the
+         *                       supported way to fetch untrusted or very large resources.
          *
          * @returns A promise resolving to a response object with:
          * - `ok` — `true` if the status code is 2xx.
@@ -728,12 +1080,23 @@ declare global {
          * }
          * ```
          *
+         * @example
+         * ```js
+         * // Refuse to buffer anything over 1 MB
+         * export function download(req) {
+         *   const resp = drift(t.fetch(req.query.url, { maxBodyBytes: 1024 * 1024 }));
+         *   if (resp.error === "body too large") return { error: "File exceeds 1 MB" };
+         *   return { size: resp.body.length };
+         * }
+         * ```
+         *
          * @see https://titanpl.vercel.app/docs/how-to-use/05-runtime-apis — Runtime APIs (t.fetch)
          */
         fetch(url: string, options?: {
             method?: "GET" | "POST" | "PUT" | "DELETE" | "PATCH";
             headers?: Record<string, string>;
             body?: string | object;
+            maxBodyBytes?: number;
         }): Promise<{
             ok: boolean;
             status?: number;
@@ -979,25 +1342,37 @@ declare global {
              *
              * @param payload - The data to encode in the token (e.g., `{ sub: "user123", role: "admin" }`).
              *                  Avoid putting sensitive data here — JWTs are encoded, not encrypted.
-             * @param secret - The secret key used for HMAC signing. Store in environment variables.
+             * @param secret - The HMAC secret, or a PEM private key for asymmetric algorithms.
+             *                 Store in environment variables.
              * @param options - Optional signing options.
              * @param options.expiresIn - Token expiration time.
              *                            Accepts duration strings (`"1h"`, `"7d"`, `"30m"`) or
              *                            a number representing seconds.
+             * @param options.algorithm - Signing algorithm. Defaults to `"HS256"`.
+             *                            `RS*`/`PS*` take an RSA PEM key, `ES*` an EC PEM key.
              * @returns The signed JWT as a string (e.g., `"eyJhbGciOi..."`).
              *
              * @example
              * ```js
              * const token = t.jwt.sign({ userId: 1 }, "my-secret", { expiresIn: "24h" });
+             *
+             * // Asymmetric: other services verify with the public key
+             * const rs = t.jwt.sign({ sub: "u1" }, process.env.JWT_PRIVATE_KEY, { algorithm: "RS256" });
              * ```
              */
-            sign(payload: object, secret: string, options?: { expiresIn?: string | number }): string;
+            sign(payload: object, secret: string, options?: {
+                expiresIn?: string | number;
+                algorithm?: JwtAlgorithm;
+            }): string;
 
             /**
              * Verify and decode a JWT, returning the original payload.
              *
              * @param token - The JWT string to verify.
-             * @param secret - The secret key used to verify the signature.
+             * @param secret - The HMAC secret, or a PEM public key for asymmetric algorithms.
+             * @param options.algorithms - Algorithms the token may use. Defaults to `["HS256"]`.
+             *                             Tokens signed with any other algorithm are rejected,
+             *                             which prevents algorithm-confusion attacks.
              * @returns The decoded payload object if the token is valid and not expired.
              * @throws If the token is invalid, expired, or the signature doesn't match.
              *
@@ -1009,13 +1384,19 @@ declare global {
              * } catch (err) {
              *   t.log("Token verification failed");
              * }
+             *
+             * const claims = t.jwt.verify(token, process.env.JWT_PUBLIC_KEY, { algorithms: ["RS256"] });
              * ```
              */
-            verify(token: string, secret: string): any;
+            verify(token: string, secret: string, options?: { algorithms?: JwtAlgorithm[] }): any;
         };
 
         /**
-         * Secure password hashing and verification powered by bcrypt (Rust implementation).
+         * Secure password hashing and verification powered by bcrypt or argon2id (Rust implementation).
+         *
+         * New hashes use bcrypt unless `{ algorithm: "argon2id" }` is passed. `verify`
+         * detects the algorithm from the hash prefix (`$2b$` / `$argon2id$`), so
+         * existing bcrypt hashes keep working after switching.
          *
          * Both methods return `Promise` values — use `drift()` to resolve them.
          *
@@ -1043,27 +1424,39 @@ declare global {
          */
         password: {
             /**
-             * Hash a plain-text password using bcrypt.
+             * Hash a plain-text password using bcrypt (default) or argon2id.
              *
              * Automatically generates a secure salt. The resulting hash string
-             * includes the salt, making it safe to store directly in a database.
+             * includes the salt and parameters, making it safe to store directly in a database.
              *
              * @param password - The plain-text password to hash.
-             * @returns A promise resolving to the bcrypt hash string.
+             * @param options.algorithm - `"bcrypt"` (default) or `"argon2id"`.
+             * @param options.cost - bcrypt cost factor (default `12`), or argon2 iteration count (default `2`).
+             * @param options.memory - argon2 memory cost in KiB (default `19456`).
+             * @param options.parallelism - argon2 lanes (default `1`).
+             * @returns A promise resolving to the encoded hash string.
              *
              * @example
              * ```js
              * const hash = drift(t.password.hash("my-secure-password"));
              * // hash → "$2b$12$LJ3m4ys..."
+             *
+             * const strong = drift(t.password.hash("my-secure-password", { algorithm: "argon2id" }));
+             * // strong → "$argon2id$v=19$m=19456,t=2,p=1$..."
              * ```
              */
-            hash(password: string): Promise<string>;
+            hash(password: string, options?: {
+                algorithm?: "bcrypt" | "argon2id";
+                cost?: number;
+                memory?: number;
+                parallelism?: number;
+            }): Promise<string>;
 
             /**
              * Verify a plain-text password against a previously hashed value.
              *
              * @param password - The plain-text password to check.
-             * @param hash - The bcrypt hash string to compare against.
+             * @param hash - The bcrypt or argon2id hash string to compare against.
              * @returns A promise resolving to `true` if the password matches, `false` otherwise.
              *
              * @example
@@ -1126,7 +1519,7 @@ declare global {
              * @returns A connection object with a `.query()` method.
              */
             connect(url: string, options?: { max?: number, pool_timeout?: number }): {
-                query(sql: string, params?: any[], options?: { timeout?: number }): Promise<any[]>;
+                query(sql: string, params?: any[], options?: DbQueryOptions): Promise<any[]>;
             };
 
             /**
@@ -1139,7 +1532,58 @@ declare global {
              * @param options - Optional query settings.
              * @returns A promise resolving to query result rows.
              */
-            query(sql: string, params?: any[], options?: { timeout?: number }): Promise<any[]>;
+            query(sql: string, params?: any[], options?: DbQueryOptions): Promise<any[]>;
+
+            /**
+             * # Keyset Pagination
+             *
+             * Runs `query` as a subquery ordered by `sortColumn` and returns one page
+             * plus an opaque cursor for the next one (`null` on the last page).
+             * The next page continues strictly after the last row's key, so rows are
+             * never skipped or repeated when inserts land between requests.
+             *
+             * The cursor is an HS256-signed token signed with `secret` or `TITAN_CURSOR_SECRET`.
+             * A tampered or expired cursor, or one issued for another sort, fails the
+             * request with `INVALID_CURSOR` (400). `sortColumn` must be selected by
+             * `query` and should be unique (e.g. a primary key).
+             *
+             * Drifts internally, so call it directly (no `drift()` around it).
+             *
+             * @example
+             * ```js
+             * export function listUsers(req) {
+             *   const conn = t.db.connect(t.env.DATABASE_URL);
+             *   const page = t.db.paginate(conn, {
+             *     query: "SELECT id, name FROM users WHERE active = $1",
+             *     params: [true],
+             *     sortColumn: "id",
+             *     limit: 50,
+             *     cursor: req.query.cursor,
+             *   });
+             *   return { users: page.rows, next: page.nextCursor };
+             * }
+             * ```
+             */
+            paginate(conn: { query: DbConnection["query"] }, options: {
+                /** Base SELECT; must not end with ORDER BY/LIMIT. */
+                query: string;
+                /** Positional parameters for `query` ($1, $2, ...). */
+                params?: any[];
+                /** Column to order and seek on. */
+                sortColumn: string;
+                /** Sort direction. Defaults to `"asc"`. */
+                direction?: "asc" | "desc";
+                /** Page size. Defaults to `20`. */
+                limit?: number;
+                /** `nextCursor` from the previous page; omit for the first page. */
+                cursor?: string | null;
+                /** Signing secret. Defaults to `t.env.TITAN_CURSOR_SECRET`. */
+                secret?: string;
+                /** Cursor lifetime (seconds or e.g. `"1h"`). Defaults to `"1d"`. */
+                ttl?: number | string;
+                /** Options forwarded to `conn.query()`. */
+                queryOptions?: DbQueryOptions;
+            }): { rows: any[]; nextCursor: string | null };
         };
 
         /**
@@ -1177,7 +1621,8 @@ declare global {
          * # Environment Variables
          * 
          * `t.env` provides access to environment variables loaded from your 
-         * project's `.env` file at startup.
+         * project's `.env` file at startup. Only the names listed in
+         * `__config.env` and variables prefixed `TITAN_PUBLIC_` are exposed.
          * 
          * @example
          * ```js
@@ -1244,6 +1689,24 @@ declare global {
          */
         buffer: TitanCore.BufferModule;
 
+        /**
+         * Native Base64 (standard and URL-safe) and hex encoding.
+         *
+         * All methods are **synchronous** — no `drift()` needed. Invalid
+         * input to the decoders throws instead of returning partial data.
+         *
+         * @see {@link TitanCore.Encode} for method signatures.
+         */
+        encode: TitanCore.Encode;
+
+        /**
+         * UUID generation. `v7` IDs sort by creation time, which keeps
+         * database indexes compact when used as primary keys.
+         *
+         * @see {@link TitanCore.Uuid} for method signatures.
+         */
+        uuid: TitanCore.Uuid;
+
 
         // -------------------------------------------------------------------
         //  Storage & State
@@ -1301,6 +1764,98 @@ declare global {
          */
         shareContext: TitanCore.ShareContext;
 
+        /**
+         * Shared key-value state across isolates and requests, with change notifications.
+         *
+         * Backed by the same store as `t.shareContext`. `set` and `delete` also notify
+         * `subscribe` waiters. Values are in-memory only and lost when the server exits.
+         *
+         * @example
+         * ```js
+         * // Feature-flag cache refreshed by an admin action
+         * export function updateFlags(req) {
+         *   t.share.set("flags", req.body);
+         *   return { ok: true };
+         * }
+         *
+         * // Long poll: answers as soon as the flags change, or after 30s
+         * export function watchFlags(req) {
+         *   const update = t.share.subscribe("flags", { timeoutMs: 30000 });
+         *   return update.timeout ? { changed: false } : { changed: true, flags: update.value };
+         * }
+         * ```
+         */
+        share: TitanCore.Share;
+
+        /**
+         * Action result cache keyed by a key the action computes, shared by all isolates.
+         *
+         * @example
+         * ```js
+         * export function getUser(req) {
+         *   // Depends only on the id, so requests with different headers share it
+         *   return t.cache.response(`user:${req.params.id}`, 60_000, () =>
+         *     drift(t.fetch(`https://api.example.com/users/${req.params.id}`)).body
+         *   );
+         * }
+         * ```
+         */
+        cache: {
+            /**
+             * Return the cached result for `key`, or run `producer` (which may `drift`)
+             * and cache what it returns for `ttlMs`. Results are stored as JSON;
+             * `undefined` is never cached.
+             */
+            response<T>(key: string, ttlMs: number, producer: () => T): T;
+            /** Drop a cached result. */
+            delete(key: string): void;
+            /**
+             * Drop route responses cached by `__config.cache` whose path and query
+             * start with `prefix`, so the next read runs the action again.
+             *
+             * @example
+             * ```js
+             * export function updateProduct(req) {
+             *   // ...write...
+             *   t.cache.invalidate(`/products/${req.params.id}`);
+             *   t.cache.invalidate("/products?");
+             * }
+             * ```
+             *
+             * @returns The number of entries removed.
+             */
+            invalidate(prefix: string): number;
+        };
+
+        /**
+         * Shared resources for the current request, built lazily on first read and
+         * at most once per request. Register more with `t.service()`.
+         *
+         * @example
+         * ```js
+         * export function listUsers(req) {
+         *   const { db, log } = t.services;
+         *   log("listing users"); // tagged with the request
+         *   return drift(db.query("SELECT id, name FROM users"));
+         * }
+         * ```
+         */
+        services: {
+            /** `t.db.connect(t.env.DATABASE_URL)`; throws if `DATABASE_URL` is unset. */
+            db: DbConnection;
+            /** `t.cache` */
+            cache: TitanRuntimeUtils["cache"];
+            /** `t.log` prefixed with the request's method, path and id. */
+            log: (...args: any[]) => void;
+            [name: string]: any;
+        };
+
+        /**
+         * Register a service factory, called with the request the first time an action
+         * reads `t.services[name]`. Register at module level in any action bundle.
+         */
+        service<T>(name: string, factory: (req: TitanRequest) => T): void;
+
         /**
          * HTTP cookie utilities for reading, setting, and deleting cookies.
          *
@@ -1345,6 +1900,14 @@ declare global {
          */
         proc: TitanCore.Process;
 
+        /**
+         * Live load figures for this Titan server (isolate pool, in-flight
+         * requests, uptime, memory). Synchronous.
+         *
+         * @see {@link TitanCore.System} for method signatures.
+         */
+        system: TitanCore.System;
+
 
         // -------------------------------------------------------------------
         //  Utilities
@@ -1494,6 +2057,30 @@ declare global {
             readonly __titan_response: true;
         }
 
+        /**
+         * One Server-Sent Event for `t.response.sse()`.
+         */
+        interface SSEEvent {
+            /** Event type; clients listen with `source.addEventListener(event, ...)`. Defaults to `"message"`. */
+            event?: string;
+            /** Payload; strings are sent as-is, anything else as JSON. */
+            data: any;
+            /** Sent back by the browser as `Last-Event-ID` when it reconnects. */
+            id?: string | number;
+            /** Reconnection delay in milliseconds the client should use. */
+            retry?: number;
+        }
+
+        /**
+         * One part of a `t.response.multipart()` body.
+         */
+        interface MultipartPart {
+            /** Part headers. `Content-Type` defaults from the body: text, octet-stream or JSON. */
+            headers?: Record<string, string>;
+            /** Strings and bytes are sent unchanged; anything else as JSON. */
+            body: string | Uint8Array | ArrayBuffer | any;
+        }
+
         /**
          * Response builder interfaces.
          */
@@ -1501,12 +2088,16 @@ declare global {
             /**
              * Return a JSON response with an optional status code and headers.
              * 
+             * A `Set` serializes as an array of its values and a `Map` as an object
+             * keyed by `String(key)`; pass `{ maps: "pairs" }` for `[key, value]` arrays.
+             * 
              * @param data - The object or value to serialize to JSON.
              * @param status - The HTTP status code (default: 200).
              * @param headers - Optional custom headers.
              * @returns A standard Titan response.
              */
             json(data: any, status?: number, headers?: Record<string, string>): TitanResponse;
+            json(data: any, options: { status?: number; headers?: Record<string, string>; maps?: "object" | "pairs"; retryAfter?: number | string }): TitanResponse;
 
             /**
              * Return an HTML response with an optional status code and headers.
@@ -1517,6 +2108,17 @@ declare global {
              * @returns A standard Titan response.
              */
             html(html: string, status?: number, headers?: Record<string, string>): TitanResponse;
+            /**
+             * Options form. `retryAfter` (seconds or an HTTP date) sets `Retry-After`,
+             * e.g. for a maintenance page:
+             *
+             * ```js
+             * return t.response.html(page, { status: 503, retryAfter: 120 });
+             * ```
+             *
+             * A static action like this is served from the fast path without entering V8.
+             */
+            html(html: string, options: { status?: number; headers?: Record<string, string>; retryAfter?: number | string }): TitanResponse;
             
             /**
              * Return a plain text response with an optional status code and headers.
@@ -1527,6 +2129,7 @@ declare global {
              * @returns A standard Titan response.
              */
             text(text: string, status?: number, headers?: Record<string, string>): TitanResponse;
+            text(text: string, options: { status?: number; headers?: Record<string, string>; retryAfter?: number | string }): TitanResponse;
             
             /**
              * Issue an HTTP redirect to a specific URL.
@@ -1537,6 +2140,137 @@ declare global {
              * @returns A standard Titan response.
              */
             redirect(url: string, status?: number, headers?: Record<string, string>): TitanResponse;
+
+            /**
+             * Serve a file from disk. The server resolves `path` against the project
+             * root with the same checks as `t.read` and streams it without loading it
+             * into the isolate. A missing file, or one outside the root, answers `404`.
+             *
+             * @param path - Path relative to the project root.
+             * @param options - `download: true` adds `Content-Disposition: attachment`
+             * named after the file (a string sets the name); `contentType` overrides the
+             * type guessed from the extension.
+             *
+             * @example
+             * ```js
+             * return t.response.file(`exports/${req.params.id}.csv`, { download: "report.csv" });
+             * ```
+             */
+            file(path: string, options?: { download?: boolean | string; contentType?: string; status?: number; headers?: Record<string, string> }): TitanResponse;
+
+            /**
+             * Stream a JSON array, writing `[`, each serialized element and `]` as
+             * the elements are produced instead of buffering the whole array.
+             *
+             * Elements that are drift handles are resolved one at a time, so a
+             * generator can page through a database cursor. Headers and status are
+             * sent before the first element; ETags and compression are skipped.
+             * Tasks and WebSocket events receive the buffered `json()` equivalent.
+             *
+             * @param items - An iterable of values or drift handles, or a drift handle resolving to an array.
+             * @param options - Status, headers and `maps`, as for `json()`.
+             *
+             * @example
+             * ```js
+             * function* pages() {
+             *   for (let page = 0; ; page++) {
+             *     const rows = drift(conn.query("SELECT * FROM events LIMIT 500 OFFSET $1", [page * 500]));
+             *     if (rows.length === 0) return;
+             *     yield* rows;
+             *   }
+             * }
+             * return t.response.jsonArray(pages());
+             * ```
+             */
+            jsonArray(items: Iterable<any> | any, options?: { status?: number; headers?: Record<string, string>; maps?: "object" | "pairs" }): TitanResponse;
+
+            /**
+             * Stream Server-Sent Events as a `text/event-stream` response.
+             *
+             * Each event is written as soon as it is produced and the connection
+             * closes when the iterable ends. Non-object values are sent as `data`.
+             * Non-string `data` is JSON-encoded, and multi-line strings become one
+             * `data:` line per line. Elements that are drift handles are resolved
+             * in turn, so a generator can emit updates as async work completes.
+             * Tasks and WebSocket events receive the whole stream as one buffered body.
+             *
+             * @param events - An iterable of events (or a drift handle resolving to one).
+             * @param options - Status and extra headers.
+             *
+             * @example
+             * ```js
+             * function* progress(jobId) {
+             *   for (let step = 1; step <= 3; step++) {
+             *     const state = drift(t.fetch(`http://jobs.internal/${jobId}/${step}`));
+             *     yield { event: "progress", id: step, data: { step, ok: state.ok } };
+             *   }
+             *   yield { event: "done", data: "finished" };
+             * }
+             * return t.response.sse(progress(req.params.id));
+             * ```
+             */
+            sse(events: Iterable<SSEEvent | any> | any, options?: { status?: number; headers?: Record<string, string> }): TitanResponse;
+
+            /**
+             * Stream a `multipart/mixed` body, one part per element of `parts`.
+             *
+             * Each part is framed by a random boundary (also sent in
+             * `Content-Type`) and written as soon as it is produced; bodies are
+             * sent byte-for-byte, and a part that contains the boundary throws
+             * instead of corrupting the stream. Bare strings or bytes are parts
+             * without headers. Elements that are drift handles are resolved in turn.
+             * Tasks and WebSocket events receive the whole body buffered.
+             *
+             * @param parts - An iterable of parts (or a drift handle resolving to one).
+             * @param options - Status, extra headers and the multipart `subtype` (default `"mixed"`).
+             *
+             * @example
+             * ```js
+             * function* report(id) {
+             *   yield { body: { id, status: "ready" } };
+             *   const csv = drift(t.fetch(`http://reports.internal/${id}.csv`));
+             *   yield { headers: { "Content-Type": "text/csv" }, body: csv.body };
+             * }
+             * return t.response.multipart(report(req.params.id));
+             * ```
+             */
+            multipart(parts: Iterable<MultipartPart | string | Uint8Array> | any, options?: { status?: number; headers?: Record<string, string>; subtype?: string }): TitanResponse;
+
+            /**
+             * Set an HTTP trailer to be sent after the response body completes.
+             * The response is sent with chunked transfer encoding and a `Trailer` header
+             * announcing the trailer names. This includes `file()` and streamed responses.
+             * A streamed response (`jsonArray`, `sse`, `multipart`) sends its headers
+             * before the items. Only trailers set before streaming starts are announced.
+             * You can still change their values until the action returns.
+             *
+             * @param name - The trailer field name (e.g. "Digest").
+             * @param value - The trailer value.
+             */
+            trailer(name: string, value: string): void;
+
+            /**
+             * Send a `103 Early Hints` informational response with these `Link` values
+             * immediately, so the browser can start preloading while the action runs.
+             * The links are also sent as `Link` headers on the final response.
+             *
+             * Only HTTP/1.1 clients get the `103`, and only until the final response
+             * starts; once a streamed body is open, calls have no effect. HTTP/2 clients
+             * get the `Link` headers on the final response only: the server cannot send
+             * interim responses on an HTTP/2 stream.
+             *
+             * @param options.links - Raw `Link` values, or `{ href, as, rel, type, crossorigin }`
+             *                        objects (`rel` defaults to `"preload"`).
+             *
+             * @example
+             * ```js
+             * t.response.earlyHints({ links: [{ href: "/app.css", as: "style" }] });
+             * return t.response.html(page);
+             * ```
+             */
+            earlyHints(options: {
+                links: (string | { href: string; as?: string; rel?: string; type?: string; crossorigin?: boolean | string })[];
+            }): void;
         }
         /**
          * Asynchronous file system operations.
@@ -1579,10 +2313,12 @@ declare global {
              * Read the entire contents of a file as a UTF-8 string.
              *
              * @param path - Path to the file to read.
+             * @param options.encoding - Pass `"binary"` to read raw bytes as a `Uint8Array`.
              * @returns A promise resolving to the file contents.
              * @throws If the file does not exist or cannot be read.
              */
-            readFile(path: string): Promise<string>;
+            readFile(path: string, options?: { encoding?: "utf8" }): Promise<string>;
+            readFile(path: string, options: { encoding: "binary" }): Promise<Uint8Array>;
 
             /**
              * Write a string to a file, creating or overwriting it.
@@ -1874,6 +2610,38 @@ declare global {
              * ```
              */
             hashKeyed(algorithm: 'hmac-sha256' | 'hmac-sha512', key: string, message: string): Promise<string>;
+
+            /**
+             * Compute an HMAC synchronously over a string or raw bytes.
+             *
+             * Signature checks must hash the body exactly as sent, so pass `req.rawBody`
+             * rather than re-serializing `req.body`.
+             *
+             * @param algorithm - `"sha256"`, `"sha384"` or `"sha512"` (`"hmac-sha256"` and `"SHA-256"` also work).
+             * @param key - The secret, as a string or bytes.
+             * @param data - The message, as a string, `ArrayBuffer` or `Uint8Array`.
+             * @param encoding - Output encoding. Defaults to `"hex"`.
+             *
+             * @example
+             * ```js
+             * // GitHub: X-Hub-Signature-256: sha256=<hex>
+             * export function githubWebhook(req) {
+             *   const expected = "sha256=" + t.crypto.hmac("sha256", t.env.GITHUB_WEBHOOK_SECRET, req.rawBody);
+             *   if (!t.crypto.timingSafeEqual(expected, req.header("x-hub-signature-256") || "")) {
+             *     return t.response.json({ error: "Invalid signature" }, 401);
+             *   }
+             *   return { ok: true };
+             * }
+             * ```
+             */
+            hmac(algorithm: string, key: string | ArrayBuffer | Uint8Array, data: string | ArrayBuffer | Uint8Array, encoding?: 'hex' | 'base64' | 'base64url'): string;
+
+            /**
+             * Compare two strings or byte sequences in constant time.
+             * Every byte is compared whatever the first mismatch, so timing reveals
+             * at most whether the lengths differ.
+             */
+            timingSafeEqual(a: string | ArrayBuffer | Uint8Array, b: string | ArrayBuffer | Uint8Array): boolean;
         }
 
         /**
@@ -1945,6 +2713,81 @@ declare global {
             toUtf8(bytes: Uint8Array): string;
         }
 
+        /**
+         * Base64 and hex encoding implemented in Rust.
+         *
+         * Inputs may be strings (encoded as UTF-8), `Uint8Array`s or
+         * `ArrayBuffer`s. Decoders return a `Uint8Array` unless `"utf8"` is
+         * passed as the second argument. Malformed input throws an `Error`
+         * naming the function and the problem.
+         *
+         * @example
+         * ```js
+         * const token = t.encode.base64("user:pass", { url: true }); // "dXNlcjpwYXNz"
+         * const raw = t.encode.base64Decode(token);              // Uint8Array
+         * const text = t.encode.base64Decode("aGk=", "utf8");     // "hi"
+         *
+         * t.encode.hex("hi");                  // "6869"
+         * t.encode.hexDecode("6869", "utf8");  // "hi"
+         * ```
+         */
+        interface Encode {
+            /**
+             * Encode to Base64.
+             *
+             * @param input - A string, `Uint8Array` or `ArrayBuffer`.
+             * @param options - `url: true` uses the URL-safe alphabet (`-`, `_`) without padding.
+             */
+            base64(input: string | Uint8Array | ArrayBuffer, options?: { url?: boolean }): string;
+
+            /**
+             * Decode Base64. Both the standard and URL-safe alphabets are
+             * accepted, with or without padding.
+             *
+             * @throws If `str` is not valid Base64, or with `"utf8"` if the bytes are not valid UTF-8.
+             */
+            base64Decode(str: string): Uint8Array;
+            base64Decode(str: string, as: "bytes"): Uint8Array;
+            base64Decode(str: string, as: "utf8"): string;
+
+            /**
+             * Encode to lowercase hex.
+             *
+             * @param input - A string, `Uint8Array` or `ArrayBuffer`.
+             */
+            hex(input: string | Uint8Array | ArrayBuffer): string;
+
+            /**
+             * Decode hex (either case).
+             *
+             * @throws If `str` has an odd length or a non-hex digit.
+             */
+            hexDecode(str: string): Uint8Array;
+            hexDecode(str: string, as: "bytes"): Uint8Array;
+            hexDecode(str: string, as: "utf8"): string;
+        }
+
+        /**
+         * UUIDs generated in Rust, as lowercase hyphenated strings.
+         *
+         * @example
+         * ```js
+         * const id = t.uuid.v4(); // "1b4e28ba-2fa1-4d3b-a3f5-ef19b5a7633b"
+         * const key = t.uuid.v7(); // "01928f3c-6a1e-7c2b-9d4e-5f6a7b8c9d0e"
+         * ```
+         */
+        interface Uuid {
+            /** A random (version 4) UUID. */
+            v4(): string;
+
+            /**
+             * A time-ordered (version 7) UUID: a millisecond Unix timestamp
+             * followed by random bits. IDs from one server process increase
+             * monotonically, even within the same millisecond.
+             */
+            v7(): string;
+        }
+
         /**
          * Persistent server-side key-value storage.
          *
@@ -2142,6 +2985,47 @@ declare global {
          * 
          * @see https://titanpl.vercel.app/docs/knowledge/05-titan-core — TitanCore Runtime APIs (t.shareContext)
          */
+        /** Result of `t.share.subscribe()`. */
+        interface ShareUpdate {
+            key: string;
+            /** New value; `null` after `delete`. Absent on timeout. */
+            value?: any;
+            /** Set when `timeoutMs` passed without a write to `key`. */
+            timeout?: true;
+        }
+
+        interface Share {
+            /**
+             * Read a shared value.
+             *
+             * @returns The value, or `null` if the key is not set.
+             */
+            get(key: string): any;
+
+            /**
+             * Store a JSON-serializable value and notify subscribers of `key`.
+             */
+            set(key: string, value: any): void;
+
+            /**
+             * Remove a key and notify its subscribers with `null`.
+             */
+            delete(key: string): void;
+
+            /**
+             * Wait for the next `set`/`delete` of `key` from any isolate.
+             *
+             * Integrates with drift: the action suspends until the change (or
+             * `timeoutMs`) and resumes with the update. Writes made before the
+             * call are not delivered, so read the current value with `get` first
+             * if you need it.
+             * `cb` runs once with the new value. Without `timeoutMs`, the wait is
+             * bounded only by the drift timeout (`__config.driftTimeoutMs`).
+             */
+            subscribe(key: string, cb?: (value: any, key: string) => void, options?: { timeoutMs?: number }): ShareUpdate;
+            subscribe(key: string, options: { timeoutMs?: number }): ShareUpdate;
+        }
+
         interface ShareContext {
             /**
              * Retrieve a value from the shared context.
@@ -2196,15 +3080,16 @@ declare global {
          *   // Read a cookie from the request
          *   const token = t.cookies.get(req, "auth_token");
          *
-         *   // Set a cookie (attach to response)
-         *   t.cookies.set(req, "visited", "true", {
+         *   // Set cookies; each call adds its own Set-Cookie header
+         *   t.cookies.set("visited", "true", {
          *     httpOnly: true,
          *     secure: true,
          *     maxAge: 86400  // 1 day in seconds
          *   });
+         *   t.cookies.set("theme", "dark", { domain: "example.com" });
          *
-         *   // Delete a cookie
-         *   t.cookies.delete(req, "old_cookie");
+         *   // Clear a cookie (Max-Age=0 and an expired date)
+         *   t.cookies.clear("old_cookie");
          *
          *   return { hasToken: !!token };
          * }
@@ -2223,22 +3108,83 @@ declare global {
             get(req: any, name: string): string | null;
 
             /**
-             * Set a cookie on the response.
+             * Set a cookie on the response. Can be called any number of times;
+             * every call is sent as a separate `Set-Cookie` header.
+             *
+             * The older `set(req, name, value, options)` form is still accepted.
              *
-             * @param res - The response context object.
              * @param name - The cookie name.
-             * @param value - The cookie value.
-             * @param options - Optional cookie attributes (e.g., `httpOnly`, `secure`, `maxAge`, `path`, `sameSite`).
+             * @param value - The cookie value (URI-encoded when sent).
+             * @param options - Cookie attributes. `path` defaults to `"/"`; `partitioned`
+             *                  and `sameSite: "none"` imply `secure`.
+             */
+            set(name: string, value: string, options?: CookieOptions): void;
+            set(res: any, name: string, value: string, options?: CookieOptions): void;
+
+            /**
+             * Delete a cookie with `Max-Age=0` and an expiry date in the past.
+             *
+             * Pass the `domain` and `path` the cookie was set with, or the browser
+             * keeps the original.
+             *
+             * @param name - The cookie name to clear.
              */
-            set(res: any, name: string, value: string, options?: any): void;
+            clear(name: string, options?: Pick<CookieOptions, "domain" | "path" | "secure" | "sameSite" | "partitioned">): void;
 
             /**
-             * Delete a cookie by setting its expiration in the past.
+             * Alias of `clear`; the leading request argument is accepted and ignored.
              *
              * @param res - The response context object.
              * @param name - The cookie name to delete.
              */
-            delete(res: any, name: string): void;
+            delete(res: any, name: string, options?: Pick<CookieOptions, "domain" | "path" | "secure" | "sameSite" | "partitioned">): void;
+        }
+
+        /**
+         * Attributes for `t.cookies.set`.
+         */
+        interface CookieOptions {
+            /** Lifetime in seconds. */
+            maxAge?: number;
+            /** Absolute expiry (a `Date` or epoch milliseconds). */
+            expires?: Date | number;
+            /** Domain the cookie is sent to, including subdomains. */
+            domain?: string;
+            /** Path prefix the cookie is sent for (default: `"/"`). */
+            path?: string;
+            secure?: boolean;
+            httpOnly?: boolean;
+            sameSite?: "strict" | "lax" | "none" | "Strict" | "Lax" | "None";
+            /** Partitioned (CHIPS) third-party cookie; implies `secure`. */
+            partitioned?: boolean;
+        }
+
+        /**
+         * Server load introspection, for status and admin pages served as actions.
+         *
+         * @example
+         * ```js
+         * export function health() {
+         *   const s = t.system.stats();
+         *   return { ok: s.freeIsolates > 0, ...s };
+         * }
+         * ```
+         */
+        interface System {
+            /**
+             * Read the current load. Cheap enough to call per request.
+             *
+             * `freeIsolates` counts isolates not running JS right now (the
+             * calling isolate is busy). `memoryMb` is the process resident set
+             * size, or `null` where it can't be read (currently non-Linux).
+             */
+            stats(): {
+                freeIsolates: number;
+                totalIsolates: number;
+                inFlightRequests: number;
+                uptimeSeconds: number;
+                memoryMb: number | null;
+            };
         }
 
         /**
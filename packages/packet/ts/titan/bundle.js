@@ -154,6 +154,9 @@ async function validateEntryPoint(entryPoint) {
     }
 }
 
+// `// @titan-no-replay` counts only among the comments above the first statement
+const NO_REPLAY_DIRECTIVE = /^(?:\s*\/\/[^\n]*\n)*?\s*\/\/\s*@titan-no-replay\b/;
+
 /**
  * Bundles a single file
  */
@@ -190,7 +193,7 @@ export async function bundleFile(options) {
                 "views": path.join(root, "views"),
                 "auth": path.join(root, "auth"),
             },
-            banner: { js: "var Titan = t;" },
+            banner: { js: (options.banner ? options.banner + "\n" : "") + "var Titan = t;" },
             footer: options.footer || {}
         });
 
@@ -262,18 +265,29 @@ export async function bundle(options = {}) {
         const exportedName = path.basename(file, path.extname(file));
         const entryPoint = path.join(actionsDir, file);
         const outfile = path.join(bundleDir, relativeNoExt + ".jsbundle");
+        // esbuild strips comments, so re-emit the directive at the top for the runtime to see
+        const noReplay = NO_REPLAY_DIRECTIVE.test(fs.readFileSync(entryPoint, 'utf8'));
 
         try {
             await bundleFile({
                 root,
                 entryPoint,
                 outfile,
+                banner: noReplay ? '// @titan-no-replay' : '',
                 footer: {
                     js: `
 (function () {
   const fn = __titan_exports["${exportedName}"] || __titan_exports.default;
-  if (typeof fn !== "function") throw new Error("[TitanPL] Action '${actionName}' not found or not a function");
-  globalThis["${actionName}"] = globalThis.defineAction(fn);
+  if (typeof fn === "function") {
+    globalThis["${actionName}"] = globalThis.defineAction(fn);
+    return;
+  }
+  // WebSocket actions may export onOpen / onMessage / onClose instead
+  const handlers = fn && typeof fn === "object" ? fn : __titan_exports;
+  if (!["onOpen", "onMessage", "onClose"].some(k => typeof handlers[k] === "function")) {
+    throw new Error("[TitanPL] Action '${actionName}' not found or not a function");
+  }
+  globalThis["${actionName}"] = globalThis.defineAction(globalThis.__titan_ws_handlers(handlers));
 })();`
                 }
             });
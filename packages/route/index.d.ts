@@ -1,6 +1,297 @@
+/**
+ * Caching policy for reply routes: max-age in seconds, a raw
+ * Cache-Control header, or an options object. Enables a stable ETag
+ * and `If-None-Match` → 304 handling.
+ */
+export type ReplyCache = number | string | { maxAge?: number; cacheControl?: string };
+
+export interface ReplyOptions {
+    cache?: ReplyCache;
+    /**
+     * `Content-Type` to serve instead of `application/json` / `text/plain`.
+     * A string value is then sent as the literal body, e.g. YAML, XML or HTML.
+     */
+    contentType?: string;
+}
+
+export interface ActionOptions {
+    /** Send a weak `ETag` with 200 JSON responses and answer a matching `If-None-Match` with 304. */
+    etag?: boolean;
+    /**
+     * Actions run before this one, in order, as `mw(req, ctx)`. Returning `undefined`
+     * continues the chain; any other value is sent as the response and the action is
+     * skipped. `ctx` (also `req.context`) is shared by the chain and the action, so
+     * middleware can attach data such as `req.user` or `ctx.user`. An `async` middleware
+     * (or one returning a promise) is awaited before the next one runs.
+     */
+    middleware?: string[];
+    /**
+     * Types for `req.query` values, e.g. `{ page: "integer", tags: "string[]" }`. Each is
+     * `"string"`, `"number"`, `"integer"` or `"boolean"`, with `[]` for a list. A value that
+     * does not fit answers `400` before the action runs; unlisted keys stay strings.
+     */
+    querySchema?: Record<string, QueryFieldType>;
+}
+
+export type QueryFieldType =
+    | "string" | "number" | "integer" | "boolean"
+    | "string[]" | "number[]" | "integer[]" | "boolean[]";
+
 export interface RouteBuilder {
-    reply(value: any): void;
-    action(name: string): void;
+    reply(value: any, options?: ReplyOptions): void;
+    action(name: string, options?: ActionOptions): void;
+    /**
+     * Reverse-proxy this route to `upstream` (the query string is appended).
+     * Request and response bodies are streamed, so large transfers use constant memory.
+     */
+    proxy(upstream: string): void;
+}
+
+/**
+ * Engine options written to routes.json under `__config`. Keys are camelCase;
+ * the engine also accepts the snake_case spelling of every key (`stack_mb`,
+ * `trust_proxy`, ...), with the camelCase one winning when both are set.
+ */
+export interface TitanConfig {
+    /**
+     * Interface to listen on, with the port from `t.start()`: `"127.0.0.1"` keeps the server
+     * local, `"::"` listens on IPv6. `"unix:/tmp/titan.sock"` listens on a Unix socket instead.
+     * `TITAN_HOST` overrides it. Defaults to `"0.0.0.0"`; an invalid value stops startup.
+     *
+     * Connections speak HTTP/1.1 or cleartext HTTP/2 (prior knowledge). Over HTTP/2,
+     * `t.response.earlyHints()` sends no `103`: the links only arrive as `Link` headers
+     * on the final response.
+     */
+    host?: string;
+    /** Default caching policy for all `.reply()` routes (per-route `cache` wins). */
+    replyCache?: ReplyCache;
+    /** @deprecated Use `replyCache`. */
+    reply_cache?: ReplyCache;
+    /** Print a startup summary (routes, extensions, threads, features). Always on in dev mode. */
+    startupSummary?: boolean;
+    /** @deprecated Use `startupSummary`. */
+    startup_summary?: boolean;
+    /**
+     * Output format for request logs, `t.log` and startup diagnostics. `"json"` emits one object per line.
+     * `"common"` / `"combined"` write Apache Common/Combined Log Format access lines for every request
+     * (production included) in place of the request logs. Defaults to `"pretty"`.
+     */
+    logFormat?: "pretty" | "json" | "common" | "combined";
+    /** @deprecated Use `logFormat`. */
+    log_format?: "pretty" | "json" | "common" | "combined";
+    /**
+     * Status for actions that return `undefined`. Defaults to `204` (empty body).
+     * Any other status sends a `null` JSON body; use `200` to keep the previous `null` response.
+     * Explicit `null` is always `200` with a `null` body, and `{}` is `200` with `{}`.
+     */
+    undefinedStatus?: number;
+    /** @deprecated Use `undefinedStatus`. */
+    undefined_status?: number;
+    /**
+     * An action returning a bare string answers `text/plain; charset=utf-8` with the string
+     * as the body. `true` keeps the previous behaviour: a quoted JSON string.
+     */
+    stringAsJson?: boolean;
+    /**
+     * Directory (relative to the project root) served for GET/HEAD requests no route matches.
+     * `.br` / `.gz` siblings are served with `Content-Encoding` when the client accepts them.
+     */
+    staticDir?: string;
+    /** @deprecated Use `staticDir`. */
+    static_dir?: string;
+    /** URL prefix for `staticDir`. Defaults to `"/"`. */
+    staticPrefix?: string;
+    /** @deprecated Use `staticPrefix`. */
+    static_prefix?: string;
+    /**
+     * URL prefix → directory mounts, e.g. `{ "/assets": "./public" }`, served
+     * like `staticDir` with `ETag` / `Last-Modified` revalidation.
+     */
+    static?: Record<string, string>;
+    /**
+     * Single-page app fallback: unmatched GET/HEAD requests under `prefix` (default `"/"`)
+     * that no route or static file claims get the `fallback` HTML (relative to the project
+     * root) with `200`. Paths ending in a file extension still get `404`. Read once at startup.
+     */
+    spa?: { prefix?: string; fallback: string };
+    /**
+     * Largest accepted request body in bytes; larger bodies are answered with `413`
+     * before any action runs. Defaults to 10 MB. Multipart uploads use `multipart` instead.
+     */
+    maxBodyBytes?: number;
+    /**
+     * Bodies larger than this many bytes are buffered partly to a temp file and reach
+     * the action as `req.bodyFile` instead of `req.body` / `req.rawBody`. Unset keeps
+     * every body in memory. Multipart uploads are unaffected.
+     */
+    bodySpillBytes?: number;
+    /**
+     * Methods whose body is not read unless `Content-Length` or `Transfer-Encoding`
+     * announces one. Defaults to `["GET", "HEAD"]`.
+     */
+    bodylessMethods?: string[];
+    /** Upload limits for `multipart/form-data` bodies; exceeding either answers `413`. */
+    multipart?: {
+        /** Per file (or field) limit in bytes. Defaults to 10 MB. */
+        maxFileBytes?: number;
+        /** Whole body limit in bytes. Defaults to 50 MB. */
+        maxTotalBytes?: number;
+    };
+    /**
+     * Pin each `titan-worker-N` thread to a CPU core. Ignored with a warning
+     * where affinity isn't available.
+     */
+    cpuAffinity?: boolean;
+    /**
+     * Elastic isolate pool. The thread count passed to `start()` is the baseline;
+     * while every worker stays busy more are started, up to `max`, and the extra
+     * ones are retired again after `cooldownMs` without that load.
+     */
+    pool?: {
+        /** Most workers to run. Defaults to the baseline (no scaling). */
+        max?: number;
+        /** Idle time before an extra worker is retired. Defaults to 30000 ms. */
+        cooldownMs?: number;
+    };
+    /**
+     * What a worker panic does. `"recover"` (default) fails the in-flight request
+     * and keeps serving; `"abort"` writes `titan-crash-<ts>.log` and exits the process.
+     */
+    onPanic?: "recover" | "abort";
+    /**
+     * Refuse to start when routes shadow each other (a route defined twice, or two
+     * dynamic routes matching the same paths). Without it they are only logged as warnings.
+     */
+    strictRoutes?: boolean;
+    /**
+     * On SIGTERM/Ctrl-C, how long in-flight requests may keep running before the
+     * process exits anyway. Defaults to 10000 ms.
+     */
+    shutdownGraceMs?: number;
+    /**
+     * Set `TCP_NODELAY` on accepted connections so small responses are sent immediately
+     * instead of being batched. Defaults to `true`.
+     */
+    tcpNoDelay?: boolean;
+    /**
+     * How long a kept-alive connection may sit idle before the next request starts.
+     * Defaults to `75000` ms; `0` disables keep-alive.
+     */
+    keepAliveTimeoutMs?: number;
+    /**
+     * Built-in probes: `/healthz` (200 while the server runs) and `/readyz` (200 once
+     * every worker has loaded its actions, 503 before). `false` disables both; a path
+     * moves a probe and `false` disables just that one.
+     */
+    health?: false | {
+        healthz?: string | false;
+        readyz?: string | false;
+    };
+    /**
+     * Behind a reverse proxy: take `req.ip`, `req.protocol` and access-log addresses from
+     * `X-Forwarded-For` / `X-Forwarded-Proto`. Only enable when the proxy sets these headers,
+     * since clients can forge them otherwise. `true` trusts one proxy (the right-most entry);
+     * a number trusts that many proxies and takes the entry that far from the right.
+     */
+    trustProxy?: boolean | number;
+    /**
+     * Default timeout in ms for every `drift()`; a drift still pending after it answers
+     * `504`. Per-call `drift(op, { timeout })` wins. Unset means no timeout.
+     */
+    driftTimeoutMs?: number;
+    /**
+     * Requests each worker may have waiting. When every worker's queue is full, new
+     * requests are answered `503` with `Retry-After: 1` instead of waiting. Tasks and
+     * WebSocket events still wait for room. Default `256`.
+     */
+    maxQueueDepth?: number;
+    /**
+     * Deepest nesting an action result may have; a deeper or circular result answers `500`
+     * instead of exhausting the worker's stack. Default `256`.
+     */
+    maxResultDepth?: number;
+    /**
+     * An action bundle that fails to compile is recompiled from disk on a later request to it,
+     * at most once per this many ms on each isolate, so a fixed or late-written bundle recovers
+     * without a restart. Default `1000`; `0` disables retries.
+     */
+    compileRetryMs?: number;
+    /**
+     * How `/users/` relates to a route declared as `/users`. `"strict"` (default) keeps them
+     * distinct; `"ignore"` serves either form from the route that exists; `"redirect"` answers
+     * `/users/` with `301` (`308` for non-GET) to `/users`. Dynamic routes match either form.
+     */
+    trailingSlash?: "strict" | "ignore" | "redirect";
+    /**
+     * Environment variables actions may read through `t.env` / `process.env`, e.g.
+     * `["DATABASE_URL", "JWT_SECRET"]`. Variables prefixed `TITAN_PUBLIC_` are always exposed;
+     * everything else is hidden. Migration: apps that read other variables must list them here
+     * (including `DATABASE_URL` for `t.services.db` and `TITAN_CURSOR_SECRET` for cursors).
+     */
+    env?: string[];
+    /**
+     * `false` answers failed actions in production with a generic `"Internal Server Error"`
+     * instead of the error message, so stack traces don't leak. Errors are still logged.
+     * Defaults to `true`. Clients preferring `text/plain` get the message as plain text.
+     */
+    exposeErrors?: boolean;
+    /** `etag: true` for every action route (see `ActionOptions.etag`). */
+    etag?: boolean;
+    /**
+     * Serve Prometheus metrics at `/metrics`: request counts by route pattern and
+     * status, action/drift/queue duration histograms and busy vs free workers.
+     */
+    metrics?: boolean;
+    /**
+     * Development only: watch the actions directory and recompile a changed bundle
+     * into every worker without restarting. A bundle that fails to compile is logged
+     * and the previous version keeps serving. Disables FastPath static responses.
+     * Dev mode itself (detailed errors, `/__heapdump`) comes from `TITAN_DEV=1` only.
+     */
+    dev?: boolean;
+    /**
+     * Per-route response cache: route (as declared, e.g. `"/products/:id"`) → TTL in ms.
+     * GET/HEAD responses are stored by path and query string and served without running
+     * the action until they expire. Only 2xx responses without cookies are kept, and routes
+     * with middleware are never cached. Clear entries after writes with `t.cache.invalidate(prefix)`.
+     */
+    cache?: Record<string, number>;
+    /**
+     * Compress action responses negotiated via `Accept-Encoding`. `true` uses
+     * the defaults; FastPath bodies are compressed once at startup.
+     */
+    compression?: boolean | {
+        /** Minimum body size in bytes. Defaults to 1024. */
+        threshold?: number;
+        /** Alias of `threshold`. */
+        minBytes?: number;
+        /** Codecs in preference order. Defaults to `["br", "gzip"]`. */
+        codecs?: ("br" | "gzip")[];
+        /** Per-codec level: brotli 0-11 (default 5), gzip 0-9 (default 6). */
+        level?: { br?: number; gzip?: number };
+        /**
+         * Content types to compress, exact (`"application/json"`) or by prefix
+         * (`"text/"`). Images, video, audio, archives and fonts are never
+         * re-compressed; responses that already have a `Content-Encoding` are left alone.
+         */
+        types?: string[];
+    };
+    /**
+     * CORS for every route. `OPTIONS` preflights are answered with 204 and
+     * `Access-Control-Allow-*` headers are added to all responses.
+     */
+    cors?: {
+        /** `"*"` or an allowlist; matching request origins are echoed back. Defaults to `"*"`. */
+        origins?: "*" | string | string[];
+        /** Defaults to GET, POST, PUT, PATCH, DELETE, OPTIONS. */
+        methods?: string[];
+        /** Allowed request headers. Defaults to echoing `Access-Control-Request-Headers`. */
+        headers?: string[];
+        credentials?: boolean;
+        /** Preflight cache lifetime in seconds. */
+        maxAge?: number;
+    };
+    [key: string]: any;
 }
 
 export interface TitanRoute {
@@ -8,9 +299,14 @@ export interface TitanRoute {
     post(route: string): RouteBuilder;
     put(route: string): RouteBuilder;
     delete(route: string): RouteBuilder;
+    patch(route: string): RouteBuilder;
+    options(route: string): RouteBuilder;
+    /** One route answering several methods, e.g. `t.route(["GET", "POST"], "/items/:id")`. */
+    route(methods: string | string[], route: string): RouteBuilder;
     ws(route: string): { action(name: string): void };
     log(module: string, msg: string): void;
     start(port?: number, msg?: string, threads?: number, stack_mb?: number): void;
+    config(options: TitanConfig): void;
 }
 
 declare const t: TitanRoute;
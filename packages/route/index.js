@@ -2,34 +2,66 @@ const routes = {};
 const dynamicRoutes = {};
 const actionMap = {};
 
+// `method` may be a list: exact routes get one key per method, dynamic routes
+// one entry listing them all
 function addRoute(method, route) {
-    const key = `${method.toUpperCase()}:${route}`;
+    const methods = [].concat(method).map((m) => String(m).toUpperCase());
+    const keys = methods.map((m) => `${m}:${route}`);
+    method = methods[0];
+    const each = (entry) => {
+        for (const k of keys) routes[k] = { ...entry };
+    };
 
     return {
-        reply(value) {
-            routes[key] = {
+        reply(value, options = {}) {
+            const entry = {
                 type: typeof value === "object" ? "json" : "text",
                 value
             };
+            if (options.cache !== undefined) entry.cache = options.cache;
+            if (options.contentType) {
+                // Checked here so a typo fails the build instead of being dropped at startup
+                if (typeof options.contentType !== "string" || !/^[^\s/;]+\/[^\s/;]+(\s*;[^\r\n]*)?$/.test(options.contentType)) {
+                    throw new Error(`reply(): invalid contentType ${JSON.stringify(options.contentType)} for ${keys.join(", ")}`);
+                }
+                entry.contentType = options.contentType;
+            }
+            each(entry);
+        },
+
+        // Stream requests to an upstream URL and its response back, bypassing actions
+        proxy(upstream) {
+            each({
+                type: "proxy",
+                value: upstream
+            });
         },
 
-        action(name) {
+        action(name, options = {}) {
             const cleanName = name.replace(/\.[jt]s$/, '').replace(/\\/g, '/');
             if (route.includes(":")) {
                 if (!dynamicRoutes[method]) dynamicRoutes[method] = [];
-                dynamicRoutes[method].push({
-                    method: method.toUpperCase(),
+                const entry = {
+                    method,
                     pattern: route,
                     action: cleanName
-                });
-                actionMap[key] = cleanName;
+                };
+                if (methods.length > 1) entry.methods = methods.slice(1);
+                if (options.etag) entry.etag = true;
+                if (options.middleware) entry.middleware = [].concat(options.middleware);
+                if (options.querySchema) entry.querySchema = options.querySchema;
+                dynamicRoutes[method].push(entry);
             } else {
-                routes[key] = {
+                const entry = {
                     type: "action",
                     value: cleanName
                 };
-                actionMap[key] = cleanName;
+                if (options.etag) entry.etag = true;
+                if (options.middleware) entry.middleware = [].concat(options.middleware);
+                if (options.querySchema) entry.querySchema = options.querySchema;
+                each(entry);
             }
+            for (const k of keys) actionMap[k] = cleanName;
         }
     };
 }
@@ -39,10 +71,19 @@ const t = {
     post(route) { return addRoute("POST", route); },
     put(route) { return addRoute("PUT", route); },
     delete(route) { return addRoute("DELETE", route); },
+    patch(route) { return addRoute("PATCH", route); },
+    options(route) { return addRoute("OPTIONS", route); },
+    // One route for several methods: t.route(["GET", "POST"], "/items/:id")
+    route(methods, route) { return addRoute(methods, route); },
     log(module, msg) { console.log(`[${module}] ${msg}`); },
 
     start(port = 3000, msg = "", threads, stack_mb = 8) {
-        globalThis.__TITAN_CONFIG__ = { port, msg, threads, stack_mb };
+        globalThis.__TITAN_CONFIG__ = { ...globalThis.__TITAN_CONFIG__, port, msg, threads, stack_mb };
+    },
+
+    // Engine options written to routes.json under __config (e.g. { replyCache: 60 })
+    config(options = {}) {
+        globalThis.__TITAN_CONFIG__ = { ...globalThis.__TITAN_CONFIG__, ...options };
     },
 
     ws(route) {